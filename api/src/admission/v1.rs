@@ -0,0 +1,347 @@
+//! Types for writing admission webhooks, which receive an
+//! `AdmissionReview` containing a request and reply with the same
+//! `AdmissionReview` containing a response.
+
+use crate::canonical;
+use crate::jsonpatch;
+use crate::meta::v1::{ListMeta, Status, StatusStatus};
+use crate::{serde_base64, Integer, TypeMeta, TypeMetaImpl};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{self, Value};
+use std::collections::BTreeMap;
+
+const API_GROUP: &str = "admission.k8s.io/v1";
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AdmissionReview {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<AdmissionReview>,
+    pub request: Option<AdmissionRequest>,
+    pub response: Option<AdmissionResponse>,
+}
+
+impl TypeMeta for AdmissionReview {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "AdmissionReview"
+    }
+}
+
+impl AdmissionReview {
+    /// Wraps `response` in the `AdmissionReview` to send back to the
+    /// API server.
+    pub fn from_response(response: AdmissionResponse) -> Self {
+        AdmissionReview {
+            typemeta: Default::default(),
+            request: None,
+            response: Some(response),
+        }
+    }
+}
+
+/// The serialized form of a group/version/kind.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct GroupVersionKind {
+    #[serde(default)]
+    pub group: String,
+    pub version: String,
+    pub kind: String,
+}
+
+/// The serialized form of a group/version/resource.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct GroupVersionResource {
+    #[serde(default)]
+    pub group: String,
+    pub version: String,
+    pub resource: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Operation {
+    Create,
+    Update,
+    Delete,
+    Connect,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UserInfo {
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub uid: String,
+    #[serde(default)]
+    pub groups: Vec<String>,
+    #[serde(default)]
+    pub extra: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AdmissionRequest {
+    pub uid: String,
+    pub kind: GroupVersionKind,
+    pub resource: GroupVersionResource,
+    pub sub_resource: Option<String>,
+    pub request_kind: Option<GroupVersionKind>,
+    pub request_resource: Option<GroupVersionResource>,
+    pub request_sub_resource: Option<String>,
+    pub name: Option<String>,
+    pub namespace: Option<String>,
+    pub operation: Operation,
+    #[serde(default)]
+    pub user_info: UserInfo,
+    pub object: Option<Value>,
+    pub old_object: Option<Value>,
+    pub dry_run: Option<bool>,
+    pub options: Option<Value>,
+}
+
+impl AdmissionRequest {
+    /// Decodes `object` as a typed object.  `object` is absent for
+    /// `DELETE` operations.
+    pub fn object_as<T: DeserializeOwned>(&self) -> Result<Option<T>, serde_json::Error> {
+        self.object.clone().map(serde_json::from_value).transpose()
+    }
+
+    /// Decodes `old_object` as a typed object.  `old_object` is only
+    /// present for `UPDATE` and `DELETE` operations.
+    pub fn old_object_as<T: DeserializeOwned>(&self) -> Result<Option<T>, serde_json::Error> {
+        self.old_object
+            .clone()
+            .map(serde_json::from_value)
+            .transpose()
+    }
+
+    /// A response admitting this request unchanged.
+    pub fn allow(&self) -> AdmissionResponse {
+        AdmissionResponse::allow(&self.uid)
+    }
+
+    /// A response rejecting this request, with `message` shown to the
+    /// user.
+    pub fn deny(&self, message: &str) -> AdmissionResponse {
+        AdmissionResponse::deny(&self.uid, message)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PatchType {
+    JSONPatch,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AdmissionResponse {
+    pub uid: String,
+    pub allowed: bool,
+    pub status: Option<Status>,
+    /// A JSON patch (RFC 6902) to apply to the object.
+    #[serde(default, with = "serde_base64::option")]
+    pub patch: Option<Vec<u8>>,
+    pub patch_type: Option<PatchType>,
+    #[serde(default)]
+    pub audit_annotations: BTreeMap<String, String>,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+impl AdmissionResponse {
+    pub fn allow(uid: &str) -> Self {
+        AdmissionResponse {
+            uid: uid.to_string(),
+            allowed: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn deny(uid: &str, message: &str) -> Self {
+        const FORBIDDEN: Integer = 403;
+        AdmissionResponse {
+            uid: uid.to_string(),
+            allowed: false,
+            status: Some(Status {
+                metadata: ListMeta::default(),
+                code: FORBIDDEN,
+                details: None,
+                message: message.to_string(),
+                reason: None,
+                status: StatusStatus::Failure,
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Sets a JSON patch (as a JSON array of operations) to apply to
+    /// the admitted object.
+    pub fn with_patch(mut self, patch: &Value) -> Result<Self, serde_json::Error> {
+        self.patch = Some(serde_json::to_vec(patch)?);
+        self.patch_type = Some(PatchType::JSONPatch);
+        Ok(self)
+    }
+
+    /// Sets the patch that turns `original` (the request's `object`,
+    /// exactly as the server sent it) into `mutated`, which is
+    /// compared in its `canonical` form.  Leaves the response without
+    /// a patch if they are the same.
+    pub fn with_mutation<T: Serialize>(
+        self,
+        original: &Value,
+        mutated: &T,
+    ) -> Result<Self, serde_json::Error> {
+        let patch = jsonpatch::diff(original, &canonical::to_value(mutated)?);
+        if patch.as_array().map(Vec::is_empty).unwrap_or(false) {
+            Ok(self)
+        } else {
+            self.with_patch(&patch)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::v1::Pod;
+
+    fn review_json() -> Value {
+        json!({
+            "apiVersion": "admission.k8s.io/v1",
+            "kind": "AdmissionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "kind": {"group": "", "version": "v1", "kind": "Pod"},
+                "resource": {"group": "", "version": "v1", "resource": "pods"},
+                "requestKind": {"group": "", "version": "v1", "kind": "Pod"},
+                "requestResource": {"group": "", "version": "v1", "resource": "pods"},
+                "name": "web",
+                "namespace": "default",
+                "operation": "CREATE",
+                "userInfo": {
+                    "username": "admin",
+                    "uid": "014fbff9a07c",
+                    "groups": ["system:authenticated"],
+                    "extra": {"some-key": ["some-value1", "some-value2"]},
+                },
+                "object": {
+                    "apiVersion": "v1",
+                    "kind": "Pod",
+                    "metadata": {"name": "web", "namespace": "default"},
+                    "spec": {
+                        "containers": [{
+                            "name": "web",
+                            "image": "nginx",
+                            "terminationMessagePath": "/dev/termination-log",
+                            "terminationMessagePolicy": "File",
+                        }],
+                        "dnsPolicy": "ClusterFirst",
+                        "restartPolicy": "Always",
+                        "terminationGracePeriodSeconds": 30,
+                    },
+                },
+                "oldObject": null,
+                "dryRun": false,
+                "options": {"apiVersion": "meta.k8s.io/v1", "kind": "CreateOptions"},
+            },
+        })
+    }
+
+    #[test]
+    fn request_roundtrip() {
+        let review: AdmissionReview = serde_json::from_value(review_json()).unwrap();
+        let req = review.request.as_ref().unwrap();
+        assert_eq!(req.operation, Operation::Create);
+        assert_eq!(req.resource.resource, "pods");
+        assert_eq!(req.user_info.groups, vec!["system:authenticated"]);
+
+        let pod: Pod = req.object_as().unwrap().unwrap();
+        assert_eq!(pod.spec.containers[0].image, Some("nginx".into()));
+        assert_eq!(req.old_object_as::<Pod>().unwrap(), None);
+
+        let again: AdmissionReview =
+            serde_json::from_value(serde_json::to_value(&review).unwrap()).unwrap();
+        assert_eq!(again, review);
+    }
+
+    #[test]
+    fn patch_response() {
+        let review: AdmissionReview = serde_json::from_value(review_json()).unwrap();
+        let patch =
+            json!([{"op": "add", "path": "/metadata/labels", "value": {"injected": "true"}}]);
+        let resp = review.request.unwrap().allow().with_patch(&patch).unwrap();
+        let json = serde_json::to_value(&AdmissionReview::from_response(resp)).unwrap();
+
+        assert_eq!(json["apiVersion"], "admission.k8s.io/v1");
+        assert_eq!(json["kind"], "AdmissionReview");
+        assert_eq!(
+            json["response"]["uid"],
+            "705ab4f5-6393-11e8-b7cc-42010a800002"
+        );
+        assert_eq!(json["response"]["allowed"], true);
+        assert_eq!(json["response"]["patchType"], "JSONPatch");
+        let encoded = json["response"]["patch"].as_str().unwrap();
+        let decoded: Value = serde_json::from_slice(&::base64::decode(encoded).unwrap()).unwrap();
+        assert_eq!(decoded, patch);
+
+        let again: AdmissionReview = serde_json::from_value(json).unwrap();
+        let resp = again.response.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&resp.patch.unwrap()).unwrap(),
+            patch
+        );
+    }
+
+    #[test]
+    fn mutation_response() {
+        use crate::core::v1::Container;
+
+        let review: AdmissionReview = serde_json::from_value(review_json()).unwrap();
+        let req = review.request.unwrap();
+        let pod: Pod = req.object_as().unwrap().unwrap();
+        let mut mutated = pod.clone();
+        mutated.spec.containers.push(Container {
+            name: "sidecar".into(),
+            image: Some("envoy".into()),
+            ..Default::default()
+        });
+
+        let object = req.object.clone().unwrap();
+        let resp = req.allow().with_mutation(&object, &mutated).unwrap();
+        assert_eq!(resp.patch_type, Some(PatchType::JSONPatch));
+        let json = serde_json::to_value(&resp).unwrap();
+        let encoded = json["patch"].as_str().unwrap();
+        let patch: Value = serde_json::from_slice(&::base64::decode(encoded).unwrap()).unwrap();
+        assert_eq!(patch.as_array().unwrap().len(), 1);
+        assert_eq!(patch[0]["op"], "add");
+        assert_eq!(patch[0]["path"], "/spec/containers/1");
+
+        // Applies to the object as the server sent it
+        let mut patched = object.clone();
+        jsonpatch::apply(&mut patched, &patch);
+        let patched: Pod = serde_json::from_value(patched).unwrap();
+        assert_eq!(patched, mutated);
+
+        let unchanged = req.allow().with_mutation(&object, &pod).unwrap();
+        assert_eq!(unchanged.patch, None);
+        assert_eq!(unchanged.patch_type, None);
+    }
+
+    #[test]
+    fn deny_response() {
+        let resp = AdmissionResponse::deny("1234", "images must come from our registry");
+        let json = serde_json::to_value(&resp).unwrap();
+        assert_eq!(json["allowed"], false);
+        assert_eq!(json["status"]["code"], 403);
+        assert_eq!(
+            json["status"]["message"],
+            "images must come from our registry"
+        );
+        assert_eq!(json["patch"], Value::Null);
+    }
+}
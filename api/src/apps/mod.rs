@@ -1 +1,3 @@
+pub mod v1;
+#[deprecated(note = "apps/v1beta2 is no longer served, use apps::v1")]
 pub mod v1beta2;
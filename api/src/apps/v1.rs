@@ -0,0 +1,741 @@
+use crate::core::v1::{ConditionStatus, NamespacedResource, PodTemplateSpec};
+use crate::meta::v1::{ItemList, LabelSelector, Metadata, ObjectMeta};
+use crate::meta::{GroupVersion, GroupVersionResource};
+use crate::{IntOrString, Integer, Time, TypeMeta, TypeMetaImpl};
+use serde_json::Value;
+use std::borrow::Cow;
+
+const API_GROUP: &str = "apps/v1";
+pub const GROUP_VERSION: GroupVersion = GroupVersion {
+    group: "apps",
+    version: "v1",
+};
+
+pub struct Deployments;
+
+impl NamespacedResource for Deployments {
+    type List = DeploymentList;
+
+    fn namespaced(&self) -> bool {
+        true
+    }
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("deployments")
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Deployment {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<Deployment>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub spec: DeploymentSpec,
+    #[serde(default)]
+    pub status: DeploymentStatus,
+}
+
+pub type DeploymentList = ItemList<Deployment>;
+
+impl TypeMeta for Deployment {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "Deployment"
+    }
+}
+
+impl Metadata for Deployment {
+    fn api_version(&self) -> &str {
+        <Deployment as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <Deployment as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+fn int1() -> Integer {
+    1
+}
+fn int10() -> Integer {
+    10
+}
+fn int600() -> Integer {
+    600
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentSpec {
+    #[serde(default)]
+    pub min_ready_seconds: Integer,
+    #[serde(default)]
+    pub paused: bool,
+    #[serde(default = "int600")]
+    pub progress_deadline_seconds: Integer,
+    #[serde(default = "int1")]
+    pub replicas: Integer,
+    #[serde(default = "int10")]
+    pub revision_history_limit: Integer,
+    #[serde(default)]
+    pub selector: LabelSelector,
+    #[serde(default)]
+    pub strategy: DeploymentStrategy,
+    #[serde(default)]
+    pub template: PodTemplateSpec,
+}
+
+impl Default for DeploymentSpec {
+    fn default() -> Self {
+        DeploymentSpec {
+            min_ready_seconds: 0,
+            paused: false,
+            progress_deadline_seconds: int600(),
+            replicas: int1(),
+            revision_history_limit: int10(),
+            selector: Default::default(),
+            strategy: Default::default(),
+            template: Default::default(),
+        }
+    }
+}
+
+// TODO: This should be an enum, with a redundant adjacent/external tag
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentStrategy {
+    #[serde(rename = "type")]
+    pub typ: DeploymentStrategyType,
+    pub rolling_update: Option<RollingUpdateDeployment>,
+}
+
+impl Default for DeploymentStrategy {
+    fn default() -> Self {
+        DeploymentStrategy {
+            typ: DeploymentStrategyType::RollingUpdate,
+            rolling_update: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum DeploymentStrategyType {
+    Recreate,
+    RollingUpdate,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RollingUpdateDeployment {
+    pub max_surge: Option<IntOrString>,
+    pub max_unavailable: Option<IntOrString>,
+}
+
+/// All counters are omitted by the server when zero, and the whole
+/// status is empty until the controller first observes the deployment.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentStatus {
+    #[serde(default)]
+    pub available_replicas: Integer,
+    #[serde(default)]
+    pub collision_count: Integer,
+    #[serde(default)]
+    pub conditions: Vec<DeploymentCondition>,
+    #[serde(default)]
+    pub observed_generation: Integer,
+    #[serde(default)]
+    pub ready_replicas: Integer,
+    #[serde(default)]
+    pub replicas: Integer,
+    #[serde(default)]
+    pub unavailable_replicas: Integer,
+    #[serde(default)]
+    pub updated_replicas: Integer,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentCondition {
+    #[serde(rename = "type")]
+    pub typ: DeploymentConditionType,
+    pub status: ConditionStatus,
+    pub last_update_time: Option<Time>,
+    pub last_transition_time: Option<Time>,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum DeploymentConditionType {
+    Available,
+    Progressing,
+    ReplicaFailure,
+}
+
+pub struct StatefulSets;
+
+impl NamespacedResource for StatefulSets {
+    type List = StatefulSetList;
+
+    fn namespaced(&self) -> bool {
+        true
+    }
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("statefulsets")
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatefulSet {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<StatefulSet>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub spec: StatefulSetSpec,
+    #[serde(default)]
+    pub status: StatefulSetStatus,
+}
+
+pub type StatefulSetList = ItemList<StatefulSet>;
+
+impl TypeMeta for StatefulSet {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "StatefulSet"
+    }
+}
+
+impl Metadata for StatefulSet {
+    fn api_version(&self) -> &str {
+        <StatefulSet as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <StatefulSet as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatefulSetSpec {
+    #[serde(default = "int1")]
+    pub replicas: Integer,
+    #[serde(default)]
+    pub selector: LabelSelector,
+    #[serde(default)]
+    pub template: PodTemplateSpec,
+    /// The headless service giving the pods their stable network
+    /// identities.
+    #[serde(default)]
+    pub service_name: String,
+    #[serde(default)]
+    pub pod_management_policy: PodManagementPolicyType,
+    #[serde(default = "int10")]
+    pub revision_history_limit: Integer,
+    #[serde(default)]
+    pub min_ready_seconds: Integer,
+}
+
+impl Default for StatefulSetSpec {
+    fn default() -> Self {
+        StatefulSetSpec {
+            replicas: int1(),
+            selector: Default::default(),
+            template: Default::default(),
+            service_name: Default::default(),
+            pod_management_policy: Default::default(),
+            revision_history_limit: int10(),
+            min_ready_seconds: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PodManagementPolicyType {
+    OrderedReady,
+    Parallel,
+}
+
+impl Default for PodManagementPolicyType {
+    fn default() -> Self {
+        PodManagementPolicyType::OrderedReady
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatefulSetStatus {
+    #[serde(default)]
+    pub observed_generation: Integer,
+    #[serde(default)]
+    pub replicas: Integer,
+    #[serde(default)]
+    pub ready_replicas: Integer,
+    #[serde(default)]
+    pub current_replicas: Integer,
+    #[serde(default)]
+    pub updated_replicas: Integer,
+    #[serde(default)]
+    pub available_replicas: Integer,
+    pub current_revision: Option<String>,
+    pub update_revision: Option<String>,
+    #[serde(default)]
+    pub collision_count: Integer,
+}
+
+pub struct DaemonSets;
+
+impl NamespacedResource for DaemonSets {
+    type List = DaemonSetList;
+
+    fn namespaced(&self) -> bool {
+        true
+    }
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("daemonsets")
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonSet {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<DaemonSet>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub spec: DaemonSetSpec,
+    #[serde(default)]
+    pub status: DaemonSetStatus,
+}
+
+pub type DaemonSetList = ItemList<DaemonSet>;
+
+impl TypeMeta for DaemonSet {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "DaemonSet"
+    }
+}
+
+impl Metadata for DaemonSet {
+    fn api_version(&self) -> &str {
+        <DaemonSet as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <DaemonSet as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonSetSpec {
+    #[serde(default)]
+    pub selector: LabelSelector,
+    #[serde(default)]
+    pub template: PodTemplateSpec,
+    #[serde(default)]
+    pub min_ready_seconds: Integer,
+    #[serde(default = "int10")]
+    pub revision_history_limit: Integer,
+}
+
+impl Default for DaemonSetSpec {
+    fn default() -> Self {
+        DaemonSetSpec {
+            selector: Default::default(),
+            template: Default::default(),
+            min_ready_seconds: 0,
+            revision_history_limit: int10(),
+        }
+    }
+}
+
+/// There is no `replicas`: the desired count is the number of nodes
+/// the pods should run on.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonSetStatus {
+    #[serde(default)]
+    pub current_number_scheduled: Integer,
+    #[serde(default)]
+    pub desired_number_scheduled: Integer,
+    #[serde(default)]
+    pub number_misscheduled: Integer,
+    #[serde(default)]
+    pub number_ready: Integer,
+    #[serde(default)]
+    pub number_available: Integer,
+    #[serde(default)]
+    pub number_unavailable: Integer,
+    #[serde(default)]
+    pub updated_number_scheduled: Integer,
+    #[serde(default)]
+    pub observed_generation: Integer,
+    #[serde(default)]
+    pub collision_count: Integer,
+}
+
+pub struct ReplicaSets;
+
+impl NamespacedResource for ReplicaSets {
+    type List = ReplicaSetList;
+
+    fn namespaced(&self) -> bool {
+        true
+    }
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("replicasets")
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicaSet {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<ReplicaSet>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub spec: ReplicaSetSpec,
+    #[serde(default)]
+    pub status: ReplicaSetStatus,
+}
+
+pub type ReplicaSetList = ItemList<ReplicaSet>;
+
+impl TypeMeta for ReplicaSet {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "ReplicaSet"
+    }
+}
+
+impl Metadata for ReplicaSet {
+    fn api_version(&self) -> &str {
+        <ReplicaSet as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <ReplicaSet as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicaSetSpec {
+    #[serde(default = "int1")]
+    pub replicas: Integer,
+    #[serde(default)]
+    pub selector: LabelSelector,
+    #[serde(default)]
+    pub template: PodTemplateSpec,
+    #[serde(default)]
+    pub min_ready_seconds: Integer,
+}
+
+impl Default for ReplicaSetSpec {
+    fn default() -> Self {
+        ReplicaSetSpec {
+            replicas: int1(),
+            selector: Default::default(),
+            template: Default::default(),
+            min_ready_seconds: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicaSetStatus {
+    #[serde(default)]
+    pub replicas: Integer,
+    #[serde(default)]
+    pub fully_labeled_replicas: Integer,
+    #[serde(default)]
+    pub ready_replicas: Integer,
+    #[serde(default)]
+    pub available_replicas: Integer,
+    #[serde(default)]
+    pub observed_generation: Integer,
+}
+
+pub struct ControllerRevisions;
+
+impl NamespacedResource for ControllerRevisions {
+    type List = ControllerRevisionList;
+
+    fn namespaced(&self) -> bool {
+        true
+    }
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("controllerrevisions")
+    }
+}
+
+/// An immutable snapshot of a StatefulSet's or DaemonSet's template,
+/// kept to roll back to.  Owned by the StatefulSet/DaemonSet.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ControllerRevision {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<ControllerRevision>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    /// The serialized state, eg: a strategic merge patch holding the
+    /// pod template.  Opaque to the server.
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub data: Value,
+    /// Unlike most counts, an int64 (not an `Integer`).
+    #[serde(default)]
+    pub revision: i64,
+}
+
+pub type ControllerRevisionList = ItemList<ControllerRevision>;
+
+impl TypeMeta for ControllerRevision {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "ControllerRevision"
+    }
+}
+
+impl Metadata for ControllerRevision {
+    fn api_version(&self) -> &str {
+        <ControllerRevision as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <ControllerRevision as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+#[test]
+fn deser_deployment() {
+    let d: Deployment = ::serde_yaml::from_str(
+        r#"
+      apiVersion: apps/v1
+      kind: Deployment
+      metadata:
+        name: web
+        namespace: default
+        generation: 1
+      spec:
+        selector:
+          matchLabels:
+            app: web
+        template:
+          metadata:
+            labels:
+              app: web
+          spec:
+            containers:
+            - name: nginx
+              image: nginx
+      status: {}
+    "#,
+    )
+    .unwrap();
+
+    assert_eq!(d.spec.replicas, 1);
+    assert_eq!(d.spec.strategy.typ, DeploymentStrategyType::RollingUpdate);
+    assert_eq!(d.spec.template.spec.containers[0].name, "nginx");
+    assert_eq!(d.status, DeploymentStatus::default());
+
+    assert!(
+        ::serde_yaml::from_str::<Deployment>("apiVersion: apps/v1beta2\nkind: Deployment").is_err()
+    );
+}
+
+#[cfg(test)]
+fn round_trip<T>(json: ::serde_json::Value) -> T
+where
+    T: ::serde::Serialize + ::serde::de::DeserializeOwned + PartialEq + ::std::fmt::Debug,
+{
+    let o: T = ::serde_json::from_value(json.clone()).unwrap();
+    let again = ::serde_json::to_value(&o).unwrap();
+    assert_eq!(again["kind"], json["kind"]);
+    assert_eq!(again["metadata"]["name"], json["metadata"]["name"]);
+    assert_eq!(again["spec"]["replicas"], json["spec"]["replicas"]);
+    assert_eq!(
+        again["spec"]["selector"]["matchLabels"],
+        json["spec"]["selector"]["matchLabels"]
+    );
+    assert_eq!(
+        again["spec"]["template"]["metadata"]["labels"],
+        json["spec"]["template"]["metadata"]["labels"]
+    );
+    for (k, v) in json["status"].as_object().unwrap() {
+        assert_eq!(&again["status"][k], v, "status.{}", k);
+    }
+    assert_eq!(::serde_json::from_value::<T>(again).unwrap(), o);
+    o
+}
+
+#[cfg(test)]
+fn template() -> ::serde_json::Value {
+    json!({
+        "metadata": {"labels": {"app": "web"}},
+        "spec": {"containers": [{"name": "nginx", "image": "nginx"}]},
+    })
+}
+
+#[test]
+fn round_trip_statefulset() {
+    let s: StatefulSet = round_trip(json!({
+        "apiVersion": "apps/v1",
+        "kind": "StatefulSet",
+        "metadata": {"name": "web"},
+        "spec": {
+            "replicas": 3,
+            "serviceName": "web",
+            "podManagementPolicy": "Parallel",
+            "selector": {"matchLabels": {"app": "web"}},
+            "template": template(),
+        },
+        "status": {
+            "replicas": 3,
+            "readyReplicas": 2,
+            "currentReplicas": 3,
+            "updatedReplicas": 1,
+            "currentRevision": "web-6b9f8c7d4",
+            "updateRevision": "web-5d8c9b7f6",
+        },
+    }));
+    assert_eq!(s.spec.service_name, "web");
+    assert_eq!(
+        s.spec.pod_management_policy,
+        PodManagementPolicyType::Parallel
+    );
+    assert_eq!(s.status.ready_replicas, 2);
+    assert_eq!(s.status.update_revision, Some("web-5d8c9b7f6".into()));
+}
+
+#[test]
+fn round_trip_daemonset() {
+    let d: DaemonSet = round_trip(json!({
+        "apiVersion": "apps/v1",
+        "kind": "DaemonSet",
+        "metadata": {"name": "web"},
+        "spec": {
+            "selector": {"matchLabels": {"app": "web"}},
+            "template": template(),
+        },
+        "status": {
+            "currentNumberScheduled": 3,
+            "desiredNumberScheduled": 3,
+            "numberMisscheduled": 0,
+            "numberReady": 2,
+            "numberUnavailable": 1,
+        },
+    }));
+    assert_eq!(d.spec.revision_history_limit, 10);
+    assert_eq!(d.status.desired_number_scheduled, 3);
+    assert_eq!(d.status.number_ready, 2);
+    assert_eq!(d.status.number_available, 0);
+}
+
+#[test]
+fn round_trip_replicaset() {
+    let r: ReplicaSet = round_trip(json!({
+        "apiVersion": "apps/v1",
+        "kind": "ReplicaSet",
+        "metadata": {"name": "web-6b9f8c7d4"},
+        "spec": {
+            "replicas": 2,
+            "selector": {"matchLabels": {"app": "web"}},
+            "template": template(),
+        },
+        "status": {
+            "replicas": 2,
+            "fullyLabeledReplicas": 2,
+            "readyReplicas": 2,
+            "availableReplicas": 2,
+            "observedGeneration": 1,
+        },
+    }));
+    assert_eq!(r.spec.replicas, 2);
+    assert_eq!(r.status.available_replicas, 2);
+
+    let r: ReplicaSet = ::serde_json::from_str("{}").unwrap();
+    assert_eq!(r.spec.replicas, 1);
+}
+
+#[test]
+fn deser_controller_revision() {
+    let yaml = r#"
+      apiVersion: apps/v1
+      kind: ControllerRevision
+      metadata:
+        name: web-6b9f8c7d4
+        namespace: default
+        labels:
+          app: web
+          controller-revision-hash: 6b9f8c7d4
+        ownerReferences:
+        - apiVersion: apps/v1
+          kind: StatefulSet
+          name: web
+          uid: 0f3c7c8e-1b2d-4e5f-8a9b-0c1d2e3f4a5b
+          controller: true
+          blockOwnerDeletion: true
+      data:
+        spec:
+          template:
+            $patch: replace
+            metadata:
+              labels:
+                app: web
+            spec:
+              containers:
+              - name: nginx
+                image: nginx:1.19
+      revision: 4294967298
+"#;
+    let r: ControllerRevision = ::serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(r.metadata.name, Some("web-6b9f8c7d4".into()));
+    assert_eq!(r.revision, 4_294_967_298);
+    assert_eq!(
+        r.data["spec"]["template"]["spec"]["containers"][0]["image"],
+        "nginx:1.19"
+    );
+
+    let json = ::serde_json::to_value(&r).unwrap();
+    assert_eq!(json["kind"], "ControllerRevision");
+    assert_eq!(json["revision"], 4_294_967_298i64);
+    assert_eq!(
+        ::serde_json::from_value::<ControllerRevision>(json).unwrap(),
+        r
+    );
+}
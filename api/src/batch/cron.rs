@@ -0,0 +1,239 @@
+//! Parsing and evaluation of the cron expressions used in
+//! `CronJobSpec.schedule`.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use std::str::FromStr;
+
+const MONTHS: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAYS: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Give up looking for a next run this far into the future (eg: for
+/// "0 0 30 2 *", which never matches).
+const MAX_YEARS: i32 = 5;
+
+#[derive(Debug, Fail)]
+#[fail(display = "invalid cron schedule {:?}: {}", schedule, reason)]
+pub struct InvalidScheduleError {
+    pub schedule: String,
+    pub reason: String,
+}
+
+/// A parsed standard 5-field cron schedule (minute, hour, day of
+/// month, month, day of week), as accepted by the CronJob controller.
+/// Ranges, steps, lists, month/day names and the `@hourly` style
+/// macros are supported.  Schedules are evaluated in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    // Bitmasks of matching values
+    minute: u64,
+    hour: u64,
+    dom: u64,
+    month: u64,
+    dow: u64,
+    // Day of month and day of week combine with OR, unless one is "*"
+    dom_star: bool,
+    dow_star: bool,
+}
+
+fn parse_value(s: &str, names: &[&str], offset: u32) -> Result<u32, String> {
+    if let Some(i) = names.iter().position(|n| n.eq_ignore_ascii_case(s)) {
+        return Ok(i as u32 + offset);
+    }
+    s.parse().map_err(|_| format!("invalid value {:?}", s))
+}
+
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.find('/') {
+            Some(i) => {
+                let step: u32 = part[i + 1..]
+                    .parse()
+                    .map_err(|_| format!("invalid step in {:?}", part))?;
+                if step == 0 {
+                    return Err(format!("zero step in {:?}", part));
+                }
+                (&part[..i], Some(step))
+            }
+            None => (part, None),
+        };
+        let (lo, hi) = if range == "*" || range == "?" {
+            (min, max)
+        } else if let Some(i) = range.find('-') {
+            (
+                parse_value(&range[..i], names, min)?,
+                parse_value(&range[i + 1..], names, min)?,
+            )
+        } else {
+            let v = parse_value(range, names, min)?;
+            // "5/10" means "5-max/10"
+            (v, if step.is_some() { max } else { v })
+        };
+        if lo < min || hi > max || lo > hi {
+            return Err(format!("{:?} out of range {}-{}", part, min, max));
+        }
+        for v in (lo..hi + 1).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << v;
+        }
+    }
+    Ok(bits)
+}
+
+fn is_star(field: &str) -> bool {
+    field.starts_with('*') || field.starts_with('?')
+}
+
+fn bit(bits: u64, v: u32) -> bool {
+    bits & (1 << v) != 0
+}
+
+impl Schedule {
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let dom = bit(self.dom, date.day());
+        let dow = bit(self.dow, date.weekday().num_days_from_sunday());
+        if self.dom_star || self.dow_star {
+            dom && dow
+        } else {
+            dom || dow
+        }
+    }
+
+    /// Returns the first time strictly after `after` matching this
+    /// schedule, or `None` if there isn't one in the next few years.
+    pub fn next_after(&self, after: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let after = after.naive_utc();
+        let mut t: NaiveDateTime =
+            after.date().and_hms_opt(after.hour(), after.minute(), 0)? + Duration::minutes(1);
+        let limit = t.year() + MAX_YEARS;
+        while t.year() <= limit {
+            if !bit(self.month, t.month()) {
+                let (y, m) = if t.month() == 12 {
+                    (t.year() + 1, 1)
+                } else {
+                    (t.year(), t.month() + 1)
+                };
+                t = NaiveDate::from_ymd_opt(y, m, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.day_matches(t.date()) {
+                t = t.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !bit(self.hour, t.hour()) {
+                t = t.date().and_hms_opt(t.hour(), 0, 0)? + Duration::hours(1);
+            } else if !bit(self.minute, t.minute()) {
+                t = t + Duration::minutes(1);
+            } else {
+                return Some(Utc.from_utc_datetime(&t));
+            }
+        }
+        None
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = InvalidScheduleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = |reason: String| InvalidScheduleError {
+            schedule: s.to_string(),
+            reason: reason,
+        };
+        let expanded = match s.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields: Vec<_> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(err(format!("expected 5 fields, found {}", fields.len())));
+        }
+
+        let mut dow = parse_field(fields[4], 0, 7, DAYS).map_err(&err)?;
+        if bit(dow, 7) {
+            // Both 0 and 7 are Sunday
+            dow = (dow | 1) & !(1 << 7);
+        }
+        Ok(Schedule {
+            minute: parse_field(fields[0], 0, 59, &[]).map_err(&err)?,
+            hour: parse_field(fields[1], 0, 23, &[]).map_err(&err)?,
+            dom: parse_field(fields[2], 1, 31, &[]).map_err(&err)?,
+            month: parse_field(fields[3], 1, 12, MONTHS).map_err(&err)?,
+            dow: dow,
+            dom_star: is_star(fields[2]),
+            dow_star: is_star(fields[4]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn next(schedule: &str, after: &str) -> Option<String> {
+        let schedule: Schedule = schedule.parse().unwrap();
+        schedule.next_after(&utc(after)).map(|t| t.to_rfc3339())
+    }
+
+    #[test]
+    fn next_after() {
+        let next_at = |s, t| next(s, t).unwrap();
+        assert_eq!(
+            next_at("*/5 * * * *", "2018-02-17T23:21:30Z"),
+            "2018-02-17T23:25:00+00:00"
+        );
+        // Strictly after
+        assert_eq!(
+            next_at("*/5 * * * *", "2018-02-17T23:55:00Z"),
+            "2018-02-18T00:00:00+00:00"
+        );
+        assert_eq!(
+            next_at("30 9 * * mon-fri", "2018-02-17T23:21:30Z"),
+            "2018-02-19T09:30:00+00:00"
+        );
+        // Day of month OR day of week: the 13th, or any Friday
+        assert_eq!(
+            next_at("0 0 13 * 5", "2018-02-10T00:00:00Z"),
+            "2018-02-13T00:00:00+00:00"
+        );
+        assert_eq!(
+            next_at("0 0 13 * 5", "2018-02-13T00:00:00Z"),
+            "2018-02-16T00:00:00+00:00"
+        );
+        assert_eq!(
+            next_at("@monthly", "2018-12-31T23:59:59Z"),
+            "2019-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            next_at("0 12 29 feb *", "2018-03-01T00:00:00Z"),
+            "2020-02-29T12:00:00+00:00"
+        );
+        assert_eq!(next("0 0 30 2 *", "2018-01-01T00:00:00Z"), None);
+    }
+
+    #[test]
+    fn invalid() {
+        for s in &[
+            "",
+            "* * * *",
+            "* * * * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "* * * foo *",
+        ] {
+            assert!(s.parse::<Schedule>().is_err(), "{:?} parsed", s);
+        }
+        let err = "61 * * * *".parse::<Schedule>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid cron schedule \"61 * * * *\": \"61\" out of range 0-59"
+        );
+    }
+}
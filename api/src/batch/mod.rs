@@ -0,0 +1,5 @@
+mod cron;
+pub mod v1;
+pub mod v1beta1;
+
+pub use self::cron::{InvalidScheduleError, Schedule};
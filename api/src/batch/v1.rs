@@ -0,0 +1,216 @@
+use crate::core::v1::{ConditionStatus, NamespacedResource, PodTemplateSpec};
+use crate::meta::v1::{ItemList, LabelSelector, Metadata, ObjectMeta};
+use crate::meta::{GroupVersion, GroupVersionResource};
+use crate::{Integer, Time, TypeMeta, TypeMetaImpl};
+use std::borrow::Cow;
+
+const API_GROUP: &str = "batch/v1";
+pub const GROUP_VERSION: GroupVersion = GroupVersion {
+    group: "batch",
+    version: "v1",
+};
+
+pub struct Jobs;
+
+impl NamespacedResource for Jobs {
+    type List = JobList;
+
+    fn namespaced(&self) -> bool {
+        true
+    }
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("jobs")
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<Job>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub spec: JobSpec,
+    #[serde(default)]
+    pub status: JobStatus,
+}
+
+pub type JobList = ItemList<Job>;
+
+impl TypeMeta for Job {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "Job"
+    }
+}
+
+impl Metadata for Job {
+    fn api_version(&self) -> &str {
+        <Job as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <Job as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+impl Job {
+    /// The condition of type `typ`, if the job has one.
+    pub fn condition(&self, typ: JobConditionType) -> Option<&JobCondition> {
+        self.status.conditions.iter().find(|c| c.typ == typ)
+    }
+
+    fn has_condition(&self, typ: JobConditionType) -> bool {
+        self.condition(typ)
+            .map(|c| c.status == ConditionStatus::True)
+            .unwrap_or(false)
+    }
+
+    /// True once the job has succeeded.
+    pub fn is_complete(&self) -> bool {
+        self.has_condition(JobConditionType::Complete)
+    }
+
+    /// True once the job has given up, eg: after `backoffLimit`
+    /// retries or `activeDeadlineSeconds`.
+    pub fn is_failed(&self) -> bool {
+        self.has_condition(JobConditionType::Failed)
+    }
+}
+
+fn int6() -> Integer {
+    6
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobSpec {
+    /// The most pods to run at once.  Defaults to 1.
+    pub parallelism: Option<Integer>,
+    /// How many pods must succeed.  If unset, the job is complete as
+    /// soon as any pod succeeds (a work queue).
+    pub completions: Option<Integer>,
+    pub active_deadline_seconds: Option<i64>,
+    #[serde(default = "int6")]
+    pub backoff_limit: Integer,
+    /// Generated by the server, unless `manualSelector` is set.
+    pub selector: Option<LabelSelector>,
+    pub manual_selector: Option<bool>,
+    #[serde(rename = "ttlSecondsAfterFinished")]
+    pub ttl_seconds_after_finished: Option<Integer>,
+    #[serde(default)]
+    pub template: PodTemplateSpec,
+}
+
+impl Default for JobSpec {
+    fn default() -> Self {
+        JobSpec {
+            parallelism: None,
+            completions: None,
+            active_deadline_seconds: None,
+            backoff_limit: int6(),
+            selector: None,
+            manual_selector: None,
+            ttl_seconds_after_finished: None,
+            template: Default::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatus {
+    #[serde(default)]
+    pub conditions: Vec<JobCondition>,
+    pub start_time: Option<Time>,
+    pub completion_time: Option<Time>,
+    /// Pods currently running.
+    #[serde(default)]
+    pub active: Integer,
+    #[serde(default)]
+    pub succeeded: Integer,
+    #[serde(default)]
+    pub failed: Integer,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobCondition {
+    #[serde(rename = "type")]
+    pub typ: JobConditionType,
+    pub status: ConditionStatus,
+    pub last_probe_time: Option<Time>,
+    pub last_transition_time: Option<Time>,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum JobConditionType {
+    Complete,
+    Failed,
+    Suspended,
+}
+
+#[test]
+fn deser_completed_job() {
+    let yaml = r#"
+      apiVersion: batch/v1
+      kind: Job
+      metadata:
+        name: pi
+        namespace: default
+        labels:
+          controller-uid: 2c5b5e6a-7f5b-4b8c-9e0a-7d9c1e3f4a5b
+          job-name: pi
+      spec:
+        backoffLimit: 4
+        completions: 1
+        parallelism: 1
+        selector:
+          matchLabels:
+            controller-uid: 2c5b5e6a-7f5b-4b8c-9e0a-7d9c1e3f4a5b
+        template:
+          metadata:
+            labels:
+              job-name: pi
+          spec:
+            containers:
+            - name: pi
+              image: perl
+              command: ["perl", "-Mbignum=bpi", "-wle", "print bpi(2000)"]
+            restartPolicy: Never
+      status:
+        completionTime: 2020-06-01T10:00:42Z
+        conditions:
+        - lastProbeTime: 2020-06-01T10:00:42Z
+          lastTransitionTime: 2020-06-01T10:00:42Z
+          status: "True"
+          type: Complete
+        startTime: 2020-06-01T10:00:05Z
+        succeeded: 1
+"#;
+    let job: Job = ::serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(job.spec.backoff_limit, 4);
+    assert_eq!(job.spec.completions, Some(1));
+    assert_eq!(job.spec.template.spec.containers[0].name, "pi");
+    assert_eq!(job.status.succeeded, 1);
+    assert_eq!(job.status.active, 0);
+    assert_eq!(job.status.failed, 0);
+    assert_eq!(
+        job.status.completion_time,
+        Some("2020-06-01T10:00:42Z".parse().unwrap())
+    );
+    assert!(job.is_complete());
+    assert!(!job.is_failed());
+
+    let job: Job = ::serde_yaml::from_str("spec: {}\nstatus: {}").unwrap();
+    assert_eq!(job.spec.backoff_limit, 6);
+    assert!(!job.is_complete());
+}
@@ -0,0 +1,161 @@
+use super::v1::JobSpec;
+use super::{InvalidScheduleError, Schedule};
+use crate::core::v1::{NamespacedResource, ObjectReference};
+use crate::meta::v1::{ItemList, Metadata, ObjectMeta};
+use crate::meta::{GroupVersion, GroupVersionResource};
+use crate::{Integer, Time, TypeMeta, TypeMetaImpl};
+use std::borrow::Cow;
+
+const API_GROUP: &str = "batch/v1beta1";
+pub const GROUP_VERSION: GroupVersion = GroupVersion {
+    group: "batch",
+    version: "v1beta1",
+};
+
+pub struct CronJobs;
+
+impl NamespacedResource for CronJobs {
+    type List = CronJobList;
+
+    fn namespaced(&self) -> bool {
+        true
+    }
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("cronjobs")
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CronJob {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<CronJob>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub spec: CronJobSpec,
+    #[serde(default)]
+    pub status: CronJobStatus,
+}
+
+pub type CronJobList = ItemList<CronJob>;
+
+impl TypeMeta for CronJob {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "CronJob"
+    }
+}
+
+impl Metadata for CronJob {
+    fn api_version(&self) -> &str {
+        <CronJob as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <CronJob as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CronJobSpec {
+    pub schedule: String,
+    pub starting_deadline_seconds: Option<i64>,
+    #[serde(default)]
+    pub concurrency_policy: ConcurrencyPolicy,
+    #[serde(default)]
+    pub suspend: bool,
+    pub job_template: JobTemplateSpec,
+    pub successful_jobs_history_limit: Option<Integer>,
+    pub failed_jobs_history_limit: Option<Integer>,
+}
+
+impl CronJobSpec {
+    /// Checks that `schedule` is a valid cron expression.
+    pub fn validate_schedule(&self) -> Result<(), InvalidScheduleError> {
+        self.schedule.parse::<Schedule>().map(|_| ())
+    }
+
+    /// The first time after `after` that this schedule fires, ignoring
+    /// `suspend`.  Returns `None` if the schedule is invalid, or never
+    /// fires.
+    pub fn next_run_after(&self, after: &Time) -> Option<Time> {
+        let schedule: Schedule = self.schedule.parse().ok()?;
+        schedule.next_after(after).map(Time)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ConcurrencyPolicy {
+    Allow,
+    Forbid,
+    Replace,
+}
+
+impl Default for ConcurrencyPolicy {
+    fn default() -> Self {
+        ConcurrencyPolicy::Allow
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobTemplateSpec {
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub spec: JobSpec,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CronJobStatus {
+    #[serde(default)]
+    pub active: Vec<ObjectReference>,
+    pub last_schedule_time: Option<Time>,
+}
+
+#[test]
+fn cronjob_schedule() {
+    let yaml = r#"
+      apiVersion: batch/v1beta1
+      kind: CronJob
+      metadata:
+        name: hello
+      spec:
+        schedule: "*/5 * * * *"
+        jobTemplate:
+          spec:
+            template:
+              spec:
+                containers:
+                - name: hello
+                  image: busybox
+                restartPolicy: OnFailure
+"#;
+    let cj: CronJob = ::serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(cj.spec.concurrency_policy, ConcurrencyPolicy::Allow);
+    assert_eq!(cj.spec.job_template.spec.backoff_limit, 6);
+    assert!(cj.spec.validate_schedule().is_ok());
+    assert_eq!(
+        cj.spec
+            .next_run_after(&"2018-02-17T23:21:30Z".parse().unwrap()),
+        Some("2018-02-17T23:25:00Z".parse().unwrap())
+    );
+
+    let bad = CronJobSpec {
+        schedule: "*/5 * * *".into(),
+        ..cj.spec
+    };
+    assert!(bad.validate_schedule().is_err());
+    assert_eq!(
+        bad.next_run_after(&"2018-02-17T23:21:30Z".parse().unwrap()),
+        None
+    );
+}
@@ -0,0 +1,109 @@
+//! Serialization in the API server's canonical form, so objects
+//! written by this crate diff cleanly against `kubectl get -o json`.
+//!
+//! Struct fields serialize in declaration order, which differs from
+//! the server's alphabetical order, and unset fields serialize as
+//! `null`, `{}`, `[]`, `false` or `0`, where the server omits them.
+//! These functions sort object keys at every level and drop those
+//! empty members.
+
+use serde::Serialize;
+use serde_json::{self, Map, Value};
+use std::io;
+
+/// Whether the server would omit `v` as an empty member (Go's
+/// `omitempty`).
+fn is_empty(v: &Value) -> bool {
+    match *v {
+        Value::Null => true,
+        Value::Bool(b) => !b,
+        Value::Number(ref n) => n.as_f64() == Some(0.0),
+        Value::Array(ref items) => items.is_empty(),
+        Value::Object(ref map) => map.is_empty(),
+        Value::String(_) => false,
+    }
+}
+
+/// Sorts object keys and removes empty members, recursively.
+pub fn canonicalize(v: Value) -> Value {
+    match v {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map
+                .into_iter()
+                .map(|(k, v)| (k, canonicalize(v)))
+                .filter(|&(_, ref v)| !is_empty(v))
+                .collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            // Insert in order, in case serde_json's `preserve_order` is on
+            let mut sorted = Map::new();
+            for (k, v) in entries {
+                sorted.insert(k, v);
+            }
+            Value::Object(sorted)
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(canonicalize).collect()),
+        v => v,
+    }
+}
+
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, serde_json::Error> {
+    serde_json::to_value(value).map(canonicalize)
+}
+
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&to_value(value)?)
+}
+
+/// Indented like `kubectl get -o json`.
+pub fn to_string_pretty<T: Serialize + ?Sized>(value: &T) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(&to_value(value)?)
+}
+
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<(), serde_json::Error>
+where
+    W: io::Write,
+    T: Serialize + ?Sized,
+{
+    serde_json::to_writer(writer, &to_value(value)?)
+}
+
+#[test]
+fn canonical_pod() {
+    use crate::core::v1::Pod;
+
+    let pod: Pod = ::serde_yaml::from_str(
+        r#"
+      apiVersion: v1
+      kind: Pod
+      metadata:
+        name: web
+        namespace: default
+        labels:
+          tier: frontend
+          app: web
+      spec:
+        containers:
+        - name: nginx
+          image: nginx:1.19
+          ports:
+          - containerPort: 80
+    "#,
+    )
+    .unwrap();
+
+    let json = to_string(&pod).unwrap();
+    let expected = concat!(
+        r#"{"apiVersion":"v1","kind":"Pod","#,
+        r#""metadata":{"labels":{"app":"web","tier":"frontend"},"name":"web","namespace":"default"},"#,
+        r#""spec":{"containers":[{"image":"nginx:1.19","name":"nginx","#,
+        r#""ports":[{"containerPort":80,"protocol":"TCP"}],"#,
+        r#""terminationMessagePath":"/dev/termination-log","#,
+        r#""terminationMessagePolicy":"File"}],"#,
+        r#""dnsPolicy":"ClusterFirst","restartPolicy":"Always","#,
+        r#""terminationGracePeriodSeconds":30}}"#,
+    );
+    assert_eq!(json, expected);
+
+    let v: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(v, to_value(&pod).unwrap());
+}
@@ -1,11 +1,16 @@
-use crate::meta::v1::{ItemList, LabelSelector, List, Metadata, ObjectMeta};
+use crate::meta::v1::{ItemList, LabelSelector, List, Metadata, ObjectMeta, ResourceVersion};
 use crate::meta::{GroupVersion, GroupVersionResource};
-use crate::{IntOrString, Integer, Quantity, Time, TypeMeta, TypeMetaImpl};
-use serde_json::{self, Map, Value};
+use crate::{
+    serde_base64, IntOrString, Integer, MicroTime, Quantity, Time, TypeMeta, TypeMetaImpl,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{self, Value};
 use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::default::Default;
-
-// TODO(gus): Uses of serde_json::{Map,Value} below are probably incorrect.
+use std::net::IpAddr;
 
 const API_GROUP: &str = "v1";
 pub const GROUP_VERSION: GroupVersion = GroupVersion {
@@ -48,7 +53,7 @@ impl NamespacedResource for Pods {
 #[serde(rename_all = "camelCase")]
 pub struct Namespace {
     #[serde(flatten)]
-    typemeta: TypeMetaImpl<Pod>,
+    typemeta: TypeMetaImpl<Namespace>,
     #[serde(default)]
     pub metadata: ObjectMeta,
     #[serde(default)]
@@ -165,10 +170,58 @@ impl Metadata for Pod {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+impl Pod {
+    /// A pod running one container, named after the pod, from `image`.
+    /// Everything else is left to the server's defaults.  Mostly
+    /// useful for examples and tests.
+    pub fn new(name: &str, namespace: &str, image: &str) -> Self {
+        Pod {
+            metadata: ObjectMeta {
+                name: Some(name.into()),
+                namespace: Some(namespace.into()),
+                ..Default::default()
+            },
+            spec: PodSpec {
+                containers: vec![Container {
+                    name: name.into(),
+                    image: Some(image.into()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// True if the pod's `Ready` condition is `True`, and so is the
+    /// condition named by each of its `spec.readinessGates`.
+    ///
+    /// The kubelet only considers readiness gates once all containers
+    /// are ready, and external controllers (eg: load balancers) set the
+    /// gate conditions, so `Ready` alone may not mean the pod should
+    /// receive traffic yet.
+    pub fn is_fully_ready(&self) -> bool {
+        let is_true = |typ: &PodConditionType| {
+            self.status
+                .conditions
+                .iter()
+                .any(|c| c.typ == *typ && c.status == ConditionStatus::True)
+        };
+        is_true(&PodConditionType::Ready)
+            && self
+                .spec
+                .readiness_gates
+                .iter()
+                .all(|g| is_true(&g.condition_type))
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct PodTemplateSpec {
+    #[serde(default)]
     pub metadata: ObjectMeta,
+    #[serde(default)]
     pub spec: PodSpec,
 }
 
@@ -196,10 +249,13 @@ pub struct PodSpec {
     #[serde(default)]
     pub init_containers: Vec<Container>,
     pub node_name: Option<String>,
-    #[serde(default)]
-    pub node_selector: Map<String, Value>,
+    /// Labels a node must have for the pod to be scheduled there.
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub node_selector: BTreeMap<String, String>,
     pub priority: Option<Integer>,
     pub priority_class_name: Option<String>,
+    #[serde(default)]
+    pub readiness_gates: Vec<PodReadinessGate>,
     #[serde(default = "always")]
     pub restart_policy: RestartPolicy,
     pub scheduler_name: Option<String>,
@@ -215,17 +271,65 @@ pub struct PodSpec {
     pub volumes: Vec<Volume>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PodReadinessGate {
+    pub condition_type: PodConditionType,
+}
+
 impl Default for PodSpec {
     fn default() -> Self {
         serde_json::from_value(Value::Object(Default::default())).unwrap()
     }
 }
 
+impl PodSpec {
+    /// Requires the node to have label `key` set to `value`, replacing
+    /// any previous requirement on `key`.
+    pub fn add_node_selector<K, V>(&mut self, key: K, value: V) -> &mut Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.node_selector.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// Treats an explicit `null` (as sent by some clients for an empty
+/// map) like a missing field, as the server does.
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Option::<T>::deserialize(deserializer).map(Option::unwrap_or_default)
+}
+
 #[test]
 fn podspec_default() {
     let _: PodSpec = Default::default();
 }
 
+#[test]
+fn podspec_node_selector() {
+    let mut spec = PodSpec::default();
+    spec.add_node_selector("disktype", "ssd")
+        .add_node_selector("kubernetes.io/os", "linux");
+    let v = serde_json::to_value(&spec).unwrap();
+    assert_eq!(
+        v["nodeSelector"],
+        json!({"disktype": "ssd", "kubernetes.io/os": "linux"})
+    );
+    assert_eq!(serde_json::from_value::<PodSpec>(v).unwrap(), spec);
+
+    let spec: PodSpec = serde_json::from_value(json!({"nodeSelector": null})).unwrap();
+    assert!(spec.node_selector.is_empty());
+
+    assert!(serde_json::from_value::<PodSpec>(json!({"nodeSelector": {"gpus": 2}})).is_err());
+    assert!(serde_json::from_value::<PodSpec>(json!({"nodeSelector": ["ssd"]})).is_err());
+}
+
 fn clusterfirst() -> DNSPolicy {
     DNSPolicy::ClusterFirst
 }
@@ -445,7 +549,7 @@ pub struct ObjectFieldSelector {
 }
 
 fn quant1() -> Quantity {
-    "1".into()
+    "1".parse().unwrap()
 }
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
@@ -589,15 +693,16 @@ fn tcp() -> Protocol {
 pub enum Protocol {
     TCP,
     UDP,
+    SCTP,
 }
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ResourceRequirements {
     #[serde(default)]
-    pub limits: Map<String, Value>,
+    pub limits: BTreeMap<String, Quantity>,
     #[serde(default)]
-    pub requests: Map<String, Value>,
+    pub requests: BTreeMap<String, Quantity>,
 }
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
@@ -660,6 +765,18 @@ pub struct LocalObjectReference {
     pub name: String,
 }
 
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectReference {
+    pub api_version: Option<String>,
+    pub field_path: Option<String>,
+    pub kind: Option<String>,
+    pub name: Option<String>,
+    pub namespace: Option<String>,
+    pub resource_version: Option<ResourceVersion>,
+    pub uid: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct PodSecurityContext {
@@ -863,13 +980,60 @@ pub struct PodStatus {
     pub qos_class: Option<PodQOSClass>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+/// Condition types set by Kubernetes itself, or `Other` for custom
+/// conditions (eg: those named by readiness gates).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum PodConditionType {
     ContainersReady,
     Initialized,
     PodScheduled,
     Ready,
     Unschedulable,
+    Other(String),
+}
+
+impl PodConditionType {
+    pub fn as_str(&self) -> &str {
+        match *self {
+            PodConditionType::ContainersReady => "ContainersReady",
+            PodConditionType::Initialized => "Initialized",
+            PodConditionType::PodScheduled => "PodScheduled",
+            PodConditionType::Ready => "Ready",
+            PodConditionType::Unschedulable => "Unschedulable",
+            PodConditionType::Other(ref s) => s,
+        }
+    }
+}
+
+impl From<String> for PodConditionType {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "ContainersReady" => PodConditionType::ContainersReady,
+            "Initialized" => PodConditionType::Initialized,
+            "PodScheduled" => PodConditionType::PodScheduled,
+            "Ready" => PodConditionType::Ready,
+            "Unschedulable" => PodConditionType::Unschedulable,
+            _ => PodConditionType::Other(s),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for PodConditionType {
+    fn from(s: &'a str) -> Self {
+        PodConditionType::from(s.to_string())
+    }
+}
+
+impl Serialize for PodConditionType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for PodConditionType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(PodConditionType::from)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -888,7 +1052,9 @@ pub struct PodCondition {
 #[serde(rename_all = "camelCase")]
 pub struct ContainerStatus {
     pub name: String,
+    #[serde(default, deserialize_with = "empty_as_none")]
     pub state: Option<ContainerState>,
+    #[serde(rename = "lastState", default, deserialize_with = "empty_as_none")]
     pub last_termination_state: Option<ContainerState>,
     pub ready: bool,
     pub restart_count: Integer,
@@ -945,10 +1111,707 @@ pub struct ContainerStateTerminated {
     pub message: Option<String>,
     pub started_at: Option<Time>,
     pub finished_at: Option<Time>,
-    #[serde(rename = "container_ID")]
+    #[serde(rename = "containerID")]
     pub container_id: Option<String>,
 }
 
+/// The server sends `{}` rather than omitting a container state that
+/// has not happened (eg: `lastState` before the first restart).
+fn empty_as_none<'de, D>(deserializer: D) -> Result<Option<ContainerState>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+    match Option::<Value>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Value::Object(ref m)) if m.is_empty() => Ok(None),
+        Some(v) => serde_json::from_value(v)
+            .map(Some)
+            .map_err(D::Error::custom),
+    }
+}
+
+pub struct Nodes;
+
+impl Resource for Nodes {
+    type List = NodeList;
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("nodes")
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Node {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<Node>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub spec: NodeSpec,
+    #[serde(default)]
+    pub status: NodeStatus,
+}
+
+pub type NodeList = ItemList<Node>;
+
+impl TypeMeta for Node {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "Node"
+    }
+}
+
+impl Metadata for Node {
+    fn api_version(&self) -> &str {
+        <Node as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <Node as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+impl Node {
+    /// The condition of type `typ`, eg: `"Ready"` or `"DiskPressure"`.
+    pub fn condition(&self, typ: &str) -> Option<&NodeCondition> {
+        self.status.conditions.iter().find(|c| c.typ == typ)
+    }
+
+    /// True if the `Ready` condition is `True`.  A node that has
+    /// stopped reporting has `Unknown` readiness, which isn't ready.
+    pub fn is_ready(&self) -> bool {
+        self.condition("Ready")
+            .map(|c| c.status == ConditionStatus::True)
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeSpec {
+    #[serde(rename = "podCIDR")]
+    pub pod_cidr: Option<String>,
+    #[serde(default, rename = "podCIDRs")]
+    pub pod_cidrs: Vec<String>,
+    #[serde(rename = "providerID")]
+    pub provider_id: Option<String>,
+    #[serde(default)]
+    pub taints: Vec<Taint>,
+    /// Set by `kubectl cordon`.
+    #[serde(default)]
+    pub unschedulable: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Taint {
+    pub key: String,
+    pub value: Option<String>,
+    pub effect: TaintEffect,
+    /// Only for `NoExecute` taints.
+    pub time_added: Option<Time>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeStatus {
+    /// Total resources of the node.
+    #[serde(default)]
+    pub capacity: BTreeMap<String, Quantity>,
+    /// Resources available to pods, after system reservations.
+    #[serde(default)]
+    pub allocatable: BTreeMap<String, Quantity>,
+    #[serde(default)]
+    pub conditions: Vec<NodeCondition>,
+    #[serde(default)]
+    pub addresses: Vec<NodeAddress>,
+    pub node_info: Option<NodeSystemInfo>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeCondition {
+    /// Eg: `Ready`, `MemoryPressure`, `DiskPressure`, `PIDPressure`, or
+    /// a custom condition from a node problem detector.
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub status: ConditionStatus,
+    pub last_heartbeat_time: Option<Time>,
+    pub last_transition_time: Option<Time>,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeAddress {
+    /// Eg: `InternalIP`, `ExternalIP` or `Hostname`.
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub address: String,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeSystemInfo {
+    pub architecture: String,
+    #[serde(rename = "bootID")]
+    pub boot_id: String,
+    pub container_runtime_version: String,
+    pub kernel_version: String,
+    pub kube_proxy_version: String,
+    pub kubelet_version: String,
+    #[serde(rename = "machineID")]
+    pub machine_id: String,
+    pub operating_system: String,
+    pub os_image: String,
+    #[serde(rename = "systemUUID")]
+    pub system_uuid: String,
+}
+
+/// Options for the pod `exec` subresource.  NB: `command` repeats in
+/// the query string (`command=sh&command=-c&...`), which
+/// `serde_urlencoded` can't express, so see `query_pairs`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PodExecOptions {
+    pub stdin: bool,
+    pub stdout: bool,
+    pub stderr: bool,
+    pub tty: bool,
+    /// Defaults to the only container, if there is only one.
+    pub container: Option<String>,
+    pub command: Vec<String>,
+}
+
+impl PodExecOptions {
+    /// The query parameters, in order.
+    pub fn query_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut ret = vec![];
+        for &(k, v) in &[
+            ("stdin", self.stdin),
+            ("stdout", self.stdout),
+            ("stderr", self.stderr),
+            ("tty", self.tty),
+        ] {
+            if v {
+                ret.push((k, "true".to_string()));
+            }
+        }
+        if let Some(ref c) = self.container {
+            ret.push(("container", c.clone()));
+        }
+        for c in &self.command {
+            ret.push(("command", c.clone()));
+        }
+        ret
+    }
+}
+
+/// Options for the pod `portforward` subresource.  Like
+/// `PodExecOptions`, see `query_pairs`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PodPortForwardOptions {
+    pub ports: Vec<u16>,
+}
+
+impl PodPortForwardOptions {
+    /// The query parameters, in order.
+    pub fn query_pairs(&self) -> Vec<(&'static str, String)> {
+        self.ports.iter().map(|p| ("ports", p.to_string())).collect()
+    }
+}
+
+/// Options for the pod `log` subresource.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PodLogOptions {
+    /// Defaults to the only container, if there is only one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    /// Keep streaming as the container writes more.
+    #[serde(skip_serializing_if = "is_false")]
+    pub follow: bool,
+    /// Logs of the previous, terminated, container instance.
+    #[serde(skip_serializing_if = "is_false")]
+    pub previous: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since_seconds: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since_time: Option<Time>,
+    /// Prefix each line with an RFC3339 timestamp.
+    #[serde(skip_serializing_if = "is_false")]
+    pub timestamps: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tail_lines: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_bytes: Option<i64>,
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+pub struct EndpointsResource;
+
+impl NamespacedResource for EndpointsResource {
+    type List = EndpointsList;
+
+    fn namespaced(&self) -> bool {
+        true
+    }
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("endpoints")
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Endpoints {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<Endpoints>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub subsets: Vec<EndpointSubset>,
+}
+
+pub type EndpointsList = ItemList<Endpoints>;
+
+impl TypeMeta for Endpoints {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "Endpoints"
+    }
+}
+
+impl Metadata for Endpoints {
+    fn api_version(&self) -> &str {
+        <Endpoints as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <Endpoints as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+impl Endpoints {
+    /// Every address:port pair of the ready addresses, across all
+    /// subsets.  `notReadyAddresses`, and ports out of range, are
+    /// skipped.
+    pub fn ready_addresses(&self) -> Vec<(IpAddr, u16)> {
+        let mut ret = vec![];
+        for subset in &self.subsets {
+            for addr in &subset.addresses {
+                if let Ok(ip) = addr.ip.parse::<IpAddr>() {
+                    let ports = subset
+                        .ports
+                        .iter()
+                        .filter_map(|p| u16::try_from(p.port).ok());
+                    ret.extend(ports.map(|port| (ip, port)));
+                }
+            }
+        }
+        ret
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointSubset {
+    #[serde(default)]
+    pub addresses: Vec<EndpointAddress>,
+    #[serde(default)]
+    pub not_ready_addresses: Vec<EndpointAddress>,
+    #[serde(default)]
+    pub ports: Vec<EndpointPort>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointAddress {
+    pub ip: String,
+    pub hostname: Option<String>,
+    pub node_name: Option<String>,
+    pub target_ref: Option<ObjectReference>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointPort {
+    pub name: Option<String>,
+    pub port: Integer,
+    pub protocol: Option<Protocol>,
+    pub app_protocol: Option<String>,
+}
+
+pub struct Services;
+
+impl NamespacedResource for Services {
+    type List = ServiceList;
+
+    fn namespaced(&self) -> bool {
+        true
+    }
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("services")
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Service {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<Service>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub spec: ServiceSpec,
+    #[serde(default)]
+    pub status: ServiceStatus,
+}
+
+pub type ServiceList = ItemList<Service>;
+
+impl TypeMeta for Service {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "Service"
+    }
+}
+
+impl Metadata for Service {
+    fn api_version(&self) -> &str {
+        <Service as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <Service as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceSpec {
+    #[serde(default)]
+    pub ports: Vec<ServicePort>,
+    /// Pods with these labels back the service.  If empty, endpoints
+    /// are managed externally.
+    #[serde(default)]
+    pub selector: BTreeMap<String, String>,
+    /// `None` (`""` on the server) until allocated, or `"None"` for a
+    /// headless service.
+    #[serde(rename = "clusterIP")]
+    pub cluster_ip: Option<String>,
+    #[serde(default, rename = "type")]
+    pub typ: ServiceType,
+    #[serde(default)]
+    pub session_affinity: SessionAffinity,
+    #[serde(default, rename = "externalIPs")]
+    pub external_ips: Vec<String>,
+    pub external_name: Option<String>,
+    #[serde(rename = "loadBalancerIP")]
+    pub load_balancer_ip: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ServiceType {
+    ClusterIP,
+    NodePort,
+    LoadBalancer,
+    ExternalName,
+}
+
+impl Default for ServiceType {
+    fn default() -> Self {
+        ServiceType::ClusterIP
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SessionAffinity {
+    None,
+    ClientIP,
+}
+
+impl Default for SessionAffinity {
+    fn default() -> Self {
+        SessionAffinity::None
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ServicePort {
+    /// Required if there is more than one port.
+    pub name: Option<String>,
+    pub port: Integer,
+    pub protocol: Option<Protocol>,
+    /// The port (or named container port) on the pods.  Defaults to
+    /// `port`.
+    pub target_port: Option<IntOrString>,
+    pub node_port: Option<Integer>,
+    pub app_protocol: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceStatus {
+    #[serde(default)]
+    pub load_balancer: LoadBalancerStatus,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadBalancerStatus {
+    #[serde(default)]
+    pub ingress: Vec<LoadBalancerIngress>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadBalancerIngress {
+    pub ip: Option<String>,
+    pub hostname: Option<String>,
+}
+
+pub struct ConfigMaps;
+
+impl NamespacedResource for ConfigMaps {
+    type List = ConfigMapList;
+
+    fn namespaced(&self) -> bool {
+        true
+    }
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("configmaps")
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigMap {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<ConfigMap>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    /// UTF-8 values.
+    #[serde(default)]
+    pub data: BTreeMap<String, String>,
+    /// Other values.  Keys must not also be in `data`.
+    #[serde(default, with = "serde_base64::map")]
+    pub binary_data: BTreeMap<String, Vec<u8>>,
+    pub immutable: Option<bool>,
+}
+
+pub type ConfigMapList = ItemList<ConfigMap>;
+
+impl TypeMeta for ConfigMap {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "ConfigMap"
+    }
+}
+
+impl Metadata for ConfigMap {
+    fn api_version(&self) -> &str {
+        <ConfigMap as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <ConfigMap as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+pub struct Secrets;
+
+impl NamespacedResource for Secrets {
+    type List = SecretList;
+
+    fn namespaced(&self) -> bool {
+        true
+    }
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("secrets")
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Secret {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<Secret>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    /// Values, base64 encoded on the wire.
+    #[serde(default, with = "serde_base64::map")]
+    pub data: BTreeMap<String, Vec<u8>>,
+    /// Write-only convenience: merged into `data` by the server, and
+    /// never returned.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub string_data: BTreeMap<String, String>,
+    /// Eg: `Opaque` (the default) or `kubernetes.io/tls`.
+    #[serde(rename = "type")]
+    pub typ: Option<String>,
+    pub immutable: Option<bool>,
+}
+
+pub type SecretList = ItemList<Secret>;
+
+impl TypeMeta for Secret {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "Secret"
+    }
+}
+
+impl Metadata for Secret {
+    fn api_version(&self) -> &str {
+        <Secret as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <Secret as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+pub struct Events;
+
+impl NamespacedResource for Events {
+    type List = EventList;
+
+    fn namespaced(&self) -> bool {
+        true
+    }
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("events")
+    }
+}
+
+/// An event about `involved_object`.
+///
+/// Events recorded through the older API set `first_timestamp`,
+/// `last_timestamp` and `count`.  Those recorded through
+/// `events.k8s.io` set `event_time` (and `series`, once repeated)
+/// instead, and leave the older fields unset.  `Event::time` handles
+/// both.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<Event>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub involved_object: ObjectReference,
+    /// Short, machine understandable, eg: `FailedScheduling`.
+    pub reason: Option<String>,
+    pub message: Option<String>,
+    #[serde(default)]
+    pub source: EventSource,
+    pub first_timestamp: Option<Time>,
+    pub last_timestamp: Option<Time>,
+    pub count: Option<Integer>,
+    /// `Normal` or `Warning`.
+    #[serde(rename = "type")]
+    pub typ: Option<String>,
+    pub event_time: Option<MicroTime>,
+    pub series: Option<EventSeries>,
+    pub action: Option<String>,
+    pub related: Option<ObjectReference>,
+    pub reporting_component: Option<String>,
+    pub reporting_instance: Option<String>,
+}
+
+pub type EventList = ItemList<Event>;
+
+impl TypeMeta for Event {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "Event"
+    }
+}
+
+impl Metadata for Event {
+    fn api_version(&self) -> &str {
+        <Event as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <Event as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+impl Event {
+    /// When the event last happened: `series.lastObservedTime` if the
+    /// event has repeated, else `lastTimestamp`, else `eventTime`.
+    /// Falls back to `firstTimestamp` and then the object's
+    /// `creationTimestamp` if none of those are set.
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        self.series
+            .as_ref()
+            .map(|s| s.last_observed_time.0)
+            .or_else(|| self.last_timestamp.map(|t| t.0))
+            .or_else(|| self.event_time.map(|t| t.0))
+            .or_else(|| self.first_timestamp.map(|t| t.0))
+            .or_else(|| self.metadata.creation_timestamp.map(|t| t.0))
+    }
+}
+
+/// Sorts `events` oldest first by `Event::time`, the order of
+/// `kubectl get events --sort-by=.lastTimestamp`, but also ordering
+/// events that only have `eventTime`.  Events without any timestamp
+/// sort first.  The sort is stable.
+pub fn sort_events_by_time(events: &mut Vec<Event>) {
+    events.sort_by_key(Event::time);
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EventSource {
+    pub component: Option<String>,
+    pub host: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EventSeries {
+    pub count: Integer,
+    pub last_observed_time: MicroTime,
+}
+
 #[test]
 fn deser_pod() {
     let yaml = r#"
@@ -1069,3 +1932,504 @@ fn deser_pod() {
     let pod2: Pod = ::serde_json::from_value(rt_json).unwrap();
     assert_eq!(pod, pod2);
 }
+
+#[test]
+fn deser_service() {
+    let yaml = r#"
+      apiVersion: v1
+      kind: Service
+      metadata:
+        creationTimestamp: 2018-02-17T23:22:02Z
+        labels:
+          k8s-app: kube-dns
+          kubernetes.io/cluster-service: "true"
+          kubernetes.io/name: KubeDNS
+        name: kube-dns
+        namespace: kube-system
+        resourceVersion: "377"
+        uid: 5c8a5c39-1439-11e8-bdc8-525400cf4e41
+      spec:
+        clusterIP: 10.96.0.10
+        ports:
+        - name: dns
+          port: 53
+          protocol: UDP
+          targetPort: 53
+        - name: dns-tcp
+          port: 53
+          protocol: TCP
+          targetPort: dns-tcp
+        selector:
+          k8s-app: kube-dns
+        sessionAffinity: None
+        type: ClusterIP
+      status:
+        loadBalancer: {}
+    "#;
+
+    let svc: Service = ::serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(svc.spec.cluster_ip, Some("10.96.0.10".to_string()));
+    assert_eq!(svc.spec.typ, ServiceType::ClusterIP);
+    assert_eq!(svc.spec.session_affinity, SessionAffinity::None);
+    assert_eq!(svc.spec.selector["k8s-app"], "kube-dns");
+    assert_eq!(svc.spec.ports.len(), 2);
+    assert_eq!(svc.spec.ports[0].protocol, Some(Protocol::UDP));
+    assert_eq!(svc.spec.ports[0].target_port, Some(IntOrString::Int(53)));
+    assert_eq!(
+        svc.spec.ports[1].target_port,
+        Some(IntOrString::String("dns-tcp".to_string()))
+    );
+
+    // roundtrip
+    let rt_json = ::serde_json::to_value(&svc).unwrap();
+    assert_eq!(rt_json["apiVersion"], "v1");
+    assert_eq!(rt_json["kind"], "Service");
+    assert_eq!(rt_json["spec"]["clusterIP"], "10.96.0.10");
+    assert_eq!(rt_json["spec"]["type"], "ClusterIP");
+    let svc2: Service = ::serde_json::from_value(rt_json).unwrap();
+    assert_eq!(svc, svc2);
+}
+
+#[test]
+fn deser_node() {
+    let json = r#"{
+        "apiVersion": "v1",
+        "kind": "Node",
+        "metadata": {
+            "annotations": {
+                "kubeadm.alpha.kubernetes.io/cri-socket": "/var/run/dockershim.sock",
+                "node.alpha.kubernetes.io/ttl": "0",
+                "volumes.kubernetes.io/controller-managed-attach-detach": "true"
+            },
+            "creationTimestamp": "2018-02-17T23:21:05Z",
+            "labels": {
+                "beta.kubernetes.io/arch": "amd64",
+                "beta.kubernetes.io/os": "linux",
+                "kubernetes.io/hostname": "minikube",
+                "node-role.kubernetes.io/master": ""
+            },
+            "name": "minikube",
+            "resourceVersion": "82901",
+            "selfLink": "/api/v1/nodes/minikube",
+            "uid": "3a5e3c8a-1439-11e8-bdc8-525400cf4e41"
+        },
+        "spec": {
+            "podCIDR": "10.244.0.0/24",
+            "podCIDRs": ["10.244.0.0/24"],
+            "taints": [
+                {"effect": "NoSchedule", "key": "node-role.kubernetes.io/master"},
+                {
+                    "effect": "NoExecute",
+                    "key": "node.kubernetes.io/unreachable",
+                    "timeAdded": "2018-02-20T18:00:07Z"
+                }
+            ],
+            "unschedulable": true
+        },
+        "status": {
+            "addresses": [
+                {"address": "192.168.42.147", "type": "InternalIP"},
+                {"address": "minikube", "type": "Hostname"}
+            ],
+            "allocatable": {
+                "cpu": "2",
+                "ephemeral-storage": "15564179840",
+                "hugepages-2Mi": "0",
+                "memory": "1945760Ki",
+                "pods": "110"
+            },
+            "capacity": {
+                "cpu": "2",
+                "ephemeral-storage": "16888216Ki",
+                "hugepages-2Mi": "0",
+                "memory": "2048160Ki",
+                "pods": "110"
+            },
+            "conditions": [
+                {
+                    "lastHeartbeatTime": "2018-02-20T18:00:07Z",
+                    "lastTransitionTime": "2018-02-17T23:21:01Z",
+                    "message": "kubelet has sufficient disk space available",
+                    "reason": "KubeletHasSufficientDisk",
+                    "status": "False",
+                    "type": "DiskPressure"
+                },
+                {
+                    "lastHeartbeatTime": "2018-02-20T18:00:07Z",
+                    "lastTransitionTime": "2018-02-17T23:21:31Z",
+                    "message": "kubelet is posting ready status",
+                    "reason": "KubeletReady",
+                    "status": "True",
+                    "type": "Ready"
+                }
+            ],
+            "daemonEndpoints": {"kubeletEndpoint": {"Port": 10250}},
+            "images": [],
+            "nodeInfo": {
+                "architecture": "amd64",
+                "bootID": "4a4f3c2e-5b2a-4d5e-8f3a-6a0c7f6f2b1d",
+                "containerRuntimeVersion": "docker://17.9.0",
+                "kernelVersion": "4.9.64",
+                "kubeProxyVersion": "v1.9.0",
+                "kubeletVersion": "v1.9.0",
+                "machineID": "6f8e2c9d4b1a4e3f9c7d5a2b8e1f0c3d",
+                "operatingSystem": "linux",
+                "osImage": "Buildroot 2017.02",
+                "systemUUID": "A1B2C3D4-E5F6-4789-ABCD-0123456789AB"
+            }
+        }
+    }"#;
+
+    let node: Node = ::serde_json::from_str(json).unwrap();
+    assert_eq!(node.spec.pod_cidr, Some("10.244.0.0/24".to_string()));
+    assert!(node.spec.unschedulable);
+    assert_eq!(node.spec.taints.len(), 2);
+    assert_eq!(node.spec.taints[0].effect, TaintEffect::NoSchedule);
+    assert_eq!(node.spec.taints[0].value, None);
+    assert_eq!(
+        node.spec.taints[1].time_added.unwrap().timestamp(),
+        1519149607
+    );
+    assert_eq!(
+        node.status.allocatable["memory"],
+        "1945760Ki".parse::<Quantity>().unwrap()
+    );
+    assert_eq!(node.status.capacity["cpu"].as_milli(), 2000);
+    assert_eq!(node.status.addresses[0].typ, "InternalIP");
+    assert_eq!(
+        node.status.node_info.as_ref().unwrap().kubelet_version,
+        "v1.9.0"
+    );
+    assert!(node.is_ready());
+    assert_eq!(
+        node.condition("DiskPressure").unwrap().status,
+        ConditionStatus::False
+    );
+    assert!(node.condition("PIDPressure").is_none());
+
+    // roundtrip
+    let rt_json = ::serde_json::to_value(&node).unwrap();
+    assert_eq!(rt_json["kind"], "Node");
+    assert_eq!(rt_json["spec"]["podCIDR"], "10.244.0.0/24");
+    assert_eq!(
+        rt_json["status"]["nodeInfo"]["bootID"],
+        "4a4f3c2e-5b2a-4d5e-8f3a-6a0c7f6f2b1d"
+    );
+    let node2: Node = ::serde_json::from_value(rt_json).unwrap();
+    assert_eq!(node, node2);
+}
+
+#[test]
+fn deser_configmap() {
+    let cm: ConfigMap = ::serde_json::from_value(json!({
+        "apiVersion": "v1",
+        "kind": "ConfigMap",
+        "metadata": {"name": "app-config", "namespace": "default"},
+        "data": {"log.level": "debug", "app.properties": "a=1\nb=2\n"},
+        "binaryData": {"logo.png": "iVBORw0K"},
+    }))
+    .unwrap();
+    assert_eq!(cm.data["log.level"], "debug");
+    assert_eq!(cm.binary_data["logo.png"], b"\x89PNG\r\n");
+
+    let rt_json = ::serde_json::to_value(&cm).unwrap();
+    assert_eq!(rt_json["binaryData"]["logo.png"], "iVBORw0K");
+    assert_eq!(::serde_json::from_value::<ConfigMap>(rt_json).unwrap(), cm);
+}
+
+#[test]
+fn deser_secret() {
+    let json = r#"{
+        "apiVersion": "v1",
+        "data": {
+            "ca.crt": "LS0tLS1CRUdJTiBDRVJUSUZJQ0FURS0tLS0tCg==",
+            "namespace": "a3ViZS1zeXN0ZW0=",
+            "token": "ZXlKaGJHY2lPaUpTVXpJMU5pSjkuZXlKcGMzTWlPaUpyZFdKbGNtNWxkR1Z6In0="
+        },
+        "kind": "Secret",
+        "metadata": {
+            "annotations": {
+                "kubernetes.io/service-account.name": "default",
+                "kubernetes.io/service-account.uid": "5d4a5a9e-1439-11e8-bdc8-525400cf4e41"
+            },
+            "creationTimestamp": "2018-02-17T23:22:04Z",
+            "name": "default-token-7x2wq",
+            "namespace": "kube-system",
+            "resourceVersion": "391",
+            "selfLink": "/api/v1/namespaces/kube-system/secrets/default-token-7x2wq",
+            "uid": "5d4c3fd5-1439-11e8-bdc8-525400cf4e41"
+        },
+        "type": "kubernetes.io/service-account-token"
+    }"#;
+
+    let secret: Secret = ::serde_json::from_str(json).unwrap();
+    assert_eq!(
+        secret.typ,
+        Some("kubernetes.io/service-account-token".to_string())
+    );
+    assert_eq!(secret.data["namespace"], b"kube-system");
+    assert_eq!(secret.data["ca.crt"], &b"-----BEGIN CERTIFICATE-----\n"[..]);
+    assert_eq!(secret.data.len(), 3);
+
+    // roundtrip
+    let rt_json = ::serde_json::to_value(&secret).unwrap();
+    assert_eq!(rt_json["kind"], "Secret");
+    assert_eq!(rt_json["data"]["namespace"], "a3ViZS1zeXN0ZW0=");
+    assert!(rt_json.get("stringData").is_none());
+    let secret2: Secret = ::serde_json::from_value(rt_json).unwrap();
+    assert_eq!(secret, secret2);
+
+    // null data, and invalid base64
+    let empty: Secret =
+        ::serde_json::from_value(json!({"metadata": {"name": "x"}, "data": null})).unwrap();
+    assert!(empty.data.is_empty());
+    assert!(::serde_json::from_value::<Secret>(json!({"data": {"a": "!!"}})).is_err());
+}
+
+#[test]
+fn new_pod() {
+    let pod = Pod::new("web", "default", "nginx:1.19");
+    let json = serde_json::to_value(&pod).unwrap();
+    assert_eq!(json["apiVersion"], "v1");
+    assert_eq!(json["kind"], "Pod");
+    assert_eq!(json["metadata"]["name"], "web");
+    assert_eq!(json["metadata"]["namespace"], "default");
+    assert_eq!(json["spec"]["containers"][0]["name"], "web");
+    assert_eq!(json["spec"]["containers"][0]["image"], "nginx:1.19");
+
+    let pod2: Pod = serde_json::from_value(json).unwrap();
+    assert_eq!(pod2, pod);
+}
+
+#[test]
+fn pod_readiness_gates() {
+    let mut pod: Pod = serde_json::from_value(json!({
+        "apiVersion": "v1",
+        "kind": "Pod",
+        "metadata": {"name": "web-0"},
+        "spec": {
+            "containers": [{"name": "web", "image": "nginx"}],
+            "readinessGates": [{"conditionType": "example.com/lb-registered"}],
+        },
+        "status": {
+            "conditions": [
+                {"type": "Ready", "status": "True"},
+                {"type": "example.com/lb-registered", "status": "False"},
+            ],
+        },
+    }))
+    .unwrap();
+    assert_eq!(
+        pod.spec.readiness_gates[0].condition_type,
+        PodConditionType::Other("example.com/lb-registered".into())
+    );
+    assert_eq!(pod.status.conditions[0].typ, PodConditionType::Ready);
+    assert!(!pod.is_fully_ready());
+
+    pod.status.conditions[1].status = ConditionStatus::True;
+    assert!(pod.is_fully_ready());
+
+    pod.status.conditions[0].status = ConditionStatus::False;
+    assert!(!pod.is_fully_ready());
+
+    let j = serde_json::to_value(&pod).unwrap();
+    assert_eq!(
+        j["status"]["conditions"][1]["type"],
+        "example.com/lb-registered"
+    );
+}
+
+#[test]
+fn endpoints_ready_addresses() {
+    let ep: Endpoints = serde_json::from_value(json!({
+        "apiVersion": "v1",
+        "kind": "Endpoints",
+        "metadata": {"name": "web", "namespace": "default"},
+        "subsets": [{
+            "addresses": [{"ip": "10.1.2.3", "nodeName": "node-1"}],
+            "notReadyAddresses": [{"ip": "10.1.2.4", "nodeName": "node-2"}],
+            "ports": [
+                {"name": "http", "port": 80, "protocol": "TCP"},
+                {"name": "bogus", "port": 65616, "protocol": "TCP"},
+            ],
+        }],
+    }))
+    .unwrap();
+    let ip: IpAddr = "10.1.2.3".parse().unwrap();
+    assert_eq!(ep.ready_addresses(), vec![(ip, 80)]);
+}
+
+#[test]
+fn sort_events() {
+    let mut events: Vec<Event> = ::serde_yaml::from_str(
+        r#"
+      - metadata:
+          name: scheduled
+          creationTimestamp: 2020-06-01T10:00:05Z
+        involvedObject:
+          kind: Pod
+          name: web
+        reason: Scheduled
+        type: Normal
+        eventTime: 2020-06-01T10:00:01.500000Z
+        reportingComponent: default-scheduler
+      - metadata:
+          name: pulled
+        involvedObject:
+          kind: Pod
+          name: web
+        reason: Pulled
+        firstTimestamp: 2020-06-01T10:00:02Z
+        lastTimestamp: 2020-06-01T10:00:09Z
+        count: 3
+      - metadata:
+          name: backoff
+        reason: BackOff
+        eventTime: 2020-06-01T10:00:03.000000Z
+        series:
+          count: 5
+          lastObservedTime: 2020-06-01T10:00:30.000000Z
+      - metadata:
+          name: created
+          creationTimestamp: 2020-06-01T10:00:04Z
+        reason: Created
+      - metadata:
+          name: started
+        reason: Started
+        firstTimestamp: 2020-06-01T10:00:01Z
+      - metadata:
+          name: unknown
+    "#,
+    )
+    .unwrap();
+
+    assert_eq!(events[0].source, EventSource::default());
+    assert_eq!(events[1].count, Some(3));
+
+    sort_events_by_time(&mut events);
+    let names: Vec<_> = events
+        .iter()
+        .map(|e| e.metadata.name.as_ref().unwrap().as_str())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "unknown",
+            "started",
+            "scheduled",
+            "created",
+            "pulled",
+            "backoff"
+        ]
+    );
+}
+
+#[test]
+fn envvarsource_serde() {
+    let env: Vec<EnvVar> = serde_json::from_value(json!([
+        {"name": "A", "valueFrom": {"configMapKeyRef": {"name": "cm", "key": "a"}}},
+        {"name": "B", "valueFrom": {"fieldRef": {"fieldPath": "metadata.name"}}},
+        {"name": "C", "valueFrom": {"resourceFieldRef": {"resource": "limits.cpu"}}},
+        {"name": "D", "valueFrom": {"secretKeyRef": {"name": "s", "key": "d"}}},
+    ]))
+    .unwrap();
+    assert_eq!(
+        env[0].value_from,
+        Some(EnvVarSource::ConfigMapKeyRef(ConfigMapKeySelector {
+            key: "a".into(),
+            name: "cm".into(),
+            optional: false,
+        }))
+    );
+    match env[1].value_from {
+        Some(EnvVarSource::FieldRef(ref f)) => assert_eq!(f.api_version, "v1"),
+        ref v => panic!("unexpected {:?}", v),
+    }
+
+    let json = serde_json::to_value(&env).unwrap();
+    assert_eq!(json[0]["valueFrom"]["configMapKeyRef"]["key"], "a");
+    assert_eq!(
+        json[1]["valueFrom"]["fieldRef"]["fieldPath"],
+        "metadata.name"
+    );
+    assert_eq!(json[2]["valueFrom"]["resourceFieldRef"]["divisor"], "1");
+    assert_eq!(json[3]["valueFrom"]["secretKeyRef"]["name"], "s");
+    let round: Vec<EnvVar> = serde_json::from_value(json).unwrap();
+    assert_eq!(round, env);
+}
+
+#[test]
+fn handler_serde() {
+    let lifecycle: Lifecycle = serde_json::from_value(json!({
+        "postStart": {"exec": {"command": ["touch", "/ready"]}},
+        "preStop": {"httpGet": {"path": "/quit", "port": 8080}},
+    }))
+    .unwrap();
+    assert_eq!(
+        lifecycle.post_start,
+        Some(Handler::Exec(ExecAction {
+            command: vec!["touch".into(), "/ready".into()],
+        }))
+    );
+    match lifecycle.pre_stop {
+        Some(Handler::HttpGet(ref a)) => {
+            assert_eq!(a.port, IntOrString::Int(8080));
+            assert_eq!(a.scheme, "HTTP");
+        }
+        ref h => panic!("unexpected {:?}", h),
+    }
+
+    let json = serde_json::to_value(&lifecycle).unwrap();
+    assert_eq!(json["postStart"]["exec"]["command"][0], "touch");
+    assert_eq!(json["preStop"]["httpGet"]["port"], 8080);
+    assert_eq!(
+        serde_json::from_value::<Lifecycle>(json).unwrap(),
+        lifecycle
+    );
+
+    let tcp = Handler::TcpSocket(TCPSocketAction {
+        host: None,
+        port: IntOrString::String("http".into()),
+    });
+    let json = serde_json::to_value(&tcp).unwrap();
+    assert_eq!(json["tcpSocket"]["port"], "http");
+    assert_eq!(serde_json::from_value::<Handler>(json).unwrap(), tcp);
+}
+
+#[test]
+fn container_state_serde() {
+    let status: ContainerStatus = serde_json::from_value(json!({
+        "name": "c",
+        "ready": false,
+        "restartCount": 1,
+        "image": "busybox",
+        "imageID": "",
+        "state": {"waiting": {"reason": "CrashLoopBackOff"}},
+        "lastState": {"terminated": {"exitCode": 1, "containerID": "docker://abc"}},
+    }))
+    .unwrap();
+    match status.last_termination_state {
+        Some(ContainerState::Terminated(ref t)) => {
+            assert_eq!(t.container_id, Some("docker://abc".into()))
+        }
+        ref s => panic!("unexpected {:?}", s),
+    }
+    let json = serde_json::to_value(&status).unwrap();
+    assert_eq!(
+        json["lastState"]["terminated"]["containerID"],
+        "docker://abc"
+    );
+    assert_eq!(json["state"]["waiting"]["reason"], "CrashLoopBackOff");
+
+    let status: ContainerStatus = serde_json::from_value(json!({
+        "name": "c",
+        "ready": true,
+        "restartCount": 0,
+        "image": "busybox",
+        "imageID": "",
+        "state": {"running": {"startedAt": "2018-02-20T18:00:07Z"}},
+        "lastState": {},
+    }))
+    .unwrap();
+    assert_eq!(status.last_termination_state, None);
+}
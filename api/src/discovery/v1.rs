@@ -0,0 +1,166 @@
+use crate::core::v1::{NamespacedResource, ObjectReference, Protocol};
+use crate::meta::v1::{ItemList, Metadata, ObjectMeta};
+use crate::meta::{GroupVersion, GroupVersionResource};
+use crate::{Integer, TypeMeta, TypeMetaImpl};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+const API_GROUP: &str = "discovery.k8s.io/v1";
+pub const GROUP_VERSION: GroupVersion = GroupVersion {
+    group: "discovery.k8s.io",
+    version: "v1",
+};
+
+pub struct EndpointSlices;
+
+impl NamespacedResource for EndpointSlices {
+    type List = EndpointSliceList;
+
+    fn namespaced(&self) -> bool {
+        true
+    }
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("endpointslices")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointSlice {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<EndpointSlice>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    pub address_type: AddressType,
+    #[serde(default)]
+    pub endpoints: Vec<Endpoint>,
+    #[serde(default)]
+    pub ports: Vec<EndpointPort>,
+}
+
+pub type EndpointSliceList = ItemList<EndpointSlice>;
+
+impl TypeMeta for EndpointSlice {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "EndpointSlice"
+    }
+}
+
+impl Metadata for EndpointSlice {
+    fn api_version(&self) -> &str {
+        <EndpointSlice as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <EndpointSlice as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+impl EndpointSlice {
+    /// Every address:port pair of the ready endpoints.  FQDN
+    /// endpoints, and ports without a number or out of range, are
+    /// skipped.
+    pub fn ready_addresses(&self) -> Vec<(IpAddr, u16)> {
+        let ports: Vec<u16> = self
+            .ports
+            .iter()
+            .filter_map(|p| p.port)
+            .filter_map(|p| u16::try_from(p).ok())
+            .collect();
+        self.endpoints
+            .iter()
+            .filter(|e| e.conditions.is_ready())
+            .flat_map(|e| e.addresses.iter())
+            .filter_map(|a| a.parse().ok())
+            .flat_map(|ip: IpAddr| ports.iter().map(move |port| (ip, *port)))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AddressType {
+    IPv4,
+    IPv6,
+    FQDN,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Endpoint {
+    pub addresses: Vec<String>,
+    #[serde(default)]
+    pub conditions: EndpointConditions,
+    pub hostname: Option<String>,
+    pub target_ref: Option<ObjectReference>,
+    pub node_name: Option<String>,
+    pub zone: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointConditions {
+    pub ready: Option<bool>,
+    pub serving: Option<bool>,
+    pub terminating: Option<bool>,
+}
+
+impl EndpointConditions {
+    /// An unknown (`None`) ready condition is treated as ready, as
+    /// the API documentation says consumers should.
+    pub fn is_ready(&self) -> bool {
+        self.ready.unwrap_or(true)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointPort {
+    pub name: Option<String>,
+    pub protocol: Option<Protocol>,
+    pub port: Option<Integer>,
+    pub app_protocol: Option<String>,
+}
+
+#[test]
+fn endpointslice_ready_addresses() {
+    use serde_json;
+
+    let slice: EndpointSlice = serde_json::from_value(json!({
+        "apiVersion": "discovery.k8s.io/v1",
+        "kind": "EndpointSlice",
+        "metadata": {
+            "name": "web-abc",
+            "namespace": "default",
+            "labels": {"kubernetes.io/service-name": "web"},
+        },
+        "addressType": "IPv4",
+        "endpoints": [
+            {
+                "addresses": ["10.1.2.3"],
+                "conditions": {"ready": true, "serving": true, "terminating": false},
+                "nodeName": "node-1",
+            },
+            {
+                "addresses": ["10.1.2.4"],
+                "conditions": {"ready": false},
+                "nodeName": "node-2",
+            },
+        ],
+        "ports": [
+            {"name": "http", "protocol": "TCP", "port": 80},
+            {"name": "https", "protocol": "TCP", "port": 443},
+            {"name": "bogus", "protocol": "TCP", "port": -1},
+        ],
+    }))
+    .unwrap();
+
+    let ip: IpAddr = "10.1.2.3".parse().unwrap();
+    assert_eq!(slice.ready_addresses(), vec![(ip, 80), (ip, 443)]);
+}
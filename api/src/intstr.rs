@@ -1,6 +1,14 @@
 use super::Integer;
 use std::fmt;
 
+/// A value that may be either an integer or a string, eg: a port
+/// number or name.
+///
+/// Serialization preserves the form exactly: a JSON number
+/// deserializes to `Int` and a JSON string (even `"80"`) to `String`,
+/// and each serializes back the same way.  This matters when writing
+/// back an object the server sent.  Use `From<String>` to coerce
+/// numeric strings into `Int`.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum IntOrString {
@@ -13,6 +21,8 @@ impl From<Integer> for IntOrString {
         IntOrString::Int(i)
     }
 }
+/// Parses numeric strings as `Int`.  Note deserialization does *not*
+/// do this.
 impl From<String> for IntOrString {
     fn from(s: String) -> Self {
         Integer::from_str_radix(&s, 10)
@@ -35,6 +45,23 @@ fn intstr_parse() {
     );
 }
 
+#[test]
+fn intstr_roundtrip() {
+    use serde_json;
+
+    let quoted: IntOrString = serde_json::from_str(r#""80""#).unwrap();
+    assert_eq!(quoted, IntOrString::String("80".to_string()));
+    assert_eq!(serde_json::to_string(&quoted).unwrap(), r#""80""#);
+
+    let bare: IntOrString = serde_json::from_str("80").unwrap();
+    assert_eq!(bare, IntOrString::Int(80));
+    assert_eq!(serde_json::to_string(&bare).unwrap(), "80");
+
+    let named: IntOrString = serde_json::from_str(r#""http""#).unwrap();
+    assert_eq!(named, IntOrString::String("http".to_string()));
+    assert_eq!(serde_json::to_string(&named).unwrap(), r#""http""#);
+}
+
 impl PartialEq<Integer> for IntOrString {
     fn eq(&self, other: &Integer) -> bool {
         match *self {
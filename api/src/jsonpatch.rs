@@ -0,0 +1,252 @@
+//! JSON patch (RFC 6902) support.
+
+use serde_json::{Map, Value};
+
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Builds a JSON pointer (eg: `/metadata/labels/app.kubernetes.io~1name`)
+/// from unescaped path segments.
+pub fn pointer(segments: &[&str]) -> String {
+    segments.iter().map(|s| format!("/{}", escape(s))).collect()
+}
+
+/// Accumulates JSON patch operations, eg:
+/// `patch.test("/metadata/resourceVersion", "42").replace("/spec/replicas", 3)`.
+///
+/// Serializes as the array of operations, so can be passed directly
+/// to a `patch` with content type `JSON_PATCH`.  Paths are JSON
+/// pointers; see `pointer` for building them from keys containing `/`.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(transparent)]
+pub struct JsonPatch {
+    ops: Vec<Value>,
+}
+
+impl JsonPatch {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn value_op<V: Into<Value>>(&mut self, op: &str, path: &str, value: V) -> &mut Self {
+        self.ops
+            .push(json!({"op": op, "path": path, "value": value.into()}));
+        self
+    }
+
+    fn from_op(&mut self, op: &str, from: &str, path: &str) -> &mut Self {
+        self.ops.push(json!({"op": op, "from": from, "path": path}));
+        self
+    }
+
+    pub fn add<V: Into<Value>>(&mut self, path: &str, value: V) -> &mut Self {
+        self.value_op("add", path, value)
+    }
+
+    pub fn remove(&mut self, path: &str) -> &mut Self {
+        self.ops.push(json!({"op": "remove", "path": path}));
+        self
+    }
+
+    pub fn replace<V: Into<Value>>(&mut self, path: &str, value: V) -> &mut Self {
+        self.value_op("replace", path, value)
+    }
+
+    /// Fails the whole patch unless the value at `path` equals
+    /// `value`.
+    pub fn test<V: Into<Value>>(&mut self, path: &str, value: V) -> &mut Self {
+        self.value_op("test", path, value)
+    }
+
+    pub fn copy(&mut self, from: &str, path: &str) -> &mut Self {
+        self.from_op("copy", from, path)
+    }
+
+    /// The `move` operation (a keyword in Rust).
+    pub fn move_(&mut self, from: &str, path: &str) -> &mut Self {
+        self.from_op("move", from, path)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub fn into_value(self) -> Value {
+        Value::Array(self.ops)
+    }
+}
+
+/// Computes a JSON patch that transforms `from` into `to`.
+///
+/// Object members that are `null` are treated as absent, so that
+/// patches computed from typed objects (which serialize unset
+/// `Option`s as `null`) also apply to the sparser JSON held by the
+/// server.  Arrays are compared element-wise, with additions and
+/// removals at the end.
+pub fn diff(from: &Value, to: &Value) -> Value {
+    let mut ops = vec![];
+    diff_into(&mut ops, "", from, to);
+    Value::Array(ops)
+}
+
+fn diff_into(ops: &mut Vec<Value>, path: &str, from: &Value, to: &Value) {
+    if from == to {
+        return;
+    }
+    match (from, to) {
+        (&Value::Object(ref a), &Value::Object(ref b)) => diff_objects(ops, path, a, b),
+        (&Value::Array(ref a), &Value::Array(ref b)) => {
+            let common = a.len().min(b.len());
+            for i in 0..common {
+                diff_into(ops, &format!("{}/{}", path, i), &a[i], &b[i]);
+            }
+            for (i, v) in b.iter().enumerate().skip(common) {
+                ops.push(json!({"op": "add", "path": format!("{}/{}", path, i), "value": v}));
+            }
+            // Back to front, so earlier indexes stay valid
+            for i in (common..a.len()).rev() {
+                ops.push(json!({"op": "remove", "path": format!("{}/{}", path, i)}));
+            }
+        }
+        _ => ops.push(json!({"op": "replace", "path": path, "value": to})),
+    }
+}
+
+fn diff_objects(
+    ops: &mut Vec<Value>,
+    path: &str,
+    from: &Map<String, Value>,
+    to: &Map<String, Value>,
+) {
+    let present = |m: &Map<String, Value>, k: &str| m.get(k).map(|v| !v.is_null()).unwrap_or(false);
+    for (k, v) in from {
+        if !v.is_null() && !present(to, k) {
+            ops.push(json!({"op": "remove", "path": format!("{}/{}", path, escape(k))}));
+        }
+    }
+    for (k, v) in to {
+        if v.is_null() {
+            continue;
+        }
+        let p = format!("{}/{}", path, escape(k));
+        if present(from, k) {
+            diff_into(ops, &p, &from[k], v);
+        } else {
+            ops.push(json!({"op": "add", "path": p, "value": v}));
+        }
+    }
+}
+
+/// Just enough of a patch implementation to check `diff` output.
+#[cfg(test)]
+pub(crate) fn apply(doc: &mut Value, patch: &Value) {
+    for op in patch.as_array().unwrap() {
+        let path = op["path"].as_str().unwrap();
+        let (parent, last) = match path.rfind('/') {
+            Some(i) => (
+                &path[..i],
+                path[i + 1..].replace("~1", "/").replace("~0", "~"),
+            ),
+            None => {
+                *doc = op["value"].clone();
+                continue;
+            }
+        };
+        let target = doc.pointer_mut(parent).unwrap();
+        match (op["op"].as_str().unwrap(), target) {
+            ("add", &mut Value::Array(ref mut a)) => {
+                a.insert(last.parse().unwrap(), op["value"].clone())
+            }
+            ("remove", &mut Value::Array(ref mut a)) => {
+                a.remove(last.parse().unwrap());
+            }
+            ("replace", &mut Value::Array(ref mut a)) => {
+                a[last.parse::<usize>().unwrap()] = op["value"].clone()
+            }
+            ("add", &mut Value::Object(ref mut m)) | ("replace", &mut Value::Object(ref mut m)) => {
+                m.insert(last, op["value"].clone());
+            }
+            ("remove", &mut Value::Object(ref mut m)) => {
+                m.remove(&last).unwrap();
+            }
+            (op, _) => panic!("unsupported op {} at {}", op, path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_and_apply() {
+        let from = json!({
+            "metadata": {"name": "web", "labels": {"app": "web", "tier": "frontend"}},
+            "spec": {
+                "containers": [{"name": "web", "image": "nginx:1.14"}],
+                "nodeName": null,
+                "tolerations": [{"key": "a"}, {"key": "b"}],
+            },
+        });
+        let to = json!({
+            "metadata": {"name": "web", "labels": {"app": "web", "a/b~c": "x"}},
+            "spec": {
+                "containers": [
+                    {"name": "web", "image": "nginx:1.15"},
+                    {"name": "proxy", "image": "envoy"},
+                ],
+                "nodeName": "node1",
+                "tolerations": [],
+            },
+        });
+        let patch = diff(&from, &to);
+        assert_eq!(
+            patch,
+            json!([
+                {"op": "remove", "path": "/metadata/labels/tier"},
+                {"op": "add", "path": "/metadata/labels/a~1b~0c", "value": "x"},
+                {"op": "replace", "path": "/spec/containers/0/image", "value": "nginx:1.15"},
+                {"op": "add", "path": "/spec/containers/1", "value": {"name": "proxy", "image": "envoy"}},
+                {"op": "add", "path": "/spec/nodeName", "value": "node1"},
+                {"op": "remove", "path": "/spec/tolerations/1"},
+                {"op": "remove", "path": "/spec/tolerations/0"},
+            ])
+        );
+
+        let mut doc = from.clone();
+        apply(&mut doc, &patch);
+        assert_eq!(doc, to);
+
+        assert_eq!(diff(&to, &to), json!([]));
+    }
+
+    #[test]
+    fn builder() {
+        let mut patch = JsonPatch::new();
+        assert!(patch.is_empty());
+        patch
+            .test("/metadata/resourceVersion", "42")
+            .add(
+                &pointer(&["metadata", "labels", "app.kubernetes.io/name"]),
+                "web",
+            )
+            .remove("/spec/nodeName")
+            .replace("/spec/replicas", 3)
+            .copy("/spec/template", "/metadata/annotations/last")
+            .move_("/spec/old", "/spec/new");
+        let expected = json!([
+            {"op": "test", "path": "/metadata/resourceVersion", "value": "42"},
+            {"op": "add", "path": "/metadata/labels/app.kubernetes.io~1name", "value": "web"},
+            {"op": "remove", "path": "/spec/nodeName"},
+            {"op": "replace", "path": "/spec/replicas", "value": 3},
+            {"op": "copy", "from": "/spec/template", "path": "/metadata/annotations/last"},
+            {"op": "move", "from": "/spec/old", "path": "/spec/new"},
+        ]);
+        assert_eq!(::serde_json::to_value(&patch).unwrap(), expected);
+        assert_eq!(patch.into_value(), expected);
+
+        assert_eq!(pointer(&[]), "");
+        assert_eq!(pointer(&["a~b", "0"]), "/a~0b/0");
+    }
+}
@@ -1,7 +1,9 @@
+extern crate base64;
+extern crate chrono;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
-#[cfg_attr(test, macro_use)]
+#[macro_use]
 extern crate serde_json;
 #[macro_use]
 extern crate failure;
@@ -14,24 +16,36 @@ use std::borrow::Cow;
 use std::fmt;
 use std::marker::PhantomData;
 
+pub mod admission;
 pub mod apps;
+pub mod batch;
+pub mod canonical;
 pub mod core;
+pub mod discovery;
 mod intstr;
+pub mod jsonpatch;
 pub mod meta;
+pub mod networking;
+pub mod object;
+pub mod openapi;
+mod quantity;
+pub mod registry;
+pub mod restmapper;
+pub mod scheduling;
+pub mod serde_base64;
+mod time;
 pub mod unstructured;
+pub mod well_known;
 
-pub type Time = String;
+pub use self::time::{MicroTime, Time};
 pub type Integer = i32;
 pub use self::intstr::IntOrString;
-
-// A fixed-point integer, serialised as a particular string format.
-// See k8s.io/apimachinery/pkg/api/resource/quantity.go
-// TODO: implement this with some appropriate Rust type.
-pub type Quantity = String;
+pub use self::quantity::{InvalidQuantityError, Quantity};
 
 pub const JSON_PATCH: &'static str = "application/json-patch+json";
 pub const MERGE_PATCH: &'static str = "application/merge-patch+json";
 pub const STRATEGIC_MERGE_PATCH: &'static str = "application/strategic-merge-patch+json";
+pub const APPLY_PATCH: &'static str = "application/apply-patch+yaml";
 
 pub trait TypeMeta {
     fn api_version() -> &'static str;
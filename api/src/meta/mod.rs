@@ -1,9 +1,16 @@
 use std::convert::From;
 use std::fmt;
 use std::result::Result;
+use std::str::FromStr;
 
+mod owned;
 pub mod v1;
 
+pub use self::owned::{
+    OwnedGroupKind, OwnedGroupResource, OwnedGroupVersion, OwnedGroupVersionKind,
+    OwnedGroupVersionResource,
+};
+
 // GroupVersionKind unambiguously identifies a kind.
 #[derive(Debug, Clone, PartialEq)]
 pub struct GroupVersionKind<'a> {
@@ -18,6 +25,20 @@ impl<'a> GroupVersionKind<'a> {
         let gv = GroupVersion::from_str(m.api_version())?;
         Ok(gv.with_kind(m.kind()))
     }
+
+    /// Turns "group/version/Kind" (or "version/Kind" for the legacy
+    /// core group) into a GroupVersionKind struct.
+    // Can't use FromStr trait because lifetimes
+    pub fn from_str(s: &'a str) -> Result<Self, InvalidGroupVersionError> {
+        let err = || InvalidGroupVersionError { value: s.into() };
+        let i = s.rfind('/').ok_or_else(err)?;
+        let (gv, kind) = (&s[..i], &s[i + 1..]);
+        let gv = GroupVersion::from_str(gv).map_err(|_| err())?;
+        if gv.version == "" || kind == "" {
+            return Err(err());
+        }
+        Ok(gv.with_kind(kind))
+    }
 }
 
 impl<'a> fmt::Display for GroupVersionKind<'a> {
@@ -123,6 +144,29 @@ fn gv_fromstr() {
     assert_eq!(GroupVersion::from_str("v1/a").unwrap(), gv("v1", "a"));
 }
 
+#[test]
+fn gvk_fromstr() {
+    fn gvk<'a>(g: &'a str, v: &'a str, k: &'a str) -> GroupVersionKind<'a> {
+        GroupVersionKind {
+            group: g,
+            version: v,
+            kind: k,
+        }
+    }
+    assert_eq!(
+        GroupVersionKind::from_str("v1/Pod").unwrap(),
+        gvk("", "v1", "Pod")
+    );
+    assert_eq!(
+        GroupVersionKind::from_str("apps/v1/Deployment").unwrap(),
+        gvk("apps", "v1", "Deployment")
+    );
+    assert!(GroupVersionKind::from_str("Pod").is_err());
+    assert!(GroupVersionKind::from_str("v1/").is_err());
+    assert!(GroupVersionKind::from_str("/Pod").is_err());
+    assert!(GroupVersionKind::from_str("a/b/c/Pod").is_err());
+}
+
 // GroupKind specifies a Group and a Kind, but does not force a
 // version.  This is useful for identifying concepts during lookup
 // stages without having partially valid types.
@@ -240,3 +284,108 @@ fn gr_fromstr() {
     assert_eq!(GroupResource::from_str("v1.a").unwrap(), gr("a", "v1"));
     assert_eq!(GroupResource::from_str("b.v1.a").unwrap(), gr("v1.a", "b"));
 }
+
+// NamespaceScope identifies a namespaced object by name, optionally
+// qualified by namespace.  The string form is "namespace/name", or a
+// bare "name" when the namespace is left to the context default.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NamespaceScope {
+    pub namespace: Option<String>,
+    pub name: String,
+}
+
+impl FromStr for NamespaceScope {
+    type Err = InvalidScopeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (ns, name) = match s.find('/') {
+            None => (None, s),
+            Some(i) => (Some(&s[..i]), &s[i + 1..]),
+        };
+        if name == "" || name.contains('/') || ns == Some("") {
+            return Err(InvalidScopeError { value: s.into() });
+        }
+        Ok(NamespaceScope {
+            namespace: ns.map(String::from),
+            name: name.into(),
+        })
+    }
+}
+
+impl fmt::Display for NamespaceScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.namespace {
+            Some(ref ns) => write!(f, "{}/{}", ns, self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+// ClusterScope identifies a cluster-scoped (non-namespaced) object.
+// The string form is just the bare "name".
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClusterScope {
+    pub name: String,
+}
+
+impl FromStr for ClusterScope {
+    type Err = InvalidScopeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "" || s.contains('/') {
+            return Err(InvalidScopeError { value: s.into() });
+        }
+        Ok(ClusterScope { name: s.into() })
+    }
+}
+
+impl fmt::Display for ClusterScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "unexpected object reference string: {}", value)]
+pub struct InvalidScopeError {
+    pub value: String,
+}
+
+#[test]
+fn scope_fromstr() {
+    let s: NamespaceScope = "default/mypod".parse().unwrap();
+    assert_eq!(
+        s,
+        NamespaceScope {
+            namespace: Some("default".into()),
+            name: "mypod".into(),
+        }
+    );
+    assert_eq!(s.to_string(), "default/mypod");
+
+    let s: NamespaceScope = "mypod".parse().unwrap();
+    assert_eq!(
+        s,
+        NamespaceScope {
+            namespace: None,
+            name: "mypod".into(),
+        }
+    );
+    assert_eq!(s.to_string(), "mypod");
+
+    let s: ClusterScope = "mynode".parse().unwrap();
+    assert_eq!(
+        s,
+        ClusterScope {
+            name: "mynode".into(),
+        }
+    );
+    assert_eq!(s.to_string(), "mynode");
+
+    assert!("".parse::<NamespaceScope>().is_err());
+    assert!("default/".parse::<NamespaceScope>().is_err());
+    assert!("/mypod".parse::<NamespaceScope>().is_err());
+    assert!("a/b/c".parse::<NamespaceScope>().is_err());
+    assert!("".parse::<ClusterScope>().is_err());
+    assert!("default/mynode".parse::<ClusterScope>().is_err());
+}
@@ -0,0 +1,129 @@
+//! Owned versions of the group/version/kind/resource identifiers, for
+//! when the strings are only known at runtime (eg: from discovery or
+//! a manifest) and borrowing them is awkward.
+
+use super::{
+    GroupKind, GroupResource, GroupVersion, GroupVersionKind, GroupVersionResource,
+    InvalidGroupVersionError,
+};
+use std::fmt;
+use std::str::FromStr;
+
+macro_rules! owned {
+    ($(#[$attr:meta])* $owned:ident => $borrowed:ident { $($field:ident),* }) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+        pub struct $owned {
+            $(pub $field: String,)*
+        }
+
+        impl $owned {
+            pub fn as_borrowed(&self) -> $borrowed {
+                $borrowed {
+                    $($field: &self.$field,)*
+                }
+            }
+        }
+
+        impl<'a> From<$borrowed<'a>> for $owned {
+            fn from(b: $borrowed<'a>) -> Self {
+                $owned {
+                    $($field: b.$field.into(),)*
+                }
+            }
+        }
+
+        impl<'a> From<&'a $owned> for $borrowed<'a> {
+            fn from(o: &'a $owned) -> Self {
+                o.as_borrowed()
+            }
+        }
+
+        impl fmt::Display for $owned {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(&self.as_borrowed(), f)
+            }
+        }
+    };
+}
+
+owned! {
+    /// An owned `GroupVersion`.
+    OwnedGroupVersion => GroupVersion { group, version }
+}
+owned! {
+    /// An owned `GroupVersionKind`.
+    OwnedGroupVersionKind => GroupVersionKind { group, version, kind }
+}
+owned! {
+    /// An owned `GroupVersionResource`.
+    OwnedGroupVersionResource => GroupVersionResource { group, version, resource }
+}
+owned! {
+    /// An owned `GroupKind`.
+    OwnedGroupKind => GroupKind { group, kind }
+}
+owned! {
+    /// An owned `GroupResource`.
+    OwnedGroupResource => GroupResource { group, resource }
+}
+
+impl FromStr for OwnedGroupVersion {
+    type Err = InvalidGroupVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GroupVersion::from_str(s).map(Self::from)
+    }
+}
+
+impl FromStr for OwnedGroupVersionKind {
+    type Err = InvalidGroupVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GroupVersionKind::from_str(s).map(Self::from)
+    }
+}
+
+impl FromStr for OwnedGroupResource {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GroupResource::from_str(s).map(Self::from)
+    }
+}
+
+#[test]
+fn owned_round_trip() {
+    let s = String::from("apps/v1/Deployment");
+    let gvk: OwnedGroupVersionKind = s.parse().unwrap();
+    drop(s);
+    assert_eq!(gvk.group, "apps");
+    assert_eq!(gvk.kind, "Deployment");
+    assert_eq!(gvk.to_string(), "apps/v1, Kind=Deployment");
+    let borrowed = gvk.as_borrowed();
+    assert_eq!(OwnedGroupVersionKind::from(borrowed.clone()), gvk);
+
+    let gv: OwnedGroupVersion = GroupVersion::from(borrowed.clone()).into();
+    assert_eq!(gv.to_string(), "apps/v1");
+    assert_eq!(gv, "apps/v1".parse().unwrap());
+    let gvr: OwnedGroupVersionResource = gv.as_borrowed().with_resource("deployments").into();
+    assert_eq!(GroupVersionResource::from(&gvr).resource, "deployments");
+
+    let gk: OwnedGroupKind = GroupKind::from(borrowed).into();
+    assert_eq!(gk.to_string(), "Deployment.apps");
+
+    let gr: OwnedGroupResource = "deployments.apps".parse().unwrap();
+    assert_eq!(
+        gr.as_borrowed(),
+        GroupResource {
+            group: "apps",
+            resource: "deployments",
+        }
+    );
+    assert_eq!(
+        OwnedGroupResource::from(GroupResource::from(gvr.as_borrowed())),
+        gr
+    );
+
+    assert!("a/b/c".parse::<OwnedGroupVersion>().is_err());
+}
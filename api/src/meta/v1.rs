@@ -1,11 +1,16 @@
+use crate::well_known;
 use crate::{Integer, Time, TypeMeta, TypeMetaImpl};
-use serde_json::{Map, Value};
+use serde::de::{self, DeserializeOwned};
+use serde::{Deserialize, Deserializer};
+use serde_json::{self, Map, Value};
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::error::Error as StdError;
 use std::fmt;
+use std::marker::PhantomData;
 use std::slice;
-use std::sync::Once;
+use std::str::FromStr;
+use std::sync::Mutex;
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -27,18 +32,316 @@ pub struct ObjectMeta {
     pub labels: HashMap<String, String>,
     #[serde(default)]
     pub owner_references: Vec<OwnerReference>,
-    pub resource_version: Option<String>,
+    #[serde(default)]
+    pub managed_fields: Vec<ManagedFieldsEntry>,
+    pub resource_version: Option<ResourceVersion>,
     pub self_link: Option<String>,
     pub uid: Option<String>,
 }
 
+impl ObjectMeta {
+    /// Clears the fields set by the server, so that metadata from an
+    /// existing object can be used to create a new one (eg: a copy in
+    /// another namespace).  The object's `status` is not part of
+    /// `ObjectMeta`, and is ignored by the server on create.
+    pub fn reset_for_create(&mut self) {
+        self.resource_version = None;
+        self.uid = None;
+        self.self_link = None;
+        self.creation_timestamp = None;
+        self.deletion_timestamp = None;
+        self.deletion_grace_period_seconds = None;
+        self.generation = None;
+        self.managed_fields.clear();
+    }
+
+    /// The owner reference with `controller` set, if any.
+    pub fn controller_ref(&self) -> Option<&OwnerReference> {
+        self.owner_references.iter().find(|o| o.controller)
+    }
+
+    /// Adds an owner reference, failing (as the server would) if it
+    /// is a second one with `controller` set.
+    pub fn add_owner_reference(
+        &mut self,
+        owner: OwnerReference,
+    ) -> Result<(), MultipleControllersError> {
+        if owner.controller {
+            if let Some(existing) = self.controller_ref() {
+                return Err(MultipleControllersError::new(&[existing, &owner]));
+            }
+        }
+        self.owner_references.push(owner);
+        Ok(())
+    }
+
+    /// Checks that at most one owner reference has `controller` set,
+    /// as the server requires.
+    pub fn validate_owner_references(&self) -> Result<(), MultipleControllersError> {
+        let controllers: Vec<_> = self
+            .owner_references
+            .iter()
+            .filter(|o| o.controller)
+            .collect();
+        if controllers.len() > 1 {
+            Err(MultipleControllersError::new(&controllers))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// True if the object was created strictly after `t`.  False if
+    /// it has no `creationTimestamp` (ie: hasn't been created yet).
+    pub fn created_after(&self, t: Time) -> bool {
+        self.creation_timestamp.map(|c| c > t).unwrap_or(false)
+    }
+
+    /// True if the object was created strictly before `t`, eg: to find
+    /// objects older than some age.  False if it has no
+    /// `creationTimestamp`.
+    pub fn created_before(&self, t: Time) -> bool {
+        self.creation_timestamp.map(|c| c < t).unwrap_or(false)
+    }
+
+    /// The `app.kubernetes.io/name` label.
+    pub fn app_name(&self) -> Option<&str> {
+        self.label(well_known::APP_NAME)
+    }
+
+    /// The `app.kubernetes.io/instance` label.
+    pub fn app_instance(&self) -> Option<&str> {
+        self.label(well_known::APP_INSTANCE)
+    }
+
+    /// The `app.kubernetes.io/managed-by` label.
+    pub fn managed_by(&self) -> Option<&str> {
+        self.label(well_known::APP_MANAGED_BY)
+    }
+
+    pub fn label(&self, key: &str) -> Option<&str> {
+        self.labels.get(key).map(String::as_str)
+    }
+
+    pub fn annotation(&self, key: &str) -> Option<&str> {
+        self.annotations.get(key).map(String::as_str)
+    }
+
+    /// Returns the fields owned by both field managers `a` and `b`.
+    pub fn field_manager_conflicts(
+        &self,
+        a: &str,
+        b: &str,
+    ) -> Result<BTreeSet<FieldPath>, InvalidFieldsV1Error> {
+        let owned = |manager: &str| -> Result<BTreeSet<FieldPath>, InvalidFieldsV1Error> {
+            let mut ret = BTreeSet::new();
+            for entry in &self.managed_fields {
+                if entry.manager.as_ref().map(String::as_str) != Some(manager) {
+                    continue;
+                }
+                if let Some(ref fields) = entry.fields_v1 {
+                    ret.extend(fields.paths()?);
+                }
+            }
+            Ok(ret)
+        };
+        let a = owned(a)?;
+        let b = owned(b)?;
+        Ok(a.intersection(&b).cloned().collect())
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ManagedFieldsEntry {
+    pub manager: Option<String>,
+    pub operation: Option<ManagedFieldsOperationType>,
+    pub api_version: Option<String>,
+    pub time: Option<Time>,
+    pub fields_type: Option<String>,
+    #[serde(rename = "fieldsV1")]
+    pub fields_v1: Option<FieldsV1>,
+    pub subresource: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ManagedFieldsOperationType {
+    Apply,
+    Update,
+}
+
+/// A set of fields in the "FieldsV1" trie format: nested objects
+/// keyed by `f:<name>` (struct field), `k:<json>` (list item by key
+/// fields), `v:<json>` (set item) or `i:<index>` (list item by
+/// position).  A `.` key marks the enclosing node itself as a member.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct FieldsV1(pub Value);
+
+impl FieldsV1 {
+    /// Flattens the trie into the set of paths it contains.
+    pub fn paths(&self) -> Result<BTreeSet<FieldPath>, InvalidFieldsV1Error> {
+        let mut ret = BTreeSet::new();
+        fields_v1_walk(&self.0, &mut vec![], &mut ret)?;
+        Ok(ret)
+    }
+
+    /// Returns the paths present in both sets, ie: the fields that
+    /// two managers would conflict over.
+    pub fn conflicts(&self, other: &FieldsV1) -> Result<BTreeSet<FieldPath>, InvalidFieldsV1Error> {
+        let a = self.paths()?;
+        let b = other.paths()?;
+        Ok(a.intersection(&b).cloned().collect())
+    }
+}
+
+fn fields_v1_walk(
+    v: &Value,
+    path: &mut Vec<PathElement>,
+    out: &mut BTreeSet<FieldPath>,
+) -> Result<(), InvalidFieldsV1Error> {
+    let obj = match *v {
+        Value::Object(ref o) => o,
+        Value::Null if path.is_empty() => return Ok(()),
+        ref v => {
+            return Err(InvalidFieldsV1Error {
+                value: v.to_string(),
+            })
+        }
+    };
+    if obj.is_empty() && !path.is_empty() {
+        out.insert(FieldPath(path.clone()));
+    }
+    for (k, child) in obj {
+        if k == "." {
+            out.insert(FieldPath(path.clone()));
+            continue;
+        }
+        path.push(k.parse()?);
+        fields_v1_walk(child, path, out)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PathElement {
+    Field(String),
+    Key(String),
+    Value(String),
+    Index(usize),
+}
+
+impl FromStr for PathElement {
+    type Err = InvalidFieldsV1Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || InvalidFieldsV1Error { value: s.into() };
+        let mut parts = s.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some("f"), Some(n)) => Ok(PathElement::Field(n.into())),
+            (Some("k"), Some(k)) => Ok(PathElement::Key(k.into())),
+            (Some("v"), Some(v)) => Ok(PathElement::Value(v.into())),
+            (Some("i"), Some(i)) => i.parse().map(PathElement::Index).map_err(|_| err()),
+            _ => Err(err()),
+        }
+    }
+}
+
+/// A path to a field, displayed like `.spec.containers[{"name":"app"}].image`
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FieldPath(pub Vec<PathElement>);
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for elem in &self.0 {
+            match *elem {
+                PathElement::Field(ref n) => write!(f, ".{}", n)?,
+                PathElement::Key(ref k) => write!(f, "[{}]", k)?,
+                PathElement::Value(ref v) => write!(f, "[={}]", v)?,
+                PathElement::Index(i) => write!(f, "[{}]", i)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "invalid fieldsV1 entry: {}", value)]
+pub struct InvalidFieldsV1Error {
+    pub value: String,
+}
+
+/// More than one owner reference has `controller` set.
+#[derive(Debug, Fail)]
+#[fail(
+    display = "only one owner reference can have controller set, found {}",
+    owners
+)]
+pub struct MultipleControllersError {
+    /// The controllers found, as `Kind/name`.
+    pub owners: String,
+}
+
+impl MultipleControllersError {
+    fn new(controllers: &[&OwnerReference]) -> Self {
+        let owners: Vec<_> = controllers
+            .iter()
+            .map(|o| format!("{}/{}", o.kind, o.name))
+            .collect();
+        MultipleControllersError {
+            owners: owners.join(", "),
+        }
+    }
+}
+
+/// An opaque token identifying a version of an object or list.
+///
+/// The API server makes no promises about the contents, so these must
+/// not be parsed or ordered; equality is the only valid comparison.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct ResourceVersion(String);
+
+impl ResourceVersion {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for ResourceVersion {
+    fn from(s: String) -> Self {
+        ResourceVersion(s)
+    }
+}
+
+impl<'a> From<&'a str> for ResourceVersion {
+    fn from(s: &'a str) -> Self {
+        ResourceVersion(s.into())
+    }
+}
+
+impl From<ResourceVersion> for String {
+    fn from(rv: ResourceVersion) -> Self {
+        rv.0
+    }
+}
+
+impl fmt::Display for ResourceVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 #[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ListMeta {
     #[serde(rename = "continue")]
     pub continu: Option<String>,
     #[serde(default)]
-    pub resource_version: String,
+    pub resource_version: ResourceVersion,
     #[serde(default)]
     pub self_link: String,
 }
@@ -63,8 +366,10 @@ pub struct Status {
     //pub api_version: String,
     //pub kind: String,
     pub metadata: ListMeta,
+    #[serde(default)]
     pub code: Integer,
     pub details: Option<StatusDetails>,
+    #[serde(default)]
     pub message: String,
     pub reason: Option<StatusReason>,
     pub status: StatusStatus,
@@ -104,6 +409,53 @@ impl fmt::Display for Status {
     }
 }
 
+/// The body of a successful DELETE.  The server returns the object
+/// itself while deletion is still pending (eg: finalizers, or
+/// `propagationPolicy: Foreground`), and a `Status` once it is gone.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeleteResponse<T> {
+    Object(T),
+    Status(Status),
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for DeleteResponse<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        let r = if value.get("kind").and_then(Value::as_str) == Some("Status") {
+            serde_json::from_value(value).map(DeleteResponse::Status)
+        } else {
+            serde_json::from_value(value).map(DeleteResponse::Object)
+        };
+        r.map_err(de::Error::custom)
+    }
+}
+
+impl Status {
+    /// The per-field causes in `details`, as `(field, reason, message)`.
+    ///
+    /// Validation failures (`StatusReason::Invalid`) report each
+    /// offending field here, eg: `("spec.replicas",
+    /// Some(&CauseType::FieldValueInvalid), Some("must be greater than
+    /// or equal to 0"))`.  Causes that don't name a field are skipped.
+    pub fn field_errors(&self) -> Vec<(&str, Option<&CauseType>, Option<&str>)> {
+        self.details
+            .iter()
+            .flat_map(|d| d.causes.iter())
+            .filter(|c| !c.field.is_empty())
+            .map(|c| {
+                (
+                    c.field.as_str(),
+                    c.reason.as_ref(),
+                    c.message.as_ref().map(String::as_str),
+                )
+            })
+            .collect()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum StatusStatus {
     Success,
@@ -127,6 +479,8 @@ pub enum StatusReason {
     InternalError,
     Expired,
     ServiceUnavailable,
+    /// From `exec`, when the command exits non-zero.
+    NonZeroExitCode,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -144,6 +498,7 @@ pub struct StatusDetails {
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct StatusCause {
+    #[serde(default)]
     pub field: String,
     pub message: Option<String>,
     pub reason: Option<CauseType>,
@@ -157,6 +512,8 @@ pub enum CauseType {
     FieldValueInvalid,
     FieldValueNotSupported,
     UnexpectedServerResponse,
+    /// From `exec`: the command's non-zero exit code, in `message`.
+    ExitCode,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -172,7 +529,7 @@ pub struct OwnerReference {
     pub controller: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct LabelSelector {
     #[serde(default)]
@@ -181,6 +538,41 @@ pub struct LabelSelector {
     pub match_labels: Map<String, Value>,
 }
 
+/// Formats as the selector string accepted in `labelSelector` query
+/// parameters, eg: `app=web,env in (prod,staging),!canary`.
+/// Requirements are sorted by key, as `kubectl` does.
+impl fmt::Display for LabelSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut reqs: Vec<(&str, String)> = self
+            .match_labels
+            .iter()
+            .map(|(k, v)| {
+                let v = match *v {
+                    Value::String(ref s) => s.clone(),
+                    ref v => v.to_string(),
+                };
+                (k.as_str(), format!("{}={}", k, v))
+            })
+            .collect();
+        for r in &self.match_expressions {
+            let mut values: Vec<&str> = r.values.iter().map(String::as_str).collect();
+            values.sort();
+            let s = match r.operator {
+                LabelSelectorOperator::In => format!("{} in ({})", r.key, values.join(",")),
+                LabelSelectorOperator::NotIn => {
+                    format!("{} notin ({})", r.key, values.join(","))
+                }
+                LabelSelectorOperator::Exists => r.key.clone(),
+                LabelSelectorOperator::DoesNotExist => format!("!{}", r.key),
+            };
+            reqs.push((r.key.as_str(), s));
+        }
+        reqs.sort_by(|a, b| a.0.cmp(b.0));
+        let strs: Vec<_> = reqs.into_iter().map(|(_, s)| s).collect();
+        f.write_str(&strs.join(","))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct LabelSelectorRequirement {
@@ -221,6 +613,15 @@ pub struct APIResource {
     pub categories: Vec<String>,
 }
 
+/// The versions of the legacy core group, served at `/api`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct APIVersions {
+    pub versions: Vec<String>,
+    #[serde(rename = "serverAddressByClientCIDRs", default)]
+    pub server_address_by_client_cidrs: Vec<ServerAddressByClientCIDR>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct APIGroupList {
@@ -231,9 +632,9 @@ pub struct APIGroupList {
 #[serde(rename_all = "camelCase")]
 pub struct APIGroup {
     pub name: String,
-    pub versions: GroupVersionForDiscovery,
+    pub versions: Vec<GroupVersionForDiscovery>,
     pub preferred_version: Option<GroupVersionForDiscovery>,
-    #[serde(rename = "serverAddressByClientCIDRs")]
+    #[serde(rename = "serverAddressByClientCIDRs", default)]
     pub server_address_by_client_cidrs: Vec<ServerAddressByClientCIDR>,
 }
 
@@ -260,6 +661,13 @@ pub struct WatchEvent {
     pub object: Value,
 }
 
+impl WatchEvent {
+    /// True for an `ERROR` event, whose object is a `Status`.
+    pub fn is_error(&self) -> bool {
+        self.typ == EventType::Error
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum EventType {
@@ -267,6 +675,11 @@ pub enum EventType {
     Modified,
     Deleted,
     Error,
+    /// Only sent if requested with `allow_watch_bookmarks`.  The
+    /// object has just `apiVersion`, `kind` and
+    /// `metadata.resourceVersion`: the version a watch can resume
+    /// from.
+    Bookmark,
 }
 
 /// Not part of the standard k8s API
@@ -293,17 +706,30 @@ where
     fn kind() -> &'static str {
         // Rust can't concatenate two string constants into another
         // constant at compile time (yet), so build a static string on
-        // first call at runtime.  TODO: Re-evaluate once constant
-        // functions are a thing.
-        static mut KIND: Option<String> = None;
-        static INIT: Once = ::std::sync::ONCE_INIT;
-        let k: &Option<String> = unsafe {
-            INIT.call_once(|| {
-                KIND = Some(format!("{}List", T::kind()));
-            });
-            &KIND
-        };
-        k.as_ref().unwrap()
+        // first call at runtime.  A static in a generic function is
+        // shared by every `T`, so they are kept by item kind.  TODO:
+        // Re-evaluate once constant functions are a thing.
+        static KINDS: Mutex<Option<HashMap<&'static str, &'static str>>> = Mutex::new(None);
+        let mut kinds = KINDS.lock().unwrap();
+        kinds
+            .get_or_insert_with(HashMap::new)
+            .entry(T::kind())
+            .or_insert_with(|| Box::leak(format!("{}List", T::kind()).into_boxed_str()))
+    }
+}
+
+impl<T> ItemList<T>
+where
+    T: TypeMeta,
+{
+    /// A list of `items`, with empty list metadata.  The inverse of
+    /// `List::into_items`.
+    pub fn from_items(items: Vec<T>) -> Self {
+        ItemList {
+            typemeta: TypeMetaImpl(PhantomData),
+            metadata: Default::default(),
+            items,
+        }
     }
 }
 
@@ -337,11 +763,40 @@ pub struct GetOptions {
     #[serde(skip_serializing_if = "is_default")]
     pub pretty: bool,
     #[serde(skip_serializing_if = "is_default")]
-    pub resource_version: String,
+    pub resource_version: ResourceVersion,
+    #[serde(skip_serializing_if = "is_default")]
+    pub include_uninitialized: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum DryRun {
+    /// Run every stage of the request except persisting the result.
+    All,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CreateOptions {
+    #[serde(skip_serializing_if = "is_default")]
+    pub dry_run: Option<DryRun>,
+    #[serde(skip_serializing_if = "is_default")]
+    pub field_manager: String,
     #[serde(skip_serializing_if = "is_default")]
     pub include_uninitialized: bool,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PatchOptions {
+    /// Required for server-side apply.
+    #[serde(skip_serializing_if = "is_default")]
+    pub field_manager: String,
+    /// Server-side apply only: take ownership of fields owned by other
+    /// field managers, rather than failing with a conflict.
+    #[serde(skip_serializing_if = "is_default")]
+    pub force: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub struct Preconditions {
     #[serde(default)]
@@ -372,12 +827,14 @@ pub struct DeleteOptions {
 #[serde(default, rename_all = "camelCase")]
 pub struct ListOptions {
     #[serde(skip_serializing_if = "is_default")]
-    pub resource_version: String, // Vec<u8>
+    pub resource_version: ResourceVersion,
     #[serde(skip_serializing_if = "is_default")]
     pub timeout_seconds: u32,
     #[serde(skip_serializing_if = "is_default")]
     pub watch: bool, // NB: set explicitly by watch()
     #[serde(skip_serializing_if = "is_default")]
+    pub allow_watch_bookmarks: bool,
+    #[serde(skip_serializing_if = "is_default")]
     pub pretty: bool,
     #[serde(skip_serializing_if = "is_default")]
     pub field_selector: String,
@@ -391,10 +848,80 @@ pub struct ListOptions {
     pub continu: String, // Vec<u8>
 }
 
+impl ListOptions {
+    /// Sets `label_selector` from a structured `LabelSelector`.
+    pub fn with_label_selector(mut self, selector: &LabelSelector) -> Self {
+        self.label_selector = selector.to_string();
+        self
+    }
+
+    /// Sets `field_selector` from a `FieldSelector`.
+    pub fn with_field_selector(mut self, selector: &FieldSelector) -> Self {
+        self.field_selector = selector.to_string();
+        self
+    }
+}
+
+/// Builds a `fieldSelector`, eg:
+/// `FieldSelector::new().eq("status.phase", "Running").ne("metadata.namespace", "kube-system")`.
+///
+/// Values are escaped as the server expects (`\\`, `\,` and `\=`), so
+/// may contain any character.  Percent-encoding is left to the query
+/// string serializer.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldSelector {
+    reqs: Vec<String>,
+}
+
+impl FieldSelector {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn eq(mut self, field: &str, value: &str) -> Self {
+        self.reqs
+            .push(format!("{}={}", field, escape_field_value(value)));
+        self
+    }
+
+    pub fn ne(mut self, field: &str, value: &str) -> Self {
+        self.reqs
+            .push(format!("{}!={}", field, escape_field_value(value)));
+        self
+    }
+}
+
+impl fmt::Display for FieldSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.reqs.join(","))
+    }
+}
+
+fn escape_field_value(v: &str) -> String {
+    let mut s = String::with_capacity(v.len());
+    for c in v.chars() {
+        if c == '\\' || c == ',' || c == '=' {
+            s.push('\\');
+        }
+        s.push(c);
+    }
+    s
+}
+
 pub trait Metadata {
     fn api_version(&self) -> &str;
     fn kind(&self) -> &str;
     fn metadata(&self) -> Cow<ObjectMeta>;
+
+    /// The object's UID, if it has been assigned one.  Unlike names,
+    /// UIDs are never reused, so this distinguishes an object from a
+    /// later one recreated with the same name.
+    fn uid(&self) -> Option<Cow<str>> {
+        match self.metadata() {
+            Cow::Borrowed(m) => m.uid.as_ref().map(|u| Cow::Borrowed(u.as_str())),
+            Cow::Owned(m) => m.uid.map(Cow::Owned),
+        }
+    }
 }
 
 pub trait List {
@@ -405,6 +932,47 @@ pub trait List {
     fn into_items(self) -> Vec<Self::Item>;
 }
 
+/// A `List` whose items have `Metadata`.
+///
+/// Bounds on associated types aren't implied at use sites, so generic
+/// code would otherwise need `L: List, L::Item: Metadata` everywhere.
+/// With this, `L: MetadataList` is enough.  Implemented for every
+/// suitable `List`.
+pub trait MetadataList: List {
+    type Object: Metadata;
+
+    fn metadata_items(&self) -> &[Self::Object];
+
+    /// The items created strictly after `t`.
+    fn created_after(&self, t: Time) -> Vec<&Self::Object> {
+        self.metadata_items()
+            .iter()
+            .filter(|o| o.metadata().created_after(t))
+            .collect()
+    }
+
+    /// The items created strictly before `t`, eg: pods older than a
+    /// week with `Time(Utc::now() - Duration::days(7))`.
+    fn created_before(&self, t: Time) -> Vec<&Self::Object> {
+        self.metadata_items()
+            .iter()
+            .filter(|o| o.metadata().created_before(t))
+            .collect()
+    }
+}
+
+impl<L> MetadataList for L
+where
+    L: List,
+    L::Item: Metadata,
+{
+    type Object = L::Item;
+
+    fn metadata_items(&self) -> &[L::Item] {
+        self.items()
+    }
+}
+
 impl<'a, T> IntoIterator for &'a List<Item = T> {
     type Item = &'a T;
     type IntoIter = slice::Iter<'a, T>;
@@ -455,6 +1023,435 @@ mod tests {
         assert_eq!(j.metadata().name.as_ref().unwrap(), "pod-example");
     }
 
+    #[test]
+    fn label_selector_string() {
+        use super::{LabelSelector, ListOptions};
+
+        let selector: LabelSelector = serde_json::from_value(json!({
+            "matchLabels": {"tier": "frontend", "app.kubernetes.io/name": "web"},
+        }))
+        .unwrap();
+        assert_eq!(
+            selector.to_string(),
+            "app.kubernetes.io/name=web,tier=frontend"
+        );
+
+        let selector: LabelSelector = serde_json::from_value(json!({
+            "matchLabels": {"app": "web"},
+            "matchExpressions": [
+                {"key": "env", "operator": "In", "values": ["staging", "prod"]},
+                {"key": "zone", "operator": "NotIn", "values": ["b", "a"]},
+                {"key": "canary", "operator": "DoesNotExist", "values": []},
+                {"key": "billing", "operator": "Exists", "values": []},
+            ],
+        }))
+        .unwrap();
+        assert_eq!(
+            selector.to_string(),
+            "app=web,billing,!canary,env in (prod,staging),zone notin (a,b)"
+        );
+
+        assert_eq!(LabelSelector::default().to_string(), "");
+
+        let opts = ListOptions::default().with_label_selector(&selector);
+        assert_eq!(
+            opts.label_selector,
+            "app=web,billing,!canary,env in (prod,staging),zone notin (a,b)"
+        );
+    }
+
+    #[test]
+    fn field_selector_string() {
+        use super::{FieldSelector, ListOptions};
+
+        let selector = FieldSelector::new()
+            .eq("status.phase", "Running")
+            .ne("metadata.namespace", "kube-system");
+        assert_eq!(
+            selector.to_string(),
+            "status.phase=Running,metadata.namespace!=kube-system"
+        );
+
+        let selector = FieldSelector::new().eq("metadata.name", r"a,b=c\d");
+        assert_eq!(selector.to_string(), r"metadata.name=a\,b\=c\\d");
+
+        assert_eq!(FieldSelector::new().to_string(), "");
+
+        let opts = ListOptions::default().with_field_selector(&selector);
+        assert_eq!(opts.field_selector, r"metadata.name=a\,b\=c\\d");
+    }
+
+    #[test]
+    fn item_list_from_items() {
+        use super::{ItemList, List};
+        use crate::core::v1::{Pod, PodList};
+
+        let mut pod = Pod::default();
+        pod.metadata.name = Some("pod-example".into());
+        let pods = vec![pod, Pod::default()];
+        let list = PodList::from_items(pods.clone());
+        let v = serde_json::to_value(&list).unwrap();
+        assert_eq!(v["kind"], "PodList");
+        assert_eq!(v["apiVersion"], "v1");
+        assert_eq!(v["metadata"]["resourceVersion"], "");
+        assert_eq!(v["items"][0]["metadata"]["name"], "pod-example");
+        assert_eq!(v["items"][1]["kind"], "Pod");
+
+        let list: ItemList<Pod> = serde_json::from_value(v).unwrap();
+        assert_eq!(list.into_items(), pods);
+    }
+
+    #[test]
+    fn fields_v1() {
+        use super::{FieldsV1, ObjectMeta};
+
+        let fields: FieldsV1 = serde_json::from_value(json!({
+            "f:metadata": {
+                "f:labels": {
+                    ".": {},
+                    "f:app": {},
+                },
+            },
+            "f:spec": {
+                "f:replicas": {},
+                "f:template": {
+                    "f:spec": {
+                        "f:containers": {
+                            "k:{\"name\":\"nginx\"}": {
+                                ".": {},
+                                "f:image": {},
+                            },
+                        },
+                    },
+                },
+            },
+        }))
+        .unwrap();
+        let paths: Vec<_> = fields
+            .paths()
+            .unwrap()
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                ".metadata.labels",
+                ".metadata.labels.app",
+                ".spec.replicas",
+                ".spec.template.spec.containers[{\"name\":\"nginx\"}]",
+                ".spec.template.spec.containers[{\"name\":\"nginx\"}].image",
+            ]
+        );
+
+        let bogus = FieldsV1(json!({"x:foo": {}}));
+        assert!(bogus.paths().is_err());
+
+        let meta: ObjectMeta = serde_json::from_value(json!({
+            "name": "example",
+            "managedFields": [
+                {
+                    "manager": "kubectl",
+                    "operation": "Apply",
+                    "apiVersion": "apps/v1",
+                    "fieldsType": "FieldsV1",
+                    "fieldsV1": {"f:spec": {"f:replicas": {}, "f:paused": {}}},
+                },
+                {
+                    "manager": "hpa",
+                    "operation": "Update",
+                    "apiVersion": "apps/v1",
+                    "fieldsType": "FieldsV1",
+                    "fieldsV1": {"f:spec": {"f:replicas": {}}},
+                },
+            ],
+        }))
+        .unwrap();
+        let conflicts: Vec<_> = meta
+            .field_manager_conflicts("kubectl", "hpa")
+            .unwrap()
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        assert_eq!(conflicts, vec![".spec.replicas"]);
+        assert!(meta
+            .field_manager_conflicts("kubectl", "nobody")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn uid() {
+        use crate::core::v1::Pod;
+
+        let mut j = pod_json();
+        assert_eq!(j.uid(), None);
+
+        // Deleted and recreated with the same name
+        j["metadata"]["uid"] = json!("d1b0d6a2-0001");
+        let old: Pod = serde_json::from_value(j.clone()).unwrap();
+        j["metadata"]["uid"] = json!("d1b0d6a2-0002");
+        let new: Pod = serde_json::from_value(j.clone()).unwrap();
+
+        assert_eq!(old.metadata().name, new.metadata().name);
+        assert_ne!(old.uid(), new.uid());
+        assert_eq!(new.uid(), j.uid());
+        assert_eq!(j.uid().unwrap(), "d1b0d6a2-0002");
+    }
+
+    #[test]
+    fn resource_version() {
+        use super::{ListMeta, ObjectMeta, ResourceVersion};
+
+        let meta: ObjectMeta =
+            serde_json::from_value(json!({"name": "x", "resourceVersion": "82834"})).unwrap();
+        let rv = meta.resource_version.unwrap();
+        assert_eq!(rv, ResourceVersion::from("82834"));
+        assert_eq!(rv.as_str(), "82834");
+        assert_eq!(serde_json::to_value(&rv).unwrap(), json!("82834"));
+
+        let list: ListMeta = serde_json::from_value(json!({})).unwrap();
+        assert!(list.resource_version.is_empty());
+    }
+
+    #[test]
+    fn bookmark_event() {
+        use super::{EventType, ListOptions, WatchEvent};
+
+        let line = r#"{"type":"BOOKMARK","object":{"kind":"Pod","apiVersion":"v1","metadata":{"resourceVersion":"12746"}}}"#;
+        let event: WatchEvent = serde_json::from_str(line).unwrap();
+        assert_eq!(event.typ, EventType::Bookmark);
+        assert!(!event.is_error());
+        assert_eq!(event.object["metadata"]["resourceVersion"], "12746");
+
+        let opts = ListOptions {
+            watch: true,
+            allow_watch_bookmarks: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&opts).unwrap(),
+            json!({"watch": true, "allowWatchBookmarks": true})
+        );
+    }
+
+    #[test]
+    fn reset_for_create() {
+        use super::ObjectMeta;
+
+        let mut meta: ObjectMeta = serde_json::from_value(json!({
+            "name": "etcd-minikube",
+            "namespace": "kube-system",
+            "labels": {"component": "etcd"},
+            "creationTimestamp": "2018-02-17T23:21:30Z",
+            "resourceVersion": "82834",
+            "selfLink": "/api/v1/namespaces/kube-system/pods/etcd-minikube",
+            "uid": "491e0972-1439-11e8-bdc8-525400cf4e41",
+            "generation": 3,
+        }))
+        .unwrap();
+        meta.reset_for_create();
+        assert_eq!(meta.uid, None);
+        assert_eq!(meta.resource_version, None);
+        assert_eq!(
+            serde_json::to_value(&meta).unwrap(),
+            serde_json::to_value(&ObjectMeta {
+                name: Some("etcd-minikube".into()),
+                namespace: Some("kube-system".into()),
+                labels: vec![("component".to_string(), "etcd".to_string())]
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            })
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn owner_references() {
+        use super::{ObjectMeta, OwnerReference};
+
+        let owner = |kind: &str, controller| OwnerReference {
+            api_version: "apps/v1".into(),
+            kind: kind.into(),
+            name: "web".into(),
+            uid: format!("{}-uid", kind),
+            block_owner_deletion: false,
+            controller,
+        };
+        let mut meta = ObjectMeta::default();
+        meta.add_owner_reference(owner("ReplicaSet", true)).unwrap();
+        meta.add_owner_reference(owner("Deployment", false))
+            .unwrap();
+        meta.add_owner_reference(owner("StatefulSet", false))
+            .unwrap();
+        assert_eq!(meta.owner_references.len(), 3);
+        assert_eq!(meta.controller_ref().unwrap().kind, "ReplicaSet");
+        meta.validate_owner_references().unwrap();
+
+        let err = meta
+            .add_owner_reference(owner("DaemonSet", true))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "only one owner reference can have controller set, found ReplicaSet/web, DaemonSet/web"
+        );
+        assert_eq!(meta.owner_references.len(), 3);
+
+        // Eg: built by hand, or deserialized
+        meta.owner_references.push(owner("Job", true));
+        assert!(meta.validate_owner_references().is_err());
+    }
+
+    #[test]
+    fn metadata_list() {
+        use super::MetadataList;
+        use crate::core::v1::PodList;
+
+        fn names<L: MetadataList>(list: &L) -> Vec<String> {
+            list.metadata_items()
+                .iter()
+                .map(|o| o.metadata().name.clone().unwrap_or_default())
+                .collect()
+        }
+
+        let list = json!({
+            "apiVersion": "v1",
+            "kind": "PodList",
+            "metadata": {},
+            "items": [pod_json(), {"metadata": {"name": "other"}}],
+        });
+        assert_eq!(names(&list), vec!["pod-example", "other"]);
+
+        let pods: PodList = serde_json::from_value(list).unwrap();
+        assert_eq!(names(&pods), vec!["pod-example", "other"]);
+    }
+
+    #[test]
+    fn item_list_kind() {
+        use super::ItemList;
+        use crate::core::v1::{Pod, Service};
+        use crate::TypeMeta;
+
+        assert_eq!(ItemList::<Pod>::kind(), "PodList");
+        assert_eq!(ItemList::<Service>::kind(), "ServiceList");
+        assert_eq!(ItemList::<Pod>::kind(), "PodList");
+    }
+
+    #[test]
+    fn created_after() {
+        use super::MetadataList;
+        use crate::core::v1::{Pod, PodList};
+        use crate::Time;
+
+        let pod = |name: &str, created: Option<&str>| {
+            json!({
+                "metadata": {"name": name, "creationTimestamp": created},
+            })
+        };
+        let pods: PodList = serde_json::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "PodList",
+            "metadata": {},
+            "items": [
+                pod("old", Some("2018-02-10T00:00:00Z")),
+                pod("threshold", Some("2018-02-17T00:00:00Z")),
+                pod("new", Some("2018-02-20T18:00:07Z")),
+                pod("uncreated", None),
+            ],
+        }))
+        .unwrap();
+        let names = |items: Vec<&Pod>| -> Vec<String> {
+            items
+                .into_iter()
+                .map(|o| o.metadata.name.clone().unwrap())
+                .collect()
+        };
+
+        let t: Time = "2018-02-17T00:00:00Z".parse().unwrap();
+        assert_eq!(names(pods.created_after(t)), vec!["new"]);
+        assert_eq!(names(pods.created_before(t)), vec!["old"]);
+        assert!(!pods.items[1].metadata.created_after(t));
+        assert!(!pods.items[3].metadata.created_after(t));
+        assert!(!pods.items[3].metadata.created_before(t));
+    }
+
+    #[test]
+    fn recommended_labels() {
+        use super::ObjectMeta;
+        use crate::well_known;
+
+        let meta: ObjectMeta = serde_json::from_value(json!({
+            "name": "mysql-abcxzy-0",
+            "labels": {
+                "app.kubernetes.io/name": "mysql",
+                "app.kubernetes.io/instance": "mysql-abcxzy",
+                "app.kubernetes.io/version": "5.7.21",
+                "app.kubernetes.io/managed-by": "helm",
+            },
+            "annotations": {
+                "kubernetes.io/change-cause": "upgrade",
+            },
+        }))
+        .unwrap();
+        assert_eq!(meta.app_name(), Some("mysql"));
+        assert_eq!(meta.app_instance(), Some("mysql-abcxzy"));
+        assert_eq!(meta.managed_by(), Some("helm"));
+        assert_eq!(meta.label(well_known::APP_VERSION), Some("5.7.21"));
+        assert_eq!(meta.label(well_known::APP_COMPONENT), None);
+        assert_eq!(meta.annotation(well_known::CHANGE_CAUSE), Some("upgrade"));
+
+        assert_eq!(ObjectMeta::default().app_name(), None);
+    }
+
+    #[test]
+    fn field_errors() {
+        use super::{CauseType, Status, StatusReason};
+        let status: Status = serde_json::from_value(json!({
+            "kind": "Status",
+            "apiVersion": "v1",
+            "metadata": {},
+            "status": "Failure",
+            "message": "Deployment.apps \"web\" is invalid",
+            "reason": "Invalid",
+            "details": {
+                "name": "web",
+                "group": "apps",
+                "kind": "Deployment",
+                "causes": [
+                    {
+                        "reason": "FieldValueInvalid",
+                        "message": "Invalid value: -1: must be greater than or equal to 0",
+                        "field": "spec.replicas",
+                    },
+                    {
+                        "reason": "FieldValueInvalid",
+                        "message": "Invalid value: \"Web\": a lowercase RFC 1123 label",
+                        "field": "spec.template.spec.containers[0].name",
+                    },
+                ],
+            },
+            "code": 422,
+        }))
+        .unwrap();
+        assert_eq!(status.reason, Some(StatusReason::Invalid));
+        assert_eq!(
+            status.field_errors(),
+            vec![
+                (
+                    "spec.replicas",
+                    Some(&CauseType::FieldValueInvalid),
+                    Some("Invalid value: -1: must be greater than or equal to 0"),
+                ),
+                (
+                    "spec.template.spec.containers[0].name",
+                    Some(&CauseType::FieldValueInvalid),
+                    Some("Invalid value: \"Web\": a lowercase RFC 1123 label"),
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn typed() {
         use crate::core::v1::Pod;
@@ -0,0 +1,166 @@
+use crate::core::v1::{NamespacedResource, Protocol};
+use crate::meta::v1::{ItemList, LabelSelector, Metadata, ObjectMeta};
+use crate::meta::{GroupVersion, GroupVersionResource};
+use crate::{IntOrString, Integer, TypeMeta, TypeMetaImpl};
+use std::borrow::Cow;
+
+const API_GROUP: &str = "networking.k8s.io/v1";
+pub const GROUP_VERSION: GroupVersion = GroupVersion {
+    group: "networking.k8s.io",
+    version: "v1",
+};
+
+pub struct NetworkPolicies;
+
+impl NamespacedResource for NetworkPolicies {
+    type List = NetworkPolicyList;
+
+    fn namespaced(&self) -> bool {
+        true
+    }
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("networkpolicies")
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkPolicy {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<NetworkPolicy>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub spec: NetworkPolicySpec,
+}
+
+pub type NetworkPolicyList = ItemList<NetworkPolicy>;
+
+impl TypeMeta for NetworkPolicy {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "NetworkPolicy"
+    }
+}
+
+impl Metadata for NetworkPolicy {
+    fn api_version(&self) -> &str {
+        <NetworkPolicy as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <NetworkPolicy as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkPolicySpec {
+    #[serde(default)]
+    pub pod_selector: LabelSelector,
+    #[serde(default)]
+    pub policy_types: Vec<PolicyType>,
+    #[serde(default)]
+    pub ingress: Vec<NetworkPolicyIngressRule>,
+    #[serde(default)]
+    pub egress: Vec<NetworkPolicyEgressRule>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PolicyType {
+    Ingress,
+    Egress,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkPolicyIngressRule {
+    #[serde(default)]
+    pub from: Vec<NetworkPolicyPeer>,
+    #[serde(default)]
+    pub ports: Vec<NetworkPolicyPort>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkPolicyEgressRule {
+    #[serde(default)]
+    pub to: Vec<NetworkPolicyPeer>,
+    #[serde(default)]
+    pub ports: Vec<NetworkPolicyPort>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkPolicyPeer {
+    pub ip_block: Option<IPBlock>,
+    pub namespace_selector: Option<LabelSelector>,
+    pub pod_selector: Option<LabelSelector>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IPBlock {
+    pub cidr: String,
+    #[serde(default)]
+    pub except: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkPolicyPort {
+    /// Defaults to TCP on the server.
+    pub protocol: Option<Protocol>,
+    /// Absent means all ports.
+    pub port: Option<IntOrString>,
+    pub end_port: Option<Integer>,
+}
+
+#[test]
+fn deser_networkpolicy() {
+    let yaml = r#"
+      apiVersion: networking.k8s.io/v1
+      kind: NetworkPolicy
+      metadata:
+        name: allow-frontend
+        namespace: default
+      spec:
+        podSelector:
+          matchLabels:
+            app: backend
+        policyTypes:
+        - Ingress
+        ingress:
+        - from:
+          - podSelector:
+              matchLabels:
+                app: frontend
+          - ipBlock:
+              cidr: 172.17.0.0/16
+              except:
+              - 172.17.1.0/24
+          ports:
+          - protocol: TCP
+            port: 8080
+"#;
+    let np: NetworkPolicy = ::serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(np.metadata.name, Some("allow-frontend".into()));
+    assert_eq!(np.spec.pod_selector.match_labels["app"], "backend");
+    assert_eq!(np.spec.policy_types, vec![PolicyType::Ingress]);
+    assert!(np.spec.egress.is_empty());
+
+    let rule = &np.spec.ingress[0];
+    let selector = rule.from[0].pod_selector.as_ref().unwrap();
+    assert_eq!(selector.match_labels["app"], "frontend");
+    assert_eq!(
+        rule.from[1].ip_block.as_ref().unwrap().cidr,
+        "172.17.0.0/16"
+    );
+    assert_eq!(rule.ports[0].protocol, Some(Protocol::TCP));
+    assert_eq!(rule.ports[0].port, Some(IntOrString::Int(8080)));
+}
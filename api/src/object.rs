@@ -0,0 +1,180 @@
+//! An enum over every kind this crate has a type for, to decode
+//! arbitrary manifests.
+
+use crate::apps::v1::{ControllerRevision, DaemonSet, Deployment, ReplicaSet, StatefulSet};
+use crate::batch::v1::Job;
+use crate::batch::v1beta1::CronJob;
+use crate::core::v1::{ConfigMap, Endpoints, Event, Namespace, Node, Pod, Secret, Service};
+use crate::discovery::v1::EndpointSlice;
+use crate::meta::v1::{Metadata, ObjectMeta};
+use crate::networking::v1::NetworkPolicy;
+use crate::scheduling::v1::PriorityClass;
+use crate::TypeMeta;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use serde_json::{self, Value};
+use std::borrow::Cow;
+
+macro_rules! objects {
+    ($($variant:ident($ty:ty),)*) => {
+        /// An object of any kind, decoded as the matching type when
+        /// its `apiVersion` and `kind` are known, else left as a
+        /// `Value`.
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum Object {
+            $($variant($ty),)*
+            Unknown(Value),
+        }
+
+        impl<'de> Deserialize<'de> for Object {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let value = Value::deserialize(deserializer)?;
+                let key = (
+                    value["apiVersion"].as_str().unwrap_or_default().to_string(),
+                    value["kind"].as_str().unwrap_or_default().to_string(),
+                );
+                $(
+                    if key.0 == <$ty as TypeMeta>::api_version()
+                        && key.1 == <$ty as TypeMeta>::kind()
+                    {
+                        return serde_json::from_value(value)
+                            .map(Object::$variant)
+                            .map_err(de::Error::custom);
+                    }
+                )*
+                Ok(Object::Unknown(value))
+            }
+        }
+
+        impl Serialize for Object {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                match *self {
+                    $(Object::$variant(ref o) => o.serialize(serializer),)*
+                    Object::Unknown(ref v) => v.serialize(serializer),
+                }
+            }
+        }
+
+        impl Metadata for Object {
+            fn api_version(&self) -> &str {
+                match *self {
+                    $(Object::$variant(ref o) => Metadata::api_version(o),)*
+                    Object::Unknown(ref v) => Metadata::api_version(v),
+                }
+            }
+            fn kind(&self) -> &str {
+                match *self {
+                    $(Object::$variant(ref o) => Metadata::kind(o),)*
+                    Object::Unknown(ref v) => Metadata::kind(v),
+                }
+            }
+            fn metadata(&self) -> Cow<ObjectMeta> {
+                match *self {
+                    $(Object::$variant(ref o) => o.metadata(),)*
+                    Object::Unknown(ref v) => v.metadata(),
+                }
+            }
+        }
+    };
+}
+
+objects! {
+    ConfigMap(ConfigMap),
+    ControllerRevision(ControllerRevision),
+    CronJob(CronJob),
+    DaemonSet(DaemonSet),
+    Deployment(Deployment),
+    EndpointSlice(EndpointSlice),
+    Endpoints(Endpoints),
+    Event(Event),
+    Job(Job),
+    Namespace(Namespace),
+    NetworkPolicy(NetworkPolicy),
+    Node(Node),
+    Pod(Pod),
+    PriorityClass(PriorityClass),
+    ReplicaSet(ReplicaSet),
+    Secret(Secret),
+    Service(Service),
+    StatefulSet(StatefulSet),
+}
+
+#[test]
+fn deser_manifest() {
+    let yaml = r#"
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: settings
+data:
+  colour: blue
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+spec:
+  replicas: 2
+  selector:
+    matchLabels: {app: web}
+  template:
+    metadata:
+      labels: {app: web}
+    spec:
+      containers:
+      - name: web
+        image: nginx
+---
+apiVersion: example.com/v1
+kind: Widget
+metadata:
+  name: sprocket
+---
+apiVersion: apps/v1beta1
+kind: Deployment
+metadata:
+  name: old
+"#;
+    let objs: Vec<Object> = ::serde_yaml::Deserializer::from_str(yaml)
+        .map(|d| Object::deserialize(d).unwrap())
+        .collect();
+    assert_eq!(objs.len(), 4);
+    match objs[0] {
+        Object::ConfigMap(ref cm) => assert_eq!(cm.data["colour"], "blue"),
+        ref o => panic!("unexpected {:?}", o),
+    }
+    match objs[1] {
+        Object::Deployment(ref d) => assert_eq!(d.spec.replicas, 2),
+        ref o => panic!("unexpected {:?}", o),
+    }
+    match objs[2] {
+        Object::Unknown(ref v) => assert_eq!(v["kind"], "Widget"),
+        ref o => panic!("unexpected {:?}", o),
+    }
+    // Only the versions we have types for
+    match objs[3] {
+        Object::Unknown(_) => (),
+        ref o => panic!("unexpected {:?}", o),
+    }
+
+    let names: Vec<_> = objs
+        .iter()
+        .map(|o| (o.kind().to_string(), o.metadata().name.clone().unwrap()))
+        .collect();
+    assert_eq!(names[1], ("Deployment".to_string(), "web".to_string()));
+    assert_eq!(names[2], ("Widget".to_string(), "sprocket".to_string()));
+
+    let json = serde_json::to_value(&objs[0]).unwrap();
+    assert_eq!(json["kind"], "ConfigMap");
+    assert_eq!(json["data"]["colour"], "blue");
+
+    // A known kind that doesn't decode is an error, not Unknown
+    let bad = json!({"apiVersion": "v1", "kind": "Pod", "spec": {"containers": 42}});
+    assert!(serde_json::from_value::<Object>(bad).is_err());
+}
@@ -0,0 +1,248 @@
+//! Field documentation from the server's OpenAPI (v2) schema, as
+//! shown by `kubectl explain`.
+
+use crate::meta::GroupVersionKind;
+use serde_json::Value;
+
+#[derive(Debug, Fail)]
+pub enum ExplainError {
+    #[fail(display = "no schema for {}", _0)]
+    UnknownKind(String),
+    #[fail(display = "field {} does not exist in {}", path, kind)]
+    UnknownField { kind: String, path: String },
+}
+
+/// The documentation for one field (or a whole kind).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDoc {
+    /// As `kubectl explain` shows it, eg: `string`, `Object`,
+    /// `[]Object` or `map[string]string`.
+    pub field_type: String,
+    pub description: String,
+    /// True if the enclosing object requires this field.  Always false
+    /// for a whole kind.
+    pub required: bool,
+}
+
+/// Looks up `field_path` (eg: `spec.containers.image`, or `""` for
+/// the kind itself) in the schema for `gvk`, within `doc` as served at
+/// `/openapi/v2`.  Lists are stepped through, so `containers.image`
+/// is the `image` of each container.
+pub fn explain(
+    doc: &Value,
+    gvk: &GroupVersionKind,
+    field_path: &str,
+) -> Result<FieldDoc, ExplainError> {
+    let mut schema =
+        find_kind(doc, gvk).ok_or_else(|| ExplainError::UnknownKind(gvk.to_string()))?;
+    let mut required = false;
+    let not_found = || ExplainError::UnknownField {
+        kind: gvk.kind.into(),
+        path: field_path.into(),
+    };
+    for field in field_path.split('.').filter(|f| *f != "") {
+        let object = element(doc, schema);
+        required = object["required"]
+            .as_array()
+            .map(|r| r.iter().any(|r| r == field))
+            .unwrap_or(false);
+        schema = object["properties"].get(field).ok_or_else(not_found)?;
+    }
+    // A field's own description, else that of the type it refers to
+    let description = schema["description"]
+        .as_str()
+        .or_else(|| resolve(doc, schema)["description"].as_str())
+        .unwrap_or("");
+    Ok(FieldDoc {
+        field_type: type_name(doc, schema),
+        description: description.into(),
+        required,
+    })
+}
+
+fn find_kind<'a>(doc: &'a Value, gvk: &GroupVersionKind) -> Option<&'a Value> {
+    let matches = |s: &Value| {
+        s["x-kubernetes-group-version-kind"]
+            .as_array()
+            .map(|gvks| {
+                gvks.iter().any(|g| {
+                    g["group"] == gvk.group && g["version"] == gvk.version && g["kind"] == gvk.kind
+                })
+            })
+            .unwrap_or(false)
+    };
+    doc["definitions"]
+        .as_object()
+        .and_then(|defs| defs.values().find(|s| matches(s)))
+}
+
+/// Follows `$ref`s to the definition they name.
+fn resolve<'a>(doc: &'a Value, mut schema: &'a Value) -> &'a Value {
+    // Bounded, in case of a reference loop
+    for _ in 0..10 {
+        let name = match schema["$ref"].as_str() {
+            Some(r) if r.starts_with("#/definitions/") => &r["#/definitions/".len()..],
+            _ => break,
+        };
+        match doc["definitions"].get(name) {
+            Some(s) => schema = s,
+            None => break,
+        }
+    }
+    schema
+}
+
+/// The schema of the elements of a list, else `schema` itself.
+fn element<'a>(doc: &'a Value, schema: &'a Value) -> &'a Value {
+    let mut schema = resolve(doc, schema);
+    while schema["type"] == "array" {
+        schema = resolve(doc, &schema["items"]);
+    }
+    schema
+}
+
+fn type_name(doc: &Value, schema: &Value) -> String {
+    let schema = resolve(doc, schema);
+    match schema["type"].as_str() {
+        Some("array") => format!("[]{}", type_name(doc, &schema["items"])),
+        Some("object") if schema.get("additionalProperties").is_some() => format!(
+            "map[string]{}",
+            type_name(doc, &schema["additionalProperties"])
+        ),
+        Some(t) if t != "object" => t.into(),
+        _ => "Object".into(),
+    }
+}
+
+#[test]
+fn explain_fields() {
+    let doc = json!({
+        "swagger": "2.0",
+        "definitions": {
+            "io.k8s.api.apps.v1.Deployment": {
+                "description": "Deployment enables declarative updates for Pods and ReplicaSets.",
+                "properties": {
+                    "metadata": {
+                        "$ref": "#/definitions/io.k8s.apimachinery.pkg.apis.meta.v1.ObjectMeta",
+                    },
+                    "spec": {
+                        "$ref": "#/definitions/io.k8s.api.apps.v1.DeploymentSpec",
+                        "description": "Specification of the desired behavior of the Deployment.",
+                    },
+                },
+                "type": "object",
+                "x-kubernetes-group-version-kind": [
+                    {"group": "apps", "kind": "Deployment", "version": "v1"},
+                ],
+            },
+            "io.k8s.api.apps.v1.DeploymentSpec": {
+                "description": "DeploymentSpec is the specification of the desired behavior of the Deployment.",
+                "properties": {
+                    "replicas": {
+                        "description": "Number of desired pods.",
+                        "format": "int32",
+                        "type": "integer",
+                    },
+                    "selector": {
+                        "description": "Label selector for pods.",
+                        "type": "object",
+                    },
+                    "template": {
+                        "$ref": "#/definitions/io.k8s.api.core.v1.PodTemplateSpec",
+                        "description": "Template describes the pods that will be created.",
+                    },
+                },
+                "required": ["selector", "template"],
+                "type": "object",
+            },
+            "io.k8s.api.core.v1.PodTemplateSpec": {
+                "properties": {
+                    "spec": {"$ref": "#/definitions/io.k8s.api.core.v1.PodSpec"},
+                },
+                "type": "object",
+            },
+            "io.k8s.api.core.v1.PodSpec": {
+                "properties": {
+                    "containers": {
+                        "description": "List of containers belonging to the pod.",
+                        "items": {"$ref": "#/definitions/io.k8s.api.core.v1.Container"},
+                        "type": "array",
+                    },
+                    "nodeSelector": {
+                        "additionalProperties": {"type": "string"},
+                        "description": "NodeSelector is a selector which must be true for the pod to fit on a node.",
+                        "type": "object",
+                    },
+                },
+                "required": ["containers"],
+                "type": "object",
+            },
+            "io.k8s.api.core.v1.Container": {
+                "properties": {
+                    "image": {"description": "Container image name.", "type": "string"},
+                    "name": {"description": "Name of the container.", "type": "string"},
+                },
+                "required": ["name"],
+                "type": "object",
+            },
+            "io.k8s.apimachinery.pkg.apis.meta.v1.ObjectMeta": {
+                "description": "ObjectMeta is metadata that all persisted resources must have.",
+                "type": "object",
+            },
+        },
+    });
+    let gvk = GroupVersionKind::from_str("apps/v1/Deployment").unwrap();
+    let doc_for = |path| explain(&doc, &gvk, path).unwrap();
+
+    assert_eq!(
+        doc_for(""),
+        FieldDoc {
+            field_type: "Object".into(),
+            description: "Deployment enables declarative updates for Pods and ReplicaSets.".into(),
+            required: false,
+        }
+    );
+    assert_eq!(
+        doc_for("spec.replicas"),
+        FieldDoc {
+            field_type: "integer".into(),
+            description: "Number of desired pods.".into(),
+            required: false,
+        }
+    );
+    assert!(doc_for("spec.template").required);
+    assert_eq!(
+        doc_for("spec").description,
+        "Specification of the desired behavior of the Deployment."
+    );
+    assert_eq!(
+        doc_for("spec.template.spec.containers.name").description,
+        "Name of the container."
+    );
+    // Falls back to the referenced definition's description
+    assert_eq!(
+        doc_for("metadata").description,
+        "ObjectMeta is metadata that all persisted resources must have."
+    );
+    let containers = doc_for("spec.template.spec.containers");
+    assert_eq!(containers.field_type, "[]Object");
+    assert!(containers.required);
+    let name = doc_for("spec.template.spec.containers.name");
+    assert_eq!(name.field_type, "string");
+    assert!(name.required);
+    assert!(!doc_for("spec.template.spec.containers.image").required);
+    assert_eq!(
+        doc_for("spec.template.spec.nodeSelector").field_type,
+        "map[string]string"
+    );
+
+    assert_eq!(
+        explain(&doc, &gvk, "spec.replica").unwrap_err().to_string(),
+        "field spec.replica does not exist in Deployment"
+    );
+    let gvk = GroupVersionKind::from_str("v1/Pod").unwrap();
+    assert_eq!(
+        explain(&doc, &gvk, "").unwrap_err().to_string(),
+        "no schema for /v1, Kind=Pod"
+    );
+}
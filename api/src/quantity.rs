@@ -0,0 +1,366 @@
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::Sum;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
+
+/// A fixed-point amount, eg: of CPU or memory, such as `128Mi`, `1.5`,
+/// `250m` or `2Gi`.
+///
+/// Parsed as in `k8s.io/apimachinery/pkg/api/resource/quantity.go`:
+/// a decimal number followed by a binary SI suffix (`Ki`, `Mi`, `Gi`,
+/// `Ti`, `Pi`, `Ei`), a decimal SI suffix (`n`, `u`, `m`, `k`, `M`,
+/// `G`, `T`, `P`, `E`), or a decimal exponent (`1e3`).  Values are held
+/// exactly to nano precision; anything finer is rounded up (away from
+/// zero), as the server does.
+///
+/// The original string is kept, and is what serializes back, so
+/// objects round-trip unchanged.  Comparisons (`Eq`, `Ord`, `Hash`)
+/// are by value: `1Gi == 1024Mi`.
+#[derive(Clone)]
+pub struct Quantity {
+    repr: String,
+    nanos: i128,
+}
+
+#[derive(Fail, Debug, Clone, PartialEq)]
+#[fail(display = "Invalid quantity {:?}: {}", quantity, reason)]
+pub struct InvalidQuantityError {
+    pub quantity: String,
+    pub reason: &'static str,
+}
+
+const NANOS_PER_UNIT: i128 = 1_000_000_000;
+
+impl Quantity {
+    fn from_nanos(nanos: i128) -> Self {
+        Quantity {
+            repr: format_nanos(nanos),
+            nanos,
+        }
+    }
+
+    /// The canonical string form, as parsed.
+    pub fn as_str(&self) -> &str {
+        &self.repr
+    }
+
+    /// The value as a float.  May lose precision.
+    pub fn as_f64(&self) -> f64 {
+        self.nanos as f64 / NANOS_PER_UNIT as f64
+    }
+
+    /// The value in thousandths, rounded up (away from zero), eg: `250`
+    /// for `250m` or `0.25`.  Saturates at the limits of `i64`.
+    pub fn as_milli(&self) -> i64 {
+        let milli = div_away_from_zero(self.nanos, 1_000_000);
+        if milli > i128::from(i64::max_value()) {
+            i64::max_value()
+        } else if milli < i128::from(i64::min_value()) {
+            i64::min_value()
+        } else {
+            milli as i64
+        }
+    }
+}
+
+fn div_away_from_zero(n: i128, d: i128) -> i128 {
+    let q = n / d;
+    if n % d == 0 {
+        q
+    } else if n < 0 {
+        q - 1
+    } else {
+        q + 1
+    }
+}
+
+/// Plain decimal, eg: `1.5` or `134217728`.  Used for the results of
+/// arithmetic, where there's no original string to keep.
+fn format_nanos(nanos: i128) -> String {
+    let sign = if nanos < 0 { "-" } else { "" };
+    let whole = (nanos / NANOS_PER_UNIT).abs();
+    let frac = (nanos % NANOS_PER_UNIT).abs();
+    if frac == 0 {
+        format!("{}{}", sign, whole)
+    } else {
+        let frac = format!("{:09}", frac);
+        format!("{}{}.{}", sign, whole, frac.trim_end_matches('0'))
+    }
+}
+
+fn pow10(exp: u32) -> Option<i128> {
+    10i128.checked_pow(exp)
+}
+
+fn parse_nanos(s: &str) -> Result<i128, &'static str> {
+    let (negative, rest) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let num_len = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(rest.len());
+    let (number, suffix) = rest.split_at(num_len);
+
+    let mut parts = number.splitn(2, '.');
+    let whole = parts.next().unwrap_or("");
+    let frac = parts.next().unwrap_or("");
+    if whole.is_empty() && frac.is_empty() {
+        return Err("no number");
+    }
+    if frac.contains('.') {
+        return Err("more than one decimal point");
+    }
+    let mut mantissa: i128 = 0;
+    for d in whole.bytes().chain(frac.bytes()) {
+        mantissa = mantissa
+            .checked_mul(10)
+            .and_then(|m| m.checked_add(i128::from(d - b'0')))
+            .ok_or("too large")?;
+    }
+
+    // value = mantissa * multiplier * 10^exp10 / 10^frac.len(), in nanos
+    let (multiplier, exp10): (i128, i32) = match suffix {
+        "" => (1, 0),
+        "n" => (1, -9),
+        "u" => (1, -6),
+        "m" => (1, -3),
+        "k" => (1, 3),
+        "M" => (1, 6),
+        "G" => (1, 9),
+        "T" => (1, 12),
+        "P" => (1, 15),
+        "E" => (1, 18),
+        "Ki" => (1 << 10, 0),
+        "Mi" => (1 << 20, 0),
+        "Gi" => (1 << 30, 0),
+        "Ti" => (1 << 40, 0),
+        "Pi" => (1 << 50, 0),
+        "Ei" => (1 << 60, 0),
+        _ if suffix.starts_with('e') || suffix.starts_with('E') => {
+            let exp = suffix[1..].parse().map_err(|_| "invalid exponent")?;
+            (1, exp)
+        }
+        _ => return Err("unknown suffix"),
+    };
+    let scale = i64::from(exp10) + 9 - frac.len() as i64;
+
+    let value = mantissa.checked_mul(multiplier).ok_or("too large")?;
+    let nanos = if value == 0 {
+        0
+    } else if scale >= 0 {
+        pow10(scale as u32)
+            .and_then(|p| value.checked_mul(p))
+            .ok_or("too large")?
+    } else {
+        // Finer than nano precision: round up
+        match pow10((-scale) as u32) {
+            Some(p) => div_away_from_zero(value, p),
+            None => 1,
+        }
+    };
+    Ok(if negative { -nanos } else { nanos })
+}
+
+impl FromStr for Quantity {
+    type Err = InvalidQuantityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_nanos(s)
+            .map(|nanos| Quantity {
+                repr: s.to_string(),
+                nanos,
+            })
+            .map_err(|reason| InvalidQuantityError {
+                quantity: s.to_string(),
+                reason,
+            })
+    }
+}
+
+impl Default for Quantity {
+    fn default() -> Self {
+        Quantity {
+            repr: "0".to_string(),
+            nanos: 0,
+        }
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.repr)
+    }
+}
+
+impl fmt::Debug for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Quantity({:?})", self.repr)
+    }
+}
+
+impl PartialEq for Quantity {
+    fn eq(&self, other: &Self) -> bool {
+        self.nanos == other.nanos
+    }
+}
+
+impl Eq for Quantity {}
+
+impl PartialOrd for Quantity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Quantity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.nanos.cmp(&other.nanos)
+    }
+}
+
+impl Hash for Quantity {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.nanos.hash(state)
+    }
+}
+
+impl Add for Quantity {
+    type Output = Quantity;
+
+    /// Saturates, rather than overflowing.
+    fn add(self, other: Quantity) -> Quantity {
+        Quantity::from_nanos(self.nanos.saturating_add(other.nanos))
+    }
+}
+
+impl Sub for Quantity {
+    type Output = Quantity;
+
+    /// Saturates, rather than overflowing.
+    fn sub(self, other: Quantity) -> Quantity {
+        Quantity::from_nanos(self.nanos.saturating_sub(other.nanos))
+    }
+}
+
+impl<'a> Add<&'a Quantity> for Quantity {
+    type Output = Quantity;
+
+    fn add(self, other: &Quantity) -> Quantity {
+        Quantity::from_nanos(self.nanos.saturating_add(other.nanos))
+    }
+}
+
+impl Sum for Quantity {
+    fn sum<I: Iterator<Item = Quantity>>(iter: I) -> Quantity {
+        iter.fold(Quantity::default(), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Quantity> for Quantity {
+    fn sum<I: Iterator<Item = &'a Quantity>>(iter: I) -> Quantity {
+        iter.fold(Quantity::default(), Add::add)
+    }
+}
+
+impl Serialize for Quantity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.repr)
+    }
+}
+
+impl<'de> Deserialize<'de> for Quantity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct QuantityVisitor;
+
+        impl<'de> Visitor<'de> for QuantityVisitor {
+            type Value = Quantity;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a quantity, such as \"128Mi\" or \"250m\"")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            // The server also accepts bare JSON numbers
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                self.visit_str(&v.to_string())
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                self.visit_str(&v.to_string())
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+                self.visit_str(&v.to_string())
+            }
+        }
+
+        deserializer.deserialize_any(QuantityVisitor)
+    }
+}
+
+#[test]
+fn quantity_parse() {
+    let q = |s: &str| s.parse::<Quantity>().unwrap();
+
+    assert_eq!(q("128Mi").as_f64(), 128.0 * 1024.0 * 1024.0);
+    assert_eq!(q("2Gi").as_milli(), 2 * 1024 * 1024 * 1024 * 1000);
+    assert_eq!(q("1.5").as_milli(), 1500);
+    assert_eq!(q("250m").as_milli(), 250);
+    assert_eq!(q("250m").as_f64(), 0.25);
+    assert_eq!(q("1k").as_milli(), 1_000_000);
+    assert_eq!(q("1e3").as_milli(), 1_000_000);
+    assert_eq!(q("1E-3").as_milli(), 1);
+    assert_eq!(q("-1.5Ki").as_milli(), -1536 * 1000);
+    assert_eq!(q(".5").as_milli(), 500);
+    assert_eq!(q("100n").as_milli(), 1); // rounded up
+    assert_eq!(q("1Ei").as_milli(), i64::max_value()); // saturated
+    assert_eq!(q("1Ei").as_f64(), (1u64 << 60) as f64);
+
+    assert_eq!(q("1Gi"), q("1024Mi"));
+    assert_eq!(q("0.5"), q("500m"));
+    assert!(q("1Gi") > q("1G"));
+    assert!(q("999m") < q("1"));
+    assert_eq!(q("1Gi").as_str(), "1Gi");
+
+    for bad in &["", "Mi", "1.2.3", "1Q", "1mi", "1e", "--1", "1 Gi"] {
+        assert!(bad.parse::<Quantity>().is_err(), "{:?}", bad);
+    }
+}
+
+#[test]
+fn quantity_arithmetic() {
+    let q = |s: &str| s.parse::<Quantity>().unwrap();
+
+    assert_eq!(q("1Gi") + q("512Mi"), q("1.5Gi"));
+    assert_eq!((q("1Gi") + q("512Mi")).as_str(), "1610612736");
+    assert_eq!((q("1") - q("250m")).as_str(), "0.75");
+    assert_eq!((q("250m") - q("1")).as_str(), "-0.75");
+    assert_eq!((q("100m") - q("100m")).as_str(), "0");
+    let total: Quantity = ["100m", "200m", "1.5"].iter().map(|s| q(s)).sum();
+    assert_eq!(total.as_milli(), 1800);
+    assert_eq!(total.as_str(), "1.8");
+}
+
+#[test]
+fn quantity_serde() {
+    use serde_json;
+
+    for s in &["128Mi", "1.5", "250m", "2Gi", "0.50", "1e3"] {
+        let json = format!("{:?}", s);
+        let q: Quantity = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&q).unwrap(), json);
+    }
+
+    let q: Quantity = serde_json::from_str("2").unwrap();
+    assert_eq!(q.as_milli(), 2000);
+    assert!(serde_json::from_str::<Quantity>(r#""2 cores""#).is_err());
+}
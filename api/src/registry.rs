@@ -0,0 +1,105 @@
+//! Runtime dispatch from a GroupVersionKind to the registered Rust
+//! type for that kind.
+
+use crate::meta::{GroupVersion, GroupVersionKind};
+use crate::TypeMeta;
+use serde::de::DeserializeOwned;
+use serde_json::{self, Value};
+use std::any::Any;
+use std::collections::HashMap;
+
+type DecodeFn = fn(Value) -> Result<Box<dyn Any>, serde_json::Error>;
+
+fn decode<T: DeserializeOwned + 'static>(v: Value) -> Result<Box<dyn Any>, serde_json::Error> {
+    let o: T = serde_json::from_value(v)?;
+    Ok(Box::new(o))
+}
+
+#[derive(Debug, Fail)]
+pub enum DecodeError {
+    #[fail(display = "no type registered for {}", _0)]
+    Unregistered(String),
+    #[fail(display = "unable to decode object: {}", _0)]
+    Json(#[cause] serde_json::Error),
+}
+
+/// Maps known kinds to the Rust types that decode them, so generic
+/// code can turn an untyped `Value` into the right typed struct.
+#[derive(Debug, Default, Clone)]
+pub struct TypeRegistry {
+    // Keyed by (apiVersion, kind)
+    types: HashMap<(String, String), DecodeFn>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn register<T>(&mut self)
+    where
+        T: TypeMeta + DeserializeOwned + 'static,
+    {
+        let key = (T::api_version().to_string(), T::kind().to_string());
+        self.types.insert(key, decode::<T>);
+    }
+
+    pub fn is_registered(&self, gvk: &GroupVersionKind) -> bool {
+        self.types.contains_key(&key(gvk))
+    }
+
+    /// Decodes `value` as the type registered for `gvk`.  Use
+    /// `Box<Any>::downcast` to recover the concrete type.
+    pub fn decode(
+        &self,
+        gvk: &GroupVersionKind,
+        value: Value,
+    ) -> Result<Box<dyn Any>, DecodeError> {
+        let f = self
+            .types
+            .get(&key(gvk))
+            .ok_or_else(|| DecodeError::Unregistered(gvk.to_string()))?;
+        f(value).map_err(DecodeError::Json)
+    }
+}
+
+fn key(gvk: &GroupVersionKind) -> (String, String) {
+    let gv: GroupVersion = gvk.clone().into();
+    (gv.to_string(), gvk.kind.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::v1::{Namespace, Pod};
+
+    #[test]
+    fn registry_decode() {
+        let mut registry = TypeRegistry::new();
+        registry.register::<Pod>();
+
+        let value = json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {"name": "mypod"},
+            "spec": {"containers": [{"name": "main", "image": "busybox"}]},
+        });
+        let gvk = GroupVersionKind::from_str("v1/Pod").unwrap();
+        assert!(registry.is_registered(&gvk));
+
+        let obj = registry.decode(&gvk, value.clone()).unwrap();
+        let pod = obj.downcast::<Pod>().unwrap();
+        assert_eq!(pod.metadata.name, Some("mypod".into()));
+        assert_eq!(pod.spec.containers[0].image, Some("busybox".into()));
+
+        let gvk = GroupVersionKind::from_str("v1/Namespace").unwrap();
+        assert!(!registry.is_registered(&gvk));
+        match registry.decode(&gvk, value) {
+            Err(DecodeError::Unregistered(ref s)) => assert_eq!(s, "/v1, Kind=Namespace"),
+            r => panic!("unexpected result {:?}", r.map(|_| ())),
+        }
+
+        registry.register::<Namespace>();
+        assert!(registry.is_registered(&gvk));
+    }
+}
@@ -0,0 +1,273 @@
+//! Maps between kinds and the resources that serve them, using
+//! discovery information from the server.
+
+use crate::meta::v1::{APIResource, APIResourceList};
+use crate::meta::{GroupResource, GroupVersion, GroupVersionKind, GroupVersionResource};
+
+#[derive(Debug, Fail)]
+#[fail(display = "no matches for {}", value)]
+pub struct NoMatchError {
+    pub value: String,
+}
+
+/// Resolves kinds to resources (eg: `apps/v1, Kind=Deployment` to
+/// `deployments`) and back, from cached `APIResourceList`s.
+///
+/// Subresources (eg: `pods/status`) are never matched.
+#[derive(Debug, Default, Clone)]
+pub struct RESTMapper {
+    lists: Vec<APIResourceList>,
+}
+
+impl RESTMapper {
+    pub fn new(lists: Vec<APIResourceList>) -> Self {
+        RESTMapper { lists }
+    }
+
+    /// Adds (or replaces) the resources for one group version.
+    pub fn add(&mut self, list: APIResourceList) {
+        self.lists.retain(|l| l.group_version != list.group_version);
+        self.lists.push(list);
+    }
+
+    fn resources<'a>(&'a self) -> impl Iterator<Item = (GroupVersion<'a>, &'a APIResource)> + 'a {
+        self.lists
+            .iter()
+            .filter_map(|l| {
+                GroupVersion::from_str(&l.group_version)
+                    .ok()
+                    .map(|gv| (gv, l))
+            })
+            .flat_map(|(gv, l)| l.resources.iter().map(move |r| (gv.clone(), r)))
+            .filter(|&(_, r)| !r.name.contains('/'))
+    }
+
+    fn find(&self, gvr: &GroupVersionResource) -> Option<(GroupVersion, &APIResource)> {
+        self.resources().find(|&(ref gv, r)| {
+            gv.group == gvr.group && gv.version == gvr.version && r.name == gvr.resource
+        })
+    }
+
+    pub fn resource_for(
+        &self,
+        gvk: &GroupVersionKind,
+    ) -> Result<GroupVersionResource, NoMatchError> {
+        self.resources()
+            .find(|&(ref gv, r)| {
+                gv.group == gvk.group && gv.version == gvk.version && r.kind == gvk.kind
+            })
+            .map(|(gv, r)| gv.with_resource(&r.name))
+            .ok_or_else(|| NoMatchError {
+                value: gvk.to_string(),
+            })
+    }
+
+    pub fn kind_for(&self, gvr: &GroupVersionResource) -> Result<GroupVersionKind, NoMatchError> {
+        self.find(gvr)
+            .map(|(gv, r)| gv.with_kind(&r.kind))
+            .ok_or_else(|| NoMatchError {
+                value: gvr.to_string(),
+            })
+    }
+
+    /// Resolves a resource as given on a command line (eg: `po`,
+    /// `deploy`, `Pod` or `deployments.apps`) to the resource that
+    /// serves it.  `arg` is matched case-insensitively against resource
+    /// names, then singular names, then short names, then kinds; an
+    /// optional `.group` suffix limits the match to that group.  Where
+    /// several versions match, the first list added wins.
+    pub fn resolve_resource<'a>(
+        &'a self,
+        arg: &str,
+    ) -> Result<GroupVersionResource<'a>, NoMatchError> {
+        let err = || NoMatchError { value: arg.into() };
+        let arg = arg.to_lowercase();
+        let gr = GroupResource::from_str(&arg).map_err(|_| err())?;
+        let in_group = |gv: &GroupVersion| gr.group == "" || gv.group == gr.group;
+        let matchers: [&dyn Fn(&APIResource) -> bool; 4] = [
+            &|r| r.name == gr.resource,
+            &|r| r.singular_name.to_lowercase() == gr.resource,
+            &|r| {
+                r.short_names
+                    .iter()
+                    .any(|s| s.to_lowercase() == gr.resource)
+            },
+            &|r| r.kind.to_lowercase() == gr.resource,
+        ];
+        matchers
+            .iter()
+            .filter_map(|m| self.resources().find(|&(ref gv, r)| in_group(gv) && m(r)))
+            .next()
+            .map(|(gv, r)| gv.with_resource(&r.name))
+            .ok_or_else(err)
+    }
+
+    /// The resources in `category` (eg: `all`), once each, in the
+    /// version of the first list added that has them.
+    pub fn resources_in_category(&self, category: &str) -> Vec<GroupVersionResource> {
+        let mut found: Vec<GroupVersionResource> = vec![];
+        for (gv, r) in self.resources() {
+            let seen = found
+                .iter()
+                .any(|f| f.group == gv.group && f.resource == r.name);
+            if !seen && r.categories.iter().any(|c| c == category) {
+                found.push(gv.with_resource(&r.name));
+            }
+        }
+        found
+    }
+
+    /// True if `gvr` is a namespaced resource.  Unknown resources are
+    /// not namespaced.
+    pub fn namespaced(&self, gvr: &GroupVersionResource) -> bool {
+        self.find(gvr).map(|(_, r)| r.namespaced).unwrap_or(false)
+    }
+}
+
+#[test]
+fn rest_mapper() {
+    use serde_json;
+
+    let lists: Vec<APIResourceList> = serde_json::from_value(json!([
+        {
+            "groupVersion": "v1",
+            "resources": [
+                {"name": "pods", "singularName": "", "namespaced": true, "kind": "Pod"},
+                {"name": "pods/status", "singularName": "", "namespaced": true, "kind": "Pod"},
+                {"name": "nodes", "singularName": "", "namespaced": false, "kind": "Node"},
+            ],
+        },
+        {
+            "groupVersion": "apps/v1",
+            "resources": [
+                {
+                    "name": "deployments", "singularName": "", "namespaced": true,
+                    "kind": "Deployment",
+                },
+            ],
+        },
+    ]))
+    .unwrap();
+    let mut mapper = RESTMapper::new(lists);
+
+    let pods = GroupVersion::from_str("v1").unwrap().with_resource("pods");
+    let nodes = GroupVersion::from_str("v1").unwrap().with_resource("nodes");
+    let deployments = GroupVersion::from_str("apps/v1")
+        .unwrap()
+        .with_resource("deployments");
+
+    let gvk = GroupVersionKind::from_str("v1/Pod").unwrap();
+    assert_eq!(mapper.resource_for(&gvk).unwrap(), pods);
+    assert_eq!(mapper.kind_for(&pods).unwrap(), gvk);
+    let gvk = GroupVersionKind::from_str("apps/v1/Deployment").unwrap();
+    assert_eq!(mapper.resource_for(&gvk).unwrap(), deployments);
+
+    assert!(mapper.namespaced(&pods));
+    assert!(!mapper.namespaced(&nodes));
+    assert!(mapper.namespaced(&deployments));
+
+    let gvk = GroupVersionKind::from_str("apps/v1beta2/Deployment").unwrap();
+    assert_eq!(
+        mapper.resource_for(&gvk).unwrap_err().to_string(),
+        "no matches for apps/v1beta2, Kind=Deployment"
+    );
+    let status = GroupVersion::from_str("v1")
+        .unwrap()
+        .with_resource("pods/status");
+    assert!(mapper.kind_for(&status).is_err());
+
+    mapper.add(
+        serde_json::from_value(json!({
+            "groupVersion": "apps/v1",
+            "resources": [],
+        }))
+        .unwrap(),
+    );
+    assert!(!mapper.namespaced(&deployments));
+}
+
+#[test]
+fn resolve_resource() {
+    use serde_json;
+
+    let lists: Vec<APIResourceList> = serde_json::from_value(json!([
+        {
+            "groupVersion": "v1",
+            "resources": [
+                {
+                    "name": "pods", "singularName": "pod", "namespaced": true,
+                    "kind": "Pod", "shortNames": ["po"], "categories": ["all"],
+                },
+                {"name": "pods/log", "singularName": "", "namespaced": true, "kind": "Pod"},
+                {
+                    "name": "services", "singularName": "service", "namespaced": true,
+                    "kind": "Service", "shortNames": ["svc"], "categories": ["all"],
+                },
+                {"name": "nodes", "singularName": "node", "namespaced": false, "kind": "Node"},
+            ],
+        },
+        {
+            "groupVersion": "apps/v1",
+            "resources": [
+                {
+                    "name": "deployments", "singularName": "deployment", "namespaced": true,
+                    "kind": "Deployment", "shortNames": ["deploy"], "categories": ["all"],
+                },
+            ],
+        },
+        {
+            "groupVersion": "apps/v1beta2",
+            "resources": [
+                {
+                    "name": "deployments", "singularName": "deployment", "namespaced": true,
+                    "kind": "Deployment", "shortNames": ["deploy"], "categories": ["all"],
+                },
+            ],
+        },
+        {
+            "groupVersion": "example.com/v1",
+            "resources": [
+                {
+                    "name": "pods", "singularName": "pod", "namespaced": true,
+                    "kind": "Pod", "shortNames": ["po"],
+                },
+            ],
+        },
+    ]))
+    .unwrap();
+    let mapper = RESTMapper::new(lists);
+
+    let pods = GroupVersion::from_str("v1").unwrap().with_resource("pods");
+    let services = GroupVersion::from_str("v1")
+        .unwrap()
+        .with_resource("services");
+    let deployments = GroupVersion::from_str("apps/v1")
+        .unwrap()
+        .with_resource("deployments");
+
+    for arg in &["po", "Pod", "pod", "pods", "PODS"] {
+        assert_eq!(mapper.resolve_resource(arg).unwrap(), pods, "{}", arg);
+    }
+    assert_eq!(mapper.resolve_resource("deploy").unwrap(), deployments);
+    assert_eq!(
+        mapper.resolve_resource("deployments.apps").unwrap(),
+        deployments
+    );
+    assert_eq!(
+        mapper.resolve_resource("po.example.com").unwrap(),
+        GroupVersion::from_str("example.com/v1")
+            .unwrap()
+            .with_resource("pods")
+    );
+    assert_eq!(
+        mapper.resolve_resource("po.apps").unwrap_err().to_string(),
+        "no matches for po.apps"
+    );
+    assert!(mapper.resolve_resource("log").is_err());
+
+    assert_eq!(
+        mapper.resources_in_category("all"),
+        vec![pods, services, deployments]
+    );
+    assert!(mapper.resources_in_category("none").is_empty());
+}
@@ -0,0 +1,108 @@
+use crate::core::v1::Resource;
+use crate::meta::v1::{ItemList, Metadata, ObjectMeta};
+use crate::meta::{GroupVersion, GroupVersionResource};
+use crate::{Integer, TypeMeta, TypeMetaImpl};
+use std::borrow::Cow;
+
+const API_GROUP: &str = "scheduling.k8s.io/v1";
+pub const GROUP_VERSION: GroupVersion = GroupVersion {
+    group: "scheduling.k8s.io",
+    version: "v1",
+};
+
+pub struct PriorityClasses;
+
+impl Resource for PriorityClasses {
+    type List = PriorityClassList;
+
+    fn gvr(&self) -> GroupVersionResource {
+        GROUP_VERSION.with_resource("priorityclasses")
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PriorityClass {
+    #[serde(flatten)]
+    typemeta: TypeMetaImpl<PriorityClass>,
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    pub value: Integer,
+    #[serde(default)]
+    pub global_default: bool,
+    pub description: Option<String>,
+    pub preemption_policy: Option<PreemptionPolicy>,
+}
+
+pub type PriorityClassList = ItemList<PriorityClass>;
+
+impl TypeMeta for PriorityClass {
+    fn api_version() -> &'static str {
+        API_GROUP
+    }
+    fn kind() -> &'static str {
+        "PriorityClass"
+    }
+}
+
+impl Metadata for PriorityClass {
+    fn api_version(&self) -> &str {
+        <PriorityClass as TypeMeta>::api_version()
+    }
+    fn kind(&self) -> &str {
+        <PriorityClass as TypeMeta>::kind()
+    }
+    fn metadata(&self) -> Cow<ObjectMeta> {
+        Cow::Borrowed(&self.metadata)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PreemptionPolicy {
+    PreemptLowerPriority,
+    Never,
+}
+
+/// Returns the class used for pods that don't name one, if any.
+pub fn find_global_default<'a, I>(classes: I) -> Option<&'a PriorityClass>
+where
+    I: IntoIterator<Item = &'a PriorityClass>,
+{
+    classes.into_iter().find(|c| c.global_default)
+}
+
+#[test]
+fn deser_priorityclass() {
+    let yaml = r#"
+      apiVersion: scheduling.k8s.io/v1
+      kind: PriorityClassList
+      metadata: {}
+      items:
+      - apiVersion: scheduling.k8s.io/v1
+        kind: PriorityClass
+        metadata:
+          name: system-cluster-critical
+        value: 2000000000
+        description: Used for system critical pods that must run in the cluster, but can be moved to another node if necessary.
+      - apiVersion: scheduling.k8s.io/v1
+        kind: PriorityClass
+        metadata:
+          name: normal
+        value: 1000
+        globalDefault: true
+        preemptionPolicy: Never
+"#;
+    let list: PriorityClassList = ::serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(list.items.len(), 2);
+    assert_eq!(list.items[0].value, 2000000000);
+    assert!(!list.items[0].global_default);
+    assert_eq!(list.items[0].preemption_policy, None);
+    assert_eq!(
+        list.items[1].preemption_policy,
+        Some(PreemptionPolicy::Never)
+    );
+
+    let default = find_global_default(&list.items).unwrap();
+    assert_eq!(default.metadata.name, Some("normal".into()));
+    assert!(find_global_default(&list.items[..1]).is_none());
+}
@@ -0,0 +1,255 @@
+//! Implements `#[serde(with = "serde_base64")]`, for `[]byte` fields,
+//! which the API encodes as base64 strings.
+
+use base64;
+use serde::{de, Deserializer, Serializer};
+use std::fmt;
+
+pub fn serialize<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_str(&base64::display::Base64Display::standard(bytes))
+}
+
+// Decodes directly from whatever string the deserializer hands us
+// (borrowed where possible), rather than first buffering into an
+// owned String.  Matters for large Secret values.
+struct Base64Visitor;
+
+impl<'de> de::Visitor<'de> for Base64Visitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a base64 encoded string")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        base64::decode(v).map_err(de::Error::custom)
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        base64::decode(v).map_err(de::Error::custom)
+    }
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(Base64Visitor)
+}
+
+/// `#[serde(with = "serde_base64::option")]`, for optional `[]byte`
+/// fields.
+pub mod option {
+    use super::Base64Visitor;
+    use serde::{de, Deserializer, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S>(bytes: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *bytes {
+            Some(ref b) => super::serialize(b, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    struct OptionVisitor;
+
+    impl<'de> de::Visitor<'de> for OptionVisitor {
+        type Value = Option<Vec<u8>>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a base64 encoded string or null")
+        }
+
+        fn visit_none<E>(self) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(None)
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(None)
+        }
+
+        fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_str(Base64Visitor).map(Some)
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_option(OptionVisitor)
+    }
+}
+
+/// `#[serde(with = "serde_base64::map")]`, for `map[string][]byte`
+/// fields.
+pub mod map {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    struct Bytes<'a>(&'a [u8]);
+
+    impl<'a> Serialize for Bytes<'a> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            super::serialize(self.0, serializer)
+        }
+    }
+
+    struct Buf(Vec<u8>);
+
+    impl<'de> Deserialize<'de> for Buf {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            super::deserialize(deserializer).map(Buf)
+        }
+    }
+
+    pub fn serialize<S>(map: &BTreeMap<String, Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(map.iter().map(|(k, v)| (k, Bytes(v))))
+    }
+
+    /// A `null` map is treated as empty.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<BTreeMap<String, Vec<u8>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let map: Option<BTreeMap<String, Buf>> = Deserialize::deserialize(deserializer)?;
+        Ok(map
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(k, v)| (k, v.0))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::serde_base64;
+    use serde_json;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Test {
+        a: Vec<u8>,
+        #[serde(with = "serde_base64")]
+        b: Vec<u8>,
+    }
+
+    #[test]
+    fn base64() {
+        let input = Test {
+            a: vec![123, 124],
+            b: vec![126, 127],
+        };
+        let expected = json!({
+            "a": [123, 124],
+            "b": "fn8="
+        });
+        let json = serde_json::to_value(&input).unwrap();
+        assert_eq!(json, expected);
+
+        let roundtrip: Test = serde_json::from_value(json).unwrap();
+        assert_eq!(roundtrip, input);
+    }
+
+    #[test]
+    fn base64_large() {
+        let input = Test {
+            a: vec![],
+            b: (0..4 * 1024 * 1024).map(|i| i as u8).collect(),
+        };
+        let json = serde_json::to_string(&input).unwrap();
+        assert!(json.len() > 5 * 1024 * 1024);
+
+        let roundtrip: Test = serde_json::from_str(&json).unwrap();
+        assert_eq!(roundtrip, input);
+    }
+
+    #[test]
+    fn base64_invalid() {
+        let r: Result<Test, _> = serde_json::from_value(json!({"a": [], "b": "!!!"}));
+        assert!(r.is_err());
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Optional {
+        #[serde(default, with = "serde_base64::option")]
+        b: Option<Vec<u8>>,
+    }
+
+    #[test]
+    fn base64_option() {
+        let input = Optional {
+            b: Some(vec![126, 127]),
+        };
+        let json = serde_json::to_value(&input).unwrap();
+        assert_eq!(json, json!({"b": "fn8="}));
+        let roundtrip: Optional = serde_json::from_value(json).unwrap();
+        assert_eq!(roundtrip, input);
+
+        let null: Optional = serde_json::from_value(json!({"b": null})).unwrap();
+        assert_eq!(null.b, None);
+        assert_eq!(serde_json::to_value(&null).unwrap(), json!({"b": null}));
+        let missing: Optional = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(missing.b, None);
+        let empty: Optional = serde_json::from_value(json!({"b": ""})).unwrap();
+        assert_eq!(empty.b, Some(vec![]));
+
+        assert!(serde_json::from_value::<Optional>(json!({"b": "!!!"})).is_err());
+        assert!(serde_json::from_value::<Optional>(json!({"b": 3})).is_err());
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Map {
+        #[serde(default, with = "serde_base64::map")]
+        data: BTreeMap<String, Vec<u8>>,
+    }
+
+    #[test]
+    fn base64_map() {
+        let mut data = BTreeMap::new();
+        data.insert("password".to_string(), b"hunter2".to_vec());
+        data.insert("empty".to_string(), vec![]);
+        let input = Map { data };
+        let json = serde_json::to_value(&input).unwrap();
+        assert_eq!(
+            json,
+            json!({"data": {"empty": "", "password": "aHVudGVyMg=="}})
+        );
+        let roundtrip: Map = serde_json::from_value(json).unwrap();
+        assert_eq!(roundtrip, input);
+
+        let null: Map = serde_json::from_value(json!({"data": null})).unwrap();
+        assert!(null.data.is_empty());
+        let missing: Map = serde_json::from_value(json!({})).unwrap();
+        assert!(missing.data.is_empty());
+
+        let invalid = json!({"data": {"password": "!!!"}});
+        assert!(serde_json::from_value::<Map>(invalid).is_err());
+        let invalid = json!({"data": {"password": null}});
+        assert!(serde_json::from_value::<Map>(invalid).is_err());
+    }
+}
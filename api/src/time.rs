@@ -0,0 +1,172 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+/// A timestamp, eg: `ObjectMeta.creationTimestamp`.
+///
+/// Serialized as RFC3339 in UTC, as the server does
+/// (`2018-02-20T18:00:07Z`).  Fractional seconds are only written if
+/// present, so timestamps from the server serialize back unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time(pub DateTime<Utc>);
+
+impl Time {
+    pub fn now() -> Self {
+        Time(Utc::now())
+    }
+}
+
+impl From<DateTime<Utc>> for Time {
+    fn from(t: DateTime<Utc>) -> Self {
+        Time(t)
+    }
+}
+
+impl From<Time> for DateTime<Utc> {
+    fn from(t: Time) -> Self {
+        t.0
+    }
+}
+
+impl Deref for Time {
+    type Target = DateTime<Utc>;
+    fn deref(&self) -> &DateTime<Utc> {
+        &self.0
+    }
+}
+
+impl FromStr for Time {
+    type Err = chrono::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DateTime::parse_from_rfc3339(s).map(|t| Time(t.with_timezone(&Utc)))
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
+}
+
+impl Serialize for Time {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Time {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// A timestamp with microsecond precision, eg: `Event.eventTime`.
+///
+/// Always serialized with six fractional digits
+/// (`2018-02-20T18:00:07.000000Z`), as the server does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MicroTime(pub DateTime<Utc>);
+
+impl MicroTime {
+    pub fn now() -> Self {
+        MicroTime(Utc::now())
+    }
+}
+
+impl From<DateTime<Utc>> for MicroTime {
+    fn from(t: DateTime<Utc>) -> Self {
+        MicroTime(t)
+    }
+}
+
+impl From<MicroTime> for DateTime<Utc> {
+    fn from(t: MicroTime) -> Self {
+        t.0
+    }
+}
+
+impl From<Time> for MicroTime {
+    fn from(t: Time) -> Self {
+        MicroTime(t.0)
+    }
+}
+
+impl Deref for MicroTime {
+    type Target = DateTime<Utc>;
+    fn deref(&self) -> &DateTime<Utc> {
+        &self.0
+    }
+}
+
+impl FromStr for MicroTime {
+    type Err = chrono::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DateTime::parse_from_rfc3339(s).map(|t| MicroTime(t.with_timezone(&Utc)))
+    }
+}
+
+impl fmt::Display for MicroTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0.to_rfc3339_opts(SecondsFormat::Micros, true))
+    }
+}
+
+impl Serialize for MicroTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MicroTime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+#[test]
+fn time_serde() {
+    use serde_json;
+
+    let t: Time = serde_json::from_str(r#""2018-02-20T18:00:07Z""#).unwrap();
+    assert_eq!(t.timestamp(), 1519149607);
+    assert_eq!(
+        serde_json::to_string(&t).unwrap(),
+        r#""2018-02-20T18:00:07Z""#
+    );
+
+    let t: Option<Time> = serde_json::from_str("null").unwrap();
+    assert_eq!(t, None);
+
+    let t: Time = serde_json::from_str(r#""2018-02-17T23:17:05.703130559Z""#).unwrap();
+    assert_eq!(
+        serde_json::to_string(&t).unwrap(),
+        r#""2018-02-17T23:17:05.703130559Z""#
+    );
+
+    assert!(serde_json::from_str::<Time>(r#""yesterday""#).is_err());
+}
+
+#[test]
+fn microtime_serde() {
+    use serde_json;
+
+    let t: MicroTime = serde_json::from_str(r#""2018-02-20T18:00:07.123456Z""#).unwrap();
+    assert_eq!(t.timestamp_subsec_micros(), 123456);
+    assert_eq!(
+        serde_json::to_string(&t).unwrap(),
+        r#""2018-02-20T18:00:07.123456Z""#
+    );
+
+    let t: MicroTime = "2018-02-20T18:00:07Z".parse::<Time>().unwrap().into();
+    assert_eq!(
+        serde_json::to_string(&t).unwrap(),
+        r#""2018-02-20T18:00:07.000000Z""#
+    );
+}
@@ -0,0 +1,35 @@
+//! Well-known label and annotation keys.
+//!
+//! See <https://kubernetes.io/docs/concepts/overview/working-with-objects/common-labels/>
+//! and <https://kubernetes.io/docs/reference/labels-annotations-taints/>.
+
+/// Recommended label: the name of the application, eg: `mysql`.
+pub const APP_NAME: &str = "app.kubernetes.io/name";
+/// Recommended label: a unique name identifying this instance of the
+/// application, eg: `mysql-abcxzy`.
+pub const APP_INSTANCE: &str = "app.kubernetes.io/instance";
+/// Recommended label: the current version of the application.
+pub const APP_VERSION: &str = "app.kubernetes.io/version";
+/// Recommended label: the component within the architecture, eg:
+/// `database`.
+pub const APP_COMPONENT: &str = "app.kubernetes.io/component";
+/// Recommended label: the name of a higher level application this
+/// one is part of.
+pub const APP_PART_OF: &str = "app.kubernetes.io/part-of";
+/// Recommended label: the tool being used to manage the application,
+/// eg: `helm`.
+pub const APP_MANAGED_BY: &str = "app.kubernetes.io/managed-by";
+
+/// Label: the node's hostname.
+pub const HOSTNAME: &str = "kubernetes.io/hostname";
+/// Label: the zone the node is running in.
+pub const TOPOLOGY_ZONE: &str = "topology.kubernetes.io/zone";
+/// Label: the region the node is running in.
+pub const TOPOLOGY_REGION: &str = "topology.kubernetes.io/region";
+
+/// Annotation: set by the kubelet on mirror pods of static pods.
+pub const CONFIG_MIRROR: &str = "kubernetes.io/config.mirror";
+/// Annotation: the last configuration applied by `kubectl apply`.
+pub const LAST_APPLIED_CONFIG: &str = "kubectl.kubernetes.io/last-applied-configuration";
+/// Annotation: the reason for a change, shown in rollout history.
+pub const CHANGE_CAUSE: &str = "kubernetes.io/change-cause";
@@ -103,6 +103,7 @@ fn main_() -> Result<(), Error> {
                             println!("deleted {}", p.metadata.name.unwrap_or("(no name)".into()));
                         }
                         EventType::Error => debug!("Ignoring error event {:#?}", event.object),
+                        EventType::Bookmark => (),
                     }
                     Ok(())
                 })
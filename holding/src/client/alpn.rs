@@ -0,0 +1,43 @@
+use futures::Future;
+use hyper::client::connect::{Connect, Connected, Destination};
+use hyper_tls::{HttpsConnector, MaybeHttpsStream};
+use std::io;
+
+/// Wraps an `HttpsConnector`, telling hyper to speak HTTP/2 on
+/// connections where the server selected "h2" during the TLS
+/// handshake, and HTTP/1.1 otherwise.
+///
+/// The `TlsConnector` inside must have been built with
+/// `request_alpns(&["h2", "http/1.1"])` for the server to ever pick
+/// HTTP/2.  See `Client::new_from_context_h2`.
+#[derive(Clone)]
+pub struct AlpnConnector<T>(pub HttpsConnector<T>);
+
+impl<T> Connect for AlpnConnector<T>
+where
+    T: Connect<Error = io::Error>,
+    T::Transport: 'static,
+    T::Future: 'static,
+{
+    type Transport = MaybeHttpsStream<T::Transport>;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = (Self::Transport, Connected), Error = io::Error> + Send>;
+
+    fn connect(&self, dst: Destination) -> Self::Future {
+        Box::new(self.0.connect(dst).map(|(stream, connected)| {
+            let h2 = match stream {
+                MaybeHttpsStream::Https(ref tls) => match tls.get_ref().negotiated_alpn() {
+                    Ok(Some(ref proto)) => proto == b"h2",
+                    _ => false,
+                },
+                MaybeHttpsStream::Http(_) => false,
+            };
+            if h2 {
+                debug!("Server negotiated HTTP/2");
+                (stream, connected.negotiated_h2())
+            } else {
+                (stream, connected)
+            }
+        }))
+    }
+}
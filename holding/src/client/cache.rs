@@ -0,0 +1,308 @@
+//! An opt-in read cache in front of a `Client`.
+
+use super::{Client, HttpService};
+use api::meta::v1::{
+    CreateOptions, DeleteOptions, DeleteResponse, GetOptions, List, ListOptions, Metadata,
+};
+use api::meta::GroupVersionResource;
+use failure::Error;
+use futures::future::{self, Either};
+use futures::Future;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{self, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+struct Entries {
+    // Keyed by resource, then request URL
+    by_resource: HashMap<String, HashMap<String, (Instant, Value)>>,
+}
+
+/// Wraps a `Client`, remembering the results of `get` and `list` for
+/// `ttl`.  Writes made through this wrapper drop everything cached for
+/// the resource written to; writes made any other way (including by
+/// other clients) are not seen until entries expire.
+///
+/// Controllers should not use this: acting on a stale read can undo
+/// another writer's changes.  Use a watch instead.
+#[derive(Debug)]
+pub struct CachingClient<C> {
+    client: Client<C>,
+    ttl: Duration,
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl<C> Clone for CachingClient<C> {
+    fn clone(&self) -> Self {
+        CachingClient {
+            client: self.client.clone(),
+            ttl: self.ttl,
+            entries: Arc::clone(&self.entries),
+        }
+    }
+}
+
+impl<C: HttpService> CachingClient<C> {
+    pub fn new(client: Client<C>, ttl: Duration) -> Self {
+        CachingClient {
+            client,
+            ttl,
+            entries: Default::default(),
+        }
+    }
+
+    /// The underlying (uncached) client.
+    pub fn client(&self) -> &Client<C> {
+        &self.client
+    }
+
+    /// Forgets everything cached.
+    pub fn invalidate_all(&self) {
+        self.entries.lock().unwrap().by_resource.clear();
+    }
+
+    fn lookup(&self, resource: &str, key: &str) -> Option<Value> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .by_resource
+            .get(resource)
+            .and_then(|m| m.get(key))
+            .filter(|&&(at, _)| at.elapsed() < self.ttl)
+            .map(|&(_, ref v)| v.clone())
+    }
+
+    fn cached<T, F>(
+        &self,
+        gvr: &GroupVersionResource,
+        key: Option<String>,
+        fetch: F,
+    ) -> impl Future<Item = T, Error = Error> + Send
+    where
+        T: DeserializeOwned + Send + 'static,
+        F: Future<Item = Value, Error = Error> + Send,
+    {
+        let resource = gvr.to_string();
+        if let Some(v) = key.as_ref().and_then(|k| self.lookup(&resource, k)) {
+            debug!("Cache hit for {}", key.unwrap());
+            return Either::A(future::result(
+                serde_json::from_value(v).map_err(Error::from),
+            ));
+        }
+        let entries = Arc::clone(&self.entries);
+        Either::B(fetch.and_then(move |v| {
+            if let Some(key) = key {
+                entries
+                    .lock()
+                    .unwrap()
+                    .by_resource
+                    .entry(resource)
+                    .or_insert_with(HashMap::new)
+                    .insert(key, (Instant::now(), v.clone()));
+            }
+            Ok(serde_json::from_value(v)?)
+        }))
+    }
+
+    pub fn get<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        name: &str,
+        opts: GetOptions,
+    ) -> impl Future<Item = T, Error = Error> + Send
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let key = self
+            .client
+            .url(gvr, namespace, Some(name), &opts)
+            .ok()
+            .map(|u| u.to_string());
+        let fetch = self.client.get::<Value>(gvr, namespace, name, opts);
+        self.cached(gvr, key, fetch)
+    }
+
+    pub fn list<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        opts: ListOptions,
+    ) -> impl Future<Item = T, Error = Error> + Send
+    where
+        T: List + DeserializeOwned + Send + 'static,
+    {
+        let key = self
+            .client
+            .url(gvr, namespace, None, &opts)
+            .ok()
+            .map(|u| u.to_string());
+        let fetch = self.client.list::<Value>(gvr, namespace, opts);
+        self.cached(gvr, key, fetch)
+    }
+
+    /// Runs a write, then forgets everything cached for `gvr`.
+    fn write<F>(
+        &self,
+        gvr: &GroupVersionResource,
+        f: F,
+    ) -> impl Future<Item = F::Item, Error = Error> + Send
+    where
+        F: Future<Error = Error> + Send,
+        F::Item: Send,
+    {
+        let entries = Arc::clone(&self.entries);
+        let resource = gvr.to_string();
+        f.then(move |r| {
+            entries.lock().unwrap().by_resource.remove(&resource);
+            r
+        })
+    }
+
+    pub fn create<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        value: &T,
+        opts: CreateOptions,
+    ) -> impl Future<Item = T, Error = Error> + Send
+    where
+        T: Metadata + Serialize + DeserializeOwned + Send + 'static,
+    {
+        self.write(gvr, self.client.create(gvr, value, opts))
+    }
+
+    pub fn update<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        value: &T,
+    ) -> impl Future<Item = T, Error = Error> + Send
+    where
+        T: Metadata + Serialize + DeserializeOwned + Send + 'static,
+    {
+        self.write(gvr, self.client.update(gvr, value))
+    }
+
+    pub fn patch<T, U>(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        name: &str,
+        patch_type: &str,
+        value: &T,
+    ) -> impl Future<Item = U, Error = Error> + Send
+    where
+        T: Serialize,
+        U: DeserializeOwned + Send + 'static,
+    {
+        self.write(
+            gvr,
+            self.client.patch(gvr, namespace, name, patch_type, value),
+        )
+    }
+
+    pub fn delete<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        name: &str,
+        opts: DeleteOptions,
+    ) -> impl Future<Item = DeleteResponse<T>, Error = Error> + Send
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        self.write(gvr, self.client.delete(gvr, namespace, name, opts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{json_response, mock_client};
+    use super::*;
+    use api::core::v1::{Pod, PodList, GROUP_VERSION};
+    use api::MERGE_PATCH;
+    use hyper::Method;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn client(ttl: Duration) -> (CachingClient<impl HttpService>, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let hits2 = Arc::clone(&hits);
+        let client = mock_client(move |req| {
+            hits2.fetch_add(1, Ordering::SeqCst);
+            let pod = json!({
+                "apiVersion": "v1",
+                "kind": "Pod",
+                "metadata": {"name": "mypod", "namespace": "default"},
+            });
+            match (req.method(), req.uri().path()) {
+                (&Method::GET, "/api/v1/namespaces/default/pods") => json_response(
+                    200,
+                    &json!({"kind": "PodList", "apiVersion": "v1", "metadata": {}, "items": [pod]}),
+                ),
+                _ => json_response(200, &pod),
+            }
+        });
+        (CachingClient::new(client, ttl), hits)
+    }
+
+    #[test]
+    fn cached_get() {
+        let (client, hits) = client(Duration::from_secs(60));
+        let pods = GROUP_VERSION.with_resource("pods");
+        let get = || {
+            client
+                .get::<Pod>(&pods, Some("default"), "mypod", Default::default())
+                .wait()
+                .unwrap()
+        };
+
+        assert_eq!(get().metadata.name, Some("mypod".into()));
+        assert_eq!(get().metadata.name, Some("mypod".into()));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        let list: PodList = client
+            .list(&pods, Some("default"), Default::default())
+            .wait()
+            .unwrap();
+        assert_eq!(list.items.len(), 1);
+        let _: PodList = client
+            .list(&pods, Some("default"), Default::default())
+            .wait()
+            .unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        // A write invalidates both
+        let pod = get();
+        client.update(&pods, &pod).wait().unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+        get();
+        let _: PodList = client
+            .list(&pods, Some("default"), Default::default())
+            .wait()
+            .unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 5);
+
+        // Other resources are unaffected
+        let nodes = GROUP_VERSION.with_resource("nodes");
+        client
+            .patch::<_, Value>(&nodes, None, "node1", MERGE_PATCH, &json!({}))
+            .wait()
+            .unwrap();
+        get();
+        assert_eq!(hits.load(Ordering::SeqCst), 6);
+    }
+
+    #[test]
+    fn expired() {
+        let (client, hits) = client(Duration::from_secs(0));
+        let pods = GROUP_VERSION.with_resource("pods");
+        for _ in 0..2 {
+            client
+                .get::<Pod>(&pods, Some("default"), "mypod", Default::default())
+                .wait()
+                .unwrap();
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,222 @@
+//! Cancelling individual requests.
+
+use super::{do_request, Client, HttpService};
+use failure::Error;
+use futures::task::AtomicTask;
+use futures::{Async, Future, Poll};
+use hyper::{Body, Request};
+use serde::de::DeserializeOwned;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+#[derive(Fail, Debug)]
+#[fail(display = "Request was cancelled")]
+pub struct Cancelled;
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    // Tasks to wake when cancelled, one per live `WithCancel`
+    tasks: Mutex<Vec<Weak<AtomicTask>>>,
+}
+
+/// Cancels the requests it was given to, from anywhere (eg: another
+/// task or thread).  Clones share the same state.
+///
+/// Cancelling drops the request's future, which closes its
+/// connection, so the server stops sending.  Dropping the future
+/// yourself does the same, where you hold it.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<Inner>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Ends every request using this token (including those started
+    /// later) with a `Cancelled` error.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        let tasks: Vec<_> = self.0.tasks.lock().unwrap().drain(..).collect();
+        for t in tasks.iter().filter_map(Weak::upgrade) {
+            t.notify();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Makes `future` fail with `Cancelled` once this token is
+    /// cancelled, eg: `token.wrap(client.get(...))`.
+    pub fn wrap<F>(&self, future: F) -> WithCancel<F>
+    where
+        F: Future<Error = Error>,
+    {
+        let task = Arc::new(AtomicTask::new());
+        {
+            let mut tasks = self.0.tasks.lock().unwrap();
+            // Forget those whose `WithCancel` has been dropped
+            tasks.retain(|t| t.upgrade().is_some());
+            tasks.push(Arc::downgrade(&task));
+        }
+        WithCancel {
+            inner: Some(future),
+            token: self.clone(),
+            task,
+        }
+    }
+}
+
+/// Future returned by `CancelToken::wrap`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct WithCancel<F> {
+    inner: Option<F>,
+    token: CancelToken,
+    task: Arc<AtomicTask>,
+}
+
+impl<F> Future for WithCancel<F>
+where
+    F: Future<Error = Error>,
+{
+    type Item = F::Item;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<F::Item, Error> {
+        if self.token.is_cancelled() {
+            // Drop the request now, rather than whenever we are
+            self.inner = None;
+            return Err(Cancelled.into());
+        }
+        let res = match self.inner {
+            Some(ref mut f) => f.poll()?,
+            None => return Err(Cancelled.into()),
+        };
+        match res {
+            Async::NotReady => {
+                // Register before checking, to avoid missing a wakeup.
+                self.task.register();
+                if self.token.is_cancelled() {
+                    self.inner = None;
+                    Err(Cancelled.into())
+                } else {
+                    Ok(Async::NotReady)
+                }
+            }
+            ready => Ok(ready),
+        }
+    }
+}
+
+impl<C: HttpService> Client<C> {
+    /// Sends a prepared request, with a full URI, and parses the
+    /// response as `T`.  Fails with `Cancelled` if `token` is cancelled
+    /// first.  For the usual requests, use `token.wrap(...)` instead.
+    pub fn request_with_cancel<T>(
+        &self,
+        req: Request<Body>,
+        token: &CancelToken,
+    ) -> WithCancel<impl Future<Item = T, Error = Error> + Send>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        token.wrap(do_request(self.transport.clone(), Ok(req)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{json_response, mock_client};
+    use super::*;
+    use api::core::v1::GROUP_VERSION;
+    use futures::Stream;
+    use hyper::{self, Method};
+    use serde_json::Value;
+    use std::thread;
+    use std::time::Duration;
+
+    // A body that never arrives, noting when the request is dropped
+    struct Pending(Arc<AtomicBool>);
+
+    impl Stream for Pending {
+        type Item = hyper::Chunk;
+        type Error = hyper::Error;
+
+        fn poll(&mut self) -> Poll<Option<hyper::Chunk>, hyper::Error> {
+            Ok(Async::NotReady)
+        }
+    }
+
+    impl Drop for Pending {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn cancel() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let dropped2 = Arc::clone(&dropped);
+        let client = mock_client(move |_| {
+            hyper::Response::new(Body::wrap_stream(Pending(Arc::clone(&dropped2))))
+        });
+        let url = client
+            .url(&GROUP_VERSION.with_resource("pods"), None, None, ())
+            .unwrap();
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(url.as_str())
+            .body(Body::empty())
+            .unwrap();
+
+        let token = CancelToken::new();
+        let pending = client.request_with_cancel::<Value>(req, &token);
+        let canceller = token.clone();
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            canceller.cancel();
+        });
+        let err = pending.wait().unwrap_err();
+        t.join().unwrap();
+        assert!(err.downcast_ref::<Cancelled>().is_some());
+        assert!(dropped.load(Ordering::SeqCst));
+        assert_eq!(client.transport.shutdown.in_flight(), 0);
+
+        // Already cancelled
+        let get = client.get::<Value>(
+            &GROUP_VERSION.with_resource("pods"),
+            Some("default"),
+            "web",
+            Default::default(),
+        );
+        let err = token.wrap(get).wait().unwrap_err();
+        assert!(err.downcast_ref::<Cancelled>().is_some());
+
+        // Unaffected otherwise
+        let client = mock_client(|_| json_response(200, &json!({"kind": "Pod"})));
+        let get = client.get::<Value>(
+            &GROUP_VERSION.with_resource("pods"),
+            Some("default"),
+            "web",
+            Default::default(),
+        );
+        let pod = CancelToken::new().wrap(get).wait().unwrap();
+        assert_eq!(pod["kind"], "Pod");
+    }
+
+    #[test]
+    fn cancel_token_reuse() {
+        use futures::future;
+
+        // A long-lived token, used for many requests in turn
+        let token = CancelToken::new();
+        for i in 0..100 {
+            let n = token.wrap(future::ok::<_, Error>(i)).wait().unwrap();
+            assert_eq!(n, i);
+        }
+        assert!(token.0.tasks.lock().unwrap().len() <= 1);
+    }
+}
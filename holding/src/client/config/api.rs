@@ -1,4 +1,4 @@
-use serde_base64;
+use api::serde_base64;
 use serde_json::{Map, Value};
 use std::collections::BTreeMap;
 use std::path::PathBuf;
@@ -86,6 +86,9 @@ pub struct AuthInfo {
     pub token_file: PathBuf,
     #[serde(default)]
     pub act_as: String,
+    /// Impersonate-Uid, to pin the exact identity being impersonated.
+    #[serde(default, rename = "as-uid")]
+    pub act_as_uid: String,
     #[serde(default)]
     pub act_as_groups: Vec<String>,
     #[serde(default)]
@@ -96,6 +99,10 @@ pub struct AuthInfo {
     pub password: String,
     #[serde(default)]
     pub auth_provider: Option<AuthProviderConfig>,
+    /// An external command that prints credentials, eg:
+    /// `gke-gcloud-auth-plugin` or `aws eks get-token`.
+    #[serde(default)]
+    pub exec: Option<ExecConfig>,
     #[serde(default)]
     pub extensions: Map<String, Value>,
 }
@@ -117,3 +124,29 @@ pub struct AuthProviderConfig {
     #[serde(default)]
     pub config: BTreeMap<String, String>,
 }
+
+// See k8s.io/client-go/tools/clientcmd/api/types.go:ExecConfig
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Added to the environment the command inherits.
+    #[serde(default)]
+    pub env: Vec<ExecEnvVar>,
+    /// Of the `ExecCredential` exchanged with the command, eg:
+    /// `client.authentication.k8s.io/v1beta1`.
+    pub api_version: String,
+    /// Shown if the command can't be found.
+    #[serde(default)]
+    pub install_hint: String,
+    #[serde(default)]
+    pub provide_cluster_info: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ExecEnvVar {
+    pub name: String,
+    pub value: String,
+}
@@ -1,7 +1,8 @@
 extern crate dirs;
-use failure::Error;
+use failure::{Error, ResultExt};
 use serde_yaml;
-use std::fs::File;
+use std::env;
+use std::fs::{self, File};
 use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 
@@ -10,6 +11,12 @@ pub mod api;
 
 pub const CONFIG_ENV: &str = "KUBECONFIG";
 
+/// Where the service account token, CA certificate and namespace are
+/// mounted into pods.
+pub const SERVICE_ACCOUNT_DIR: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+pub const SERVICE_HOST_ENV: &str = "KUBERNETES_SERVICE_HOST";
+pub const SERVICE_PORT_ENV: &str = "KUBERNETES_SERVICE_PORT";
+
 #[derive(Fail, Debug)]
 #[fail(display = "Config error: {}", msg)]
 pub struct ConfigError {
@@ -29,6 +36,86 @@ pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Config, Error> {
     Ok(config)
 }
 
+/// The kubeconfig files to read: those listed in `$KUBECONFIG`
+/// (separated like `$PATH`), or else `~/.kube/config`.  Files that
+/// don't exist are left out.
+pub fn config_paths() -> Vec<PathBuf> {
+    let paths: Vec<_> = match env::var_os(CONFIG_ENV) {
+        Some(ref val) if !val.is_empty() => env::split_paths(val).collect(),
+        _ => default_path().into_iter().collect(),
+    };
+    paths
+        .into_iter()
+        .filter(|p| p.as_os_str().len() > 0 && p.exists())
+        .collect()
+}
+
+/// Reads and merges the kubeconfig files from `config_paths`, as
+/// kubectl does.
+pub fn load_from_env() -> Result<Config, Error> {
+    load_from_paths(&config_paths())
+}
+
+/// Reads each of `paths` and merges them with `merge`.
+pub fn load_from_paths<P: AsRef<Path>>(paths: &[P]) -> Result<Config, Error> {
+    if paths.is_empty() {
+        return Err(format_err!("Unable to find config"));
+    }
+    let mut configs = vec![];
+    for path in paths {
+        let path = path.as_ref();
+        debug!("Reading config from {}", path.display());
+        let config = load_from_file(path)
+            .with_context(|e| format!("Unable to read {}: {}", path.display(), e))?;
+        configs.push(config);
+    }
+    Ok(merge(configs))
+}
+
+/// Merges several kubeconfigs into one, following kubectl: the first
+/// cluster, user or context with a given name wins, as does the first
+/// non-empty `current-context` (and `preferences`, `apiVersion` and
+/// `kind`).  Later entries with the same name are dropped whole, not
+/// merged field by field.
+pub fn merge<I: IntoIterator<Item = Config>>(configs: I) -> Config {
+    let mut merged = Config::default();
+    for config in configs {
+        for c in config.clusters {
+            if !merged.clusters.iter().any(|e| e.name == c.name) {
+                merged.clusters.push(c);
+            }
+        }
+        for u in config.users {
+            if !merged.users.iter().any(|e| e.name == u.name) {
+                merged.users.push(u);
+            }
+        }
+        for c in config.contexts {
+            if !merged.contexts.iter().any(|e| e.name == c.name) {
+                merged.contexts.push(c);
+            }
+        }
+        for (k, v) in config.extensions {
+            merged.extensions.entry(k).or_insert(v);
+        }
+        if merged.current_context == "" {
+            merged.current_context = config.current_context;
+        }
+        if merged.api_version == "" {
+            merged.api_version = config.api_version;
+            merged.kind = config.kind;
+            merged.preferences = config.preferences;
+        }
+    }
+    merged
+}
+
+/// Parses a kubeconfig already in memory, eg: from a secret or a
+/// command line flag.
+pub fn load_from_slice(data: &[u8]) -> Result<Config, Error> {
+    Ok(serde_yaml::from_slice(data)?)
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct ConfigContext {
     pub user: api::AuthInfo,
@@ -36,11 +123,95 @@ pub struct ConfigContext {
     pub default_namespace: Option<String>,
 }
 
+/// True if this process looks like it is running in a pod.
+pub fn is_in_cluster() -> bool {
+    env::var_os(SERVICE_HOST_ENV).is_some()
+}
+
+/// Builds a config for talking to the API server from within a pod,
+/// using the pod's service account.
+pub fn in_cluster() -> Result<ConfigContext, Error> {
+    let host = env::var(SERVICE_HOST_ENV)
+        .map_err(|_| format_err!("{} is not set, not running in a pod?", SERVICE_HOST_ENV))?;
+    let port = env::var(SERVICE_PORT_ENV)
+        .map_err(|_| format_err!("{} is not set, not running in a pod?", SERVICE_PORT_ENV))?;
+    in_cluster_from(Path::new(SERVICE_ACCOUNT_DIR), &host, &port)
+}
+
+fn in_cluster_from(dir: &Path, host: &str, port: &str) -> Result<ConfigContext, Error> {
+    let token_file = dir.join("token");
+    if !token_file.is_file() {
+        return Err(format_err!(
+            "Service account token {} not found",
+            token_file.display()
+        ));
+    }
+    let mut ctx = ConfigContext::default();
+    ctx.cluster.server = if host.contains(':') {
+        format!("https://[{}]:{}", host, port) // IPv6
+    } else {
+        format!("https://{}:{}", host, port)
+    };
+    ctx.cluster.certificate_authority = dir.join("ca.crt");
+    ctx.user.token_file = token_file;
+    ctx.default_namespace = fs::read_to_string(dir.join("namespace"))
+        .ok()
+        .map(|ns| ns.trim().to_string());
+    Ok(ctx)
+}
+
+/// Expands a leading `~`, and `$VAR` or `${VAR}` anywhere, in a path
+/// from a kubeconfig.  Unset variables expand to nothing.
+pub fn expand_path(path: &Path) -> PathBuf {
+    expand_path_with(path, dirs::home_dir(), |v| env::var(v).ok())
+}
+
+fn expand_path_with<F>(path: &Path, home: Option<PathBuf>, var: F) -> PathBuf
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let s = match path.to_str() {
+        Some(s) => s,
+        None => return path.to_path_buf(), // Not ours to mangle
+    };
+    let mut ret = String::with_capacity(s.len());
+    let mut rest = s;
+    if let Some(home) = home.as_ref().and_then(|h| h.to_str()) {
+        if rest == "~" || rest.starts_with("~/") {
+            ret.push_str(home);
+            rest = &rest[1..];
+        }
+    }
+    while let Some(i) = rest.find('$') {
+        ret.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        let (name, next) = if after.starts_with('{') {
+            match after.find('}') {
+                Some(end) => (&after[1..end], &after[end + 1..]),
+                None => ("", after), // Unterminated: left alone
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+        if name == "" {
+            ret.push('$');
+        } else if let Some(value) = var(name) {
+            ret.push_str(&value);
+        }
+        rest = next;
+    }
+    ret.push_str(rest);
+    PathBuf::from(ret)
+}
+
 fn data_or_file(data: &[u8], file: &Path) -> Option<io::Result<Vec<u8>>> {
     let ret = if data.len() > 0 {
         Ok(data.to_vec())
     } else if file.as_os_str().len() > 0 {
-        File::open(file).and_then(|f| f.bytes().collect())
+        File::open(expand_path(file)).and_then(|f| f.bytes().collect())
     } else {
         return None;
     };
@@ -64,6 +235,19 @@ impl api::AuthInfo {
     pub fn client_key_read(&self) -> Option<io::Result<Vec<u8>>> {
         data_or_file(&self.client_key_data, &self.client_key)
     }
+
+    /// The bearer token, from `token` or else read from `tokenFile`.
+    /// Surrounding whitespace (eg: a trailing newline in the file) is
+    /// removed.
+    pub fn token_read(&self) -> Option<io::Result<String>> {
+        data_or_file(self.token.as_bytes(), &self.token_file).map(|r| {
+            r.and_then(|data| {
+                String::from_utf8(data)
+                    .map(|s| s.trim().to_string())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            })
+        })
+    }
 }
 
 impl api::Config {
@@ -95,3 +279,179 @@ impl api::Config {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST: &str = r#"
+apiVersion: v1
+kind: Config
+current-context: dev
+clusters:
+- name: dev
+  cluster: {server: "https://dev.example.com"}
+- name: shared
+  cluster: {server: "https://first.example.com"}
+users:
+- name: alice
+  user: {token: first}
+contexts:
+- name: dev
+  context: {cluster: dev, user: alice}
+"#;
+
+    const SECOND: &str = r#"
+apiVersion: v1
+kind: Config
+current-context: prod
+clusters:
+- name: shared
+  cluster: {server: "https://second.example.com"}
+- name: prod
+  cluster: {server: "https://prod.example.com"}
+users:
+- name: alice
+  user: {token: second}
+- name: bob
+  user: {token: bob}
+contexts:
+- name: prod
+  context: {cluster: prod, user: bob, namespace: web}
+"#;
+
+    #[test]
+    fn merge_configs() {
+        let first = load_from_slice(FIRST.as_bytes()).unwrap();
+        let second = load_from_slice(SECOND.as_bytes()).unwrap();
+        let config = merge(vec![first, second.clone()]);
+
+        let names = |v: Vec<&String>| v.into_iter().cloned().collect::<Vec<_>>();
+        assert_eq!(
+            names(config.clusters.iter().map(|e| &e.name).collect()),
+            vec!["dev", "shared", "prod"]
+        );
+        assert_eq!(
+            names(config.users.iter().map(|e| &e.name).collect()),
+            vec!["alice", "bob"]
+        );
+        assert_eq!(config.current_context, "dev");
+
+        // First wins
+        let shared = config.clusters.iter().find(|e| e.name == "shared");
+        assert_eq!(shared.unwrap().cluster.server, "https://first.example.com");
+        let ctx = config.config_context("dev").unwrap();
+        assert_eq!(ctx.user.token, "first");
+
+        // Distinct entries from later files are usable
+        let ctx = config.config_context("prod").unwrap();
+        assert_eq!(ctx.cluster.server, "https://prod.example.com");
+        assert_eq!(ctx.user.token, "bob");
+        assert_eq!(ctx.default_namespace, Some("web".into()));
+
+        // A later current-context applies when earlier files have none
+        let mut first = load_from_slice(FIRST.as_bytes()).unwrap();
+        first.current_context = String::new();
+        assert_eq!(merge(vec![first, second]).current_context, "prod");
+    }
+
+    #[test]
+    fn load_paths() {
+        let dir = env::temp_dir().join(format!("k8s-kubeconfig-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("first"), FIRST).unwrap();
+        fs::write(dir.join("second"), SECOND).unwrap();
+
+        let config = load_from_paths(&[dir.join("first"), dir.join("second")]).unwrap();
+        assert_eq!(config.current_context, "dev");
+        assert_eq!(config.contexts.len(), 2);
+
+        let config = load_from_paths(&[dir.join("second"), dir.join("first")]).unwrap();
+        assert_eq!(config.current_context, "prod");
+        assert!(load_from_paths::<PathBuf>(&[]).is_err());
+        assert!(load_from_paths(&[dir.join("missing")]).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn expand_paths() {
+        let home = Some(PathBuf::from("/home/jane"));
+        let var = |v: &str| match v {
+            "HOME" => Some("/home/jane".to_string()),
+            "CONFIG_DIR" => Some("/etc/kube".to_string()),
+            _ => None,
+        };
+        let expand = |p: &str| expand_path_with(Path::new(p), home.clone(), var);
+
+        assert_eq!(
+            expand("~/.minikube/ca.crt"),
+            Path::new("/home/jane/.minikube/ca.crt")
+        );
+        assert_eq!(expand("~"), Path::new("/home/jane"));
+        assert_eq!(expand("/srv/~/ca.crt"), Path::new("/srv/~/ca.crt"));
+        assert_eq!(expand("~bob/ca.crt"), Path::new("~bob/ca.crt"));
+        assert_eq!(
+            expand("$HOME/.kube/token"),
+            Path::new("/home/jane/.kube/token")
+        );
+        assert_eq!(
+            expand("${CONFIG_DIR}/ca.crt"),
+            Path::new("/etc/kube/ca.crt")
+        );
+        assert_eq!(expand("/x/$UNSET/ca.crt"), Path::new("/x//ca.crt"));
+        assert_eq!(expand("/x/${UNTERMINATED"), Path::new("/x/${UNTERMINATED"));
+        assert_eq!(expand("/x/$/ca.crt"), Path::new("/x/$/ca.crt"));
+        assert_eq!(
+            expand_path_with(Path::new("~/ca.crt"), None, var),
+            Path::new("~/ca.crt")
+        );
+    }
+
+    #[test]
+    fn read_expanded_ca() {
+        let dir = env::temp_dir().join(format!("k8s-ca-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("ca.crt"), "cert").unwrap();
+        let var = format!("K8S_TEST_CA_DIR_{}", ::std::process::id());
+        env::set_var(&var, &dir);
+
+        let mut cluster = api::Cluster::default();
+        cluster.certificate_authority = format!("${{{}}}/ca.crt", var).into();
+        assert_eq!(
+            cluster.certificate_authority_read().unwrap().unwrap(),
+            b"cert"
+        );
+
+        // Inline data is never expanded
+        cluster.certificate_authority_data = b"$HOME".to_vec();
+        assert_eq!(
+            cluster.certificate_authority_read().unwrap().unwrap(),
+            b"$HOME"
+        );
+
+        env::remove_var(&var);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn in_cluster() {
+        let dir = env::temp_dir().join(format!("k8s-sa-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        assert!(in_cluster_from(&dir, "10.96.0.1", "443").is_err());
+
+        fs::write(dir.join("token"), "sometoken\n").unwrap();
+        fs::write(dir.join("namespace"), "kube-system").unwrap();
+        let ctx = in_cluster_from(&dir, "10.96.0.1", "443").unwrap();
+        assert_eq!(ctx.cluster.server, "https://10.96.0.1:443");
+        assert_eq!(ctx.cluster.certificate_authority, dir.join("ca.crt"));
+        assert_eq!(ctx.user.token_read().unwrap().unwrap(), "sometoken");
+        assert_eq!(ctx.default_namespace, Some("kube-system".into()));
+
+        let ctx = in_cluster_from(&dir, "fd00::1", "443").unwrap();
+        assert_eq!(ctx.cluster.server, "https://[fd00::1]:443");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
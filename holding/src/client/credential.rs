@@ -0,0 +1,351 @@
+//! Credentials from exec plugins (`client.authentication.k8s.io`),
+//! as configured by `exec` in a kubeconfig user.
+
+use super::config::api::{Cluster, ExecConfig};
+use api::Time;
+use chrono::{Duration, Utc};
+use failure::{Error, ResultExt};
+use futures::future::{self, Either, Shared};
+use futures::sync::oneshot;
+use futures::Future;
+use serde_json;
+use std::io;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Also used for `KUBERNETES_EXEC_INFO`, with `spec` instead of
+/// `status`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct ExecCredential {
+    api_version: String,
+    kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spec: Option<ExecCredentialSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status: Option<ExecCredentialStatus>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct ExecCredentialSpec {
+    interactive: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cluster: Option<ExecCluster>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+struct ExecCluster {
+    server: String,
+    insecure_skip_tls_verify: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct ExecCredentialStatus {
+    pub expiration_timestamp: Option<Time>,
+    #[serde(default)]
+    pub token: String,
+    /// PEM, not base64 like the kubeconfig fields.
+    #[serde(default)]
+    pub client_certificate_data: String,
+    #[serde(default)]
+    pub client_key_data: String,
+}
+
+/// Treat credentials as expired this long before they say, so they
+/// don't expire in flight.
+fn expiry_margin() -> Duration {
+    Duration::seconds(10)
+}
+
+/// Runs the plugin as needed, keeping its credentials until they
+/// expire.  Credentials without an expiry are kept for good.
+#[derive(Debug)]
+pub(super) struct ExecAuth {
+    config: ExecConfig,
+    cluster: Cluster,
+    state: Mutex<State>,
+}
+
+/// The plugin's result, as shared between waiting requests.  Errors
+/// aren't `Clone`, so they are shared as their message.
+type Running = Shared<oneshot::Receiver<Result<ExecCredentialStatus, String>>>;
+
+#[derive(Debug, Default)]
+struct State {
+    cached: Option<ExecCredentialStatus>,
+    /// A run of the plugin in progress, on its own thread
+    running: Option<Running>,
+}
+
+impl State {
+    fn fresh(&self) -> Option<ExecCredentialStatus> {
+        let fresh = match self.cached {
+            Some(ExecCredentialStatus {
+                expiration_timestamp: Some(t),
+                ..
+            }) => Utc::now() + expiry_margin() < *t,
+            Some(_) => true,
+            None => false,
+        };
+        if fresh {
+            self.cached.clone()
+        } else {
+            None
+        }
+    }
+}
+
+impl ExecAuth {
+    pub fn new(config: ExecConfig, cluster: &Cluster) -> Self {
+        ExecAuth {
+            config,
+            cluster: cluster.clone(),
+            state: Default::default(),
+        }
+    }
+
+    /// Blocks while the plugin runs, when it needs to: only for use
+    /// outside of futures, eg: while building a client.
+    pub fn credentials(&self) -> Result<ExecCredentialStatus, Error> {
+        if let Some(c) = self.state.lock().unwrap().fresh() {
+            return Ok(c);
+        }
+        let creds = self.run()?;
+        self.state.lock().unwrap().cached = Some(creds.clone());
+        Ok(creds)
+    }
+
+    /// Like `credentials`, but the plugin runs on a thread of its own
+    /// once the future is polled, so the executor isn't blocked.
+    /// Requests waiting at the same time share one run.
+    pub fn credentials_async(
+        auth: &Arc<Self>,
+    ) -> impl Future<Item = ExecCredentialStatus, Error = Error> + Send {
+        let auth = Arc::clone(auth);
+        future::lazy(move || {
+            let mut state = auth.state.lock().unwrap();
+            if let Some(c) = state.fresh() {
+                return Either::A(future::ok(c));
+            }
+            let running = match state.running {
+                Some(ref running) => running.clone(),
+                None => {
+                    let (tx, rx) = oneshot::channel();
+                    let auth = Arc::clone(&auth);
+                    thread::spawn(move || {
+                        let res = auth.run();
+                        let mut state = auth.state.lock().unwrap();
+                        if let Ok(ref creds) = res {
+                            state.cached = Some(creds.clone());
+                        }
+                        state.running = None;
+                        let _ = tx.send(res.map_err(|e| e.to_string()));
+                    });
+                    let running = rx.shared();
+                    state.running = Some(running.clone());
+                    running
+                }
+            };
+            Either::B(
+                running
+                    .map_err(|_| format_err!("Exec credential plugin thread failed"))
+                    .and_then(|res| (*res).clone().map_err(|e| format_err!("{}", e))),
+            )
+        })
+    }
+
+    fn run(&self) -> Result<ExecCredentialStatus, Error> {
+        let config = &self.config;
+        debug!("Running exec credential plugin {}", config.command);
+        let info = ExecCredential {
+            api_version: config.api_version.clone(),
+            kind: "ExecCredential".into(),
+            spec: Some(ExecCredentialSpec {
+                interactive: false,
+                cluster: if config.provide_cluster_info {
+                    Some(ExecCluster {
+                        server: self.cluster.server.clone(),
+                        insecure_skip_tls_verify: self.cluster.insecure_skip_tls_verify,
+                    })
+                } else {
+                    None
+                },
+            }),
+            status: None,
+        };
+        let output = Command::new(&config.command)
+            .args(&config.args)
+            .envs(config.env.iter().map(|e| (&e.name, &e.value)))
+            .env("KUBERNETES_EXEC_INFO", serde_json::to_string(&info)?)
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()
+            .map_err(|e| {
+                if e.kind() == io::ErrorKind::NotFound && config.install_hint != "" {
+                    format_err!(
+                        "Unable to run {}: {}\n{}",
+                        config.command,
+                        e,
+                        config.install_hint
+                    )
+                } else {
+                    format_err!("Unable to run {}: {}", config.command, e)
+                }
+            })?;
+        if !output.status.success() {
+            bail!("{} failed: {}", config.command, output.status);
+        }
+        let cred: ExecCredential = serde_json::from_slice(&output.stdout)
+            .with_context(|e| format!("Unable to parse output of {}: {}", config.command, e))?;
+        if cred.kind != "ExecCredential" || cred.api_version != config.api_version {
+            bail!(
+                "{} returned {} {}, expected ExecCredential {}",
+                config.command,
+                cred.api_version,
+                cred.kind,
+                config.api_version
+            );
+        }
+        let status = cred
+            .status
+            .ok_or_else(|| format_err!("{} returned no credentials", config.command))?;
+        if status.token == "" && status.client_certificate_data == "" {
+            bail!("{} returned no token or client certificate", config.command);
+        }
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::config::api::ExecEnvVar;
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    fn plugin(script: &str) -> ExecConfig {
+        ExecConfig {
+            command: "sh".into(),
+            args: vec!["-c".into(), script.into()],
+            env: vec![ExecEnvVar {
+                name: "GREETING".into(),
+                value: "hello".into(),
+            }],
+            api_version: "client.authentication.k8s.io/v1beta1".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn exec_token() {
+        let count = env::temp_dir().join(format!("k8s-exec-{}", ::std::process::id()));
+        let _ = fs::remove_file(&count);
+        let script = format!(
+            r#"echo run >> {}
+echo "$KUBERNETES_EXEC_INFO" | grep -q '"kind":"ExecCredential"' || exit 1
+cat <<EOF
+{{
+  "apiVersion": "client.authentication.k8s.io/v1beta1",
+  "kind": "ExecCredential",
+  "status": {{"token": "$GREETING-token", "expirationTimestamp": "$EXPIRY"}}
+}}
+EOF"#,
+            count.display()
+        );
+        let runs = || fs::read_to_string(&count).unwrap().lines().count();
+
+        // Far off expiry: cached
+        let mut config = plugin(&script);
+        config.env.push(ExecEnvVar {
+            name: "EXPIRY".into(),
+            value: "2999-01-01T00:00:00Z".into(),
+        });
+        let auth = ExecAuth::new(config, &Default::default());
+        assert_eq!(auth.credentials().unwrap().token, "hello-token");
+        assert_eq!(auth.credentials().unwrap().token, "hello-token");
+        assert_eq!(runs(), 1);
+
+        // Expired: run every time
+        let mut config = plugin(&script);
+        config.env.push(ExecEnvVar {
+            name: "EXPIRY".into(),
+            value: "2000-01-01T00:00:00Z".into(),
+        });
+        let auth = ExecAuth::new(config, &Default::default());
+        auth.credentials().unwrap();
+        auth.credentials().unwrap();
+        assert_eq!(runs(), 3);
+
+        fs::remove_file(&count).unwrap();
+    }
+
+    #[test]
+    fn exec_token_async() {
+        let count = env::temp_dir().join(format!("k8s-exec-async-{}", ::std::process::id()));
+        let _ = fs::remove_file(&count);
+        let script = format!(
+            r#"echo run >> {}
+sleep 0.1
+echo '{{"apiVersion": "client.authentication.k8s.io/v1beta1", "kind": "ExecCredential", "status": {{"token": "t"}}}}'"#,
+            count.display()
+        );
+        let runs = || {
+            fs::read_to_string(&count)
+                .map(|c| c.lines().count())
+                .unwrap_or(0)
+        };
+        let auth = Arc::new(ExecAuth::new(plugin(&script), &Default::default()));
+
+        // Nothing runs until polled
+        let creds: Vec<_> = (0..3).map(|_| ExecAuth::credentials_async(&auth)).collect();
+        assert_eq!(runs(), 0);
+        // Waiting at the same time, so sharing one run
+        let creds = future::join_all(creds).wait().unwrap();
+        assert!(creds.iter().all(|c| c.token == "t"));
+        assert_eq!(runs(), 1);
+        // Then cached
+        assert_eq!(
+            ExecAuth::credentials_async(&auth).wait().unwrap().token,
+            "t"
+        );
+        assert_eq!(auth.credentials().unwrap().token, "t");
+        assert_eq!(runs(), 1);
+
+        let auth = Arc::new(ExecAuth::new(plugin("exit 3"), &Default::default()));
+        let err = ExecAuth::credentials_async(&auth).wait().unwrap_err();
+        assert!(err.to_string().starts_with("sh failed"), "{}", err);
+
+        fs::remove_file(&count).unwrap();
+    }
+
+    #[test]
+    fn exec_errors() {
+        let auth = ExecAuth::new(plugin("exit 3"), &Default::default());
+        assert!(auth.credentials().is_err());
+
+        let auth = ExecAuth::new(plugin("echo '{}'"), &Default::default());
+        assert!(auth.credentials().is_err());
+
+        let auth = ExecAuth::new(
+            plugin(
+                r#"echo '{"apiVersion": "client.authentication.k8s.io/v1beta1", "kind": "ExecCredential", "status": {}}'"#,
+            ),
+            &Default::default(),
+        );
+        assert!(auth.credentials().is_err());
+
+        let config = ExecConfig {
+            command: "/nonexistent/plugin".into(),
+            install_hint: "Install the plugin".into(),
+            ..plugin("")
+        };
+        let err = ExecAuth::new(config, &Default::default())
+            .credentials()
+            .unwrap_err();
+        assert!(err.to_string().ends_with("Install the plugin"), "{}", err);
+    }
+}
@@ -0,0 +1,355 @@
+//! Discovery of the API groups, versions and resources a server
+//! offers, from the `/api` and `/apis` endpoints.
+
+use super::{do_request, hyper_uri, Client, HttpService};
+use api::meta::v1::{
+    APIGroup, APIGroupList, APIResourceList, APIVersions, GroupVersionForDiscovery,
+};
+use api::restmapper::RESTMapper;
+use failure::Error;
+use futures::{future, Future};
+use hyper::{Body, Method, Request};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use url::Url;
+
+/// Fetches discovery documents.  Create with `Client::discovery`.
+#[derive(Debug)]
+pub struct Discovery<C> {
+    client: Client<C>,
+}
+
+impl<C> Clone for Discovery<C> {
+    fn clone(&self) -> Self {
+        Discovery {
+            client: self.client.clone(),
+        }
+    }
+}
+
+impl<C: HttpService> Discovery<C> {
+    pub(super) fn new(client: Client<C>) -> Self {
+        Discovery { client }
+    }
+
+    /// Every API group, including the legacy core group (named `""`)
+    /// from `/api` first, as `kubectl api-versions` lists them.
+    pub fn server_groups(&self) -> impl Future<Item = APIGroupList, Error = Error> + Send {
+        let core = self.get::<APIVersions>(&["api"]);
+        let groups = self.get::<APIGroupList>(&["apis"]);
+        core.join(groups).map(|(core, mut list)| {
+            let versions: Vec<_> = core
+                .versions
+                .into_iter()
+                .map(|v| GroupVersionForDiscovery {
+                    group_version: v.clone(),
+                    version: v,
+                })
+                .collect();
+            let legacy = APIGroup {
+                name: "".into(),
+                preferred_version: versions.first().cloned(),
+                versions,
+                server_address_by_client_cidrs: core.server_address_by_client_cidrs,
+            };
+            list.groups.insert(0, legacy);
+            list
+        })
+    }
+
+    /// The resources in `group_version`, eg: `"v1"` or `"apps/v1"`.
+    pub fn server_resources_for_group_version(
+        &self,
+        group_version: &str,
+    ) -> impl Future<Item = APIResourceList, Error = Error> + Send {
+        self.client.api_resources(group_version)
+    }
+
+    /// The resources in the preferred version of every group.  A
+    /// resource offered in several groups (eg: `events` in `v1` and
+    /// `events.k8s.io`) appears once per group.  Group versions that
+    /// can't be fetched (eg: an aggregated API whose server is down)
+    /// are logged and left out, as by client-go.
+    pub fn server_preferred_resources(
+        &self,
+    ) -> impl Future<Item = Vec<APIResourceList>, Error = Error> + Send {
+        let this = self.clone();
+        self.server_groups().and_then(move |list| {
+            let group_versions = list
+                .groups
+                .iter()
+                .filter_map(|g| g.preferred_version.as_ref().or_else(|| g.versions.first()))
+                .map(|v| v.group_version.clone())
+                .collect();
+            this.resources_for(group_versions)
+        })
+    }
+
+    /// A `RESTMapper` for every version of every group.  As for
+    /// `server_preferred_resources`, group versions that can't be
+    /// fetched are logged and left out.
+    pub fn rest_mapper(&self) -> impl Future<Item = RESTMapper, Error = Error> + Send {
+        let this = self.clone();
+        self.server_groups().and_then(move |list| {
+            let group_versions = list
+                .groups
+                .iter()
+                .flat_map(|g| g.versions.iter())
+                .map(|v| v.group_version.clone())
+                .collect();
+            this.resources_for(group_versions).map(RESTMapper::new)
+        })
+    }
+
+    /// The server's OpenAPI (v2) schema, eg: for
+    /// `api::openapi::explain`.  This is large (megabytes), so fetch
+    /// it once and keep it.
+    pub fn openapi_v2(&self) -> impl Future<Item = Value, Error = Error> + Send {
+        self.get(&["openapi", "v2"])
+    }
+
+    /// The resources in each of `group_versions`, skipping those that
+    /// fail.
+    fn resources_for(
+        &self,
+        group_versions: Vec<String>,
+    ) -> impl Future<Item = Vec<APIResourceList>, Error = Error> + Send {
+        let fetches: Vec<_> = group_versions
+            .into_iter()
+            .map(|gv| {
+                self.server_resources_for_group_version(&gv)
+                    .then(move |res| match res {
+                        Ok(list) => Ok(Some(list)),
+                        Err(e) => {
+                            warn!("Skipping discovery of {}: {}", gv, e);
+                            Ok(None)
+                        }
+                    })
+            })
+            .collect();
+        future::join_all(fetches).map(|lists| lists.into_iter().flatten().collect())
+    }
+
+    fn get<T>(&self, path: &[&str]) -> impl Future<Item = T, Error = Error> + Send
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let req = self
+            .client
+            .config
+            .cluster
+            .server
+            .parse::<Url>()
+            .map_err(Error::from)
+            .and_then(|mut url| {
+                url.path_segments_mut()
+                    .map_err(|_| format_err!("URL scheme does not support paths"))?
+                    .clear()
+                    .extend(path);
+                Ok(url)
+            })
+            .and_then(|url| {
+                Request::builder()
+                    .method(Method::GET)
+                    .uri(hyper_uri(url))
+                    .body(Body::empty())
+                    .map_err(Error::from)
+            });
+        do_request(self.client.transport.clone(), req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{json_response, mock_client};
+    use futures::Future;
+
+    fn client() -> super::Client<impl super::HttpService> {
+        mock_client(|req| match req.uri().path() {
+            "/api" => json_response(200, &json!({"kind": "APIVersions", "versions": ["v1"]})),
+            "/apis" => json_response(
+                200,
+                &json!({
+                    "kind": "APIGroupList",
+                    "apiVersion": "v1",
+                    "groups": [{
+                        "name": "apps",
+                        "versions": [{"groupVersion": "apps/v1", "version": "v1"}],
+                        "preferredVersion": {"groupVersion": "apps/v1", "version": "v1"},
+                    }, {
+                        "name": "batch",
+                        "versions": [
+                            {"groupVersion": "batch/v1", "version": "v1"},
+                            {"groupVersion": "batch/v1beta1", "version": "v1beta1"},
+                        ],
+                        "preferredVersion": {"groupVersion": "batch/v1", "version": "v1"},
+                    }, {
+                        "name": "metrics.k8s.io",
+                        "versions": [
+                            {"groupVersion": "metrics.k8s.io/v1beta1", "version": "v1beta1"},
+                        ],
+                        "preferredVersion": {
+                            "groupVersion": "metrics.k8s.io/v1beta1",
+                            "version": "v1beta1",
+                        },
+                    }],
+                }),
+            ),
+            "/api/v1" => json_response(
+                200,
+                &json!({
+                    "kind": "APIResourceList",
+                    "groupVersion": "v1",
+                    "resources": [{
+                        "name": "pods", "singularName": "", "namespaced": true,
+                        "kind": "Pod", "verbs": ["get", "list"], "shortNames": ["po"],
+                    }],
+                }),
+            ),
+            "/apis/apps/v1" => json_response(
+                200,
+                &json!({
+                    "kind": "APIResourceList",
+                    "groupVersion": "apps/v1",
+                    "resources": [{
+                        "name": "deployments", "singularName": "", "namespaced": true,
+                        "kind": "Deployment",
+                    }],
+                }),
+            ),
+            "/apis/batch/v1" => json_response(
+                200,
+                &json!({
+                    "kind": "APIResourceList",
+                    "groupVersion": "batch/v1",
+                    "resources": [{
+                        "name": "jobs", "singularName": "", "namespaced": true,
+                        "kind": "Job",
+                    }],
+                }),
+            ),
+            "/apis/batch/v1beta1" => json_response(
+                200,
+                &json!({
+                    "kind": "APIResourceList",
+                    "groupVersion": "batch/v1beta1",
+                    "resources": [{
+                        "name": "cronjobs", "singularName": "", "namespaced": true,
+                        "kind": "CronJob",
+                    }],
+                }),
+            ),
+            // An aggregated API whose server is down
+            "/apis/metrics.k8s.io/v1beta1" => json_response(
+                503,
+                &json!({
+                    "kind": "Status",
+                    "apiVersion": "v1",
+                    "metadata": {},
+                    "status": "Failure",
+                    "message": "service unavailable",
+                    "reason": "ServiceUnavailable",
+                    "code": 503,
+                }),
+            ),
+            "/openapi/v2" => json_response(
+                200,
+                &json!({
+                    "swagger": "2.0",
+                    "definitions": {
+                        "io.k8s.api.core.v1.Pod": {
+                            "description": "Pod is a collection of containers.",
+                            "properties": {
+                                "spec": {"description": "Specification of the pod.", "type": "object"},
+                            },
+                            "x-kubernetes-group-version-kind": [
+                                {"group": "", "kind": "Pod", "version": "v1"},
+                            ],
+                        },
+                    },
+                }),
+            ),
+            p => panic!("unexpected request for {}", p),
+        })
+    }
+
+    #[test]
+    fn server_groups() {
+        let groups = client().discovery().server_groups().wait().unwrap();
+        let names: Vec<_> = groups.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["", "apps", "batch", "metrics.k8s.io"]);
+        assert_eq!(
+            groups.groups[0]
+                .preferred_version
+                .as_ref()
+                .unwrap()
+                .group_version,
+            "v1"
+        );
+        assert_eq!(groups.groups[2].versions.len(), 2);
+    }
+
+    #[test]
+    fn server_resources_for_group_version() {
+        let list = client()
+            .discovery()
+            .server_resources_for_group_version("apps/v1")
+            .wait()
+            .unwrap();
+        assert_eq!(list.group_version, "apps/v1");
+        assert_eq!(list.resources[0].kind, "Deployment");
+    }
+
+    #[test]
+    fn rest_mapper() {
+        use api::meta::{GroupVersion, GroupVersionKind};
+
+        let mapper = client().discovery().rest_mapper().wait().unwrap();
+        let gvk = GroupVersionKind::from_str("batch/v1beta1/CronJob").unwrap();
+        let cronjobs = GroupVersion::from_str("batch/v1beta1")
+            .unwrap()
+            .with_resource("cronjobs");
+        assert_eq!(mapper.resource_for(&gvk).unwrap(), cronjobs);
+        assert_eq!(mapper.kind_for(&cronjobs).unwrap(), gvk);
+        assert!(mapper.namespaced(&cronjobs));
+
+        let gvk = GroupVersionKind::from_str("v1/Pod").unwrap();
+        assert_eq!(mapper.resource_for(&gvk).unwrap().resource, "pods");
+    }
+
+    #[test]
+    fn openapi_v2() {
+        use api::meta::GroupVersionKind;
+        use api::openapi::explain;
+
+        let doc = client().discovery().openapi_v2().wait().unwrap();
+        let gvk = GroupVersionKind::from_str("v1/Pod").unwrap();
+        let spec = explain(&doc, &gvk, "spec").unwrap();
+        assert_eq!(spec.description, "Specification of the pod.");
+    }
+
+    #[test]
+    fn server_preferred_resources() {
+        let lists = client()
+            .discovery()
+            .server_preferred_resources()
+            .wait()
+            .unwrap();
+        let resources: Vec<_> = lists
+            .iter()
+            .flat_map(|l| {
+                l.resources
+                    .iter()
+                    .map(move |r| (l.group_version.as_str(), r.name.as_str()))
+            })
+            .collect();
+        assert_eq!(
+            resources,
+            vec![
+                ("v1", "pods"),
+                ("apps/v1", "deployments"),
+                ("batch/v1", "jobs")
+            ]
+        );
+    }
+}
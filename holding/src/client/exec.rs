@@ -0,0 +1,330 @@
+//! Running commands in a pod, over the `exec`/`attach` subresources
+//! (`v4.channel.k8s.io`).
+//!
+//! `Client::exec` upgrades the request to a websocket, where each
+//! message starts with a channel byte: stdin is 0, stdout 1, stderr 2,
+//! and the server sends a final `Status` on 3 (error) when the command
+//! exits.  The channels are returned as separate readers and a writer;
+//! the framing helpers here are also usable on their own.
+
+use super::websocket::{io_error, Channels, WebSocket};
+use api::meta::v1::{CauseType, Status, StatusStatus};
+use failure::Error;
+use futures::{Async, AsyncSink, Future, Poll, Stream};
+use serde_json;
+use std::cmp;
+use std::io::{self, Read, Write};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+pub const STDIN: u8 = 0;
+pub const STDOUT: u8 = 1;
+pub const STDERR: u8 = 2;
+pub const ERROR: u8 = 3;
+pub const RESIZE: u8 = 4;
+
+/// The websocket subprotocol to request when upgrading.
+pub const PROTOCOL: &str = "v4.channel.k8s.io";
+
+/// A decoded message from the server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecFrame {
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+    Status(Status),
+}
+
+/// Decodes one message received from the server.
+pub fn decode_frame(msg: &[u8]) -> Result<ExecFrame, Error> {
+    let (channel, data) = match msg.split_first() {
+        Some((c, d)) => (*c, d),
+        None => bail!("Empty exec message"),
+    };
+    match channel {
+        STDOUT => Ok(ExecFrame::Stdout(data.to_vec())),
+        STDERR => Ok(ExecFrame::Stderr(data.to_vec())),
+        ERROR => Ok(ExecFrame::Status(serde_json::from_slice(data)?)),
+        c => bail!("Unexpected exec channel {}", c),
+    }
+}
+
+/// Encodes `data` as a message for the command's stdin.
+pub fn encode_stdin(data: &[u8]) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(data.len() + 1);
+    msg.push(STDIN);
+    msg.extend_from_slice(data);
+    msg
+}
+
+/// Everything a command wrote, once it has exited.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExecOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub status: Option<Status>,
+}
+
+impl ExecOutput {
+    /// The command's exit code, if the server reported one.
+    pub fn exit_code(&self) -> Option<i32> {
+        exit_code(self.status.as_ref()?)
+    }
+}
+
+/// The exit code in a command's final status.  A `Success` status
+/// means 0; otherwise the code is in the `ExitCode` cause.
+pub fn exit_code(status: &Status) -> Option<i32> {
+    if status.status == StatusStatus::Success {
+        return Some(0);
+    }
+    status
+        .details
+        .as_ref()?
+        .causes
+        .iter()
+        .find(|c| c.reason == Some(CauseType::ExitCode))
+        .and_then(|c| c.message.as_ref())
+        .and_then(|m| m.parse().ok())
+}
+
+/// Collects the messages of an exec session into stdout, stderr and
+/// the final status.
+pub fn demux<S>(messages: S) -> impl Future<Item = ExecOutput, Error = Error>
+where
+    S: Stream<Item = Vec<u8>, Error = Error>,
+{
+    messages.fold(ExecOutput::default(), |mut out, msg| {
+        match decode_frame(&msg)? {
+            ExecFrame::Stdout(d) => out.stdout.extend(d),
+            ExecFrame::Stderr(d) => out.stderr.extend(d),
+            ExecFrame::Status(s) => out.status = Some(s),
+        }
+        Ok::<_, Error>(out)
+    })
+}
+
+/// A command started by `Client::exec`.  Its streams are only
+/// connected if the `PodExecOptions` asked for them.
+///
+/// All of them share one connection: whichever is polled reads it, and
+/// buffers what arrives for the others.  Dropping a reader discards
+/// its stream's output from then on.
+#[derive(Debug)]
+pub struct Exec {
+    /// Writes to the command's stdin.  `v4.channel.k8s.io` has no way
+    /// to close stdin on its own, so `shutdown` only flushes.
+    pub stdin: ExecStdin,
+    pub stdout: ExecReader,
+    pub stderr: ExecReader,
+    /// Resolves to the final status once the command exits; see
+    /// `exit_code`.
+    pub status: ExecStatus,
+}
+
+impl Exec {
+    pub(super) fn new(ws: WebSocket) -> Self {
+        let channels = Channels::new(ws, &[STDOUT, STDERR, ERROR]);
+        let reader = |channel| ExecReader {
+            channels: channels.clone(),
+            channel,
+            pending: Vec::new(),
+        };
+        Exec {
+            stdin: ExecStdin {
+                channels: channels.clone(),
+            },
+            stdout: reader(STDOUT),
+            stderr: reader(STDERR),
+            status: ExecStatus {
+                channels: channels.clone(),
+            },
+        }
+    }
+}
+
+/// The command's stdin.
+#[derive(Debug)]
+pub struct ExecStdin {
+    channels: Channels,
+}
+
+impl Write for ExecStdin {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.channels.start_send(encode_stdin(buf)) {
+            Ok(AsyncSink::Ready) => Ok(buf.len()),
+            Ok(AsyncSink::NotReady(_)) => Err(io::ErrorKind::WouldBlock.into()),
+            Err(e) => Err(io_error(e)),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.channels.poll_complete() {
+            Ok(Async::Ready(())) => Ok(()),
+            Ok(Async::NotReady) => Err(io::ErrorKind::WouldBlock.into()),
+            Err(e) => Err(io_error(e)),
+        }
+    }
+}
+
+impl AsyncWrite for ExecStdin {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        self.channels.poll_complete().map_err(io_error)
+    }
+}
+
+/// The command's stdout or stderr.  Reaches EOF when the connection
+/// closes.
+#[derive(Debug)]
+pub struct ExecReader {
+    channels: Channels,
+    channel: u8,
+    /// The rest of a message that didn't fit the last read
+    pending: Vec<u8>,
+}
+
+impl Read for ExecReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pending.is_empty() {
+            match self.channels.poll_recv(self.channel) {
+                Ok(Async::Ready(Some(data))) => self.pending = data,
+                Ok(Async::Ready(None)) => return Ok(0),
+                Ok(Async::NotReady) => return Err(io::ErrorKind::WouldBlock.into()),
+                Err(e) => return Err(io_error(e)),
+            }
+        }
+        let n = cmp::min(buf.len(), self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        Ok(n)
+    }
+}
+
+impl AsyncRead for ExecReader {}
+
+impl Drop for ExecReader {
+    fn drop(&mut self) {
+        self.channels.close(self.channel);
+    }
+}
+
+/// The final status of the command.
+#[derive(Debug)]
+pub struct ExecStatus {
+    channels: Channels,
+}
+
+impl Future for ExecStatus {
+    type Item = Status;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Status, Error> {
+        match self.channels.poll_recv(ERROR)? {
+            Async::Ready(Some(data)) => Ok(Async::Ready(serde_json::from_slice(&data)?)),
+            Async::Ready(None) => bail!("exec connection closed without a status"),
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{upgrade_client, ws_recv, ws_send};
+    use super::super::websocket::{BINARY, CLOSE};
+    use super::*;
+    use api::core::v1::PodExecOptions;
+    use futures::stream;
+    use tokio::io as tokio_io;
+    use tokio::runtime::current_thread::Runtime;
+
+    fn frame(channel: u8, data: &[u8]) -> Vec<u8> {
+        let mut f = vec![channel];
+        f.extend_from_slice(data);
+        f
+    }
+
+    #[test]
+    fn demux_channels() {
+        let failed = json!({
+            "metadata": {},
+            "status": "Failure",
+            "message": "command terminated with non-zero exit code",
+            "reason": "NonZeroExitCode",
+            "details": {"causes": [{"reason": "ExitCode", "message": "2"}]},
+        });
+        let messages = vec![
+            frame(STDOUT, b"hello "),
+            frame(STDERR, b"oops\n"),
+            frame(STDOUT, b"world\n"),
+            frame(ERROR, failed.to_string().as_bytes()),
+        ];
+        let out = demux(stream::iter_ok(messages)).wait().unwrap();
+        assert_eq!(out.stdout, b"hello world\n");
+        assert_eq!(out.stderr, b"oops\n");
+        assert_eq!(out.exit_code(), Some(2));
+
+        let ok = frame(ERROR, br#"{"metadata": {}, "status": "Success"}"#);
+        let out = demux(stream::iter_ok(vec![ok])).wait().unwrap();
+        assert_eq!(out.exit_code(), Some(0));
+
+        assert!(demux(stream::iter_ok(vec![frame(9, b"")])).wait().is_err());
+        assert!(decode_frame(b"").is_err());
+        assert_eq!(encode_stdin(b"ls\n"), b"\0ls\n");
+    }
+
+    #[test]
+    fn exec_session() {
+        let client = upgrade_client(|mut conn| {
+            let mut buf = Vec::new();
+            let stdin = ws_recv(&mut conn, &mut buf);
+            assert_eq!(stdin.opcode, BINARY);
+            assert_eq!(stdin.payload, b"\0ping\n");
+
+            ws_send(&mut conn, BINARY, &frame(STDOUT, b"pong\n"));
+            ws_send(&mut conn, BINARY, &frame(STDERR, b"warning\n"));
+            ws_send(&mut conn, BINARY, &frame(STDOUT, b"done\n"));
+            let failed = json!({
+                "metadata": {},
+                "status": "Failure",
+                "reason": "NonZeroExitCode",
+                "details": {"causes": [{"reason": "ExitCode", "message": "3"}]},
+            });
+            ws_send(
+                &mut conn,
+                BINARY,
+                &frame(ERROR, failed.to_string().as_bytes()),
+            );
+            ws_send(&mut conn, CLOSE, b"");
+        });
+        let opts = PodExecOptions {
+            stdin: true,
+            stdout: true,
+            stderr: true,
+            command: vec!["sh".to_string()],
+            ..Default::default()
+        };
+
+        let mut rt = Runtime::new().unwrap();
+        let exec = rt.block_on(client.exec("default", "web-0", &opts)).unwrap();
+        let stdin =
+            tokio_io::write_all(exec.stdin, b"ping\n").and_then(|(w, _)| tokio_io::flush(w));
+        let stdout = tokio_io::read_to_end(exec.stdout, Vec::new());
+        let stderr = tokio_io::read_to_end(exec.stderr, Vec::new());
+        let (_, (_, stdout), (_, stderr), status) = rt
+            .block_on(stdin.from_err::<Error>().join4(
+                stdout.from_err(),
+                stderr.from_err(),
+                exec.status,
+            ))
+            .unwrap();
+        assert_eq!(stdout, b"pong\ndone\n");
+        assert_eq!(stderr, b"warning\n");
+        assert_eq!(exit_code(&status), Some(3));
+
+        assert_eq!(
+            *client.transport.service.uris.lock().unwrap(),
+            vec![
+                "https://192.168.42.147:8443/api/v1/namespaces/default/pods/web-0/exec\
+                  ?stdin=true&stdout=true&stderr=true&command=sh"
+            ]
+        );
+    }
+}
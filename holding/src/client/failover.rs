@@ -0,0 +1,181 @@
+use super::{HttpService, UpgradedIo};
+use failure::Error;
+use futures::future::{self, Either, Loop};
+use futures::{Future, Stream};
+use hyper::{self, Body, Request, StatusCode};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use url::Url;
+
+/// Several addresses of the same API server, eg: each member of an HA
+/// control plane.
+///
+/// Requests go to the preferred server.  If it can't be reached or
+/// responds `503 Service Unavailable`, the request is resent to the
+/// next server, which becomes preferred if it succeeds.
+#[derive(Debug)]
+pub(super) struct Servers {
+    /// Scheme, host and port of each server, eg: `https://10.0.0.1:6443`
+    origins: Vec<String>,
+    preferred: AtomicUsize,
+}
+
+impl Servers {
+    pub fn new<I, S>(servers: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let origins = servers
+            .into_iter()
+            .map(|s| {
+                let url: Url = s.as_ref().parse()?;
+                if !url.has_host() {
+                    bail!("Server URL {} has no host", url);
+                }
+                Ok(url.origin().ascii_serialization())
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        if origins.is_empty() {
+            bail!("At least one server is required");
+        }
+        Ok(Servers {
+            origins,
+            preferred: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn first(&self) -> &str {
+        &self.origins[0]
+    }
+}
+
+/// An `HttpService` that sends each request to one of `servers`.
+pub(super) struct Failover<C> {
+    pub service: Arc<C>,
+    pub servers: Arc<Servers>,
+}
+
+impl<C: HttpService> HttpService for Failover<C> {
+    fn request(
+        &self,
+        req: Request<Body>,
+    ) -> Box<dyn Future<Item = hyper::Response<Body>, Error = Error> + Send> {
+        let service = Arc::clone(&self.service);
+        let servers = Arc::clone(&self.servers);
+        let (parts, body) = req.into_parts();
+        let path = parts
+            .uri
+            .path_and_query()
+            .map(|p| p.as_str().to_string())
+            .unwrap_or_else(|| "/".to_string());
+        let (method, version, headers) = (parts.method, parts.version, parts.headers);
+        // The body is read into memory, so it can be resent
+        let fut = body.concat2().from_err().and_then(move |body| {
+            let body = body.into_bytes();
+            let start = servers.preferred.load(Ordering::Relaxed);
+            let count = servers.origins.len();
+            future::loop_fn(0, move |attempt| {
+                let i = (start + attempt) % count;
+                let last = attempt + 1 == count;
+                let uri = format!("{}{}", servers.origins[i], path);
+                let req = Request::builder()
+                    .method(method.clone())
+                    .uri(uri.as_str())
+                    .version(version)
+                    .body(Body::from(body.clone()));
+                let mut req = match req {
+                    Ok(r) => r,
+                    Err(e) => return Either::A(future::err(e.into())),
+                };
+                *req.headers_mut() = headers.clone();
+                let servers = Arc::clone(&servers);
+                Either::B(service.request(req).then(move |res| {
+                    let failed = match res {
+                        Ok(ref r) => r.status() == StatusCode::SERVICE_UNAVAILABLE,
+                        Err(_) => true,
+                    };
+                    if failed && !last {
+                        let origin = &servers.origins[i];
+                        match res {
+                            Ok(ref r) => debug!("{} responded {}", origin, r.status()),
+                            Err(ref e) => debug!("{} failed: {}", origin, e),
+                        }
+                        return Ok(Loop::Continue(attempt + 1));
+                    }
+                    if !failed {
+                        servers.preferred.store(i, Ordering::Relaxed);
+                    }
+                    res.map(Loop::Break)
+                }))
+            })
+        });
+        Box::new(fut)
+    }
+
+    fn upgrade(
+        &self,
+        res: hyper::Response<Body>,
+    ) -> Box<dyn Future<Item = Box<dyn UpgradedIo>, Error = Error> + Send> {
+        self.service.upgrade(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::json_response;
+    use super::super::{Client, ConfigContext};
+    use super::*;
+    use api::core::v1::GROUP_VERSION;
+    use serde_json::Value;
+    use std::sync::Mutex;
+
+    /// Only 10.0.0.2 and 10.0.0.3 are reachable.
+    struct Cluster(Arc<Mutex<Vec<String>>>);
+
+    impl HttpService for Cluster {
+        fn request(
+            &self,
+            req: Request<Body>,
+        ) -> Box<dyn Future<Item = hyper::Response<Body>, Error = Error> + Send> {
+            let host = req.uri().host().unwrap().to_string();
+            self.0.lock().unwrap().push(host.clone());
+            if host == "10.0.0.1" {
+                Box::new(future::err(format_err!("Connection refused")))
+            } else {
+                Box::new(future::ok(json_response(200, &json!({"host": host}))))
+            }
+        }
+    }
+
+    #[test]
+    fn failover() {
+        let hosts = Arc::new(Mutex::new(vec![]));
+        let cluster = Cluster(Arc::clone(&hosts));
+        let client = Client::new_with_client(cluster, ConfigContext::default())
+            .unwrap()
+            .with_servers(&[
+                "https://10.0.0.1:6443",
+                "https://10.0.0.2:6443/",
+                "https://10.0.0.3:6443",
+            ])
+            .unwrap();
+
+        let gvr = GROUP_VERSION.with_resource("pods");
+        for _ in 0..2 {
+            let res: Value = client
+                .get(&gvr, Some("default"), "mypod", Default::default())
+                .wait()
+                .unwrap();
+            assert_eq!(res["host"], "10.0.0.2");
+        }
+        // The unreachable server is only tried once
+        assert_eq!(
+            *hosts.lock().unwrap(),
+            vec!["10.0.0.1", "10.0.0.2", "10.0.0.2"]
+        );
+
+        assert!(Servers::new(Vec::<String>::new()).is_err());
+        assert!(Servers::new(&["not a url"]).is_err());
+    }
+}
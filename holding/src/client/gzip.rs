@@ -0,0 +1,156 @@
+//! Response decompression, with the `gzip` feature.
+//!
+//! Requests advertise `Accept-Encoding: gzip`, and responses the server
+//! chose to compress are inflated as they arrive, before anything else
+//! reads the body.  Watch responses are decoded incrementally, so
+//! events are not held back until the (never ending) stream completes.
+
+use flate2::write::GzDecoder;
+use futures::{Async, Poll, Stream};
+use hyper::header::{HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH};
+use hyper::{Body, Request, Response};
+use std::io::{self, Write};
+use std::mem;
+
+/// Asks for a compressed response, unless the caller already chose an
+/// encoding.
+pub(super) fn accept(req: &mut Request<Body>) {
+    if !req.headers().contains_key(ACCEPT_ENCODING) {
+        req.headers_mut()
+            .insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+    }
+}
+
+/// Replaces a gzip encoded body with its decoded contents.  Other
+/// responses are returned unchanged.
+pub(super) fn decode(res: Response<Body>) -> Response<Body> {
+    let gzipped = res
+        .headers()
+        .get(CONTENT_ENCODING)
+        .map(|v| v.as_bytes().eq_ignore_ascii_case(b"gzip"))
+        .unwrap_or(false);
+    if !gzipped {
+        return res;
+    }
+    let (mut parts, body) = res.into_parts();
+    parts.headers.remove(CONTENT_ENCODING);
+    parts.headers.remove(CONTENT_LENGTH);
+    let body = Inflate {
+        inner: body,
+        decoder: Some(GzDecoder::new(Vec::new())),
+    };
+    Response::from_parts(parts, Body::wrap_stream(body))
+}
+
+struct Inflate {
+    inner: Body,
+    /// `None` once the trailer has been checked
+    decoder: Option<GzDecoder<Vec<u8>>>,
+}
+
+impl Stream for Inflate {
+    type Item = Vec<u8>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Vec<u8>>, io::Error> {
+        loop {
+            let mut decoder = match self.decoder.take() {
+                Some(d) => d,
+                None => return Ok(Async::Ready(None)),
+            };
+            let chunk = self
+                .inner
+                .poll()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e));
+            match chunk {
+                Ok(Async::Ready(Some(chunk))) => {
+                    decoder.write_all(&chunk)?;
+                    decoder.flush()?;
+                }
+                Ok(Async::Ready(None)) => {
+                    decoder.try_finish()?;
+                    let rest = mem::replace(decoder.get_mut(), Vec::new());
+                    return Ok(Async::Ready(if rest.is_empty() {
+                        None
+                    } else {
+                        Some(rest)
+                    }));
+                }
+                Ok(Async::NotReady) => {
+                    self.decoder = Some(decoder);
+                    return Ok(Async::NotReady);
+                }
+                Err(e) => return Err(e),
+            }
+            let out = mem::replace(decoder.get_mut(), Vec::new());
+            self.decoder = Some(decoder);
+            if !out.is_empty() {
+                return Ok(Async::Ready(Some(out)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{chunked_body, mock_client};
+    use super::*;
+    use api::core::v1::GROUP_VERSION;
+    use api::meta::v1::{EventType, WatchEvent};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use futures::Future;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut e = GzEncoder::new(Vec::new(), Compression::default());
+        e.write_all(data).unwrap();
+        e.finish().unwrap()
+    }
+
+    #[test]
+    fn gzip_watch() {
+        let client = mock_client(|req| {
+            assert_eq!(req.headers()[ACCEPT_ENCODING], "gzip");
+            let lines = b"{\"type\":\"ADDED\",\"object\":{\"kind\":\"Pod\"}}\n\
+                {\"type\":\"MODIFIED\",\"object\":{\"kind\":\"Pod\"}}\n\
+                {\"type\":\"DELETED\",\"object\":{\"kind\":\"Pod\"}}\n";
+            Response::builder()
+                .header(CONTENT_ENCODING, "gzip")
+                .body(chunked_body(&gzip(lines), 7))
+                .unwrap()
+        });
+        let events: Vec<WatchEvent> = client
+            .watch_list(
+                &GROUP_VERSION.with_resource("pods"),
+                None,
+                Default::default(),
+            )
+            .collect()
+            .wait()
+            .unwrap();
+        let types: Vec<_> = events.iter().map(|e| e.typ.clone()).collect();
+        assert_eq!(
+            types,
+            vec![EventType::Added, EventType::Modified, EventType::Deleted]
+        );
+    }
+
+    #[test]
+    fn decode_passthrough() {
+        let res = Response::builder().body(Body::from("plain")).unwrap();
+        let body = decode(res).into_body().concat2().wait().unwrap();
+        assert_eq!(&body[..], b"plain");
+    }
+
+    #[test]
+    fn decode_truncated() {
+        let mut data = gzip(b"hello world");
+        let len = data.len();
+        data.truncate(len - 4);
+        let res = Response::builder()
+            .header(CONTENT_ENCODING, "gzip")
+            .body(Body::from(data))
+            .unwrap();
+        assert!(decode(res).into_body().concat2().wait().is_err());
+    }
+}
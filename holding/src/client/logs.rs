@@ -0,0 +1,364 @@
+//! Reading a pod's logs as an `AsyncRead`, and following them across
+//! container restarts.
+
+use super::{Client, HttpService, RetryPolicy};
+use api::core::v1::{self as core_v1, ContainerState, Pod, PodLogOptions, PodPhase};
+use api::{Integer, Time};
+use chrono::{self, Utc};
+use failure::Error;
+use futures::{Async, Future, Poll, Stream};
+use hyper;
+use std::io::{self, Read};
+use std::time::Instant;
+use std::{cmp, mem};
+use tokio::io::AsyncRead;
+use tokio::timer::Delay;
+
+/// A container's logs, as returned by `Client::read_log_reader`.
+/// Reaches EOF when the log stream ends.
+#[must_use = "readers do nothing unless read"]
+pub struct LogReader {
+    inner: Box<dyn Stream<Item = hyper::Chunk, Error = Error> + Send>,
+    /// The rest of a chunk that didn't fit the last read
+    pending: Vec<u8>,
+}
+
+impl LogReader {
+    pub(super) fn new(logs: Box<dyn Stream<Item = hyper::Chunk, Error = Error> + Send>) -> Self {
+        LogReader {
+            inner: logs,
+            pending: vec![],
+        }
+    }
+}
+
+impl Read for LogReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pending.is_empty() {
+            match self.inner.poll() {
+                Ok(Async::Ready(Some(chunk))) => self.pending = chunk.to_vec(),
+                Ok(Async::Ready(None)) => return Ok(0),
+                Ok(Async::NotReady) => return Err(io::ErrorKind::WouldBlock.into()),
+                Err(e) => return Err(io::Error::new(io::ErrorKind::Other, e.compat())),
+            }
+        }
+        let n = cmp::min(buf.len(), self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        Ok(n)
+    }
+}
+
+impl AsyncRead for LogReader {}
+
+impl<C: HttpService> Client<C> {
+    /// Like `read_log` with `opts.follow`, but when the log stream
+    /// ends while the pod is still `Running` (eg: because its container
+    /// restarted), the logs are followed again after a backoff set by
+    /// `policy`, from when the previous stream ended.  While the
+    /// container isn't running (eg: in `CrashLoopBackOff`) its status
+    /// is polled instead, with the same backoff.  The backoff grows
+    /// with each follow or poll that brings no output, up to
+    /// `policy.max_backoff`, and starts over when the container
+    /// restarts.  The stream ends once the pod has stopped running (or
+    /// is being deleted).
+    ///
+    /// The restart point is the local time, adjusted by
+    /// `server_time_skew`, so a few lines around a restart may be
+    /// repeated or missed if the clocks disagree.
+    ///
+    /// Requires a tokio timer (ie: runs within a tokio runtime).
+    pub fn follow_logs(
+        &self,
+        namespace: &str,
+        name: &str,
+        mut opts: PodLogOptions,
+        policy: RetryPolicy,
+    ) -> impl Stream<Item = hyper::Chunk, Error = Error> + Send {
+        opts.follow = true;
+        opts.previous = false;
+        let state = State::Following(Box::new(self.read_log(namespace, name, opts.clone())));
+        FollowLogs {
+            client: self.clone(),
+            namespace: namespace.into(),
+            name: name.into(),
+            opts,
+            policy,
+            failures: 0,
+            restarts: None,
+            ended: Time::now(),
+            state,
+        }
+    }
+}
+
+enum State {
+    Following(Box<dyn Stream<Item = hyper::Chunk, Error = Error> + Send>),
+    Waiting(Delay),
+    /// Checking the pod is still running
+    Checking(Box<dyn Future<Item = Pod, Error = Error> + Send>),
+    Done,
+}
+
+#[must_use = "streams do nothing unless polled"]
+struct FollowLogs<C> {
+    client: Client<C>,
+    namespace: String,
+    name: String,
+    opts: PodLogOptions,
+    policy: RetryPolicy,
+    // Consecutive follows or polls, without any output
+    failures: u32,
+    // The container's restart count, when last polled
+    restarts: Option<Integer>,
+    // When the last log stream ended, in server time
+    ended: Time,
+    state: State,
+}
+
+impl<C: HttpService> FollowLogs<C> {
+    /// Backs off before checking on the pod again.
+    fn wait(&mut self) -> State {
+        let delay = self.policy.backoff(self.failures);
+        self.failures = self.failures.saturating_add(1);
+        State::Waiting(Delay::new(Instant::now() + delay))
+    }
+
+    /// The total restart count of the followed container (all of
+    /// them, if `opts.container` is unset), and whether they are all
+    /// running.
+    fn container_status(&self, pod: &Pod) -> (Integer, bool) {
+        let statuses = pod.status.container_statuses.iter().filter(|s| {
+            self.opts
+                .container
+                .as_ref()
+                .map(|c| *c == s.name)
+                .unwrap_or(true)
+        });
+        statuses.fold((0, true), |(restarts, started), s| {
+            let running = match s.state {
+                Some(ContainerState::Running(_)) => true,
+                _ => false,
+            };
+            (restarts + s.restart_count, started && running)
+        })
+    }
+}
+
+impl<C: HttpService> Stream for FollowLogs<C> {
+    type Item = hyper::Chunk;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            self.state = match mem::replace(&mut self.state, State::Done) {
+                State::Done => return Ok(Async::Ready(None)),
+
+                State::Following(mut logs) => match logs.poll()? {
+                    Async::NotReady => {
+                        self.state = State::Following(logs);
+                        return Ok(Async::NotReady);
+                    }
+                    Async::Ready(Some(chunk)) => {
+                        self.failures = 0;
+                        self.state = State::Following(logs);
+                        return Ok(Async::Ready(Some(chunk)));
+                    }
+                    Async::Ready(None) => {
+                        let skew = self
+                            .client
+                            .server_time_skew()
+                            .unwrap_or_else(chrono::Duration::zero);
+                        self.ended = Time(Utc::now() + skew);
+                        self.wait()
+                    }
+                },
+
+                State::Waiting(mut delay) => match delay.poll()? {
+                    Async::NotReady => {
+                        self.state = State::Waiting(delay);
+                        return Ok(Async::NotReady);
+                    }
+                    Async::Ready(()) => {
+                        let pods = core_v1::GROUP_VERSION.with_resource("pods");
+                        State::Checking(Box::new(self.client.get(
+                            &pods,
+                            Some(self.namespace.as_str()),
+                            &self.name,
+                            Default::default(),
+                        )))
+                    }
+                },
+
+                State::Checking(mut pod) => match pod.poll()? {
+                    Async::NotReady => {
+                        self.state = State::Checking(pod);
+                        return Ok(Async::NotReady);
+                    }
+                    Async::Ready(pod) => {
+                        let running = pod.status.phase == Some(PodPhase::Running)
+                            && pod.metadata.deletion_timestamp.is_none();
+                        let (restarts, started) = self.container_status(&pod);
+                        if self.restarts.map(|r| r != restarts).unwrap_or(false) {
+                            // A new container: follow it promptly
+                            self.failures = 0;
+                        }
+                        self.restarts = Some(restarts);
+                        if !running || self.client.transport.shutdown.is_closed() {
+                            State::Done
+                        } else if !started {
+                            debug!("Container of {} not running yet", self.name);
+                            self.wait()
+                        } else {
+                            debug!("Log stream ended, following {} again", self.name);
+                            self.opts.since_seconds = None;
+                            self.opts.since_time = Some(self.ended);
+                            self.opts.tail_lines = None;
+                            State::Following(Box::new(self.client.read_log(
+                                &self.namespace,
+                                &self.name,
+                                self.opts.clone(),
+                            )))
+                        }
+                    }
+                },
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{json_response, mock_client};
+    use super::*;
+    use hyper::{Body, Method};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::runtime::current_thread::Runtime;
+
+    #[test]
+    fn follow_across_restart() {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let requests2 = Arc::clone(&requests);
+        let client = mock_client(move |req| {
+            assert_eq!(req.method(), &Method::GET);
+            let mut requests = requests2.lock().unwrap();
+            let path = req.uri().path().to_string();
+            let query = req.uri().query().unwrap_or("").to_string();
+            requests.push((path.clone(), query.clone()));
+            let logs = requests.iter().filter(|r| r.0.ends_with("/log")).count();
+            match path.as_str() {
+                "/api/v1/namespaces/default/pods/web/log" => {
+                    hyper::Response::new(Body::from(format!("container {}\n", logs)))
+                }
+                "/api/v1/namespaces/default/pods/web" => {
+                    let phase = if logs < 2 { "Running" } else { "Succeeded" };
+                    json_response(
+                        200,
+                        &json!({
+                            "kind": "Pod",
+                            "apiVersion": "v1",
+                            "metadata": {"name": "web", "namespace": "default"},
+                            "status": {"phase": phase},
+                        }),
+                    )
+                }
+                p => panic!("unexpected request for {}", p),
+            }
+        });
+
+        let opts = PodLogOptions {
+            tail_lines: Some(10),
+            ..Default::default()
+        };
+        let policy = RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(10),
+        };
+        let logs = client
+            .follow_logs("default", "web", opts, policy)
+            .concat2()
+            .map(|body| String::from_utf8(body.to_vec()).unwrap());
+        let logs = Runtime::new().unwrap().block_on(logs).unwrap();
+        assert_eq!(logs, "container 1\ncontainer 2\n");
+
+        let requests = requests.lock().unwrap();
+        let queries: Vec<_> = requests.iter().map(|r| r.1.as_str()).collect();
+        assert_eq!(queries.len(), 4);
+        assert_eq!(queries[0], "follow=true&tailLines=10");
+        assert_eq!(queries[1], "");
+        assert!(queries[2].starts_with("follow=true&sinceTime="));
+        assert!(!queries[2].contains("tailLines"));
+        assert_eq!(queries[3], "");
+    }
+
+    #[test]
+    fn follow_after_empty_follows() {
+        let (logs, checks) = (Arc::new(Mutex::new(0)), Arc::new(Mutex::new(0)));
+        let (logs2, checks2) = (Arc::clone(&logs), Arc::clone(&checks));
+        let client = mock_client(move |req| match req.uri().path() {
+            "/api/v1/namespaces/default/pods/web/log" => {
+                let mut logs = logs2.lock().unwrap();
+                *logs += 1;
+                let body = match *logs {
+                    1 => "container 1\n",
+                    5 => "container 2\n",
+                    _ => "",
+                };
+                hyper::Response::new(Body::from(body))
+            }
+            "/api/v1/namespaces/default/pods/web" => {
+                let mut checks = checks2.lock().unwrap();
+                *checks += 1;
+                // More empty follows than max_retries, then the
+                // container crashes and is restarted
+                let (phase, restarts, state) = match *checks {
+                    1..=3 => ("Running", 0, json!({"running": {}})),
+                    4 => (
+                        "Running",
+                        1,
+                        json!({"waiting": {"reason": "CrashLoopBackOff"}}),
+                    ),
+                    5 => ("Running", 1, json!({"running": {}})),
+                    _ => ("Succeeded", 1, json!({"terminated": {"exitCode": 0}})),
+                };
+                json_response(
+                    200,
+                    &json!({
+                        "kind": "Pod",
+                        "apiVersion": "v1",
+                        "metadata": {"name": "web", "namespace": "default"},
+                        "status": {
+                            "phase": phase,
+                            "containerStatuses": [{
+                                "name": "web",
+                                "state": state,
+                                "ready": true,
+                                "restartCount": restarts,
+                                "image": "web",
+                                "imageID": "",
+                            }],
+                        },
+                    }),
+                )
+            }
+            p => panic!("unexpected request for {}", p),
+        });
+
+        let policy = RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(10),
+        };
+        let body = client
+            .follow_logs("default", "web", Default::default(), policy)
+            .concat2()
+            .map(|body| String::from_utf8(body.to_vec()).unwrap());
+        let body = Runtime::new().unwrap().block_on(body).unwrap();
+        assert_eq!(body, "container 1\ncontainer 2\n");
+        // No follow while the container was waiting
+        assert_eq!(*logs.lock().unwrap(), 5);
+        assert_eq!(*checks.lock().unwrap(), 6);
+    }
+}
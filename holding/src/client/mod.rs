@@ -1,29 +1,73 @@
-use api::core::v1::{NamespacedResource, Resource};
-use api::meta::v1::{DeleteOptions, GetOptions, List, ListOptions, Metadata, Status, WatchEvent};
-use api::meta::GroupVersionResource;
-use api::TypeMeta;
+use api::core::v1::{
+    self as core_v1, Namespace, NamespacedResource, Pod, PodExecOptions, PodList, PodLogOptions,
+    PodPortForwardOptions, Resource, Service, Services,
+};
+use api::meta::v1::{
+    APIGroup, APIResourceList, CreateOptions, DeleteOptions, DeleteResponse, EventType, GetOptions,
+    List, ListOptions, Metadata, PatchOptions, ResourceVersion, Status, StatusReason, WatchEvent,
+};
+use api::meta::{GroupVersion, GroupVersionResource};
+use api::restmapper::RESTMapper;
+use api::{TypeMeta, APPLY_PATCH, MERGE_PATCH};
+use base64;
+use chrono;
 use failure::{Error, ResultExt};
+use futures::future::{Either, Loop};
 use futures::{future, stream, Future, Stream};
-use hyper::header::{HeaderValue, CONTENT_TYPE};
-use hyper::{self, Body, Method, Request};
+use hyper::header::{HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use hyper::{self, Body, HeaderMap, Method, Request};
 use hyper_tls::HttpsConnector;
-use native_tls::{Certificate, Identity, TlsConnector};
+use native_tls::{Certificate, Identity, TlsConnector, TlsConnectorBuilder};
 use openssl;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use serde_json;
+use serde_json::{self, Value};
 use serde_urlencoded;
+use std::cmp;
 use std::default::Default;
-use std::env;
 use std::fmt;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::timer::Timeout;
 use url::Url;
 
+mod alpn;
+mod cache;
+mod cancel;
 pub mod config;
+mod credential;
+mod discovery;
+pub mod exec;
+mod failover;
+#[cfg(feature = "gzip")]
+mod gzip;
+mod logs;
+mod pool;
+pub mod portforward;
 mod resplit;
+mod retry;
+mod shutdown;
+mod skew;
+mod warning;
+mod watch;
+mod websocket;
 
+pub use self::alpn::AlpnConnector;
+pub use self::cache::CachingClient;
+pub use self::cancel::{CancelToken, Cancelled, WithCancel};
 use self::config::ConfigContext;
+use self::credential::ExecAuth;
+pub use self::discovery::Discovery;
+use self::failover::{Failover, Servers};
+pub use self::logs::LogReader;
+pub use self::pool::BufferPool;
+pub use self::retry::RetryPolicy;
+pub use self::shutdown::{Drained, ShutdownError};
+use self::shutdown::{Shutdown, UntilShutdown};
+use self::skew::ClockSkew;
+use self::warning::WarningHandler;
+pub use self::watch::{coalesce_latest, only_status_changed, CoalesceLatest, OnlyStatusChanged};
 
 #[derive(Fail, Debug)]
 #[fail(display = "HTTP client error: {}", err)]
@@ -46,12 +90,208 @@ pub fn required_attr(attr: &'static str) -> RequiredAttributeError {
     RequiredAttributeError { attr: attr }
 }
 
-#[derive(Debug, Clone)]
+/// A list response that was only the first page, from `list_complete`.
+#[derive(Fail, Debug)]
+#[fail(
+    display = "List is incomplete (continue token {:?}), use iter to read every page",
+    continu
+)]
+pub struct IncompleteListError {
+    pub continu: String,
+}
+
+/// True if `err` is an API `Status` error with the given reason.
+pub fn is_status_reason(err: &Error, reason: StatusReason) -> bool {
+    err.downcast_ref::<Status>()
+        .map(|s| s.reason == Some(reason))
+        .unwrap_or(false)
+}
+
+/// Something that can send an HTTP request and return the response.
+///
+/// Implemented for `hyper::Client`.  Other implementations can be
+/// plugged into `Client::new_with_client`, eg: to mock the API server
+/// in tests.
+pub trait HttpService: Send + Sync + 'static {
+    fn request(
+        &self,
+        req: Request<Body>,
+    ) -> Box<dyn Future<Item = hyper::Response<Body>, Error = Error> + Send>;
+
+    /// Takes over the connection of a `101 Switching Protocols`
+    /// response, eg: for `exec`.  The default is hyper's
+    /// `Body::on_upgrade`.
+    fn upgrade(
+        &self,
+        res: hyper::Response<Body>,
+    ) -> Box<dyn Future<Item = Box<dyn UpgradedIo>, Error = Error> + Send> {
+        Box::new(
+            res.into_body()
+                .on_upgrade()
+                .from_err()
+                .map(|io| Box::new(io) as Box<dyn UpgradedIo>),
+        )
+    }
+}
+
+/// A connection taken over from HTTP, by `HttpService::upgrade`.
+pub trait UpgradedIo: AsyncRead + AsyncWrite + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Send> UpgradedIo for T {}
+
+impl<C> HttpService for hyper::Client<C>
+where
+    C: hyper::client::connect::Connect + 'static,
+{
+    fn request(
+        &self,
+        req: Request<Body>,
+    ) -> Box<dyn Future<Item = hyper::Response<Body>, Error = Error> + Send> {
+        Box::new(hyper::Client::request(self, req).from_err())
+    }
+}
+
+#[derive(Clone)]
+struct Interceptor(Arc<dyn Fn(&mut Request<Body>) + Send + Sync>);
+
+impl fmt::Debug for Interceptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Interceptor")
+    }
+}
+
+/// Everything needed to send a request, cheap to clone into futures.
+#[derive(Debug)]
+struct Transport<C> {
+    service: Arc<C>,
+    headers: HeaderMap,
+    interceptors: Vec<Interceptor>,
+    shutdown: Arc<Shutdown>,
+    buffers: Option<Arc<BufferPool>>,
+    warnings: Option<Arc<WarningHandler>>,
+    retry: Option<RetryPolicy>,
+    servers: Option<Arc<Servers>>,
+    skew: Arc<ClockSkew>,
+    exec: Option<Arc<ExecAuth>>,
+    // The policy group version used for evictions, once discovered
+    eviction_version: Arc<Mutex<Option<&'static str>>>,
+}
+
+impl<C> Clone for Transport<C> {
+    fn clone(&self) -> Self {
+        Transport {
+            service: Arc::clone(&self.service),
+            headers: self.headers.clone(),
+            interceptors: self.interceptors.clone(),
+            shutdown: Arc::clone(&self.shutdown),
+            buffers: self.buffers.clone(),
+            warnings: self.warnings.clone(),
+            retry: self.retry.clone(),
+            servers: self.servers.clone(),
+            skew: Arc::clone(&self.skew),
+            exec: self.exec.clone(),
+            eviction_version: Arc::clone(&self.eviction_version),
+        }
+    }
+}
+
+impl<C: HttpService> Transport<C> {
+    fn send(
+        &self,
+        mut req: Request<Body>,
+    ) -> Box<dyn Future<Item = hyper::Response<Body>, Error = Error> + Send> {
+        // Headers already on the request (eg: its own Authorization)
+        // take precedence over the configured ones
+        for name in self.headers.keys() {
+            if req.headers().contains_key(name) {
+                continue;
+            }
+            for value in self.headers.get_all(name) {
+                req.headers_mut().append(name, value.clone());
+            }
+        }
+        let exec = match self.exec {
+            Some(ref exec) => Arc::clone(exec),
+            None => return self.send_authorized(req),
+        };
+        // The plugin runs (when it needs to) once this is polled, off
+        // the executor
+        let transport = self.clone();
+        Box::new(
+            ExecAuth::credentials_async(&exec)
+                .and_then(move |c| {
+                    if c.token != "" {
+                        let mut value = HeaderValue::from_str(&format!("Bearer {}", c.token))?;
+                        value.set_sensitive(true);
+                        req.headers_mut().insert(AUTHORIZATION, value);
+                    }
+                    Ok(req)
+                })
+                .and_then(move |req| transport.send_authorized(req)),
+        )
+    }
+
+    fn send_authorized(
+        &self,
+        mut req: Request<Body>,
+    ) -> Box<dyn Future<Item = hyper::Response<Body>, Error = Error> + Send> {
+        for i in &self.interceptors {
+            (i.0)(&mut req);
+        }
+        #[cfg(feature = "gzip")]
+        gzip::accept(&mut req);
+        let res = match self.servers {
+            Some(ref servers) => {
+                let failover = Failover {
+                    service: Arc::clone(&self.service),
+                    servers: Arc::clone(servers),
+                };
+                self.dispatch(Arc::new(failover), req)
+            }
+            None => self.dispatch(Arc::clone(&self.service), req),
+        };
+        #[cfg(feature = "gzip")]
+        let res: Box<dyn Future<Item = hyper::Response<Body>, Error = Error> + Send> =
+            Box::new(res.map(gzip::decode));
+        let skew = Arc::clone(&self.skew);
+        let res: Box<dyn Future<Item = hyper::Response<Body>, Error = Error> + Send> =
+            Box::new(res.inspect(move |res| skew.record(res.headers())));
+        match self.warnings {
+            Some(ref w) => {
+                let w = Arc::clone(w);
+                Box::new(res.inspect(move |res| w.handle(res.headers())))
+            }
+            None => res,
+        }
+    }
+
+    fn dispatch<S: HttpService>(
+        &self,
+        service: Arc<S>,
+        req: Request<Body>,
+    ) -> Box<dyn Future<Item = hyper::Response<Body>, Error = Error> + Send> {
+        match self.retry {
+            Some(ref policy) => retry::send_with_retry(service, policy.clone(), req),
+            None => service.request(req),
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct Client<C> {
-    client: Arc<hyper::Client<C>>,
+    transport: Transport<C>,
     config: ConfigContext,
 }
 
+impl<C> Clone for Client<C> {
+    fn clone(&self) -> Self {
+        Client {
+            transport: self.transport.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NamespacedClient<'a, C> {
     namespace: &'a str,
@@ -65,77 +305,421 @@ impl<'a, C> Client<C> {
             client: self,
         }
     }
+
+    /// The namespace from the kubeconfig context (or the pod's own
+    /// namespace, in-cluster), else `default`.
+    pub fn default_namespace(&self) -> &str {
+        self.config
+            .default_namespace
+            .as_ref()
+            .map(String::as_str)
+            .unwrap_or("default")
+    }
+
+    /// Like `namespace`, using `default_namespace()`.
+    pub fn in_default_namespace(&'a self) -> NamespacedClient<'a, C> {
+        self.namespace(self.default_namespace())
+    }
 }
 
-impl Client<HttpsConnector<hyper::client::HttpConnector>> {
+impl Client<hyper::Client<HttpsConnector<hyper::client::HttpConnector>>> {
     pub fn new() -> Result<Self, Error> {
         let dns_threads = 1; // Only need a single DNS lookup
         let http = hyper::client::HttpConnector::new(dns_threads);
         Client::new_from_http(http)
     }
 
+    /// Builds a client using the pod's service account, for use when
+    /// running inside the cluster.
+    pub fn new_in_cluster() -> Result<Self, Error> {
+        let dns_threads = 1;
+        let http = hyper::client::HttpConnector::new(dns_threads);
+        Client::new_from_context(http, config::in_cluster()?)
+    }
+
+    /// Reads the files listed in `$KUBECONFIG` (merged as kubectl
+    /// does), or else `~/.kube/config`.  If none exist but we appear to
+    /// be running in a pod, uses the in-cluster config instead.
     pub fn new_from_http(http: hyper::client::HttpConnector) -> Result<Self, Error> {
-        let config_path = env::var_os(config::CONFIG_ENV)
-            .map(PathBuf::from)
-            .or_else(config::default_path)
-            .ok_or(format_err!("Unable to find config"))?;
-        debug!("Reading config from {}", config_path.display());
-        let config = config::load_from_file(&config_path)
-            .with_context(|e| format!("Unable to read {}: {}", config_path.display(), e))?;
+        let config_paths = config::config_paths();
+        if config_paths.is_empty() && config::is_in_cluster() {
+            debug!("No kubeconfig found, using in-cluster config");
+            return Client::new_from_context(http, config::in_cluster()?);
+        }
+        let config = config::load_from_paths(&config_paths)?;
         let context = config.config_context(&config.current_context)?;
         Client::new_from_context(http, context)
     }
 
+    /// Builds a client from kubeconfig YAML in memory, using the named
+    /// `context` or else the config's `current-context`.
+    pub fn new_from_yaml(data: &[u8], context: Option<&str>) -> Result<Self, Error> {
+        let config = config::load_from_slice(data)
+            .with_context(|e| format!("Unable to parse config: {}", e))?;
+        let context = config.config_context(context.unwrap_or(&config.current_context))?;
+        let http = hyper::client::HttpConnector::new(1);
+        Client::new_from_context(http, context)
+    }
+
     pub fn new_from_context(
         mut http: hyper::client::HttpConnector,
         config: ConfigContext,
     ) -> Result<Self, Error> {
         http.enforce_http(false);
-        let mut tls = TlsConnector::builder();
-        if let (Some(certdata), Some(keydata)) = (
-            config.user.client_certificate_read(),
-            config.user.client_key_read(),
-        ) {
-            debug!("Setting user client cert");
-            let cert = openssl::x509::X509::from_pem(&certdata?)?;
-            let pkey = openssl::pkey::PKey::private_key_from_pem(&keydata?)?;
-            // openssl pkcs12 -export -clcerts -inkey kubecfg.key -in kubecfg.crt -out kubecfg.p12 -name "kubecfg"
-            let password = "";
-            let p12 =
-                openssl::pkcs12::Pkcs12::builder().build(password, "kubeconfig", &pkey, &cert)?;
-            tls.identity(Identity::from_pkcs12(&p12.to_der()?, password)?);
-        }
+        let exec = exec_auth(&config);
+        let tls = tls_builder(&config, exec.as_ref())?.build()?;
+        let client = Client::new_with_connector(HttpsConnector::from((http, tls)), config)?;
+        Ok(client.with_exec_auth(exec))
+    }
+}
+
+impl Client<hyper::Client<AlpnConnector<hyper::client::HttpConnector>>> {
+    /// Like `new_from_context`, but offers HTTP/2 to the server during
+    /// the TLS handshake, falling back to HTTP/1.1 if it declines.
+    ///
+    /// HTTP/2 multiplexes requests over a single connection, so a
+    /// controller holding many concurrent watches needs only one
+    /// connection to the API server rather than one per watch.
+    pub fn new_from_context_h2(
+        mut http: hyper::client::HttpConnector,
+        config: ConfigContext,
+    ) -> Result<Self, Error> {
+        http.enforce_http(false);
+        let exec = exec_auth(&config);
+        let mut tls = tls_builder(&config, exec.as_ref())?;
+        tls.request_alpns(&["h2", "http/1.1"]);
+        let https = HttpsConnector::from((http, tls.build()?));
+        let client = Client::new_with_connector(AlpnConnector(https), config)?;
+        Ok(client.with_exec_auth(exec))
+    }
+}
+
+impl<C> Client<hyper::Client<C>>
+where
+    C: hyper::client::connect::Connect + 'static,
+{
+    /// Builds a client that opens connections using `connector`.
+    ///
+    /// Use this when the default connector won't do (SOCKS proxy,
+    /// custom resolver, keys on a hardware token, ...).  The connector
+    /// is responsible for TLS; `tls_connector` will build a
+    /// `TlsConnector` from the client certificate and CA in `config`
+    /// if you want to wrap it.  Authentication headers from `config`
+    /// are added by the returned `Client` as usual.
+    pub fn new_with_connector(connector: C, config: ConfigContext) -> Result<Self, Error> {
+        Client::new_with_client(hyper::Client::builder().build(connector), config)
+    }
+}
 
-        if let Some(data) = config.cluster.certificate_authority_read() {
-            debug!("Setting cluster CA cert");
-            let cert = Certificate::from_pem(&data?)?;
-            // FIXME: want to validate against _only_ this cert ..
-            tls.add_root_certificate(cert);
+/// Builds a `TlsConnector` using the client certificate, CA
+/// certificate and verification settings in `config`.
+pub fn tls_connector(config: &ConfigContext) -> Result<TlsConnector, Error> {
+    Ok(tls_builder(config, exec_auth(config).as_ref())?.build()?)
+}
+
+/// The exec plugin of `config`'s user, if it has one.
+fn exec_auth(config: &ConfigContext) -> Option<Arc<ExecAuth>> {
+    config
+        .user
+        .exec
+        .as_ref()
+        .map(|e| Arc::new(ExecAuth::new(e.clone(), &config.cluster)))
+}
+
+/// `exec` is the plugin of `config`'s user, so that the client using
+/// the connector can share its credentials rather than running it
+/// again.
+fn tls_builder(
+    config: &ConfigContext,
+    exec: Option<&Arc<ExecAuth>>,
+) -> Result<TlsConnectorBuilder, Error> {
+    let mut tls = TlsConnector::builder();
+    if let (Some(certdata), Some(keydata)) = (
+        config.user.client_certificate_read(),
+        config.user.client_key_read(),
+    ) {
+        debug!("Setting user client cert");
+        tls.identity(identity(&certdata?, &keydata?)?);
+    } else if let (Some(auth), Some(exec)) = (exec, config.user.exec.as_ref()) {
+        // Certificates are fixed for the life of the connector, so
+        // unlike tokens, aren't refreshed when they expire.
+        let creds = auth.credentials()?;
+        if creds.client_certificate_data != "" {
+            debug!("Setting user client cert from {}", exec.command);
+            tls.identity(identity(
+                creds.client_certificate_data.as_bytes(),
+                creds.client_key_data.as_bytes(),
+            )?);
         }
+    }
+
+    if let Some(data) = config.cluster.certificate_authority_read() {
+        debug!("Setting cluster CA cert");
+        let cert = Certificate::from_pem(&data?)?;
+        // FIXME: want to validate against _only_ this cert ..
+        tls.add_root_certificate(cert);
+    }
+
+    if config.cluster.insecure_skip_tls_verify {
+        debug!("Disabling CA verification");
+        // TODO: do this only for the endpoint in question, not globally.
+        tls.danger_accept_invalid_certs(true);
+    }
+
+    Ok(tls)
+}
+
+fn identity(certdata: &[u8], keydata: &[u8]) -> Result<Identity, Error> {
+    let cert = openssl::x509::X509::from_pem(certdata)?;
+    let pkey = openssl::pkey::PKey::private_key_from_pem(keydata)?;
+    // openssl pkcs12 -export -clcerts -inkey kubecfg.key -in kubecfg.crt -out kubecfg.p12 -name "kubecfg"
+    let password = "";
+    let p12 = openssl::pkcs12::Pkcs12::builder().build(password, "kubeconfig", &pkey, &cert)?;
+    Ok(Identity::from_pkcs12(&p12.to_der()?, password)?)
+}
+
+const IMPERSONATE_USER: &str = "impersonate-user";
+const IMPERSONATE_GROUP: &str = "impersonate-group";
+const IMPERSONATE_EXTRA: &str = "impersonate-extra-";
+const IMPERSONATE_UID: &str = "impersonate-uid";
 
-        if config.cluster.insecure_skip_tls_verify {
-            debug!("Disabling CA verification");
-            // TODO: do this only for the endpoint in question, not globally.
-            tls.danger_accept_invalid_certs(true);
+/// Escapes `key` for use in an `Impersonate-Extra-` header name, the
+/// way the apiserver unescapes it: bytes that aren't header name
+/// characters (and `%` itself) are percent-encoded.
+fn impersonate_extra_key(key: &str) -> String {
+    let mut escaped = String::with_capacity(key.len());
+    for b in key.bytes() {
+        match b {
+            b'a'..=b'z'
+            | b'A'..=b'Z'
+            | b'0'..=b'9'
+            | b'!'
+            | b'#'
+            | b'$'
+            | b'&'
+            | b'\''
+            | b'*'
+            | b'+'
+            | b'-'
+            | b'.'
+            | b'^'
+            | b'_'
+            | b'`'
+            | b'|'
+            | b'~' => escaped.push(b as char),
+            _ => escaped.push_str(&format!("%{:02X}", b)),
         }
+    }
+    escaped
+}
 
-        let hyper_client =
-            hyper::Client::builder().build(HttpsConnector::from((http, tls.build()?)));
+/// How many times `iter` starts a listing over after its continue
+/// token expires, before giving up.
+const MAX_LIST_RESTARTS: usize = 3;
 
-        Self::new_with_client(hyper_client, config)
+/// Headers derived from `config` that are sent with every request.
+/// Kept separate from connection setup, so they apply whatever
+/// `HttpService` is used.
+fn config_headers(config: &ConfigContext) -> Result<HeaderMap, Error> {
+    let mut headers = HeaderMap::new();
+    let user = &config.user;
+    let token = match user.token_read() {
+        Some(token) => token.with_context(|e| {
+            format!(
+                "Unable to read token file {}: {}",
+                user.token_file.display(),
+                e
+            )
+        })?,
+        None => String::new(),
+    };
+    if token != "" {
+        debug!("Using bearer token auth");
+        let mut value = HeaderValue::from_str(&format!("Bearer {}", token))?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    } else if user.username != "" {
+        debug!("Using basic auth as user {}", user.username);
+        let creds = base64::encode(&format!("{}:{}", user.username, user.password));
+        let mut value = HeaderValue::from_str(&format!("Basic {}", creds))?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
     }
+    if user.act_as != "" {
+        headers.insert(IMPERSONATE_USER, HeaderValue::from_str(&user.act_as)?);
+    }
+    for group in &user.act_as_groups {
+        headers.append(IMPERSONATE_GROUP, HeaderValue::from_str(group)?);
+    }
+    for (key, values) in &user.act_as_user_extra {
+        let name = HeaderName::from_bytes(
+            format!("{}{}", IMPERSONATE_EXTRA, impersonate_extra_key(key)).as_bytes(),
+        )?;
+        for value in values {
+            headers.append(name.clone(), HeaderValue::from_str(value)?);
+        }
+    }
+    if user.act_as_uid != "" {
+        headers.insert(IMPERSONATE_UID, HeaderValue::from_str(&user.act_as_uid)?);
+    }
+    Ok(headers)
 }
 
 impl<C> Client<C> {
-    pub fn new_with_client(client: hyper::Client<C>, config: ConfigContext) -> Result<Self, Error> {
+    /// Builds a client that sends requests using `client`.
+    ///
+    /// Authentication headers from `config` are added to every request
+    /// before it is passed to `client`; connection-level settings
+    /// (TLS certificates, proxies) are up to `client` itself.
+    pub fn new_with_client(client: C, config: ConfigContext) -> Result<Self, Error> {
         Ok(Client {
-            client: Arc::new(client),
+            transport: Transport {
+                service: Arc::new(client),
+                headers: config_headers(&config)?,
+                interceptors: vec![],
+                shutdown: Default::default(),
+                buffers: None,
+                warnings: None,
+                retry: None,
+                servers: None,
+                skew: Default::default(),
+                exec: exec_auth(&config),
+                eviction_version: Default::default(),
+            },
             config: config,
         })
     }
 
-    pub fn client(&self) -> &hyper::Client<C> {
-        &self.client
+    pub fn client(&self) -> &C {
+        &self.transport.service
+    }
+
+    /// Uses `exec` for tokens, eg: the plugin already run for the
+    /// client certificate, rather than a fresh one.
+    fn with_exec_auth(mut self, exec: Option<Arc<ExecAuth>>) -> Self {
+        self.transport.exec = exec;
+        self
+    }
+
+    /// Adds a function that is called on every outgoing request
+    /// (including watches) just before it is sent, eg: to add tracing
+    /// or tenant headers.  Interceptors run in the order added.
+    pub fn with_interceptor<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut Request<Body>) + Send + Sync + 'static,
+    {
+        self.transport.interceptors.push(Interceptor(Arc::new(f)));
+        self
+    }
+
+    /// Retries requests that fail with `503 Service Unavailable` (or
+    /// `429` with `Retry-After`), eg: while the API server restarts.
+    /// The backoff between retries needs a tokio timer.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.transport.retry = Some(policy);
+        self
+    }
+
+    /// Sends requests to any of `servers`, eg: each API server of an
+    /// HA control plane without a load balancer, rather than just the
+    /// configured `cluster.server`.  Requests that can't connect or get
+    /// `503 Service Unavailable` are resent to the next server, and the
+    /// last server to respond is preferred for later requests.
+    ///
+    /// Only the scheme, host and port of each URL are used.  All
+    /// servers must share the configured TLS settings.
+    pub fn with_servers<I, S>(mut self, servers: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let servers = Servers::new(servers)?;
+        self.config.cluster.server = servers.first().to_string();
+        self.transport.servers = Some(Arc::new(servers));
+        Ok(self)
+    }
+
+    /// Calls `f` with the text of each `Warning` header sent by the
+    /// server, eg: for use of a deprecated API.  Each distinct warning
+    /// is only reported once, so a warning repeated on every request
+    /// isn't reported over and over.
+    pub fn with_warning_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.transport.warnings = Some(Arc::new(WarningHandler::new(f)));
+        self
+    }
+
+    /// Impersonates the user with UID `uid`, overriding any `as-uid`
+    /// in the kubeconfig.  The API server only accepts this alongside
+    /// a user to impersonate.
+    pub fn with_impersonate_uid(mut self, uid: &str) -> Result<Self, Error> {
+        self.transport
+            .headers
+            .insert(IMPERSONATE_UID, HeaderValue::from_str(uid)?);
+        self.config.user.act_as_uid = uid.to_string();
+        Ok(self)
+    }
+
+    /// Reads response bodies into buffers taken from `pool`, rather
+    /// than allocating a new buffer for each response.  The pool may
+    /// be shared between clients.
+    pub fn with_buffer_pool(mut self, pool: Arc<BufferPool>) -> Self {
+        self.transport.buffers = Some(pool);
+        self
+    }
+
+    /// How far the server's clock is ahead of ours (negative if
+    /// behind), from the `Date` header of the latest response, or
+    /// `None` before any response with one.  `Date` has a resolution
+    /// of one second, so smaller skews can't be detected.
+    ///
+    /// Add this to local times before comparing them with server
+    /// times, eg: token or lease expiry.
+    pub fn server_time_skew(&self) -> Option<chrono::Duration> {
+        self.transport.skew.get()
+    }
+
+    /// Begins a graceful shutdown: new requests and watches fail with
+    /// `ShutdownError`, open watch streams end, and the returned future
+    /// resolves once requests already in flight have completed.
+    ///
+    /// Applies to all clones of this client.
+    pub fn shutdown(&self) -> Drained {
+        self.transport.shutdown.close();
+        Drained(Arc::clone(&self.transport.shutdown))
+    }
+
+    /// Like `shutdown`, but gives up waiting for in-flight requests
+    /// after `timeout`.  Requires a tokio runtime.
+    pub fn shutdown_timeout(&self, timeout: Duration) -> impl Future<Item = (), Error = Error> {
+        Timeout::new(self.shutdown(), timeout).map_err(|e| {
+            if e.is_elapsed() {
+                format_err!("Timed out waiting for in-flight requests")
+            } else {
+                format_err!("Shutdown timer failed: {:?}", e)
+            }
+        })
+    }
+}
+
+/// Removes `null` object members, recursively.
+fn strip_nulls(v: &mut Value) {
+    match *v {
+        Value::Object(ref mut map) => {
+            let nulls: Vec<_> = map
+                .iter()
+                .filter(|&(_, v)| v.is_null())
+                .map(|(k, _)| k.clone())
+                .collect();
+            for k in nulls {
+                map.remove(&k);
+            }
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(ref mut items) => items.iter_mut().for_each(strip_nulls),
+        _ => (),
     }
 }
 
@@ -146,34 +730,163 @@ fn hyper_uri(u: Url) -> hyper::Uri {
 }
 
 fn do_request<C, T>(
-    client: Arc<hyper::Client<C>>,
+    transport: Transport<C>,
     req: Result<Request<hyper::Body>, Error>,
 ) -> impl Future<Item = T, Error = Error> + Send
 where
-    C: hyper::client::connect::Connect + 'static,
+    C: HttpService,
     T: DeserializeOwned + Send + 'static,
 {
-    future::result(req)
+    do_request_with_status(transport, req).map(|(_, o)| o)
+}
+
+/// Like `do_request`, but also returns the (successful) HTTP status.
+fn do_request_with_status<C, T>(
+    transport: Transport<C>,
+    req: Result<Request<hyper::Body>, Error>,
+) -> impl Future<Item = (hyper::StatusCode, T), Error = Error> + Send
+where
+    C: HttpService,
+    T: DeserializeOwned + Send + 'static,
+{
+    // Held until the response has been read and parsed.
+    let in_flight = match Shutdown::begin(&transport.shutdown) {
+        Ok(guard) => guard,
+        Err(e) => return Either::A(future::err(e.into())),
+    };
+    let buffers = transport.buffers.clone();
+    let fut = future::result(req)
         .inspect(|req|
                  // Avoid body, since it may not be Debug
                  debug!("Request: {} {}", req.method(), req.uri()))
         .and_then(move |req|
                   // TODO: add method/uri context to error
-                  client.request(req).from_err::<Error>())
+                  transport.send(req))
         .inspect(|res| debug!("Response: {} {:?}", res.status(), res.headers()))
         // Verbose!
         //.inspect(|res| debug!("Response: {:#?}", res))
-        .and_then(|res| {
+        .and_then(move |res| {
             let status = res.status();
-            res.into_body()
-                .concat2()
-                .map(move |body| (status, body))
-                .from_err()
+            let text = is_text(res.headers());
+            pool::read_body(res.into_body(), buffers).map(move |body| (status, text, body))
         })
         // Verbose!
-        //.inspect(|(_, body)| debug!("Response body: {:?}", ::std::str::from_utf8(body.as_ref())))
-        .and_then(move |(httpstatus, body)| -> Result<T, Error> {
-            if !httpstatus.is_success() {
+        //.inspect(|(_, _, body)| debug!("Response body: {:?}", ::std::str::from_utf8(body.as_ref())))
+        .and_then(
+            move |(httpstatus, text, body)| -> Result<(hyper::StatusCode, T), Error> {
+                if !httpstatus.is_success() {
+                    debug!("failure body: {:#?}", ::std::str::from_utf8(body.as_ref()));
+                    let status: Status = serde_json::from_slice(body.as_ref()).map_err(|e| {
+                        debug!(
+                            "Failed to parse error Status ({}), falling back to HTTP status",
+                            e
+                        );
+                        HttpStatusError { status: httpstatus }
+                    })?;
+                    Err(status.into())
+                } else if text {
+                    // eg: logs.  Decoded as a JSON string, so `T` can
+                    // be `String` (or `Value`).
+                    let text = String::from_utf8_lossy(body.as_ref()).into_owned();
+                    let o = serde_json::from_value(Value::String(text))
+                        .with_context(|e| format!("Unable to parse text response body: {}", e))?;
+                    Ok((httpstatus, o))
+                } else {
+                    let o = serde_json::from_slice(body.as_ref()).with_context(|e| {
+                        format!(
+                            "Unable to parse response body: {} (near {:?})",
+                            e,
+                            error_snippet(body.as_ref(), e.line(), e.column())
+                        )
+                    })?;
+                    Ok((httpstatus, o))
+                }
+            },
+        );
+    Either::B(fut.then(move |r| {
+        drop(in_flight);
+        r
+    }))
+}
+
+const SNIPPET_LEN: usize = 40;
+
+/// Up to `SNIPPET_LEN` bytes of `body` leading up to `line` and
+/// `column` (1-based, as in `serde_json::Error`), for error messages.
+/// Pretty-printed bodies span many lines and the column may be past
+/// the end of its line, so the snippet continues into earlier lines
+/// as needed.
+fn error_snippet(body: &[u8], line: usize, column: usize) -> String {
+    let text = String::from_utf8_lossy(body);
+    let mut offset = 0;
+    for (i, l) in text.split('\n').enumerate() {
+        if i + 1 >= line {
+            offset += cmp::min(column, l.len());
+            break;
+        }
+        offset += l.len() + 1;
+    }
+    let end = cmp::min(offset, text.len());
+    let start = end.saturating_sub(SNIPPET_LEN);
+    let boundary = |i: usize| (i..text.len()).find(|&i| text.is_char_boundary(i));
+    let start = boundary(start).unwrap_or(end);
+    let end = boundary(end).unwrap_or(text.len());
+    text[start..end].to_string()
+}
+
+/// True if `headers` say the body is `text/plain`, with any parameters
+/// (eg: `charset`).
+fn is_text(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().eq_ignore_ascii_case("text/plain"))
+        .unwrap_or(false)
+}
+
+fn do_watch<C, T>(
+    transport: &Transport<C>,
+    req: Result<hyper::Request<hyper::Body>, Error>,
+) -> impl Future<Item = (HeaderMap, impl Stream<Item = T, Error = Error> + Send), Error = Error> + Send
+where
+    C: HttpService,
+    T: DeserializeOwned + Send + 'static,
+{
+    let transport = transport.clone();
+    let shutdown = Arc::clone(&transport.shutdown);
+    let req = if shutdown.is_closed() {
+        Err(ShutdownError.into())
+    } else {
+        req
+    };
+    future::result(req)
+        .inspect(|req| debug!("Watch request: {} {}", req.method(), req.uri()))
+        .and_then(move |req|
+                  // TODO: add method/uri context to error
+                  transport.send(req))
+        .inspect(|res| debug!("Response: {:#?}", res))
+        .and_then(watch_response)
+        .map(move |(headers, inner)| (headers, UntilShutdown { inner, shutdown }))
+}
+
+/// Passes through a successful streaming response.  Non-2xx responses
+/// are read to completion (they are usually chunked, with no
+/// Content-Length) and returned as an error.
+fn stream_response(
+    res: hyper::Response<Body>,
+) -> impl Future<Item = hyper::Response<Body>, Error = Error> + Send {
+    let httpstatus = res.status();
+    let r = if httpstatus.is_success() {
+        Ok(res)
+    } else {
+        Err(res)
+    };
+    future::result(r).or_else(move |res| {
+        res.into_body()
+            .concat2()
+            .from_err::<Error>()
+            .and_then(move |body| {
                 debug!("failure body: {:#?}", ::std::str::from_utf8(body.as_ref()));
                 let status: Status = serde_json::from_slice(body.as_ref()).map_err(|e| {
                     debug!(
@@ -182,74 +895,40 @@ where
                     );
                     HttpStatusError { status: httpstatus }
                 })?;
+
                 Err(status.into())
-            } else {
-                let o = serde_json::from_slice(body.as_ref())
-                    .with_context(|e| format!("Unable to parse response body: {}", e))?;
-                Ok(o)
-            }
-        })
+            })
+    })
 }
 
-fn do_watch<C, T>(
-    client: &Arc<hyper::Client<C>>,
-    req: Result<hyper::Request<hyper::Body>, Error>,
-) -> impl Stream<Item = T, Error = Error> + Send
+/// Turns a watch response into its headers and a stream of decoded
+/// lines.
+fn watch_response<T>(
+    res: hyper::Response<Body>,
+) -> impl Future<Item = (HeaderMap, impl Stream<Item = T, Error = Error> + Send), Error = Error> + Send
 where
-    C: hyper::client::connect::Connect + 'static,
     T: DeserializeOwned + Send + 'static,
 {
-    let client = Arc::clone(client);
-    future::result(req)
-        .inspect(|req| debug!("Watch request: {} {}", req.method(), req.uri()))
-        .and_then(move |req|
-                  // TODO: add method/uri context to error
-                  client.request(req).from_err::<Error>())
-        .inspect(|res| debug!("Response: {:#?}", res))
-        .and_then(|res| {
-            let httpstatus = res.status();
-            let r = if httpstatus.is_success() {
-                Ok(res)
-            } else {
-                Err(res)
-            };
-            future::result(r)
-                .or_else(move |res| {
-                    res.into_body()
-                        .concat2()
-                        .from_err::<Error>()
-                        .and_then(move |body| {
-                            debug!("failure body: {:#?}", ::std::str::from_utf8(body.as_ref()));
-                            let status: Status = serde_json::from_slice(body.as_ref()).map_err(
-                                |e| {
-                                    debug!("Failed to parse error Status ({}), falling back to HTTP status", e);
-                                    HttpStatusError { status: httpstatus }
-                                },
-                            )?;
-
-                            Err(status.into())
-                        })
-                })
-                .map(|res| {
-                    resplit::new(res.into_body(), |&c| c == b'\n')
-                        .from_err()
-                        .inspect(|line| {
-                            debug!(
-                                "Got line: {:#?}",
-                                ::std::str::from_utf8(line).unwrap_or("<invalid utf8>")
-                            )
-                        })
-                        .and_then(move |line| {
-                            let o: T = serde_json::from_slice(line.as_ref())
-                                .with_context(|e| format!("Unable to parse watch line : {}", e))?;
-                            Ok(o)
-                        })
-                })
-        })
-        .flatten_stream()
+    let headers = res.headers().clone();
+    stream_response(res).map(move |res| {
+        let lines = resplit::new(res.into_body(), |&c| c == b'\n')
+            .from_err()
+            .inspect(|line| {
+                debug!(
+                    "Got line: {:#?}",
+                    ::std::str::from_utf8(line).unwrap_or("<invalid utf8>")
+                )
+            })
+            .and_then(move |line| {
+                let o: T = serde_json::from_slice(line.as_ref())
+                    .with_context(|e| format!("Unable to parse watch line : {}", e))?;
+                Ok(o)
+            });
+        (headers, lines)
+    })
 }
 
-impl<'a, C: hyper::client::connect::Connect + 'static> NamespacedClient<'a, C> {
+impl<'a, C: HttpService> NamespacedClient<'a, C> {
     pub fn iter<T>(
         &self,
         rsrc: T,
@@ -279,9 +958,52 @@ impl<'a, C: hyper::client::connect::Connect + 'static> NamespacedClient<'a, C> {
         };
         self.client._do_iter::<T::List>(rsrc.gvr(), ns, opts)
     }
+
+    /// Lists the pods currently selected by the named service's
+    /// `spec.selector`.  A service without a selector (whose endpoints
+    /// are managed by hand) selects no pods.
+    pub fn service_pods(&self, name: &str) -> impl Future<Item = Vec<Pod>, Error = Error> + Send {
+        let transport = self.client.transport.clone();
+        let pods_url = self.client.url(
+            &core_v1::GROUP_VERSION.with_resource("pods"),
+            Some(self.namespace),
+            None,
+            (),
+        );
+        self.client
+            .get::<Service>(
+                &Services.gvr(),
+                Some(self.namespace),
+                name,
+                Default::default(),
+            )
+            .and_then(move |svc| {
+                let selector = svc.spec.selector;
+                if selector.is_empty() {
+                    return Either::A(future::ok(vec![]));
+                }
+                let opts = ListOptions {
+                    label_selector: selector
+                        .iter()
+                        .map(|(k, v)| format!("{}={}", k, v))
+                        .collect::<Vec<_>>()
+                        .join(","),
+                    ..Default::default()
+                };
+                let req = pods_url.and_then(|mut url| {
+                    url.set_query(Some(&serde_urlencoded::to_string(&opts)?));
+                    Request::builder()
+                        .method(Method::GET)
+                        .uri(hyper_uri(url))
+                        .body(Body::empty())
+                        .map_err(Error::from)
+                });
+                Either::B(do_request::<_, PodList>(transport, req).map(|l| l.items))
+            })
+    }
 }
 
-impl<C: hyper::client::connect::Connect + 'static> Client<C> {
+impl<C: HttpService> Client<C> {
     fn url<O>(
         &self,
         gvr: &GroupVersionResource,
@@ -347,59 +1069,159 @@ impl<C: hyper::client::connect::Connect + 'static> Client<C> {
                 .body(Body::empty())
                 .map_err(|e| e.into())
         });
-        do_request(Arc::clone(&self.client), req)
+        do_request(self.transport.clone(), req)
     }
 
-    pub fn create<T>(
+    /// Fetches an object, unless it is still at `last_rv`.
+    ///
+    /// The API server has no ETags, but a get with `resourceVersion`
+    /// set may be served from the watch cache instead of a quorum read
+    /// of etcd, which makes this cheap to call in a polling loop.  The
+    /// object returned is no older than `last_rv`, and if it is the
+    /// same version this returns `None`.
+    pub fn get_if_changed<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        name: &str,
+        last_rv: &ResourceVersion,
+    ) -> impl Future<Item = Option<T>, Error = Error> + Send
+    where
+        T: Metadata + DeserializeOwned + Send + 'static,
+    {
+        let opts = GetOptions {
+            resource_version: last_rv.clone(),
+            ..Default::default()
+        };
+        let last_rv = last_rv.clone();
+        self.get::<T>(gvr, namespace, name, opts).map(move |o| {
+            if o.metadata().resource_version.as_ref() == Some(&last_rv) {
+                None
+            } else {
+                Some(o)
+            }
+        })
+    }
+
+    fn create_request<T>(
         &self,
         gvr: &GroupVersionResource,
         value: &T,
-        opts: GetOptions,
-    ) -> impl Future<Item = T, Error = Error> + Send
+        opts: CreateOptions,
+    ) -> Result<Request<Body>, Error>
     where
-        T: Metadata + Serialize + DeserializeOwned + Send + 'static,
+        T: Metadata + Serialize,
     {
-        let req = || -> Result<_, Error> {
-            let metadata = value.metadata();
-            let namespace = &metadata.namespace; // NB: assumes input object is correctly qualified
-            let name = metadata.name.as_ref().ok_or(required_attr("name"))?;
+        let metadata = value.metadata();
+        let namespace = &metadata.namespace; // NB: assumes input object is correctly qualified
 
-            let json = serde_json::to_vec(value)?;
+        let json = serde_json::to_vec(value)?;
 
-            Request::builder()
-                .method(Method::POST)
-                .uri(hyper_uri(self.url(
-                    gvr,
-                    namespace.as_ref().map(|v| v.as_str()),
-                    Some(&name),
-                    opts,
-                )?))
-                .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
-                .body(Body::from(json))
-                .map_err(|e| e.into())
-        }();
-        do_request(Arc::clone(&self.client), req)
+        Request::builder()
+            .method(Method::POST)
+            .uri(hyper_uri(self.url(
+                gvr,
+                namespace.as_ref().map(|v| v.as_str()),
+                None,
+                opts,
+            )?))
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .body(Body::from(json))
+            .map_err(|e| e.into())
     }
 
-    pub fn update<T>(
+    pub fn create<T>(
         &self,
         gvr: &GroupVersionResource,
         value: &T,
+        opts: CreateOptions,
     ) -> impl Future<Item = T, Error = Error> + Send
     where
         T: Metadata + Serialize + DeserializeOwned + Send + 'static,
     {
-        let req = || -> Result<_, Error> {
-            let metadata = value.metadata();
-            let namespace = &metadata.namespace; // NB: assumes input object is correctly qualified
-            let name = metadata.name.as_ref().ok_or(required_attr("name"))?;
+        let req = self.create_request(gvr, value, opts);
+        do_request(self.transport.clone(), req)
+    }
 
-            let json = serde_json::to_vec(value)?;
+    /// Like `create`, but also returns the HTTP status of the
+    /// response, eg: to distinguish `201 Created` from `200 OK`.
+    pub fn create_with_status<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        value: &T,
+        opts: CreateOptions,
+    ) -> impl Future<Item = (T, hyper::StatusCode), Error = Error> + Send
+    where
+        T: Metadata + Serialize + DeserializeOwned + Send + 'static,
+    {
+        let req = self.create_request(gvr, value, opts);
+        do_request_with_status(self.transport.clone(), req).map(|(status, o)| (o, status))
+    }
 
-            Request::builder()
-                .method(Method::PUT)
-                .uri(hyper_uri(self.url(
-                    gvr,
+    /// Gets the object with `value`'s name, creating it from `value`
+    /// if it doesn't exist.  The returned bool is true if the object
+    /// was created.
+    pub fn get_or_create<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        value: &T,
+        opts: GetOptions,
+    ) -> impl Future<Item = (T, bool), Error = Error> + Send
+    where
+        T: Metadata + Serialize + DeserializeOwned + Send + 'static,
+    {
+        let metadata = value.metadata();
+        let namespace = metadata.namespace.as_ref().map(|v| v.as_str());
+        let gets = metadata
+            .name
+            .as_ref()
+            .ok_or(required_attr("name"))
+            .map(|name| {
+                (
+                    self.get(gvr, namespace, name, opts.clone()),
+                    // Retried if we lose a race with another creator
+                    self.get(gvr, namespace, name, opts),
+                )
+            });
+        let create = self.create(gvr, value, Default::default());
+
+        future::result(gets)
+            .from_err::<Error>()
+            .and_then(|(get, reget)| {
+                get.map(|o| (o, false)).or_else(move |err| {
+                    if !is_status_reason(&err, StatusReason::NotFound) {
+                        return Either::A(future::err(err));
+                    }
+                    Either::B(create.map(|o| (o, true)).or_else(move |err| {
+                        if is_status_reason(&err, StatusReason::AlreadyExists) {
+                            Either::A(reget.map(|o| (o, false)))
+                        } else {
+                            Either::B(future::err(err))
+                        }
+                    }))
+                })
+            })
+    }
+
+    pub fn update<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        value: &T,
+    ) -> impl Future<Item = T, Error = Error> + Send
+    where
+        T: Metadata + Serialize + DeserializeOwned + Send + 'static,
+    {
+        let req = || -> Result<_, Error> {
+            let metadata = value.metadata();
+            let namespace = &metadata.namespace; // NB: assumes input object is correctly qualified
+            let name = metadata.name.as_ref().ok_or(required_attr("name"))?;
+
+            let json = serde_json::to_vec(value)?;
+
+            Request::builder()
+                .method(Method::PUT)
+                .uri(hyper_uri(self.url(
+                    gvr,
                     namespace.as_ref().map(|v| v.as_str()),
                     Some(&name),
                     (),
@@ -408,7 +1230,7 @@ impl<C: hyper::client::connect::Connect + 'static> Client<C> {
                 .body(Body::from(json))
                 .map_err(|e| e.into())
         }();
-        do_request(Arc::clone(&self.client), req)
+        do_request(self.transport.clone(), req)
     }
 
     pub fn patch<T, U>(
@@ -423,26 +1245,247 @@ impl<C: hyper::client::connect::Connect + 'static> Client<C> {
         T: Serialize,
         U: DeserializeOwned + Send + 'static,
     {
-        let req = || -> Result<_, Error> {
-            let json = serde_json::to_vec(value)?;
+        let req = serde_json::to_vec(value)
+            .map_err(Error::from)
+            .and_then(|json| self.patch_request(gvr, namespace, name, patch_type, json, ()));
+        do_request(self.transport.clone(), req)
+    }
+
+    /// Like `patch`, but with a body that is already serialized, eg: a
+    /// YAML apply patch (`APPLY_PATCH`) as written by hand.  `body` is
+    /// sent unchanged, with `content_type`.
+    pub fn patch_raw<U>(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        name: &str,
+        content_type: &str,
+        body: Vec<u8>,
+        opts: PatchOptions,
+    ) -> impl Future<Item = U, Error = Error> + Send
+    where
+        U: DeserializeOwned + Send + 'static,
+    {
+        let req = self.patch_request(gvr, namespace, name, content_type, body, opts);
+        do_request(self.transport.clone(), req)
+    }
+
+    fn patch_request<O: Serialize + fmt::Debug>(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        name: &str,
+        content_type: &str,
+        body: Vec<u8>,
+        opts: O,
+    ) -> Result<Request<Body>, Error> {
+        Request::builder()
+            .method(Method::PATCH)
+            .uri(hyper_uri(self.url(gvr, namespace, Some(name), opts)?))
+            .header(CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .map_err(|e| e.into())
+    }
 
+    /// Merge patch (RFC 7386) using a typed partial object, eg: a
+    /// struct with all `Option` fields.
+    ///
+    /// Fields that serialize to `null` (ie: `None`) are left out of
+    /// the patch, meaning "unchanged", rather than sent as `null`,
+    /// which would delete them.  To delete a field, use `patch` with
+    /// an explicit `null`.
+    pub fn patch_merge_typed<P, U>(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        name: &str,
+        partial: &P,
+    ) -> impl Future<Item = U, Error = Error> + Send
+    where
+        P: Serialize,
+        U: DeserializeOwned + Send + 'static,
+    {
+        let body = serde_json::to_value(partial).map(|mut v| {
+            strip_nulls(&mut v);
+            v
+        });
+        let req = || -> Result<_, Error> {
             Request::builder()
                 .method(Method::PATCH)
                 .uri(hyper_uri(self.url(gvr, namespace, Some(name), ())?))
-                .header(CONTENT_TYPE, patch_type)
-                .body(Body::from(json))
+                .header(CONTENT_TYPE, MERGE_PATCH)
+                .body(Body::from(serde_json::to_vec(&body?)?))
                 .map_err(|e| e.into())
         }();
-        do_request(Arc::clone(&self.client), req)
+        do_request(self.transport.clone(), req)
+    }
+
+    /// Server-side apply: sends `value` as the complete intent of
+    /// `opts.field_manager`, which must be set.
+    pub fn apply<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        value: &T,
+        opts: PatchOptions,
+    ) -> impl Future<Item = T, Error = Error> + Send
+    where
+        T: Metadata + Serialize + DeserializeOwned + Send + 'static,
+    {
+        do_request(
+            self.transport.clone(),
+            self.apply_request(gvr, value, &opts),
+        )
+    }
+
+    /// Like `apply`, but if another field manager owns any of the
+    /// fields (eg: `kubectl`'s client-side apply, or
+    /// `before-first-apply`), retries with `force` to take ownership of
+    /// them.  This is the documented way to migrate objects to
+    /// server-side apply.
+    pub fn apply_with_takeover<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        value: &T,
+        field_manager: &str,
+    ) -> impl Future<Item = T, Error = Error> + Send
+    where
+        T: Metadata + Serialize + DeserializeOwned + Send + 'static,
+    {
+        let mut opts = PatchOptions {
+            field_manager: field_manager.to_string(),
+            force: false,
+        };
+        let first = self.apply_request(gvr, value, &opts);
+        opts.force = true;
+        let forced = self.apply_request(gvr, value, &opts);
+        let transport = self.transport.clone();
+        do_request(self.transport.clone(), first).or_else(move |err| {
+            if is_status_reason(&err, StatusReason::Conflict) {
+                debug!("Apply conflict, retrying with force: {}", err);
+                Either::A(do_request(transport, forced))
+            } else {
+                Either::B(future::err(err))
+            }
+        })
+    }
+
+    fn apply_request<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        value: &T,
+        opts: &PatchOptions,
+    ) -> Result<Request<Body>, Error>
+    where
+        T: Metadata + Serialize,
+    {
+        if opts.field_manager.is_empty() {
+            return Err(required_attr("field_manager").into());
+        }
+        let metadata = value.metadata();
+        let namespace = &metadata.namespace; // NB: assumes input object is correctly qualified
+        let name = metadata.name.as_ref().ok_or(required_attr("name"))?;
+
+        // JSON is valid YAML
+        let json = serde_json::to_vec(value)?;
+
+        Request::builder()
+            .method(Method::PATCH)
+            .uri(hyper_uri(self.url(
+                gvr,
+                namespace.as_ref().map(|v| v.as_str()),
+                Some(name),
+                opts,
+            )?))
+            .header(CONTENT_TYPE, APPLY_PATCH)
+            .body(Body::from(json))
+            .map_err(|e| e.into())
     }
 
-    pub fn delete(
+    /// Removes `finalizer` from the named object, using a JSON patch
+    /// that first tests the finalizer is still at the expected index.
+    /// If a concurrent writer gets in first, the object is re-read and
+    /// the patch retried.  Returns the object unchanged if the
+    /// finalizer is not present.
+    pub fn remove_finalizer_safe<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        name: &str,
+        finalizer: &str,
+    ) -> impl Future<Item = T, Error = Error> + Send
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        const MAX_ATTEMPTS: u32 = 5;
+        let transport = self.transport.clone();
+        let finalizer = finalizer.to_string();
+        future::result(self.url(gvr, namespace, Some(name), ()))
+            .and_then(move |url| {
+                future::loop_fn(1, move |attempt| {
+                    let get = Request::builder()
+                        .method(Method::GET)
+                        .uri(hyper_uri(url.clone()))
+                        .body(Body::empty())
+                        .map_err(Error::from);
+                    let transport2 = transport.clone();
+                    let url = url.clone();
+                    let finalizer = finalizer.clone();
+                    do_request::<_, Value>(transport.clone(), get).and_then(move |obj| {
+                        let index = obj["metadata"]["finalizers"].as_array().and_then(|fs| {
+                            fs.iter()
+                                .position(|f| f.as_str() == Some(finalizer.as_str()))
+                        });
+                        let index = match index {
+                            Some(i) => i,
+                            None => return Either::A(future::ok(Loop::Break(obj))),
+                        };
+                        let path = format!("/metadata/finalizers/{}", index);
+                        let patch = json!([
+                            {"op": "test", "path": path, "value": finalizer},
+                            {"op": "remove", "path": path},
+                        ]);
+                        let req =
+                            serde_json::to_vec(&patch)
+                                .map_err(Error::from)
+                                .and_then(|body| {
+                                    Request::builder()
+                                        .method(Method::PATCH)
+                                        .uri(hyper_uri(url))
+                                        .header(CONTENT_TYPE, api::JSON_PATCH)
+                                        .body(Body::from(body))
+                                        .map_err(Error::from)
+                                });
+                        Either::B(do_request(transport2, req).then(move |r| match r {
+                            Ok(obj) => Ok(Loop::Break(obj)),
+                            // A failed "test" op is reported as Invalid
+                            Err(ref e)
+                                if attempt < MAX_ATTEMPTS
+                                    && (is_status_reason(e, StatusReason::Invalid)
+                                        || is_status_reason(e, StatusReason::Conflict)) =>
+                            {
+                                debug!("Finalizer removal raced another writer ({}), retrying", e);
+                                Ok(Loop::Continue(attempt + 1))
+                            }
+                            Err(e) => Err(e),
+                        }))
+                    })
+                })
+            })
+            .and_then(|obj| Ok(serde_json::from_value(obj)?))
+    }
+
+    /// Deletes `name`.  Returns the object if deletion is still in
+    /// progress, or the server's `Status` if it is already gone.
+    pub fn delete<T>(
         &self,
         gvr: &GroupVersionResource,
         namespace: Option<&str>,
         name: &str,
         opts: DeleteOptions,
-    ) -> impl Future<Item = (), Error = Error> + Send {
+    ) -> impl Future<Item = DeleteResponse<T>, Error = Error> + Send
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
         let req = self.url(gvr, namespace, Some(name), opts).and_then(|url| {
             Request::builder()
                 .method(Method::DELETE)
@@ -450,7 +1493,7 @@ impl<C: hyper::client::connect::Connect + 'static> Client<C> {
                 .body(Body::empty())
                 .map_err(|e| e.into())
         });
-        do_request(Arc::clone(&self.client), req)
+        do_request(self.transport.clone(), req)
     }
 
     pub fn delete_collection(
@@ -466,16 +1509,382 @@ impl<C: hyper::client::connect::Connect + 'static> Client<C> {
                 .body(Body::empty())
                 .map_err(|e| e.into())
         });
-        do_request(Arc::clone(&self.client), req)
+        do_request(self.transport.clone(), req)
+    }
+
+    /// Fetches discovery information for the API group `name`,
+    /// including the versions the server offers.
+    pub fn api_group(&self, name: &str) -> impl Future<Item = APIGroup, Error = Error> + Send {
+        let req = self.group_url(name).and_then(|url| {
+            Request::builder()
+                .method(Method::GET)
+                .uri(hyper_uri(url))
+                .body(Body::empty())
+                .map_err(|e| e.into())
+        });
+        do_request(self.transport.clone(), req)
+    }
+
+    /// Evicts a pod, subject to any PodDisruptionBudgets.
+    ///
+    /// The Eviction is posted as `policy/v1` if the server offers it,
+    /// otherwise as `policy/v1beta1` (Kubernetes < 1.22).  The version
+    /// is discovered on the first eviction, and remembered by the
+    /// client (and its clones).
+    pub fn create_eviction(
+        &self,
+        namespace: &str,
+        name: &str,
+        opts: DeleteOptions,
+    ) -> impl Future<Item = (), Error = Error> + Send {
+        let transport = self.transport.clone();
+        let pods = core_v1::GROUP_VERSION.with_resource("pods");
+        let url = self
+            .url(&pods, Some(namespace), Some(name), ())
+            .and_then(|mut url| {
+                url.path_segments_mut()
+                    .map_err(|_| format_err!("URL scheme does not support paths"))?
+                    .push("eviction");
+                Ok(url)
+            });
+        let metadata = json!({"namespace": namespace, "name": name});
+        let cached = Arc::clone(&self.transport.eviction_version);
+        let known = *cached.lock().unwrap();
+        let version = match known {
+            Some(version) => Either::A(future::ok(version)),
+            None => Either::B(self.api_group("policy").and_then(move |group| {
+                let version = *["v1", "v1beta1"]
+                    .iter()
+                    .find(|v| group.versions.iter().any(|gv| gv.version == **v))
+                    .ok_or_else(|| format_err!("Server does not support pod eviction"))?;
+                *cached.lock().unwrap() = Some(version);
+                Ok(version)
+            })),
+        };
+        version
+            .and_then(move |version| {
+                let eviction = json!({
+                    "apiVersion": format!("policy/{}", version),
+                    "kind": "Eviction",
+                    "metadata": metadata,
+                    "deleteOptions": opts,
+                });
+                Request::builder()
+                    .method(Method::POST)
+                    .uri(hyper_uri(url?))
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(serde_json::to_vec(&eviction)?))
+                    .map_err(Error::from)
+            })
+            .and_then(move |req| do_request::<_, Value>(transport, Ok(req)).map(|_| ()))
+    }
+
+    /// Streams a container's logs, as they are read from the server.
+    /// With `opts.follow`, the stream continues until the container
+    /// exits (or the client shuts down).
+    pub fn read_log(
+        &self,
+        namespace: &str,
+        name: &str,
+        opts: PodLogOptions,
+    ) -> impl Stream<Item = hyper::Chunk, Error = Error> + Send {
+        let transport = self.transport.clone();
+        let shutdown = Arc::clone(&transport.shutdown);
+        let req = self.log_request(namespace, name, opts).and_then(|req| {
+            if shutdown.is_closed() {
+                Err(ShutdownError.into())
+            } else {
+                Ok(req)
+            }
+        });
+        let shutdown = Arc::clone(&transport.shutdown);
+        future::result(req)
+            .and_then(move |req| transport.send(req))
+            .and_then(stream_response)
+            .map(move |res| UntilShutdown {
+                inner: res.into_body().from_err(),
+                shutdown,
+            })
+            .flatten_stream()
+    }
+
+    /// A container's logs, read in full.  Not useful with
+    /// `opts.follow`, since the result is only available once the
+    /// container exits.
+    pub fn read_log_string(
+        &self,
+        namespace: &str,
+        name: &str,
+        opts: PodLogOptions,
+    ) -> impl Future<Item = String, Error = Error> + Send {
+        do_request(
+            self.transport.clone(),
+            self.log_request(namespace, name, opts),
+        )
+    }
+
+    fn log_request(
+        &self,
+        namespace: &str,
+        name: &str,
+        opts: PodLogOptions,
+    ) -> Result<Request<Body>, Error> {
+        let pods = core_v1::GROUP_VERSION.with_resource("pods");
+        let mut url = self.url(&pods, Some(namespace), Some(name), opts)?;
+        url.path_segments_mut()
+            .map_err(|_| format_err!("URL scheme does not support paths"))?
+            .push("log");
+        Ok(Request::builder()
+            .method(Method::GET)
+            .uri(hyper_uri(url))
+            .body(Body::empty())?)
+    }
+
+    /// Like `read_log`, but split into lines (without the trailing
+    /// newline), eg: to print like `kubectl logs -f`.  Invalid UTF-8 is
+    /// replaced, rather than ending the stream.
+    pub fn read_log_lines(
+        &self,
+        namespace: &str,
+        name: &str,
+        opts: PodLogOptions,
+    ) -> impl Stream<Item = String, Error = Error> + Send {
+        resplit::new(self.read_log(namespace, name, opts), |&c| c == b'\n').map(|mut line| {
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            String::from_utf8_lossy(&line).into_owned()
+        })
+    }
+
+    /// Like `read_log`, but as an `AsyncRead` over the log body, eg:
+    /// to `tokio::io::copy` it to a file.
+    pub fn read_log_reader(&self, namespace: &str, name: &str, opts: PodLogOptions) -> LogReader {
+        LogReader::new(Box::new(self.read_log(namespace, name, opts)))
+    }
+
+    /// Runs a command in a pod, like `kubectl exec`.  Resolves once the
+    /// server has upgraded the connection, with the command's streams.
+    pub fn exec(
+        &self,
+        namespace: &str,
+        name: &str,
+        opts: &PodExecOptions,
+    ) -> impl Future<Item = exec::Exec, Error = Error> + Send {
+        let url = self.exec_url(namespace, name, opts);
+        websocket::connect(&self.transport, url, exec::PROTOCOL).map(exec::Exec::new)
+    }
+
+    /// The URL of a pod's `exec` subresource, which `exec` upgrades to
+    /// a websocket with the `exec::PROTOCOL` subprotocol.
+    pub fn exec_url(
+        &self,
+        namespace: &str,
+        name: &str,
+        opts: &PodExecOptions,
+    ) -> Result<Url, Error> {
+        if opts.command.is_empty() {
+            bail!("exec requires a command");
+        }
+        let pods = core_v1::GROUP_VERSION.with_resource("pods");
+        let mut url = self.url(&pods, Some(namespace), Some(name), ())?;
+        url.path_segments_mut()
+            .map_err(|_| format_err!("URL scheme does not support paths"))?
+            .push("exec");
+        url.query_pairs_mut().extend_pairs(opts.query_pairs());
+        Ok(url)
+    }
+
+    /// Forwards connections to a pod's ports, like `kubectl
+    /// port-forward`.  Resolves once the server has upgraded the
+    /// connection, with a stream for each port in `opts`, in order.
+    pub fn portforward(
+        &self,
+        namespace: &str,
+        name: &str,
+        opts: &PodPortForwardOptions,
+    ) -> impl Future<Item = Vec<(u16, portforward::PortStream)>, Error = Error> + Send {
+        let url = self.portforward_url(namespace, name, opts);
+        let ports = opts.ports.clone();
+        websocket::connect(&self.transport, url, portforward::PROTOCOL)
+            .map(move |ws| portforward::streams(ws, &ports))
+    }
+
+    /// The URL of a pod's `portforward` subresource, which
+    /// `portforward` upgrades to a websocket with the
+    /// `portforward::PROTOCOL` subprotocol.
+    pub fn portforward_url(
+        &self,
+        namespace: &str,
+        name: &str,
+        opts: &PodPortForwardOptions,
+    ) -> Result<Url, Error> {
+        if opts.ports.is_empty() {
+            bail!("portforward requires at least one port");
+        }
+        if opts.ports.len() > portforward::MAX_PORTS {
+            bail!(
+                "portforward supports at most {} ports",
+                portforward::MAX_PORTS
+            );
+        }
+        let pods = core_v1::GROUP_VERSION.with_resource("pods");
+        let mut url = self.url(&pods, Some(namespace), Some(name), ())?;
+        url.path_segments_mut()
+            .map_err(|_| format_err!("URL scheme does not support paths"))?
+            .push("portforward");
+        url.query_pairs_mut().extend_pairs(opts.query_pairs());
+        Ok(url)
+    }
+
+    /// A client for the server's discovery endpoints.
+    pub fn discovery(&self) -> Discovery<C> {
+        Discovery::new(self.clone())
+    }
+
+    /// The resources the server offers in `group_version`, eg: `"v1"`
+    /// or `"apps/v1"`.
+    pub fn api_resources(
+        &self,
+        group_version: &str,
+    ) -> impl Future<Item = APIResourceList, Error = Error> + Send {
+        let req = self.group_version_url(group_version).and_then(|url| {
+            Request::builder()
+                .method(Method::GET)
+                .uri(hyper_uri(url))
+                .body(Body::empty())
+                .map_err(Error::from)
+        });
+        do_request(self.transport.clone(), req)
+    }
+
+    /// Creates an object whose type is only known at runtime, eg: from
+    /// a YAML manifest.  The resource to create is found from the
+    /// object's `apiVersion` and `kind` via discovery.  Namespaced
+    /// objects without a namespace are created in the default
+    /// namespace.
+    pub fn create_dynamic(&self, value: Value) -> impl Future<Item = Value, Error = Error> + Send {
+        let client = self.clone();
+        let api_version = value["apiVersion"].as_str().unwrap_or_default().to_string();
+        let kind = value["kind"].as_str().unwrap_or_default().to_string();
+        let resources = if api_version.is_empty() || kind.is_empty() {
+            Either::A(future::err(required_attr("apiVersion and kind").into()))
+        } else {
+            Either::B(self.api_resources(&api_version))
+        };
+        resources
+            .and_then(move |list| {
+                let mapper = RESTMapper::new(vec![list]);
+                let gvk = GroupVersion::from_str(&api_version)?.with_kind(&kind);
+                let gvr = mapper.resource_for(&gvk)?;
+                let namespace = if mapper.namespaced(&gvr) {
+                    let ns = value["metadata"]["namespace"].as_str();
+                    Some(ns.unwrap_or(client.default_namespace()).to_string())
+                } else {
+                    None
+                };
+                let url = client.url(&gvr, namespace.as_ref().map(|ns| ns.as_str()), None, ())?;
+                Request::builder()
+                    .method(Method::POST)
+                    .uri(hyper_uri(url))
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(serde_json::to_vec(&value)?))
+                    .map_err(Error::from)
+                    .map(|req| (client, req))
+            })
+            .and_then(|(client, req)| do_request(client.transport, Ok(req)))
+    }
+
+    fn group_version_url(&self, group_version: &str) -> Result<Url, Error> {
+        let gv = GroupVersion::from_str(group_version)?;
+        let mut url: Url = self.config.cluster.server.parse()?;
+        {
+            let mut path = url
+                .path_segments_mut()
+                .map_err(|_| format_err!("URL scheme does not support paths"))?;
+            path.clear();
+            if gv.group == "" {
+                path.extend(&["api", gv.version]);
+            } else {
+                path.extend(&["apis", gv.group, gv.version]);
+            }
+        }
+        Ok(url)
+    }
+
+    fn group_url(&self, group: &str) -> Result<Url, Error> {
+        let mut url: Url = self.config.cluster.server.parse()?;
+        url.path_segments_mut()
+            .map_err(|_| format_err!("URL scheme does not support paths"))?
+            .clear()
+            .extend(&["apis", group]);
+        Ok(url)
     }
 
     pub fn watch(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        name: &str,
+        opts: ListOptions,
+    ) -> impl Stream<Item = WatchEvent, Error = Error> + Send {
+        self.watch_with_headers(gvr, namespace, name, opts)
+            .map(|(_, events)| events)
+            .flatten_stream()
+    }
+
+    /// Watches a single object, starting with its current state: the
+    /// first event is `Added` with the object as it is now, followed by
+    /// any changes.  `opts.resource_version` is ignored.
+    pub fn watch_object(
         &self,
         gvr: &GroupVersionResource,
         namespace: Option<&str>,
         name: &str,
         mut opts: ListOptions,
     ) -> impl Stream<Item = WatchEvent, Error = Error> + Send {
+        let transport = self.transport.clone();
+        let url = self.url(gvr, namespace, Some(name), ());
+        self.get::<Value>(gvr, namespace, name, Default::default())
+            .and_then(move |object| {
+                opts.watch = true;
+                opts.resource_version = object["metadata"]["resourceVersion"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .into();
+                let req = url.and_then(|mut url| {
+                    url.set_query(Some(&serde_urlencoded::to_string(&opts)?));
+                    Request::builder()
+                        .method(Method::GET)
+                        .uri(hyper_uri(url))
+                        .body(Body::empty())
+                        .map_err(Error::from)
+                });
+                let current = WatchEvent {
+                    typ: EventType::Added,
+                    object,
+                };
+                do_watch(&transport, req)
+                    .map(move |(_, events)| stream::once(Ok(current)).chain(events))
+            })
+            .flatten_stream()
+    }
+
+    /// Like `watch`, but also returns the HTTP response headers,
+    /// available before any events are consumed.
+    pub fn watch_with_headers(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        name: &str,
+        mut opts: ListOptions,
+    ) -> impl Future<
+        Item = (
+            HeaderMap,
+            impl Stream<Item = WatchEvent, Error = Error> + Send,
+        ),
+        Error = Error,
+    > + Send {
         opts.watch = true;
         let req = self.url(gvr, namespace, Some(name), opts).and_then(|url| {
             Request::builder()
@@ -484,15 +1893,34 @@ impl<C: hyper::client::connect::Connect + 'static> Client<C> {
                 .body(Body::empty())
                 .map_err(|e| e.into())
         });
-        do_watch(&self.client, req)
+        do_watch(&self.transport, req)
     }
 
     pub fn watch_list(
         &self,
         gvr: &GroupVersionResource,
         namespace: Option<&str>,
-        mut opts: ListOptions,
+        opts: ListOptions,
     ) -> impl Stream<Item = WatchEvent, Error = Error> + Send {
+        self.watch_list_with_headers(gvr, namespace, opts)
+            .map(|(_, events)| events)
+            .flatten_stream()
+    }
+
+    /// Like `watch_list`, but also returns the HTTP response headers,
+    /// available before any events are consumed.
+    pub fn watch_list_with_headers(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        mut opts: ListOptions,
+    ) -> impl Future<
+        Item = (
+            HeaderMap,
+            impl Stream<Item = WatchEvent, Error = Error> + Send,
+        ),
+        Error = Error,
+    > + Send {
         opts.watch = true;
         let req = self.url(gvr, namespace, None, opts).and_then(|url| {
             Request::builder()
@@ -501,9 +1929,13 @@ impl<C: hyper::client::connect::Connect + 'static> Client<C> {
                 .body(Body::empty())
                 .map_err(|e| e.into())
         });
-        do_watch(&self.client, req)
+        do_watch(&self.transport, req)
     }
 
+    /// Fetches a single page.  The server may return fewer objects than
+    /// exist (with a `continue` token), even without `opts.limit`; this
+    /// is logged as a warning.  Use `iter` to read every page, or
+    /// `list_complete` to fail instead.
     pub fn list<T>(
         &self,
         gvr: &GroupVersionResource,
@@ -511,19 +1943,69 @@ impl<C: hyper::client::connect::Connect + 'static> Client<C> {
         opts: ListOptions,
     ) -> impl Future<Item = T, Error = Error> + Send
     where
-        T: DeserializeOwned + Send + 'static,
+        T: List + DeserializeOwned + Send + 'static,
     {
-        let req = self.url(gvr, namespace, None, opts).and_then(|url| {
-            Request::builder()
-                .method(Method::GET)
-                .uri(hyper_uri(url))
-                .body(Body::empty())
-                .map_err(|e| e.into())
-        });
-        do_request(Arc::clone(&self.client), req)
+        let paging = opts.limit != 0;
+        let path = gvr.to_string();
+        self.list_page(gvr, namespace, opts).inspect(move |list: &T| {
+            if !paging {
+                if let Some(ref c) = list.listmeta().continu {
+                    if !c.is_empty() {
+                        warn!(
+                            "List of {} is incomplete (continue token present), use iter to read every page",
+                            path
+                        );
+                    }
+                }
+            }
+        })
     }
 
-    pub fn iter<T>(
+    /// Like `list`, but fails with `IncompleteListError` if the
+    /// response has a `continue` token.
+    pub fn list_complete<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        opts: ListOptions,
+    ) -> impl Future<Item = T, Error = Error> + Send
+    where
+        T: List + DeserializeOwned + Send + 'static,
+    {
+        self.list_page(gvr, namespace, opts).and_then(|list: T| {
+            let continu = list.listmeta().continu.clone().unwrap_or_default();
+            if continu.is_empty() {
+                Ok(list)
+            } else {
+                Err(IncompleteListError { continu }.into())
+            }
+        })
+    }
+
+    fn list_page<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        opts: ListOptions,
+    ) -> impl Future<Item = T, Error = Error> + Send
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let req = self.url(gvr, namespace, None, opts).and_then(|url| {
+            Request::builder()
+                .method(Method::GET)
+                .uri(hyper_uri(url))
+                .body(Body::empty())
+                .map_err(|e| e.into())
+        });
+        do_request(self.transport.clone(), req)
+    }
+
+    /// Streams every item, fetching one page at a time.  If the
+    /// continue token expires part way through (`410 Gone`), the
+    /// listing starts again from the first page, so items may be
+    /// repeated.
+    pub fn iter<T>(
         &self,
         rsrc: T,
     ) -> impl Stream<Item = <T::List as List>::Item, Error = Error> + Send
@@ -548,6 +2030,13 @@ impl<C: hyper::client::connect::Connect + 'static> Client<C> {
         self._do_iter::<T::List>(rsrc.gvr(), None, opts)
     }
 
+    /// Lists the names of all namespaces.
+    pub fn namespace_names(&self) -> impl Future<Item = Vec<String>, Error = Error> + Send {
+        self.iter(Namespace::default())
+            .filter_map(|ns| ns.metadata.name)
+            .collect()
+    }
+
     fn _do_iter<L>(
         &self,
         gvr: GroupVersionResource,
@@ -560,26 +2049,50 @@ impl<C: hyper::client::connect::Connect + 'static> Client<C> {
     {
         let url = self.url(&gvr, namespace, None, opts.clone());
 
-        let client = Arc::clone(&self.client);
+        let transport = self.transport.clone();
         let fetch_pages = move |url: Url| {
-            stream::unfold(Some((url, opts)), move |context| {
-                context.and_then(|(mut url, mut opts)| {
+            let first = (url.clone(), opts.clone());
+            stream::unfold(Some((url, opts, 0)), move |context| {
+                let first = first.clone();
+                context.and_then(|(mut url, mut opts, restarts)| {
                     let req = Request::builder()
                         .method(Method::GET)
                         .uri(hyper_uri(url.clone()))
                         .body(Body::empty())
                         .map_err(|e| e.into());
-                    let res = do_request(Arc::clone(&client), req).and_then(move |list: L| {
-                        let next = match list.listmeta().continu {
-                            Some(ref continu) => {
-                                opts.continu = continu.clone();
-                                let query = serde_urlencoded::to_string(&opts)?;
-                                url.set_query(Some(&query));
-                                Some((url, opts))
-                            }
-                            None => None,
-                        };
-                        Ok((list, next))
+                    let paging = opts.continu != "";
+                    let res = do_request(transport.clone(), req).then(move |r| match r {
+                        Ok(list) => {
+                            let list: L = list;
+                            let next = match list.listmeta().continu {
+                                Some(ref continu) => {
+                                    opts.continu = continu.clone();
+                                    let query = serde_urlencoded::to_string(&opts)?;
+                                    url.set_query(Some(&query));
+                                    Some((url, opts, restarts))
+                                }
+                                None => None,
+                            };
+                            Ok((list.into_items(), next))
+                        }
+                        // The continue token expired (after ~5 minutes)
+                        Err(ref e)
+                            if paging
+                                && restarts < MAX_LIST_RESTARTS
+                                && (is_status_reason(e, StatusReason::Expired)
+                                    || is_status_reason(e, StatusReason::Gone)) =>
+                        {
+                            warn!(
+                                "List expired while paging ({}), restarting from the first page",
+                                e
+                            );
+                            let (mut url, mut opts) = first;
+                            opts.continu = String::new();
+                            let query = serde_urlencoded::to_string(&opts)?;
+                            url.set_query(Some(&query));
+                            Ok((vec![], Some((url, opts, restarts + 1))))
+                        }
+                        Err(e) => Err(e),
                     });
                     Some(res)
                 })
@@ -589,7 +2102,7 @@ impl<C: hyper::client::connect::Connect + 'static> Client<C> {
         future::result(url)
             .and_then(move |url| future::ok(fetch_pages(url)))
             .flatten_stream()
-            .map(|page| stream::iter_ok(page.into_items().into_iter()))
+            .map(|items| stream::iter_ok(items.into_iter()))
             .flatten()
     }
 }
@@ -656,3 +2169,1855 @@ fn test_url() {
         "https://192.168.42.147:8443/api/v1/namespaces?resourceVersion=abcdef&limit=27"
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use api::core::v1::GROUP_VERSION;
+    use hyper::header::{
+        CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_PROTOCOL,
+        TRANSFER_ENCODING, UPGRADE,
+    };
+    use std::collections::BTreeMap;
+    use std::env;
+    use std::fs;
+    use std::io::{self, Read, Write};
+    use std::net::{self, SocketAddr};
+    use std::sync::Mutex;
+    use std::thread;
+    use tokio::net::TcpStream;
+
+    pub fn chunked_body(data: &[u8], chunk_size: usize) -> Body {
+        let chunks: Vec<Vec<u8>> = data.chunks(chunk_size).map(|c| c.to_vec()).collect();
+        Body::wrap_stream(stream::iter_ok::<_, hyper::Error>(chunks))
+    }
+
+    pub struct MockService<F>(pub F);
+
+    impl<F> HttpService for MockService<F>
+    where
+        F: Fn(Request<Body>) -> hyper::Response<Body> + Send + Sync + 'static,
+    {
+        fn request(
+            &self,
+            req: Request<Body>,
+        ) -> Box<dyn Future<Item = hyper::Response<Body>, Error = Error> + Send> {
+            Box::new(future::ok((self.0)(req)))
+        }
+    }
+
+    pub fn mock_client<F>(f: F) -> Client<MockService<F>>
+    where
+        F: Fn(Request<Body>) -> hyper::Response<Body> + Send + Sync + 'static,
+    {
+        let mut context: ConfigContext = Default::default();
+        context.cluster.server = "https://192.168.42.147:8443".into();
+        Client::new_with_client(MockService(f), context).unwrap()
+    }
+
+    /// Answers every request with a websocket upgrade, connecting it
+    /// to `server`, run on its own thread as the far end.
+    pub struct MockUpgrade {
+        addr: SocketAddr,
+        pub uris: Mutex<Vec<String>>,
+    }
+
+    impl HttpService for MockUpgrade {
+        fn request(
+            &self,
+            req: Request<Body>,
+        ) -> Box<dyn Future<Item = hyper::Response<Body>, Error = Error> + Send> {
+            self.uris.lock().unwrap().push(req.uri().to_string());
+            let header = |name| req.headers()[name].to_str().unwrap();
+            let res = hyper::Response::builder()
+                .status(101)
+                .header(CONNECTION, "Upgrade")
+                .header(UPGRADE, "websocket")
+                .header(
+                    SEC_WEBSOCKET_ACCEPT,
+                    websocket::accept_key(header(SEC_WEBSOCKET_KEY)).as_str(),
+                )
+                .header(SEC_WEBSOCKET_PROTOCOL, header(SEC_WEBSOCKET_PROTOCOL))
+                .body(Body::empty())
+                .unwrap();
+            Box::new(future::ok(res))
+        }
+
+        fn upgrade(
+            &self,
+            _res: hyper::Response<Body>,
+        ) -> Box<dyn Future<Item = Box<dyn UpgradedIo>, Error = Error> + Send> {
+            Box::new(
+                TcpStream::connect(&self.addr)
+                    .from_err()
+                    .map(|s| Box::new(s) as Box<dyn UpgradedIo>),
+            )
+        }
+    }
+
+    pub fn upgrade_client<F>(server: F) -> Client<MockUpgrade>
+    where
+        F: FnOnce(net::TcpStream) + Send + 'static,
+    {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || server(listener.accept().unwrap().0));
+        let mut context: ConfigContext = Default::default();
+        context.cluster.server = "https://192.168.42.147:8443".into();
+        let service = MockUpgrade {
+            addr,
+            uris: Default::default(),
+        };
+        Client::new_with_client(service, context).unwrap()
+    }
+
+    /// Sends a message from the server end of a websocket.
+    pub fn ws_send(conn: &mut net::TcpStream, opcode: u8, msg: &[u8]) {
+        let mut frame = Vec::new();
+        websocket::encode_frame(opcode, msg, None, &mut frame);
+        conn.write_all(&frame).unwrap();
+    }
+
+    /// Receives a frame at the server end of a websocket.
+    pub fn ws_recv(conn: &mut net::TcpStream, buf: &mut Vec<u8>) -> websocket::Frame {
+        loop {
+            if let Some(frame) = websocket::decode_frame(buf).unwrap() {
+                return frame;
+            }
+            let mut chunk = [0; 4096];
+            let n = conn.read(&mut chunk).unwrap();
+            assert!(n > 0, "websocket closed");
+            buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    pub fn json_response(status: u16, body: &Value) -> hyper::Response<Body> {
+        hyper::Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::to_vec(body).unwrap()))
+            .unwrap()
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingConnector(Arc<Mutex<Vec<String>>>);
+
+    impl hyper::client::connect::Connect for RecordingConnector {
+        type Transport =
+            <hyper::client::HttpConnector as hyper::client::connect::Connect>::Transport;
+        type Error = io::Error;
+        type Future =
+            future::FutureResult<(Self::Transport, hyper::client::connect::Connected), io::Error>;
+
+        fn connect(&self, dst: hyper::client::connect::Destination) -> Self::Future {
+            self.0.lock().unwrap().push(format!(
+                "{}://{}:{}",
+                dst.scheme(),
+                dst.host(),
+                dst.port().unwrap_or(0)
+            ));
+            future::err(io::Error::new(io::ErrorKind::ConnectionRefused, "test"))
+        }
+    }
+
+    #[test]
+    fn test_custom_connector() {
+        let connector = RecordingConnector::default();
+        let mut context: ConfigContext = Default::default();
+        context.cluster.server = "https://192.168.42.147:8443".into();
+        let client = Client::new_with_connector(connector.clone(), context).unwrap();
+
+        let res: Result<Value, _> = client
+            .get(
+                &GROUP_VERSION.with_resource("pods"),
+                Some("default"),
+                "mypod",
+                Default::default(),
+            )
+            .wait();
+        assert!(res.is_err());
+        assert_eq!(
+            *connector.0.lock().unwrap(),
+            vec!["https://192.168.42.147:8443"]
+        );
+    }
+
+    #[test]
+    fn test_new_h2() {
+        let mut context: ConfigContext = Default::default();
+        context.cluster.server = "https://192.168.42.147:8443".into();
+        let http = hyper::client::HttpConnector::new(1);
+        assert!(Client::new_from_context_h2(http, context).is_ok());
+    }
+
+    #[test]
+    fn test_config_headers() {
+        let mut context: ConfigContext = Default::default();
+        context.cluster.server = "https://192.168.42.147:8443".into();
+        context.user.username = "admin".into();
+        context.user.password = "secret".into();
+        let seen = Arc::new(Mutex::new(vec![]));
+        let seen2 = Arc::clone(&seen);
+        let service = MockService(move |req: Request<Body>| {
+            let auth: Vec<_> = req.headers().get_all(AUTHORIZATION).iter().collect();
+            assert_eq!(auth.len(), 1);
+            assert!(auth[0].is_sensitive());
+            seen2.lock().unwrap().push(auth[0].clone());
+            json_response(200, &json!({}))
+        });
+        let client = Client::new_with_client(service, context).unwrap();
+
+        let _: Value = client
+            .get(
+                &GROUP_VERSION.with_resource("pods"),
+                Some("default"),
+                "mypod",
+                Default::default(),
+            )
+            .wait()
+            .unwrap();
+
+        // A request's own Authorization replaces the configured one
+        let url = client
+            .url(&GROUP_VERSION.with_resource("pods"), None, None, ())
+            .unwrap();
+        let mut own = HeaderValue::from_static("Bearer own");
+        own.set_sensitive(true);
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(url.as_str())
+            .header(AUTHORIZATION, own)
+            .body(Body::empty())
+            .unwrap();
+        let _: Value = do_request(client.transport.clone(), Ok(req))
+            .wait()
+            .unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                HeaderValue::from_static("Basic YWRtaW46c2VjcmV0"),
+                HeaderValue::from_static("Bearer own"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exec_auth() {
+        let mut context: ConfigContext = Default::default();
+        context.cluster.server = "https://192.168.42.147:8443".into();
+        context.user.token = "static".into();
+        context.user.exec = Some(config::api::ExecConfig {
+            command: "sh".into(),
+            args: vec![
+                "-c".into(),
+                r#"echo '{"apiVersion": "client.authentication.k8s.io/v1", "kind": "ExecCredential", "status": {"token": "fromexec"}}'"#.into(),
+            ],
+            api_version: "client.authentication.k8s.io/v1".into(),
+            ..Default::default()
+        });
+        let service = MockService(|req: Request<Body>| {
+            assert_eq!(req.headers().get_all(AUTHORIZATION).iter().count(), 1);
+            assert_eq!(req.headers()[AUTHORIZATION], "Bearer fromexec");
+            json_response(200, &json!({}))
+        });
+        let client = Client::new_with_client(service, context.clone()).unwrap();
+        let pods = GROUP_VERSION.with_resource("pods");
+        client
+            .get::<Value>(&pods, None, "mypod", Default::default())
+            .wait()
+            .unwrap();
+
+        // Plugin failures fail the request
+        context.user.exec.as_mut().unwrap().args = vec!["-c".into(), "exit 1".into()];
+        let client =
+            Client::new_with_client(MockService(|_| json_response(200, &json!({}))), context)
+                .unwrap();
+        assert!(client
+            .get::<Value>(&pods, None, "mypod", Default::default())
+            .wait()
+            .is_err());
+    }
+
+    #[test]
+    fn test_exec_auth_shared() {
+        // The plugin run for TLS setup also supplies request tokens
+        let count = env::temp_dir().join(format!("k8s-exec-shared-{}", ::std::process::id()));
+        let _ = fs::remove_file(&count);
+        let mut context: ConfigContext = Default::default();
+        context.cluster.server = "https://192.168.42.147:8443".into();
+        context.user.exec = Some(config::api::ExecConfig {
+            command: "sh".into(),
+            args: vec![
+                "-c".into(),
+                format!(
+                    r#"echo run >> {}; echo '{{"apiVersion": "client.authentication.k8s.io/v1", "kind": "ExecCredential", "status": {{"token": "fromexec"}}}}'"#,
+                    count.display()
+                ),
+            ],
+            api_version: "client.authentication.k8s.io/v1".into(),
+            ..Default::default()
+        });
+        let client =
+            Client::new_from_context(hyper::client::HttpConnector::new(1), context).unwrap();
+        let exec = client.transport.exec.as_ref().unwrap();
+        assert_eq!(
+            ExecAuth::credentials_async(exec).wait().unwrap().token,
+            "fromexec"
+        );
+        assert_eq!(fs::read_to_string(&count).unwrap().lines().count(), 1);
+        fs::remove_file(&count).unwrap();
+    }
+
+    #[test]
+    fn test_impersonate() {
+        let mut context: ConfigContext = Default::default();
+        context.cluster.server = "https://example.com".into();
+        context.user.act_as = "jane".into();
+        context.user.act_as_groups = vec!["devs".into(), "admins".into()];
+        context
+            .user
+            .act_as_user_extra
+            .insert("scopes".into(), vec!["view".into(), "edit".into()]);
+        context
+            .user
+            .act_as_user_extra
+            .insert("example.com/team id".into(), vec!["42".into()]);
+        let service = MockService(|req: Request<Body>| {
+            let all = |name: &str| -> Vec<_> {
+                req.headers()
+                    .get_all(name)
+                    .iter()
+                    .map(|v| v.to_str().unwrap().to_string())
+                    .collect()
+            };
+            assert_eq!(all("Impersonate-User"), vec!["jane"]);
+            assert_eq!(all("Impersonate-Group"), vec!["devs", "admins"]);
+            assert_eq!(all("Impersonate-Extra-scopes"), vec!["view", "edit"]);
+            assert_eq!(all("Impersonate-Extra-example.com%2Fteam%20id"), vec!["42"]);
+            assert!(req.headers().get("Impersonate-Uid").is_none());
+            json_response(200, &json!({}))
+        });
+        let client = Client::new_with_client(service, context).unwrap();
+        let pods = GROUP_VERSION.with_resource("pods");
+        client
+            .get::<Value>(&pods, None, "mypod", Default::default())
+            .wait()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_impersonate_uid() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let client = |user: config::api::AuthInfo| {
+            let mut context = ConfigContext {
+                user,
+                ..Default::default()
+            };
+            context.cluster.server = "https://example.com".into();
+            let seen = Arc::clone(&seen);
+            let service = MockService(move |req: Request<Body>| {
+                seen.lock()
+                    .unwrap()
+                    .push(req.headers().get("Impersonate-Uid").cloned());
+                json_response(200, &json!({}))
+            });
+            Client::new_with_client(service, context).unwrap()
+        };
+        let get = |client: &Client<_>| {
+            let pods = GROUP_VERSION.with_resource("pods");
+            client
+                .get::<Value>(&pods, None, "mypod", Default::default())
+                .wait()
+                .unwrap();
+        };
+
+        let mut user: config::api::AuthInfo = Default::default();
+        get(&client(user.clone()));
+        user.act_as_uid = "1234-abcd".into();
+        get(&client(user.clone()));
+        get(&client(user).with_impersonate_uid("5678-efgh").unwrap());
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                None,
+                Some(HeaderValue::from_static("1234-abcd")),
+                Some(HeaderValue::from_static("5678-efgh")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        let auth = |user: config::api::AuthInfo| {
+            let context = ConfigContext {
+                user,
+                ..Default::default()
+            };
+            config_headers(&context)
+                .unwrap()
+                .get(AUTHORIZATION)
+                .map(|v| v.to_str().unwrap().to_string())
+        };
+
+        let mut user: config::api::AuthInfo = Default::default();
+        assert_eq!(auth(user.clone()), None);
+
+        user.token = "abc123".into();
+        assert_eq!(auth(user.clone()), Some("Bearer abc123".into()));
+
+        let path = env::temp_dir().join(format!("k8s-token-{}", std::process::id()));
+        std::fs::write(&path, "fromfile\n").unwrap();
+        user.token_file = path.clone();
+        // token takes precedence over tokenFile
+        assert_eq!(auth(user.clone()), Some("Bearer abc123".into()));
+        user.token = "".into();
+        assert_eq!(auth(user.clone()), Some("Bearer fromfile".into()));
+
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(auth(user.clone()), None);
+        std::fs::remove_file(&path).unwrap();
+
+        // Missing token file is an error, rather than silently anonymous
+        let context = ConfigContext {
+            user,
+            ..Default::default()
+        };
+        assert!(config_headers(&context).is_err());
+    }
+
+    #[test]
+    fn test_get_or_create() {
+        let pods = GROUP_VERSION.with_resource("pods");
+        let mut pod = Pod::default();
+        pod.metadata.name = Some("mypod".into());
+        pod.metadata.namespace = Some("default".into());
+
+        let methods = Arc::new(Mutex::new(vec![]));
+        let methods2 = Arc::clone(&methods);
+        let client = mock_client(move |req| {
+            methods2.lock().unwrap().push(req.method().clone());
+            match *req.method() {
+                Method::GET => json_response(
+                    404,
+                    &json!({
+                        "kind": "Status",
+                        "apiVersion": "v1",
+                        "metadata": {},
+                        "status": "Failure",
+                        "message": "pods \"mypod\" not found",
+                        "reason": "NotFound",
+                        "code": 404,
+                    }),
+                ),
+                _ => {
+                    assert_eq!(req.uri().path(), "/api/v1/namespaces/default/pods");
+                    json_response(201, &json!({"metadata": {"name": "mypod", "uid": "1"}}))
+                }
+            }
+        });
+        let (created, was_created) = client
+            .get_or_create(&pods, &pod, Default::default())
+            .wait()
+            .unwrap();
+        assert!(was_created);
+        assert_eq!(created.metadata.uid, Some("1".into()));
+        assert_eq!(*methods.lock().unwrap(), vec![Method::GET, Method::POST]);
+
+        let methods = Arc::new(Mutex::new(vec![]));
+        let methods2 = Arc::clone(&methods);
+        let client = mock_client(move |req| {
+            methods2.lock().unwrap().push(req.method().clone());
+            json_response(200, &json!({"metadata": {"name": "mypod", "uid": "2"}}))
+        });
+        let (found, was_created) = client
+            .get_or_create(&pods, &pod, Default::default())
+            .wait()
+            .unwrap();
+        assert!(!was_created);
+        assert_eq!(found.metadata.uid, Some("2".into()));
+        assert_eq!(*methods.lock().unwrap(), vec![Method::GET]);
+    }
+
+    #[test]
+    fn test_remove_finalizer_safe() {
+        let pods = GROUP_VERSION.with_resource("pods");
+        let finalizers = Arc::new(Mutex::new(vec!["example.com/a", "example.com/mine"]));
+        let patches = Arc::new(Mutex::new(vec![]));
+        let patches2 = Arc::clone(&patches);
+        let client = mock_client(move |req| {
+            let pod = |fs: &[&str]| json!({"metadata": {"name": "mypod", "finalizers": fs}});
+            match *req.method() {
+                Method::GET => json_response(200, &pod(&finalizers.lock().unwrap())),
+                _ => {
+                    let body = req.into_body().concat2().wait().unwrap();
+                    let patch: Value = serde_json::from_slice(&body).unwrap();
+                    patches2.lock().unwrap().push(patch);
+                    let mut fs = finalizers.lock().unwrap();
+                    if fs.len() == 2 {
+                        // Someone else removes "a" first
+                        fs.remove(0);
+                        json_response(
+                            422,
+                            &json!({
+                                "kind": "Status",
+                                "apiVersion": "v1",
+                                "metadata": {},
+                                "status": "Failure",
+                                "message": "the server rejected our request due to an error in our request",
+                                "reason": "Invalid",
+                                "code": 422,
+                            }),
+                        )
+                    } else {
+                        fs.clear();
+                        json_response(200, &pod(&fs))
+                    }
+                }
+            }
+        });
+
+        let pod: Pod = client
+            .remove_finalizer_safe(&pods, Some("default"), "mypod", "example.com/mine")
+            .wait()
+            .unwrap();
+        assert!(pod.metadata.finalizers.is_empty());
+        assert_eq!(
+            *patches.lock().unwrap(),
+            vec![
+                json!([
+                    {"op": "test", "path": "/metadata/finalizers/1", "value": "example.com/mine"},
+                    {"op": "remove", "path": "/metadata/finalizers/1"},
+                ]),
+                json!([
+                    {"op": "test", "path": "/metadata/finalizers/0", "value": "example.com/mine"},
+                    {"op": "remove", "path": "/metadata/finalizers/0"},
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_service_pods() {
+        let client = mock_client(|req| match req.uri().path() {
+            "/api/v1/namespaces/default/services/web" => json_response(
+                200,
+                &json!({
+                    "metadata": {"name": "web", "namespace": "default"},
+                    "spec": {"selector": {"app": "web"}},
+                }),
+            ),
+            "/api/v1/namespaces/default/pods" => {
+                assert_eq!(req.uri().query(), Some("labelSelector=app%3Dweb"));
+                let pods = vec![
+                    json!({"metadata": {"name": "web-1", "labels": {"app": "web"}}}),
+                    json!({"metadata": {"name": "web-2", "labels": {"app": "web"}}}),
+                    json!({"metadata": {"name": "db-1", "labels": {"app": "db"}}}),
+                ];
+                // Play the part of the API server's label selector
+                let items: Vec<_> = pods
+                    .into_iter()
+                    .filter(|p| p["metadata"]["labels"]["app"] == "web")
+                    .collect();
+                json_response(200, &json!({"metadata": {}, "items": items}))
+            }
+            p => panic!("unexpected request for {}", p),
+        });
+
+        let pods = client
+            .namespace("default")
+            .service_pods("web")
+            .wait()
+            .unwrap();
+        let names: Vec<_> = pods
+            .iter()
+            .map(|p| p.metadata.name.as_ref().unwrap().as_str())
+            .collect();
+        assert_eq!(names, vec!["web-1", "web-2"]);
+    }
+
+    #[test]
+    fn test_create_with_status() {
+        let client = mock_client(|req| {
+            assert_eq!(req.method(), &Method::POST);
+            assert_eq!(req.uri().path(), "/api/v1/namespaces/default/pods");
+            json_response(201, &json!({"metadata": {"name": "mypod", "uid": "1"}}))
+        });
+        let mut pod = Pod::default();
+        pod.metadata.name = Some("mypod".into());
+        pod.metadata.namespace = Some("default".into());
+
+        let (pod, status) = client
+            .create_with_status(
+                &GROUP_VERSION.with_resource("pods"),
+                &pod,
+                Default::default(),
+            )
+            .wait()
+            .unwrap();
+        assert_eq!(status, hyper::StatusCode::CREATED);
+        assert_eq!(pod.metadata.uid, Some("1".into()));
+    }
+
+    #[test]
+    fn test_create_dry_run() {
+        use api::meta::v1::DryRun;
+
+        let client = mock_client(|req| {
+            assert_eq!(req.method(), &Method::POST);
+            assert_eq!(req.uri().path(), "/api/v1/namespaces/default/pods");
+            assert_eq!(req.uri().query(), Some("dryRun=All&fieldManager=test"));
+            json_response(201, &json!({"metadata": {"name": "mypod"}}))
+        });
+        let mut pod = Pod::default();
+        pod.metadata.name = Some("mypod".into());
+        pod.metadata.namespace = Some("default".into());
+
+        let opts = CreateOptions {
+            dry_run: Some(DryRun::All),
+            field_manager: "test".into(),
+            ..Default::default()
+        };
+        let pod = client
+            .create(&GROUP_VERSION.with_resource("pods"), &pod, opts)
+            .wait()
+            .unwrap();
+        assert_eq!(pod.metadata.uid, None);
+    }
+
+    #[test]
+    fn test_delete() {
+        use api::meta::v1::StatusStatus;
+
+        let client = mock_client(|req| {
+            assert_eq!(req.method(), &Method::DELETE);
+            match req.uri().path() {
+                "/api/v1/namespaces/default/pods/gone" => json_response(
+                    200,
+                    &json!({
+                        "kind": "Status",
+                        "apiVersion": "v1",
+                        "metadata": {},
+                        "status": "Success",
+                        "details": {"name": "gone", "kind": "pods"},
+                    }),
+                ),
+                "/api/v1/namespaces/default/pods/pending" => json_response(
+                    200,
+                    &json!({
+                        "kind": "Pod",
+                        "apiVersion": "v1",
+                        "metadata": {
+                            "name": "pending",
+                            "deletionTimestamp": "2018-02-20T18:00:07Z",
+                            "finalizers": ["example.com/cleanup"],
+                        },
+                    }),
+                ),
+                p => panic!("unexpected request for {}", p),
+            }
+        });
+        let pods = GROUP_VERSION.with_resource("pods");
+
+        let res = client
+            .delete::<Pod>(&pods, Some("default"), "gone", Default::default())
+            .wait()
+            .unwrap();
+        match res {
+            DeleteResponse::Status(s) => assert_eq!(s.status, StatusStatus::Success),
+            r => panic!("unexpected {:?}", r),
+        }
+
+        let res = client
+            .delete::<Pod>(&pods, Some("default"), "pending", Default::default())
+            .wait()
+            .unwrap();
+        match res {
+            DeleteResponse::Object(pod) => {
+                assert!(pod.metadata.deletion_timestamp.is_some());
+                assert_eq!(pod.metadata.finalizers, vec!["example.com/cleanup"]);
+            }
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_list_incomplete() {
+        let client = mock_client(|req| {
+            let continu = match req.uri().query() {
+                None => "page2",
+                _ => "",
+            };
+            json_response(
+                200,
+                &json!({
+                    "kind": "PodList",
+                    "apiVersion": "v1",
+                    "metadata": {"continue": continu},
+                    "items": [{"metadata": {"name": "pod1"}}],
+                }),
+            )
+        });
+        let pods = GROUP_VERSION.with_resource("pods");
+
+        let list: PodList = client.list(&pods, None, Default::default()).wait().unwrap();
+        assert_eq!(list.metadata.continu, Some("page2".into()));
+
+        let err = client
+            .list_complete::<PodList>(&pods, None, Default::default())
+            .wait()
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<IncompleteListError>().unwrap().continu,
+            "page2"
+        );
+
+        let opts = ListOptions {
+            limit: 2,
+            ..Default::default()
+        };
+        let list: PodList = client.list_complete(&pods, None, opts).wait().unwrap();
+        assert_eq!(list.items.len(), 1);
+    }
+
+    #[test]
+    fn test_field_selector_query() {
+        use api::meta::v1::FieldSelector;
+
+        let client = mock_client(|req| {
+            assert_eq!(
+                req.uri().query(),
+                Some("fieldSelector=metadata.name%3Da%5C%2Cb%2Cspec.nodeName%21%3Dnode1")
+            );
+            json_response(
+                200,
+                &json!({"kind": "PodList", "apiVersion": "v1", "metadata": {}, "items": []}),
+            )
+        });
+        let selector = FieldSelector::new()
+            .eq("metadata.name", "a,b")
+            .ne("spec.nodeName", "node1");
+        let opts = ListOptions::default().with_field_selector(&selector);
+        let list: PodList = client
+            .list(&GROUP_VERSION.with_resource("pods"), None, opts)
+            .wait()
+            .unwrap();
+        assert!(list.items.is_empty());
+    }
+
+    #[test]
+    fn test_namespace_names() {
+        let client = mock_client(|req| {
+            assert_eq!(req.uri().path(), "/api/v1/namespaces");
+            match req.uri().query() {
+                None => json_response(
+                    200,
+                    &json!({
+                        "kind": "NamespaceList",
+                        "apiVersion": "v1",
+                        "metadata": {"continue": "page2"},
+                        "items": [{"metadata": {"name": "default"}}],
+                    }),
+                ),
+                Some("continue=page2") => json_response(
+                    200,
+                    &json!({
+                        "kind": "NamespaceList",
+                        "apiVersion": "v1",
+                        "metadata": {},
+                        "items": [{"metadata": {"name": "kube-system"}}],
+                    }),
+                ),
+                Some(q) => panic!("unexpected query {}", q),
+            }
+        });
+        let names = client.namespace_names().wait().unwrap();
+        assert_eq!(names, vec!["default", "kube-system"]);
+    }
+
+    #[test]
+    fn test_get_if_changed() {
+        let client = mock_client(|req| {
+            assert_eq!(req.uri().path(), "/api/v1/namespaces/default/pods/web");
+            let rv = req
+                .uri()
+                .query()
+                .unwrap()
+                .trim_start_matches("resourceVersion=");
+            // Only a newer version than 100 is "changed"
+            let current = if rv == "100" { "100" } else { "101" };
+            json_response(
+                200,
+                &json!({"metadata": {"name": "web", "resourceVersion": current}}),
+            )
+        });
+        let pods = GROUP_VERSION.with_resource("pods");
+
+        let pod: Option<Pod> = client
+            .get_if_changed(&pods, Some("default"), "web", &"100".into())
+            .wait()
+            .unwrap();
+        assert_eq!(pod, None);
+
+        let pod: Option<Pod> = client
+            .get_if_changed(&pods, Some("default"), "web", &"99".into())
+            .wait()
+            .unwrap();
+        assert_eq!(pod.unwrap().metadata.resource_version, Some("101".into()));
+    }
+
+    #[test]
+    fn test_iter_deployments() {
+        use api::apps::v1::Deployments;
+
+        let client = mock_client(|req| {
+            assert_eq!(
+                req.uri().path(),
+                "/apis/apps/v1/namespaces/default/deployments"
+            );
+            json_response(
+                200,
+                &json!({
+                    "kind": "DeploymentList",
+                    "apiVersion": "apps/v1",
+                    "metadata": {"resourceVersion": "1234"},
+                    "items": [
+                        {
+                            "metadata": {"name": "web"},
+                            "spec": {"replicas": 3},
+                            "status": {"replicas": 3, "readyReplicas": 2},
+                        },
+                        {"metadata": {"name": "new"}, "status": {}},
+                    ],
+                }),
+            )
+        });
+        let deployments: Vec<_> = client
+            .namespace("default")
+            .iter(Deployments)
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(deployments.len(), 2);
+        assert_eq!(deployments[0].metadata.name, Some("web".into()));
+        assert_eq!(deployments[0].spec.replicas, 3);
+        assert_eq!(deployments[0].status.ready_replicas, 2);
+        assert_eq!(deployments[1].spec.replicas, 1);
+        assert_eq!(deployments[1].status.replicas, 0);
+    }
+
+    #[test]
+    fn test_iter_restarts_on_expired_continue() {
+        use std::sync::Mutex;
+
+        let queries = Arc::new(Mutex::new(vec![]));
+        let queries2 = Arc::clone(&queries);
+        let client = mock_client(move |req| {
+            let query = req.uri().query().unwrap_or("").to_string();
+            let expired = {
+                let mut queries = queries2.lock().unwrap();
+                queries.push(query.clone());
+                queries.len() == 2
+            };
+            let ns = |name| json!({"metadata": {"name": name}});
+            if expired {
+                json_response(
+                    410,
+                    &json!({
+                        "kind": "Status",
+                        "apiVersion": "v1",
+                        "metadata": {},
+                        "status": "Failure",
+                        "message": "The provided continue parameter is too old",
+                        "reason": "Expired",
+                        "code": 410,
+                    }),
+                )
+            } else if query.contains("continue=") {
+                json_response(200, &json!({"metadata": {}, "items": [ns("b")]}))
+            } else {
+                json_response(
+                    200,
+                    &json!({"metadata": {"continue": "abc"}, "items": [ns("a")]}),
+                )
+            }
+        });
+        let names: Vec<_> = client
+            .iter_opt(Namespace::default(), Default::default())
+            .map(|ns| ns.metadata.name.unwrap())
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(names, vec!["a", "a", "b"]);
+        let queries = queries.lock().unwrap();
+        assert_eq!(queries.len(), 4);
+        assert!(queries[1].contains("continue=abc"));
+        assert!(!queries[2].contains("continue="));
+        assert!(queries[3].contains("continue=abc"));
+
+        // An expired first page is not retried
+        let client = mock_client(|_| {
+            json_response(
+                410,
+                &json!({
+                    "kind": "Status",
+                    "apiVersion": "v1",
+                    "metadata": {},
+                    "status": "Failure",
+                    "message": "The provided continue parameter is too old",
+                    "reason": "Expired",
+                    "code": 410,
+                }),
+            )
+        });
+        let err = client
+            .iter(Namespace::default())
+            .collect()
+            .wait()
+            .unwrap_err();
+        assert!(is_status_reason(&err, StatusReason::Expired));
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let client = mock_client(|_| json_response(200, &json!({"kind": "Pod"})));
+        let pods = GROUP_VERSION.with_resource("pods");
+
+        let in_flight = client.get::<Value>(&pods, Some("default"), "before", Default::default());
+        let drained = client.shutdown();
+        assert_eq!(client.transport.shutdown.in_flight(), 1);
+
+        let err = client
+            .get::<Value>(&pods, Some("default"), "after", Default::default())
+            .wait()
+            .unwrap_err();
+        assert!(err.downcast_ref::<ShutdownError>().is_some());
+
+        let watch = client
+            .watch_list(&pods, None, Default::default())
+            .collect()
+            .wait();
+        assert!(watch.is_err());
+
+        assert_eq!(in_flight.wait().unwrap()["kind"], "Pod");
+        assert_eq!(client.transport.shutdown.in_flight(), 0);
+        drained.wait().unwrap();
+    }
+
+    #[test]
+    fn test_buffer_pool() {
+        let pool = Arc::new(BufferPool::default());
+        let client = mock_client(|req| {
+            json_response(
+                200,
+                &json!({"kind": "Pod", "metadata": {"name": req.uri().path()}}),
+            )
+        })
+        .with_buffer_pool(Arc::clone(&pool));
+        let pods = GROUP_VERSION.with_resource("pods");
+
+        for i in 0..100 {
+            let pod: Value = client
+                .get(
+                    &pods,
+                    Some("default"),
+                    &format!("pod-{}", i),
+                    Default::default(),
+                )
+                .wait()
+                .unwrap();
+            assert_eq!(
+                pod["metadata"]["name"],
+                format!("/api/v1/namespaces/default/pods/pod-{}", i)
+            );
+        }
+        // Every response after the first reused the same buffer
+        assert_eq!(pool.allocated(), 1);
+    }
+
+    #[test]
+    fn test_buffer_pool_large() {
+        let pool = Arc::new(BufferPool::default().with_max_capacity(4096));
+        let client = mock_client(|req| {
+            let size = if req.uri().path().ends_with("big") {
+                64 * 1024
+            } else {
+                10
+            };
+            json_response(200, &json!({"kind": "Pod", "data": "x".repeat(size)}))
+        })
+        .with_buffer_pool(Arc::clone(&pool));
+        let pods = GROUP_VERSION.with_resource("pods");
+        let get = |name: &str| {
+            let _: Value = client
+                .get(&pods, Some("default"), name, Default::default())
+                .wait()
+                .unwrap();
+        };
+
+        get("small");
+        assert_eq!(pool.allocated(), 1);
+        // Reuses the small buffer, which then grows too large to keep
+        get("big");
+        assert_eq!(pool.allocated(), 1);
+        get("small");
+        assert_eq!(pool.allocated(), 2);
+        // Small buffers are still kept
+        get("small");
+        assert_eq!(pool.allocated(), 2);
+    }
+
+    fn eviction_client(
+        versions: &'static [&'static str],
+        posted: Arc<Mutex<Vec<(String, Value)>>>,
+    ) -> Client<impl HttpService> {
+        mock_client(move |req| match *req.method() {
+            Method::GET => {
+                assert_eq!(req.uri().path(), "/apis/policy");
+                let versions: Vec<_> = versions
+                    .iter()
+                    .map(|v| json!({"groupVersion": format!("policy/{}", v), "version": v}))
+                    .collect();
+                json_response(
+                    200,
+                    &json!({
+                        "kind": "APIGroup",
+                        "apiVersion": "v1",
+                        "name": "policy",
+                        "versions": versions,
+                        "preferredVersion": versions[0],
+                    }),
+                )
+            }
+            _ => {
+                let path = req.uri().path().to_string();
+                let body = req.into_body().concat2().wait().unwrap();
+                posted
+                    .lock()
+                    .unwrap()
+                    .push((path, serde_json::from_slice(&body).unwrap()));
+                json_response(
+                    201,
+                    &json!({"kind": "Status", "apiVersion": "v1", "status": "Success"}),
+                )
+            }
+        })
+    }
+
+    #[test]
+    fn test_create_eviction() {
+        for &(versions, expected) in &[
+            (&["v1", "v1beta1"][..], "policy/v1"),
+            (&["v1"][..], "policy/v1"),
+            (&["v1beta1"][..], "policy/v1beta1"),
+        ] {
+            let posted = Arc::new(Mutex::new(vec![]));
+            let client = eviction_client(versions, Arc::clone(&posted));
+            let opts = DeleteOptions {
+                grace_period_seconds: Some(30),
+                ..Default::default()
+            };
+            client
+                .create_eviction("default", "web-0", opts)
+                .wait()
+                .unwrap();
+
+            let posted = posted.lock().unwrap();
+            assert_eq!(posted.len(), 1);
+            assert_eq!(
+                posted[0].0,
+                "/api/v1/namespaces/default/pods/web-0/eviction"
+            );
+            assert_eq!(
+                posted[0].1,
+                json!({
+                    "apiVersion": expected,
+                    "kind": "Eviction",
+                    "metadata": {"namespace": "default", "name": "web-0"},
+                    "deleteOptions": {"gracePeriodSeconds": 30},
+                })
+            );
+        }
+
+        let client = eviction_client(&["v2alpha1"], Default::default());
+        assert!(client
+            .create_eviction("default", "web-0", Default::default())
+            .wait()
+            .is_err());
+    }
+
+    #[test]
+    fn test_create_eviction_cached() {
+        let gets = Arc::new(Mutex::new(0));
+        let gets2 = Arc::clone(&gets);
+        let client = mock_client(move |req| match *req.method() {
+            Method::GET => {
+                *gets2.lock().unwrap() += 1;
+                json_response(
+                    200,
+                    &json!({
+                        "kind": "APIGroup",
+                        "apiVersion": "v1",
+                        "name": "policy",
+                        "versions": [{"groupVersion": "policy/v1beta1", "version": "v1beta1"}],
+                    }),
+                )
+            }
+            _ => {
+                let body = req.into_body().concat2().wait().unwrap();
+                let eviction: Value = serde_json::from_slice(&body).unwrap();
+                assert_eq!(eviction["apiVersion"], "policy/v1beta1");
+                json_response(
+                    201,
+                    &json!({"kind": "Status", "apiVersion": "v1", "status": "Success"}),
+                )
+            }
+        });
+
+        for i in 0..5 {
+            // Clones share what was discovered
+            client
+                .clone()
+                .create_eviction("default", &format!("web-{}", i), Default::default())
+                .wait()
+                .unwrap();
+        }
+        assert_eq!(*gets.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_exec_url() {
+        let client = mock_client(|_| unreachable!());
+        let opts = PodExecOptions {
+            stdout: true,
+            stderr: true,
+            container: Some("app".to_string()),
+            command: vec!["sh".to_string(), "-c".to_string(), "echo hi".to_string()],
+            ..Default::default()
+        };
+        let url = client.exec_url("default", "web-0", &opts).unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://192.168.42.147:8443/api/v1/namespaces/default/pods/web-0/exec\
+             ?stdout=true&stderr=true&container=app&command=sh&command=-c&command=echo+hi"
+        );
+        assert!(client
+            .exec_url("default", "web-0", &Default::default())
+            .is_err());
+    }
+
+    #[test]
+    fn test_portforward_url() {
+        let client = mock_client(|_| unreachable!());
+        let opts = PodPortForwardOptions {
+            ports: vec![8080, 443],
+        };
+        let url = client.portforward_url("default", "web-0", &opts).unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://192.168.42.147:8443/api/v1/namespaces/default/pods/web-0/portforward\
+             ?ports=8080&ports=443"
+        );
+        assert!(client
+            .portforward_url("default", "web-0", &Default::default())
+            .is_err());
+        let opts = PodPortForwardOptions {
+            ports: (1..=129).collect(),
+        };
+        assert!(client.portforward_url("default", "web-0", &opts).is_err());
+    }
+
+    #[test]
+    fn test_json_charset() {
+        // Media type parameters don't matter: responses are always
+        // parsed as JSON.
+        let client = mock_client(|req| {
+            let (status, body) = match req.uri().path() {
+                "/api/v1/namespaces/default/pods/web" => (
+                    200,
+                    json!({"kind": "Pod", "apiVersion": "v1", "metadata": {"name": "web"}}),
+                ),
+                _ => (
+                    404,
+                    json!({
+                        "kind": "Status",
+                        "apiVersion": "v1",
+                        "metadata": {},
+                        "status": "Failure",
+                        "reason": "NotFound",
+                        "code": 404,
+                    }),
+                ),
+            };
+            hyper::Response::builder()
+                .status(status)
+                .header(CONTENT_TYPE, "application/json; charset=utf-8")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap()
+        });
+        let pods = GROUP_VERSION.with_resource("pods");
+
+        let pod: Pod = client
+            .get(&pods, Some("default"), "web", Default::default())
+            .wait()
+            .unwrap();
+        assert_eq!(pod.metadata.name, Some("web".into()));
+
+        let err = client
+            .get::<Pod>(&pods, Some("default"), "missing", Default::default())
+            .wait()
+            .unwrap_err();
+        assert!(is_status_reason(&err, StatusReason::NotFound));
+    }
+
+    #[test]
+    fn test_error_snippet() {
+        let body = "{\n  \"kind\": \"Pod\",\n  \"metadata\": {\n    \"name\": web\n  }\n}";
+        assert_eq!(
+            error_snippet(body.as_bytes(), 4, 13),
+            "d\": \"Pod\",\n  \"metadata\": {\n    \"name\": w"
+        );
+        // Past the end of the line, or of the body
+        assert_eq!(
+            error_snippet(body.as_bytes(), 2, 100),
+            "{\n  \"kind\": \"Pod\","
+        );
+        assert_eq!(
+            error_snippet(body.as_bytes(), 10, 1),
+            "\",\n  \"metadata\": {\n    \"name\": web\n  }\n}"
+        );
+        assert_eq!(error_snippet(b"", 1, 5), "");
+        // Not splitting characters
+        assert_eq!(
+            error_snippet("\u{e9}\u{e9}".as_bytes(), 1, 3),
+            "\u{e9}\u{e9}"
+        );
+
+        let client = mock_client(move |_| hyper::Response::new(Body::from(body)));
+        let err = client
+            .get::<Pod>(
+                &GROUP_VERSION.with_resource("pods"),
+                Some("default"),
+                "web",
+                Default::default(),
+            )
+            .wait()
+            .unwrap_err();
+        assert!(err.to_string().ends_with(r#"\"name\": w")"#), "{}", err);
+    }
+
+    #[test]
+    fn test_read_log_string() {
+        let client = mock_client(|req| {
+            assert_eq!(
+                req.uri().path(),
+                "/api/v1/namespaces/default/pods/web-0/log"
+            );
+            assert_eq!(req.uri().query(), Some("container=nginx"));
+            hyper::Response::builder()
+                .status(200)
+                .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+                .body(Body::from("starting\n{\"not\": \"json\"}\n"))
+                .unwrap()
+        });
+        let opts = PodLogOptions {
+            container: Some("nginx".into()),
+            ..Default::default()
+        };
+        let logs = client
+            .read_log_string("default", "web-0", opts)
+            .wait()
+            .unwrap();
+        assert_eq!(logs, "starting\n{\"not\": \"json\"}\n");
+
+        let mut headers = HeaderMap::new();
+        assert!(!is_text(&headers));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("Text/Plain"));
+        assert!(is_text(&headers));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain-ish"));
+        assert!(!is_text(&headers));
+    }
+
+    #[test]
+    fn test_read_log_lines() {
+        let client = mock_client(|req| {
+            assert_eq!(
+                req.uri().path(),
+                "/api/v1/namespaces/default/pods/web-0/log"
+            );
+            assert_eq!(req.uri().query(), Some("follow=true&tailLines=10"));
+            hyper::Response::builder()
+                .status(200)
+                .header(CONTENT_TYPE, "text/plain")
+                .body(chunked_body(
+                    b"starting\nlistening on :80\nbad \xff\nno newline",
+                    5,
+                ))
+                .unwrap()
+        });
+        let opts = PodLogOptions {
+            follow: true,
+            tail_lines: Some(10),
+            ..Default::default()
+        };
+        let lines = client
+            .read_log_lines("default", "web-0", opts)
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(
+            lines,
+            vec!["starting", "listening on :80", "bad \u{fffd}", "no newline"]
+        );
+
+        let client = mock_client(|_| {
+            json_response(
+                404,
+                &json!({
+                    "kind": "Status",
+                    "apiVersion": "v1",
+                    "metadata": {},
+                    "status": "Failure",
+                    "message": "pods \"web-0\" not found",
+                    "reason": "NotFound",
+                    "code": 404,
+                }),
+            )
+        });
+        let err = client
+            .read_log("default", "web-0", Default::default())
+            .collect()
+            .wait()
+            .unwrap_err();
+        assert!(is_status_reason(&err, StatusReason::NotFound));
+    }
+
+    #[test]
+    fn test_read_log_reader() {
+        let client = mock_client(|req| {
+            assert_eq!(
+                req.uri().path(),
+                "/api/v1/namespaces/default/pods/web-0/log"
+            );
+            hyper::Response::builder()
+                .status(200)
+                .header(CONTENT_TYPE, "text/plain")
+                .body(chunked_body(b"starting\nlistening on :80\n", 5))
+                .unwrap()
+        });
+        let reader = client.read_log_reader("default", "web-0", Default::default());
+        let (_, logs) = tokio::io::read_to_end(reader, vec![]).wait().unwrap();
+        assert_eq!(logs, b"starting\nlistening on :80\n");
+    }
+
+    #[test]
+    fn test_create_dynamic() {
+        let posted = Arc::new(Mutex::new(vec![]));
+        let posted2 = Arc::clone(&posted);
+        let client = mock_client(move |req| {
+            let path = req.uri().path().to_string();
+            if *req.method() == Method::POST {
+                let body = req.into_body().concat2().wait().unwrap();
+                let body: Value = serde_json::from_slice(&body).unwrap();
+                posted2.lock().unwrap().push((path, body.clone()));
+                return json_response(201, &body);
+            }
+            assert_eq!(path, "/api/v1");
+            json_response(
+                200,
+                &json!({
+                    "kind": "APIResourceList",
+                    "groupVersion": "v1",
+                    "resources": [
+                        {"name": "configmaps", "singularName": "", "namespaced": true,
+                         "kind": "ConfigMap", "verbs": ["create", "get"]},
+                        {"name": "namespaces", "singularName": "", "namespaced": false,
+                         "kind": "Namespace", "verbs": ["create", "get"]},
+                        {"name": "namespaces/status", "singularName": "", "namespaced": false,
+                         "kind": "Namespace", "verbs": ["get"]},
+                    ],
+                }),
+            )
+        });
+
+        let cm = json!({
+            "apiVersion": "v1",
+            "kind": "ConfigMap",
+            "metadata": {"name": "app-config", "namespace": "prod"},
+            "data": {"log.level": "debug"},
+        });
+        let created = client.create_dynamic(cm.clone()).wait().unwrap();
+        assert_eq!(created, cm);
+
+        let ns = json!({"apiVersion": "v1", "kind": "Namespace", "metadata": {"name": "prod"}});
+        client.create_dynamic(ns).wait().unwrap();
+
+        let mut cm = cm;
+        cm["metadata"]["namespace"].take();
+        client.create_dynamic(cm).wait().unwrap();
+
+        let paths: Vec<String> = posted.lock().unwrap().iter().map(|p| p.0.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/api/v1/namespaces/prod/configmaps",
+                "/api/v1/namespaces",
+                "/api/v1/namespaces/default/configmaps",
+            ]
+        );
+
+        let unknown = json!({"apiVersion": "v1", "kind": "Widget", "metadata": {"name": "w"}});
+        assert!(client.create_dynamic(unknown).wait().is_err());
+        assert!(client
+            .create_dynamic(json!({"kind": "ConfigMap"}))
+            .wait()
+            .is_err());
+    }
+
+    #[test]
+    fn test_patch_raw() {
+        const YAML: &str = "apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: settings\ndata:\n  colour: blue  # not JSON\n";
+        let client = mock_client(|req| {
+            assert_eq!(*req.method(), Method::PATCH);
+            assert_eq!(
+                req.uri().path(),
+                "/api/v1/namespaces/default/configmaps/settings"
+            );
+            assert_eq!(req.uri().query(), Some("fieldManager=me"));
+            assert_eq!(req.headers()[CONTENT_TYPE], APPLY_PATCH);
+            let body = req.into_body().concat2().wait().unwrap();
+            assert_eq!(&body[..], YAML.as_bytes());
+            json_response(
+                200,
+                &json!({
+                    "apiVersion": "v1",
+                    "kind": "ConfigMap",
+                    "metadata": {"name": "settings", "namespace": "default"},
+                    "data": {"colour": "blue"},
+                }),
+            )
+        });
+        let opts = PatchOptions {
+            field_manager: "me".into(),
+            force: false,
+        };
+        let cm: Value = client
+            .patch_raw(
+                &GROUP_VERSION.with_resource("configmaps"),
+                Some("default"),
+                "settings",
+                APPLY_PATCH,
+                YAML.as_bytes().to_vec(),
+                opts,
+            )
+            .wait()
+            .unwrap();
+        assert_eq!(cm["data"]["colour"], "blue");
+    }
+
+    #[test]
+    fn test_apply_with_takeover() {
+        let queries = Arc::new(Mutex::new(vec![]));
+        let queries2 = Arc::clone(&queries);
+        let client = mock_client(move |req| {
+            assert_eq!(*req.method(), Method::PATCH);
+            assert_eq!(req.uri().path(), "/api/v1/namespaces/default/pods/mypod");
+            assert_eq!(req.headers()[CONTENT_TYPE], APPLY_PATCH);
+            let query = req.uri().query().unwrap_or("").to_string();
+            queries2.lock().unwrap().push(query.clone());
+            if query.contains("force=true") {
+                json_response(
+                    200,
+                    &json!({
+                        "apiVersion": "v1",
+                        "kind": "Pod",
+                        "metadata": {"name": "mypod", "namespace": "default"},
+                    }),
+                )
+            } else {
+                json_response(
+                    409,
+                    &json!({
+                        "kind": "Status",
+                        "apiVersion": "v1",
+                        "metadata": {},
+                        "status": "Failure",
+                        "message": "Apply failed with 1 conflict: conflict with \"kubectl-client-side-apply\": .spec.containers",
+                        "reason": "Conflict",
+                        "code": 409,
+                    }),
+                )
+            }
+        });
+
+        let mut pod = Pod::default();
+        pod.metadata.name = Some("mypod".into());
+        pod.metadata.namespace = Some("default".into());
+        let pods = GROUP_VERSION.with_resource("pods");
+        let applied = client
+            .apply_with_takeover(&pods, &pod, "my-controller")
+            .wait()
+            .unwrap();
+        assert_eq!(applied.metadata.name, Some("mypod".into()));
+        assert_eq!(
+            *queries.lock().unwrap(),
+            vec![
+                "fieldManager=my-controller",
+                "fieldManager=my-controller&force=true",
+            ]
+        );
+
+        // Without takeover, the conflict is returned
+        let opts = PatchOptions {
+            field_manager: "my-controller".into(),
+            ..Default::default()
+        };
+        let err = client.apply(&pods, &pod, opts).wait().unwrap_err();
+        assert!(is_status_reason(&err, StatusReason::Conflict));
+    }
+
+    #[test]
+    fn test_apply_unstructured() {
+        let client = mock_client(|req| {
+            assert_eq!(*req.method(), Method::PATCH);
+            assert_eq!(
+                req.uri().path(),
+                "/api/v1/namespaces/default/configmaps/settings"
+            );
+            assert_eq!(req.uri().query(), Some("fieldManager=my-tool&force=true"));
+            assert_eq!(req.headers()[CONTENT_TYPE], "application/apply-patch+yaml");
+            let body = req.into_body().concat2().wait().unwrap();
+            let obj: Value = serde_json::from_slice(&body).unwrap();
+            json_response(200, &obj)
+        });
+
+        let obj = json!({
+            "apiVersion": "v1",
+            "kind": "ConfigMap",
+            "metadata": {"name": "settings", "namespace": "default"},
+            "data": {"colour": "blue"},
+        });
+        let opts = PatchOptions {
+            field_manager: "my-tool".into(),
+            force: true,
+        };
+        let applied = client
+            .apply(&GROUP_VERSION.with_resource("configmaps"), &obj, opts)
+            .wait()
+            .unwrap();
+        assert_eq!(applied, obj);
+
+        // fieldManager is required
+        assert!(client
+            .apply(
+                &GROUP_VERSION.with_resource("configmaps"),
+                &obj,
+                Default::default()
+            )
+            .wait()
+            .is_err());
+    }
+
+    #[test]
+    fn test_patch_merge_typed() {
+        #[derive(Serialize, Default)]
+        #[serde(rename_all = "camelCase")]
+        struct MetaPatch {
+            labels: Option<BTreeMap<String, String>>,
+            annotations: Option<BTreeMap<String, String>>,
+        }
+        #[derive(Serialize, Default)]
+        #[serde(rename_all = "camelCase")]
+        struct SpecPatch {
+            active_deadline_seconds: Option<i64>,
+            node_name: Option<String>,
+        }
+        #[derive(Serialize, Default)]
+        struct PodPatch {
+            metadata: Option<MetaPatch>,
+            spec: Option<SpecPatch>,
+        }
+
+        let bodies = Arc::new(Mutex::new(vec![]));
+        let bodies2 = Arc::clone(&bodies);
+        let client = mock_client(move |req| {
+            assert_eq!(*req.method(), Method::PATCH);
+            assert_eq!(req.headers()[CONTENT_TYPE], MERGE_PATCH);
+            let body = req.into_body().concat2().wait().unwrap();
+            let patch: Value = serde_json::from_slice(&body).unwrap();
+            bodies2.lock().unwrap().push(patch);
+            json_response(
+                200,
+                &json!({"apiVersion": "v1", "kind": "Pod", "metadata": {"name": "mypod"}}),
+            )
+        });
+
+        let mut labels = BTreeMap::new();
+        labels.insert("tier".to_string(), "frontend".to_string());
+        let patch = PodPatch {
+            metadata: Some(MetaPatch {
+                labels: Some(labels),
+                ..Default::default()
+            }),
+            spec: Some(SpecPatch {
+                active_deadline_seconds: Some(60),
+                ..Default::default()
+            }),
+        };
+        let pod: Pod = client
+            .patch_merge_typed(
+                &GROUP_VERSION.with_resource("pods"),
+                Some("default"),
+                "mypod",
+                &patch,
+            )
+            .wait()
+            .unwrap();
+        assert_eq!(pod.metadata.name, Some("mypod".into()));
+        assert_eq!(
+            *bodies.lock().unwrap(),
+            vec![json!({
+                "metadata": {"labels": {"tier": "frontend"}},
+                "spec": {"activeDeadlineSeconds": 60},
+            })]
+        );
+    }
+
+    #[test]
+    fn test_default_namespace() {
+        let client = mock_client(|_| unreachable!());
+        assert_eq!(client.default_namespace(), "default");
+
+        let mut context: ConfigContext = Default::default();
+        context.cluster.server = "https://192.168.42.147:8443".into();
+        context.default_namespace = Some("team-a".into());
+        let service = MockService(|req: Request<Body>| {
+            assert_eq!(req.uri().path(), "/api/v1/namespaces/team-a/pods");
+            json_response(
+                200,
+                &json!({"kind": "PodList", "apiVersion": "v1", "metadata": {}, "items": []}),
+            )
+        });
+        let client = Client::new_with_client(service, context).unwrap();
+        assert_eq!(client.default_namespace(), "team-a");
+        let pods: Vec<Pod> = client
+            .in_default_namespace()
+            .iter(core_v1::Pods)
+            .collect()
+            .wait()
+            .unwrap();
+        assert!(pods.is_empty());
+    }
+
+    #[test]
+    fn test_new_from_yaml() {
+        let yaml = br#"
+apiVersion: v1
+kind: Config
+clusters:
+- name: prod
+  cluster:
+    server: https://prod.example.com:6443
+    insecure-skip-tls-verify: true
+- name: staging
+  cluster:
+    server: https://staging.example.com:6443
+    insecure-skip-tls-verify: true
+users:
+- name: admin
+  user:
+    token: sometoken
+contexts:
+- name: prod
+  context: {cluster: prod, user: admin, namespace: team-a}
+- name: staging
+  context: {cluster: staging, user: admin}
+current-context: prod
+"#;
+        let client = Client::new_from_yaml(yaml, None).unwrap();
+        assert_eq!(
+            client.config.cluster.server,
+            "https://prod.example.com:6443"
+        );
+        assert_eq!(client.default_namespace(), "team-a");
+
+        let client = Client::new_from_yaml(yaml, Some("staging")).unwrap();
+        assert_eq!(
+            client.config.cluster.server,
+            "https://staging.example.com:6443"
+        );
+        assert_eq!(client.default_namespace(), "default");
+
+        assert!(Client::new_from_yaml(yaml, Some("missing")).is_err());
+        assert!(Client::new_from_yaml(b"clusters: 42", None).is_err());
+    }
+
+    #[test]
+    fn test_server_time_skew() {
+        let client = mock_client(|_| {
+            let date = chrono::Utc::now() + chrono::Duration::minutes(5);
+            hyper::Response::builder()
+                .header(
+                    hyper::header::DATE,
+                    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+                )
+                .body(Body::from("{}"))
+                .unwrap()
+        });
+        assert_eq!(client.server_time_skew(), None);
+        let _: Value = client
+            .get(
+                &GROUP_VERSION.with_resource("pods"),
+                None,
+                "mypod",
+                Default::default(),
+            )
+            .wait()
+            .unwrap();
+        let skew = client.server_time_skew().unwrap();
+        // Date truncates to whole seconds
+        assert!(skew <= chrono::Duration::minutes(5));
+        assert!(skew > chrono::Duration::minutes(5) - chrono::Duration::seconds(2));
+    }
+
+    #[test]
+    fn test_interceptor() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let seen2 = Arc::clone(&seen);
+        let client = mock_client(move |req| {
+            let tenant = req.headers().get("x-tenant-id").cloned();
+            seen2.lock().unwrap().push(tenant);
+            // Good enough as both a get response and a watch line
+            json_response(200, &json!({"type": "ADDED", "object": {"kind": "Pod"}}))
+        })
+        .with_interceptor(|req| {
+            req.headers_mut()
+                .insert("x-tenant-id", HeaderValue::from_static("acme"));
+        });
+
+        let pods = GROUP_VERSION.with_resource("pods");
+        let _: Value = client
+            .get(&pods, Some("default"), "mypod", Default::default())
+            .wait()
+            .unwrap();
+        let events = client
+            .watch_list(&pods, Some("default"), Default::default())
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(events.len(), 1);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(
+            *seen,
+            vec![
+                Some(HeaderValue::from_static("acme")),
+                Some(HeaderValue::from_static("acme")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_watch_object() {
+        let client = mock_client(|req| {
+            assert_eq!(req.uri().path(), "/api/v1/namespaces/default/pods/mypod");
+            let query = req.uri().query().unwrap_or("");
+            if !query.contains("watch=true") {
+                return json_response(
+                    200,
+                    &json!({
+                        "kind": "Pod",
+                        "metadata": {"name": "mypod", "resourceVersion": "10"},
+                    }),
+                );
+            }
+            assert!(query.contains("resourceVersion=10"));
+            let lines = concat!(
+                r#"{"type":"MODIFIED","object":{"kind":"Pod","metadata":{"name":"mypod","resourceVersion":"11"}}}"#,
+                "\n",
+            );
+            hyper::Response::builder()
+                .status(200)
+                .body(chunked_body(lines.as_bytes(), 5))
+                .unwrap()
+        });
+        let events: Vec<WatchEvent> = client
+            .watch_object(
+                &GROUP_VERSION.with_resource("pods"),
+                Some("default"),
+                "mypod",
+                Default::default(),
+            )
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].typ, EventType::Added);
+        assert_eq!(events[0].object["metadata"]["resourceVersion"], "10");
+        assert_eq!(events[1].typ, EventType::Modified);
+        assert_eq!(events[1].object["metadata"]["resourceVersion"], "11");
+    }
+
+    #[test]
+    fn test_watch_response() {
+        let lines = b"{\"type\":\"ADDED\",\"object\":{\"kind\":\"Pod\"}}\n\
+    {\"type\":\"DELETED\",\"object\":{\"kind\":\"Pod\"}}\n";
+        let res = hyper::Response::builder()
+            .status(200)
+            .body(chunked_body(lines, 5))
+            .unwrap();
+        let events: Vec<WatchEvent> = watch_response(res)
+            .map(|(_, events)| events)
+            .flatten_stream()
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].typ, EventType::Added);
+        assert_eq!(events[1].typ, EventType::Deleted);
+    }
+
+    #[test]
+    fn test_watch_error_chunked() {
+        let status = json!({
+            "kind": "Status",
+            "apiVersion": "v1",
+            "metadata": {},
+            "status": "Failure",
+            "message": "too old resource version: 123 (456)",
+            "reason": "Expired",
+            "code": 410,
+        });
+        let body = serde_json::to_vec(&status).unwrap();
+        // No Content-Length, and the Status split over several chunks
+        let res = hyper::Response::builder()
+            .status(410)
+            .header(TRANSFER_ENCODING, "chunked")
+            .body(chunked_body(&body, 7))
+            .unwrap();
+        let err = watch_response::<WatchEvent>(res).wait().err().unwrap();
+        let status = err.downcast::<Status>().unwrap();
+        assert_eq!(status.code, 410);
+        assert_eq!(status.reason, Some(StatusReason::Expired));
+        assert_eq!(status.message, "too old resource version: 123 (456)");
+    }
+
+    #[test]
+    fn test_watch_headers() {
+        let lines = b"{\"type\":\"ADDED\",\"object\":{\"kind\":\"Pod\"}}\n";
+        let res = hyper::Response::builder()
+            .status(200)
+            .header(CONTENT_TYPE, "application/json")
+            .header("X-Aggregated-Thing", "hello")
+            .body(chunked_body(lines, 5))
+            .unwrap();
+        let (headers, events) = watch_response::<WatchEvent>(res).wait().unwrap();
+        assert_eq!(headers[CONTENT_TYPE], "application/json");
+        assert_eq!(headers["x-aggregated-thing"], "hello");
+
+        let events = events.collect().wait().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].typ, EventType::Added);
+    }
+}
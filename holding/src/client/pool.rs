@@ -0,0 +1,128 @@
+use failure::Error;
+use futures::{future, Future, Stream};
+use hyper::Body;
+use std::mem;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A pool of buffers for reading response bodies.
+///
+/// Without a pool, every response body is accumulated into a freshly
+/// allocated buffer (grown, and so reallocated, as chunks arrive).  A
+/// controller issuing many list/get requests can share one pool
+/// between its clients (see `Client::with_buffer_pool`), so buffers
+/// are reused once they have grown to fit typical responses.
+///
+/// Buffers that grew beyond `max_capacity` (eg: for an occasional
+/// large list) are freed rather than kept, so they don't stay
+/// allocated for the life of the pool.
+#[derive(Debug)]
+pub struct BufferPool {
+    free: Mutex<Vec<Vec<u8>>>,
+    max_free: usize,
+    max_capacity: usize,
+    allocated: AtomicUsize,
+}
+
+/// Default for `BufferPool::with_max_capacity`.
+const DEFAULT_MAX_CAPACITY: usize = 1024 * 1024;
+
+impl BufferPool {
+    /// Creates a pool that keeps at most `max_free` idle buffers.
+    pub fn new(max_free: usize) -> Self {
+        BufferPool {
+            free: Mutex::new(Vec::with_capacity(max_free)),
+            max_free: max_free,
+            max_capacity: DEFAULT_MAX_CAPACITY,
+            allocated: AtomicUsize::new(0),
+        }
+    }
+
+    /// Only keeps idle buffers of at most `max_capacity` bytes (1MiB by
+    /// default).
+    pub fn with_max_capacity(mut self, max_capacity: usize) -> Self {
+        self.max_capacity = max_capacity;
+        self
+    }
+
+    /// Number of buffers this pool has had to allocate, because no
+    /// idle buffer was available.
+    pub fn allocated(&self) -> usize {
+        self.allocated.load(Ordering::Relaxed)
+    }
+
+    fn take(&self) -> Vec<u8> {
+        match self.free.lock().unwrap().pop() {
+            Some(buf) => buf,
+            None => {
+                self.allocated.fetch_add(1, Ordering::Relaxed);
+                Vec::new()
+            }
+        }
+    }
+
+    fn put(&self, mut buf: Vec<u8>) {
+        if buf.capacity() > self.max_capacity {
+            return;
+        }
+        buf.clear();
+        let mut free = self.free.lock().unwrap();
+        if free.len() < self.max_free {
+            free.push(buf);
+        }
+    }
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        BufferPool::new(16)
+    }
+}
+
+/// A buffer that is returned to its pool (if any) when dropped.
+#[derive(Debug)]
+pub(super) struct PooledBuf {
+    buf: Vec<u8>,
+    pool: Option<Arc<BufferPool>>,
+}
+
+impl PooledBuf {
+    fn new(pool: Option<Arc<BufferPool>>) -> Self {
+        let buf = pool.as_ref().map(|p| p.take()).unwrap_or_default();
+        PooledBuf { buf, pool }
+    }
+}
+
+impl Deref for PooledBuf {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl AsRef<[u8]> for PooledBuf {
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl Drop for PooledBuf {
+    fn drop(&mut self) {
+        if let Some(ref pool) = self.pool {
+            pool.put(mem::replace(&mut self.buf, Vec::new()));
+        }
+    }
+}
+
+/// Reads all of `body` into a buffer from `pool`.
+pub(super) fn read_body(
+    body: Body,
+    pool: Option<Arc<BufferPool>>,
+) -> impl Future<Item = PooledBuf, Error = Error> + Send {
+    body.fold(PooledBuf::new(pool), |mut buf, chunk| {
+        buf.buf.extend_from_slice(&chunk);
+        future::ok::<_, hyper::Error>(buf)
+    })
+    .from_err()
+}
@@ -0,0 +1,328 @@
+//! Forwarding connections to a pod's ports, over the `portforward`
+//! subresource (`portforward.k8s.io`).
+//!
+//! `Client::portforward` upgrades the request to a websocket, where
+//! each requested port gets two channels: `2*i` carries data and
+//! `2*i + 1` errors, where `i` is the port's index in the request.
+//! The first message the server sends on each channel is the port
+//! number (little-endian `u16`).  The session is returned as a stream
+//! per port; `Demux` decodes the messages on their own.
+
+use super::websocket::{io_error, Channels, WebSocket};
+use failure::Error;
+use futures::{Async, AsyncSink, Poll};
+use std::cmp;
+use std::io::{self, Read, Write};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Channel numbers are a byte, and each port takes two.
+pub const MAX_PORTS: usize = 128;
+
+/// The websocket subprotocol to request when upgrading.
+pub const PROTOCOL: &str = "portforward.k8s.io";
+
+/// The server reported an error on a port's error channel, eg: nothing
+/// is listening on the port in the pod.
+#[derive(Fail, Debug, Clone, PartialEq)]
+#[fail(display = "Port forward to port {} failed: {}", port, message)]
+pub struct PortForwardError {
+    pub port: u16,
+    pub message: String,
+}
+
+/// Decodes messages received from the server for a session forwarding
+/// `ports`.
+#[derive(Debug)]
+pub struct Demux {
+    ports: Vec<u16>,
+    /// Channels we have seen the initial port number message on
+    started: Vec<bool>,
+}
+
+impl Demux {
+    /// Fails for more than `MAX_PORTS` ports.
+    pub fn new(ports: Vec<u16>) -> Result<Self, Error> {
+        if ports.len() > MAX_PORTS {
+            bail!(
+                "Can't forward {} ports at once, at most {}",
+                ports.len(),
+                MAX_PORTS
+            );
+        }
+        let started = vec![false; ports.len() * 2];
+        Ok(Demux { ports, started })
+    }
+
+    /// Decodes one message: data for a port, or `None` for the
+    /// channel's initial port number.  A message on an error channel is
+    /// returned as a `PortForwardError`.
+    pub fn decode(&mut self, msg: &[u8]) -> Result<Option<(u16, Vec<u8>)>, Error> {
+        let (channel, data) = match msg.split_first() {
+            Some((c, d)) => (usize::from(*c), d),
+            None => bail!("Empty port forward message"),
+        };
+        let port = match self.ports.get(channel / 2) {
+            Some(p) => *p,
+            None => bail!("Unexpected port forward channel {}", channel),
+        };
+        if !self.started[channel] {
+            check_initial(channel as u8, port, data)?;
+            self.started[channel] = true;
+            return Ok(None);
+        }
+        if channel % 2 == 1 {
+            Err(PortForwardError {
+                port,
+                message: String::from_utf8_lossy(data).into_owned(),
+            }
+            .into())
+        } else {
+            Ok(Some((port, data.to_vec())))
+        }
+    }
+
+    /// Encodes `data` as a message for `port`.
+    pub fn encode(&self, port: u16, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let i = self
+            .ports
+            .iter()
+            .position(|p| *p == port)
+            .ok_or_else(|| format_err!("Port {} is not being forwarded", port))?;
+        let mut msg = Vec::with_capacity(data.len() + 1);
+        msg.push((i * 2) as u8);
+        msg.extend_from_slice(data);
+        Ok(msg)
+    }
+}
+
+/// Checks the first message on a channel names its port.
+fn check_initial(channel: u8, port: u16, data: &[u8]) -> Result<(), Error> {
+    let initial = match *data {
+        [lo, hi] => Some(u16::from(lo) | u16::from(hi) << 8),
+        _ => None,
+    };
+    if initial != Some(port) {
+        bail!("Bad initial message on port forward channel {}", channel);
+    }
+    Ok(())
+}
+
+/// Splits a session into a stream per port, in the order requested.
+pub(super) fn streams(ws: WebSocket, ports: &[u16]) -> Vec<(u16, PortStream)> {
+    let all: Vec<u8> = (0..ports.len() * 2).map(|c| c as u8).collect();
+    let channels = Channels::new(ws, &all);
+    ports
+        .iter()
+        .enumerate()
+        .map(|(i, port)| {
+            let stream = PortStream {
+                channels: channels.clone(),
+                port: *port,
+                data: (i * 2) as u8,
+                started: [false, false],
+                pending: Vec::new(),
+            };
+            (*port, stream)
+        })
+        .collect()
+}
+
+/// A connection to one forwarded port.
+///
+/// The ports of a session share one connection: whichever stream is
+/// polled reads it, and buffers what arrives for the others.  A message
+/// on the port's error channel fails the next read, with a
+/// `PortForwardError`.  `portforward.k8s.io` has no way to close one
+/// direction, so `shutdown` only flushes.
+#[derive(Debug)]
+pub struct PortStream {
+    channels: Channels,
+    port: u16,
+    /// The data channel; errors are on the next
+    data: u8,
+    /// Whether the initial messages have arrived, on data and error
+    started: [bool; 2],
+    /// The rest of a message that didn't fit the last read
+    pending: Vec<u8>,
+}
+
+impl PortStream {
+    /// The next message on the data (0) or error (1) channel, after
+    /// the initial port number.
+    fn poll_channel(&mut self, i: usize) -> Poll<Option<Vec<u8>>, Error> {
+        let channel = self.data + i as u8;
+        loop {
+            let msg = match self.channels.poll_recv(channel)? {
+                Async::Ready(Some(msg)) => msg,
+                other => return Ok(other),
+            };
+            if self.started[i] {
+                return Ok(Async::Ready(Some(msg)));
+            }
+            check_initial(channel, self.port, &msg)?;
+            self.started[i] = true;
+        }
+    }
+
+    fn poll_read_msg(&mut self) -> Poll<Option<Vec<u8>>, Error> {
+        if let Async::Ready(Some(msg)) = self.poll_channel(1)? {
+            return Err(PortForwardError {
+                port: self.port,
+                message: String::from_utf8_lossy(&msg).into_owned(),
+            }
+            .into());
+        }
+        self.poll_channel(0)
+    }
+}
+
+impl Read for PortStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pending.is_empty() {
+            match self.poll_read_msg() {
+                Ok(Async::Ready(Some(data))) => self.pending = data,
+                Ok(Async::Ready(None)) => return Ok(0),
+                Ok(Async::NotReady) => return Err(io::ErrorKind::WouldBlock.into()),
+                Err(e) => return Err(io_error(e)),
+            }
+        }
+        let n = cmp::min(buf.len(), self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        Ok(n)
+    }
+}
+
+impl AsyncRead for PortStream {}
+
+impl Write for PortStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut msg = Vec::with_capacity(buf.len() + 1);
+        msg.push(self.data);
+        msg.extend_from_slice(buf);
+        match self.channels.start_send(msg) {
+            Ok(AsyncSink::Ready) => Ok(buf.len()),
+            Ok(AsyncSink::NotReady(_)) => Err(io::ErrorKind::WouldBlock.into()),
+            Err(e) => Err(io_error(e)),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.channels.poll_complete() {
+            Ok(Async::Ready(())) => Ok(()),
+            Ok(Async::NotReady) => Err(io::ErrorKind::WouldBlock.into()),
+            Err(e) => Err(io_error(e)),
+        }
+    }
+}
+
+impl AsyncWrite for PortStream {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        self.channels.poll_complete().map_err(io_error)
+    }
+}
+
+impl Drop for PortStream {
+    fn drop(&mut self) {
+        self.channels.close(self.data);
+        self.channels.close(self.data + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{upgrade_client, ws_recv, ws_send};
+    use super::super::websocket::{BINARY, CLOSE};
+    use super::*;
+    use api::core::v1::PodPortForwardOptions;
+    use futures::Future;
+    use tokio::io as tokio_io;
+    use tokio::runtime::current_thread::Runtime;
+
+    #[test]
+    fn demux() {
+        let mut d = Demux::new(vec![8080, 443]).unwrap();
+        // 8080 = 0x1f90, 443 = 0x01bb
+        for init in &[[0, 0x90, 0x1f], [1, 0x90, 0x1f], [2, 0xbb, 0x01]] {
+            assert_eq!(d.decode(init).unwrap(), None);
+        }
+        assert_eq!(
+            d.decode(b"\x00HTTP/1.1 200 OK").unwrap(),
+            Some((8080, b"HTTP/1.1 200 OK".to_vec()))
+        );
+        assert_eq!(
+            d.decode(b"\x02\x16\x03").unwrap(),
+            Some((443, b"\x16\x03".to_vec()))
+        );
+
+        let err = d
+            .decode(&[3, 0xbb, 0x01])
+            .and_then(|_| d.decode(b"\x03connection refused"))
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<PortForwardError>().unwrap(),
+            PortForwardError {
+                port: 443,
+                message: "connection refused".to_string(),
+            }
+        );
+
+        assert!(d.decode(b"\x04").is_err());
+        assert!(Demux::new(vec![80]).unwrap().decode(&[0, 81, 0]).is_err());
+
+        assert_eq!(d.encode(443, b"hi").unwrap(), b"\x02hi");
+        assert!(d.encode(22, b"hi").is_err());
+
+        // The last port's channels are 254 and 255
+        let mut d = Demux::new((1..=128).collect()).unwrap();
+        assert_eq!(d.encode(128, b"").unwrap(), b"\xfe");
+        assert_eq!(d.decode(&[255, 128, 0]).unwrap(), None);
+        assert!(Demux::new((1..=129).collect()).is_err());
+    }
+
+    fn initial(channel: u8, port: u16) -> Vec<u8> {
+        vec![channel, port as u8, (port >> 8) as u8]
+    }
+
+    #[test]
+    fn forward_session() {
+        let client = upgrade_client(|mut conn| {
+            for c in 0..4 {
+                let port = if c < 2 { 8080 } else { 443 };
+                ws_send(&mut conn, BINARY, &initial(c, port));
+            }
+            let mut buf = Vec::new();
+            let req = ws_recv(&mut conn, &mut buf);
+            assert_eq!(req.payload, b"\x00GET / HTTP/1.0\r\n\r\n");
+            ws_send(&mut conn, BINARY, b"\x00HTTP/1.0 200 OK\r\n\r\n");
+            ws_send(&mut conn, BINARY, b"\x03connection refused");
+            ws_send(&mut conn, CLOSE, b"");
+        });
+        let opts = PodPortForwardOptions {
+            ports: vec![8080, 443],
+        };
+
+        let mut rt = Runtime::new().unwrap();
+        let mut streams = rt
+            .block_on(client.portforward("default", "web-0", &opts))
+            .unwrap();
+        let (port, https) = streams.pop().unwrap();
+        assert_eq!(port, 443);
+        let (port, http) = streams.pop().unwrap();
+        assert_eq!(port, 8080);
+
+        let http = tokio_io::write_all(http, b"GET / HTTP/1.0\r\n\r\n")
+            .and_then(|(s, _)| tokio_io::flush(s))
+            .and_then(|s| tokio_io::read_to_end(s, Vec::new()));
+        let (_, res) = rt.block_on(http).unwrap();
+        assert_eq!(res, b"HTTP/1.0 200 OK\r\n\r\n");
+
+        let err = rt
+            .block_on(tokio_io::read_to_end(https, Vec::new()))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Port forward to port 443 failed: connection refused"
+        );
+    }
+}
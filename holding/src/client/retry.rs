@@ -0,0 +1,223 @@
+use super::HttpService;
+use failure::Error;
+use futures::future::{self, Either, Loop};
+use futures::{Future, Stream};
+use hyper::header::RETRY_AFTER;
+use hyper::{self, Body, Request, StatusCode};
+use std::cmp;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::timer::Delay;
+
+/// When to retry requests the server couldn't handle right now.
+///
+/// Retries `503 Service Unavailable`, which the API server returns
+/// while it is shutting down (eg: during a control plane upgrade) and
+/// load balancers return while no server is available, and `429 Too
+/// Many Requests` when the server says when to try again with
+/// `Retry-After`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// Backoff before the first retry, doubling each retry after.
+    pub initial_backoff: Duration,
+    /// Limit on backoff, including any `Retry-After` from the server.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait before retrying, or `None` if `res` shouldn't
+    /// be retried.  `attempt` counts from 0.
+    fn retry_delay(&self, attempt: u32, res: &hyper::Response<Body>) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+        let retry_after = res
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs);
+        match res.status() {
+            StatusCode::SERVICE_UNAVAILABLE => (),
+            StatusCode::TOO_MANY_REQUESTS if retry_after.is_some() => (),
+            _ => return None,
+        }
+        let backoff = retry_after.unwrap_or_else(|| self.backoff(attempt));
+        Some(cmp::min(backoff, self.max_backoff))
+    }
+
+    /// `initial_backoff`, doubled for each `attempt` after the first,
+    /// up to `max_backoff`.
+    pub(super) fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .checked_mul(1 << cmp::min(attempt, 16))
+            .unwrap_or(self.max_backoff);
+        cmp::min(backoff, self.max_backoff)
+    }
+}
+
+/// Sends `req`, retrying according to `policy`.  The request body is
+/// read into memory first, so it can be resent.
+pub(super) fn send_with_retry<C: HttpService>(
+    service: Arc<C>,
+    policy: RetryPolicy,
+    req: Request<Body>,
+) -> Box<dyn Future<Item = hyper::Response<Body>, Error = Error> + Send> {
+    let (parts, body) = req.into_parts();
+    let (method, uri, version, headers) = (parts.method, parts.uri, parts.version, parts.headers);
+    let fut = body.concat2().from_err().and_then(move |body| {
+        let body = body.into_bytes();
+        future::loop_fn(0, move |attempt| {
+            let mut req = Request::new(Body::from(body.clone()));
+            *req.method_mut() = method.clone();
+            *req.uri_mut() = uri.clone();
+            *req.version_mut() = version;
+            *req.headers_mut() = headers.clone();
+            let policy = policy.clone();
+            service
+                .request(req)
+                .and_then(move |res| match policy.retry_delay(attempt, &res) {
+                    Some(delay) => {
+                        debug!("Server responded {}, retrying in {:?}", res.status(), delay);
+                        Either::A(
+                            Delay::new(Instant::now() + delay)
+                                .from_err()
+                                .map(move |_| Loop::Continue(attempt + 1)),
+                        )
+                    }
+                    None => Either::B(future::ok(Loop::Break(res))),
+                })
+        })
+    });
+    Box::new(fut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{json_response, mock_client};
+    use super::*;
+    use api::core::v1::GROUP_VERSION;
+    use serde_json::Value;
+    use std::sync::Mutex;
+    use tokio::runtime::current_thread::Runtime;
+
+    fn shutting_down() -> hyper::Response<Body> {
+        json_response(
+            503,
+            &json!({
+                "kind": "Status",
+                "apiVersion": "v1",
+                "metadata": {},
+                "status": "Failure",
+                "message": "apiserver is shutting down",
+                "reason": "ServiceUnavailable",
+                "code": 503,
+            }),
+        )
+    }
+
+    #[test]
+    fn retry_shutting_down() {
+        let calls = Arc::new(Mutex::new(0));
+        let calls2 = Arc::clone(&calls);
+        let client = mock_client(move |_| {
+            let mut calls = calls2.lock().unwrap();
+            *calls += 1;
+            if *calls == 1 {
+                shutting_down()
+            } else {
+                json_response(200, &json!({"kind": "Pod"}))
+            }
+        })
+        .with_retry(RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        });
+
+        let mut rt = Runtime::new().unwrap();
+        let pod: Value = rt
+            .block_on(client.get(
+                &GROUP_VERSION.with_resource("pods"),
+                None,
+                "mypod",
+                Default::default(),
+            ))
+            .unwrap();
+        assert_eq!(pod["kind"], "Pod");
+        assert_eq!(*calls.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn give_up() {
+        let calls = Arc::new(Mutex::new(0));
+        let calls2 = Arc::clone(&calls);
+        let client = mock_client(move |_| {
+            *calls2.lock().unwrap() += 1;
+            shutting_down()
+        })
+        .with_retry(RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        });
+
+        let mut rt = Runtime::new().unwrap();
+        let err = rt
+            .block_on(client.get::<Value>(
+                &GROUP_VERSION.with_resource("pods"),
+                None,
+                "mypod",
+                Default::default(),
+            ))
+            .unwrap_err();
+        assert!(super::super::is_status_reason(
+            &err,
+            ::api::meta::v1::StatusReason::ServiceUnavailable
+        ));
+        assert_eq!(*calls.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn retry_delay() {
+        let policy = RetryPolicy::default();
+        let res = |status: u16, retry_after: Option<&str>| {
+            let mut b = hyper::Response::builder();
+            b.status(status);
+            if let Some(r) = retry_after {
+                b.header(RETRY_AFTER, r);
+            }
+            b.body(Body::empty()).unwrap()
+        };
+        assert_eq!(
+            policy.retry_delay(0, &res(503, None)),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            policy.retry_delay(2, &res(503, None)),
+            Some(Duration::from_millis(400))
+        );
+        assert_eq!(policy.retry_delay(3, &res(503, None)), None);
+        assert_eq!(
+            policy.retry_delay(0, &res(429, Some("2"))),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            policy.retry_delay(0, &res(503, Some("60"))),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(policy.retry_delay(0, &res(429, None)), None);
+        assert_eq!(policy.retry_delay(0, &res(500, None)), None);
+    }
+}
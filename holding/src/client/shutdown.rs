@@ -0,0 +1,117 @@
+use futures::task::{self, Task};
+use futures::{Async, Future, Poll, Stream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Fail, Debug)]
+#[fail(display = "Client is shutting down")]
+pub struct ShutdownError;
+
+/// Shutdown state shared by all clones of a `Client`.
+#[derive(Debug, Default)]
+pub(super) struct Shutdown {
+    closed: AtomicBool,
+    in_flight: AtomicUsize,
+    // Tasks to wake when `closed` is set, or `in_flight` drops to 0.
+    tasks: Mutex<Vec<Task>>,
+}
+
+impl Shutdown {
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Registers a new request, or fails if shutdown has begun.
+    pub fn begin(this: &Arc<Self>) -> Result<InFlight, ShutdownError> {
+        if this.is_closed() {
+            return Err(ShutdownError);
+        }
+        this.in_flight.fetch_add(1, Ordering::SeqCst);
+        Ok(InFlight(Arc::clone(this)))
+    }
+
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.notify_all();
+    }
+
+    fn register(&self) {
+        let mut tasks = self.tasks.lock().unwrap();
+        if !tasks.iter().any(Task::will_notify_current) {
+            tasks.push(task::current());
+        }
+    }
+
+    fn notify_all(&self) {
+        let tasks: Vec<_> = self.tasks.lock().unwrap().drain(..).collect();
+        for t in tasks {
+            t.notify();
+        }
+    }
+}
+
+/// Held for the lifetime of an outstanding request.
+#[derive(Debug)]
+pub(super) struct InFlight(Arc<Shutdown>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.0.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.notify_all();
+        }
+    }
+}
+
+/// Future returned by `Client::shutdown`.  Resolves once there are
+/// no more requests in flight.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Drained(pub(super) Arc<Shutdown>);
+
+impl Future for Drained {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        // Register before checking, to avoid missing a wakeup.
+        self.0.register();
+        if self.0.in_flight() == 0 {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+/// Ends the wrapped (watch) stream once shutdown begins.
+#[must_use = "streams do nothing unless polled"]
+pub(super) struct UntilShutdown<S> {
+    pub inner: S,
+    pub shutdown: Arc<Shutdown>,
+}
+
+impl<S: Stream> Stream for UntilShutdown<S> {
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        if self.shutdown.is_closed() {
+            return Ok(Async::Ready(None));
+        }
+        match self.inner.poll()? {
+            Async::NotReady => {
+                self.shutdown.register();
+                if self.shutdown.is_closed() {
+                    Ok(Async::Ready(None))
+                } else {
+                    Ok(Async::NotReady)
+                }
+            }
+            ready => Ok(ready),
+        }
+    }
+}
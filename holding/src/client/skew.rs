@@ -0,0 +1,64 @@
+use chrono::{DateTime, Duration, Utc};
+use hyper::header::DATE;
+use hyper::HeaderMap;
+use std::sync::Mutex;
+
+/// The difference between the server's clock and ours, from the
+/// `Date` header of the latest response.
+#[derive(Debug, Default)]
+pub(super) struct ClockSkew {
+    skew: Mutex<Option<Duration>>,
+}
+
+impl ClockSkew {
+    pub fn record(&self, headers: &HeaderMap) {
+        self.record_at(headers, Utc::now());
+    }
+
+    fn record_at(&self, headers: &HeaderMap, now: DateTime<Utc>) {
+        let date = headers
+            .get(DATE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| DateTime::parse_from_rfc2822(v).ok());
+        if let Some(date) = date {
+            *self.skew.lock().unwrap() = Some(date.with_timezone(&Utc) - now);
+        }
+    }
+
+    pub fn get(&self) -> Option<Duration> {
+        *self.skew.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+
+    #[test]
+    fn skew_from_date() {
+        let skew = ClockSkew::default();
+        assert_eq!(skew.get(), None);
+
+        let now = "2018-02-20T18:00:00Z".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            DATE,
+            HeaderValue::from_static("Tue, 20 Feb 2018 18:01:30 GMT"),
+        );
+        skew.record_at(&headers, now);
+        assert_eq!(skew.get(), Some(Duration::seconds(90)));
+
+        headers.insert(
+            DATE,
+            HeaderValue::from_static("Tue, 20 Feb 2018 17:59:55 GMT"),
+        );
+        skew.record_at(&headers, now);
+        assert_eq!(skew.get(), Some(Duration::seconds(-5)));
+
+        // Unparseable dates are ignored
+        headers.insert(DATE, HeaderValue::from_static("yesterday"));
+        skew.record_at(&headers, now);
+        assert_eq!(skew.get(), Some(Duration::seconds(-5)));
+    }
+}
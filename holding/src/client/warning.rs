@@ -0,0 +1,160 @@
+use hyper::header::WARNING;
+use hyper::HeaderMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Mutex;
+
+/// Calls a function for each distinct `Warning` header the server
+/// sends, eg: for use of a deprecated API version.
+pub(super) struct WarningHandler {
+    f: Box<dyn Fn(&str) + Send + Sync>,
+    seen: Mutex<HashSet<String>>,
+}
+
+impl fmt::Debug for WarningHandler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("WarningHandler")
+    }
+}
+
+impl WarningHandler {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        WarningHandler {
+            f: Box::new(f),
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn handle(&self, headers: &HeaderMap) {
+        for value in headers.get_all(WARNING) {
+            let value = match value.to_str() {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+            for text in parse_warnings(value) {
+                // Like kubectl, only report each warning once
+                let new = self.seen.lock().unwrap().insert(text.clone());
+                if new {
+                    (self.f)(&text);
+                }
+            }
+        }
+    }
+}
+
+/// Parses the text out of a `Warning` header value, which may hold a
+/// comma-separated list of `code agent "text" ["date"]`.  The API
+/// server always uses code 299 and agent `-`.
+fn parse_warnings(value: &str) -> Vec<String> {
+    let mut ret = vec![];
+    let mut rest = value;
+    loop {
+        // Skip code and agent
+        let quote = match rest.find('"') {
+            Some(i) => i,
+            None => break,
+        };
+        let mut text = String::new();
+        let mut chars = rest[quote + 1..].char_indices();
+        let mut end = None;
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    if let Some((_, c)) = chars.next() {
+                        text.push(c);
+                    }
+                }
+                '"' => {
+                    end = Some(quote + 1 + i + 1);
+                    break;
+                }
+                c => text.push(c),
+            }
+        }
+        let end = match end {
+            Some(e) => e,
+            None => break, // Unterminated
+        };
+        ret.push(text);
+
+        // Skip optional date, up to the next warning
+        rest = &rest[end..];
+        let mut in_quote = false;
+        let next = rest.char_indices().find(|&(_, c)| {
+            if c == '"' {
+                in_quote = !in_quote;
+            }
+            c == ',' && !in_quote
+        });
+        match next {
+            Some((i, _)) => rest = &rest[i + 1..],
+            None => break,
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{json_response, mock_client};
+    use super::*;
+    use api::core::v1::GROUP_VERSION;
+    use futures::Future;
+    use serde_json::Value;
+    use std::sync::Arc;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            parse_warnings(r#"299 - "extensions/v1beta1 Ingress is deprecated""#),
+            vec!["extensions/v1beta1 Ingress is deprecated"]
+        );
+        assert_eq!(
+            parse_warnings(
+                r#"299 - "one, with comma", 299 - "two \"quoted\"" "Sat, 25 Aug 2012 23:34:45 GMT", 299 - "three""#
+            ),
+            vec!["one, with comma", r#"two "quoted""#, "three"]
+        );
+        assert_eq!(parse_warnings("299 -"), Vec::<String>::new());
+        assert_eq!(
+            parse_warnings(r#"299 - "unterminated"#),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn dedup() {
+        let warnings = Arc::new(Mutex::new(vec![]));
+        let warnings2 = Arc::clone(&warnings);
+        let client = mock_client(|req| {
+            let mut res = json_response(200, &json!({}));
+            let headers = res.headers_mut();
+            headers.append(
+                WARNING,
+                "299 - \"batch/v1beta1 CronJob is deprecated\""
+                    .parse()
+                    .unwrap(),
+            );
+            if req.uri().path().ends_with("other") {
+                headers.append(WARNING, "299 - \"something else\"".parse().unwrap());
+            }
+            res
+        })
+        .with_warning_handler(move |w| warnings2.lock().unwrap().push(w.to_string()));
+
+        let gvr = GROUP_VERSION.with_resource("pods");
+        for name in &["a", "b", "other", "c"] {
+            let _: Value = client
+                .get(&gvr, None, name, Default::default())
+                .wait()
+                .unwrap();
+        }
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            vec!["batch/v1beta1 CronJob is deprecated", "something else"]
+        );
+    }
+}
@@ -0,0 +1,1276 @@
+use super::{
+    do_request, do_watch, hyper_uri, Client, HttpService, HttpStatusError, RetryPolicy, Transport,
+};
+use api::meta::v1::{EventType, ListOptions, Status, StatusReason, WatchEvent};
+use api::meta::GroupVersionResource;
+use failure::Error;
+use futures::{future, Async, Future, Poll, Stream};
+use hyper::{Body, Method, Request};
+use serde_json::{self, Value};
+use serde_urlencoded;
+use std::collections::{HashMap, VecDeque};
+use std::mem;
+use std::time::{Duration, Instant};
+use tokio::timer::Delay;
+use url::Url;
+
+impl<C: HttpService> Client<C> {
+    /// Like `watch_list`, but when the server ends the watch cleanly
+    /// (as it does once `opts.timeout_seconds` has passed, or after
+    /// its own default timeout) a new watch is started immediately,
+    /// from the last resourceVersion seen.  Errors, including `ERROR`
+    /// events, still end the stream.
+    ///
+    /// A watch that ends without any events is renewed after a
+    /// backoff (as set by `RetryPolicy::default()`), growing while
+    /// watches keep ending empty, so a server that closes watches
+    /// straight away isn't hammered.  That backoff needs a tokio timer
+    /// (ie: runs within a tokio runtime).
+    pub fn watch_list_renewing(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        mut opts: ListOptions,
+    ) -> impl Stream<Item = WatchEvent, Error = Error> + Send {
+        opts.watch = true;
+        let transport = self.transport.clone();
+        future::result(self.url(gvr, namespace, None, ()))
+            .map(move |url| RenewingWatch {
+                transport,
+                url,
+                opts,
+                current: None,
+                saw_event: false,
+                empty_closes: 0,
+                delay: None,
+                done: false,
+            })
+            .flatten_stream()
+    }
+
+    /// Like `watch_list_renewing`, but also survives dropped
+    /// connections and server errors: the watch is restarted from the
+    /// last resourceVersion seen, after a backoff set by `policy`.
+    /// Only `policy.max_retries` consecutive failures are tolerated;
+    /// any event seen resets the count.  A watch the server closes
+    /// cleanly is renewed straight away if it delivered any events,
+    /// else after a backoff that grows while watches keep ending
+    /// empty; either way it is not a failure, as the server ends idle
+    /// watches after a timeout.  Client errors (eg: `403 Forbidden`)
+    /// end the stream immediately.
+    ///
+    /// If the server no longer has the resourceVersion (`410 Gone`,
+    /// or an `Expired` `ERROR` event), events may have been missed, so
+    /// the objects are listed afresh and compared with those seen so
+    /// far (by UID, where objects have one): missing objects are
+    /// emitted as `Deleted` (with their last known state), then new
+    /// ones as `Added` and changed ones (by resourceVersion) as
+    /// `Modified`, before watching from the version of the list.  So an
+    /// object deleted and recreated with the same name is reported as
+    /// deleted and then added.  This means the latest state of every object is
+    /// kept in memory.  Starting from a resourceVersion rather than
+    /// from scratch, objects not seen since are reported as `Added`
+    /// and deletions of them are missed.
+    ///
+    /// Requires a tokio timer (ie: runs within a tokio runtime).
+    pub fn watch_list_resilient(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        mut opts: ListOptions,
+        policy: RetryPolicy,
+    ) -> impl Stream<Item = WatchEvent, Error = Error> + Send {
+        opts.watch = true;
+        let transport = self.transport.clone();
+        future::result(self.url(gvr, namespace, None, ()))
+            .map(move |url| ResilientWatch {
+                transport,
+                url,
+                opts,
+                policy,
+                failures: 0,
+                saw_event: false,
+                empty_closes: 0,
+                listed: VecDeque::new(),
+                known: HashMap::new(),
+                state: State::Idle,
+            })
+            .flatten_stream()
+    }
+}
+
+#[must_use = "streams do nothing unless polled"]
+struct RenewingWatch<C> {
+    transport: Transport<C>,
+    url: Url,
+    opts: ListOptions,
+    current: Option<Box<dyn Stream<Item = WatchEvent, Error = Error> + Send>>,
+    // Whether the current watch has delivered anything
+    saw_event: bool,
+    // Consecutive watches that ended without an event
+    empty_closes: u32,
+    // Backoff before renewing after an empty watch
+    delay: Option<Delay>,
+    done: bool,
+}
+
+impl<C: HttpService> RenewingWatch<C> {
+    fn start(&mut self) -> Box<dyn Stream<Item = WatchEvent, Error = Error> + Send> {
+        debug!(
+            "Starting watch from resourceVersion {:?}",
+            self.opts.resource_version
+        );
+        self.saw_event = false;
+        let mut url = self.url.clone();
+        let req = serde_urlencoded::to_string(&self.opts)
+            .map_err(Error::from)
+            .and_then(|query| {
+                url.set_query(Some(&query));
+                Request::builder()
+                    .method(Method::GET)
+                    .uri(hyper_uri(url))
+                    .body(Body::empty())
+                    .map_err(Error::from)
+            });
+        Box::new(
+            do_watch(&self.transport, req)
+                .map(|(_, events)| events)
+                .flatten_stream(),
+        )
+    }
+}
+
+impl<C: HttpService> Stream for RenewingWatch<C> {
+    type Item = WatchEvent;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            if self.current.is_none() {
+                if self.done || self.transport.shutdown.is_closed() {
+                    return Ok(Async::Ready(None));
+                }
+                if let Some(mut delay) = self.delay.take() {
+                    if delay.poll()?.is_not_ready() {
+                        self.delay = Some(delay);
+                        return Ok(Async::NotReady);
+                    }
+                }
+                self.current = Some(self.start());
+            }
+
+            match self.current.as_mut().unwrap().poll() {
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+
+                Ok(Async::Ready(Some(event))) => {
+                    if event.typ == EventType::Error {
+                        // Let the caller see the error Status, then stop
+                        self.done = true;
+                    } else if let Some(rv) = event.object["metadata"]["resourceVersion"].as_str() {
+                        self.opts.resource_version = rv.into();
+                    }
+                    self.saw_event = true;
+                    self.empty_closes = 0;
+                    return Ok(Async::Ready(Some(event)));
+                }
+
+                Ok(Async::Ready(None)) => {
+                    // Clean close, ie: server timeout.  Renew.
+                    self.current = None;
+                    if !self.saw_event {
+                        let delay = RetryPolicy::default().backoff(self.empty_closes);
+                        debug!("Watch closed without any events, renewing in {:?}", delay);
+                        self.empty_closes = self.empty_closes.saturating_add(1);
+                        self.delay = Some(Delay::new(Instant::now() + delay));
+                    }
+                }
+
+                Err(e) => {
+                    self.done = true;
+                    self.current = None;
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+enum State {
+    /// Ready to start the next watch
+    Idle,
+    /// Backing off, then relisting if `true`
+    Waiting(Delay, bool),
+    Listing(Box<dyn Future<Item = Value, Error = Error> + Send>),
+    Watching(Box<dyn Stream<Item = WatchEvent, Error = Error> + Send>),
+    Done,
+}
+
+#[must_use = "streams do nothing unless polled"]
+struct ResilientWatch<C> {
+    transport: Transport<C>,
+    url: Url,
+    opts: ListOptions,
+    policy: RetryPolicy,
+    // Consecutive failures, without an event
+    failures: u32,
+    // Whether the current watch has delivered anything
+    saw_event: bool,
+    // Consecutive watches that ended without an event
+    empty_closes: u32,
+    listed: VecDeque<WatchEvent>,
+    // Latest state of each object seen, to diff against a relist
+    known: HashMap<ObjectKey, Value>,
+    state: State,
+}
+
+/// True if `status` means the requested resourceVersion is too old.
+fn is_expired(status: &Status) -> bool {
+    status.code == 410
+        || status.reason == Some(StatusReason::Expired)
+        || status.reason == Some(StatusReason::Gone)
+}
+
+/// True if `err` might go away by itself, eg: a dropped connection or
+/// an API server restart, rather than needing the request changed.
+fn is_recoverable(err: &Error) -> bool {
+    let retry = |code: u16| code >= 500 || code == 429;
+    if let Some(s) = err.downcast_ref::<Status>() {
+        retry(s.code as u16)
+    } else if let Some(e) = err.downcast_ref::<HttpStatusError>() {
+        retry(e.status.as_u16())
+    } else {
+        true
+    }
+}
+
+impl<C: HttpService> ResilientWatch<C> {
+    fn request(&self, opts: &ListOptions) -> Result<Request<Body>, Error> {
+        let mut url = self.url.clone();
+        url.set_query(Some(&serde_urlencoded::to_string(opts)?));
+        Ok(Request::builder()
+            .method(Method::GET)
+            .uri(hyper_uri(url))
+            .body(Body::empty())?)
+    }
+
+    fn watch(&mut self) -> State {
+        debug!(
+            "Starting watch from resourceVersion {:?}",
+            self.opts.resource_version
+        );
+        self.saw_event = false;
+        let req = self.request(&self.opts);
+        State::Watching(Box::new(
+            do_watch(&self.transport, req)
+                .map(|(_, events)| events)
+                .flatten_stream(),
+        ))
+    }
+
+    fn list(&self) -> State {
+        debug!("Listing afresh");
+        let opts = ListOptions {
+            watch: false,
+            allow_watch_bookmarks: false,
+            resource_version: Default::default(),
+            timeout_seconds: 0,
+            ..self.opts.clone()
+        };
+        let req = self.request(&opts);
+        State::Listing(Box::new(do_request(self.transport.clone(), req)))
+    }
+
+    /// Queues the events that turn `known` into `items`.  Deletions
+    /// go first, so a recreated object's old and new versions don't
+    /// overlap.
+    fn resync(&mut self, items: Vec<Value>) {
+        let mut known = mem::replace(&mut self.known, HashMap::new());
+        let mut changed = vec![];
+        for object in items {
+            let event = WatchEvent {
+                typ: EventType::Added,
+                object,
+            };
+            let key = match object_key(&event) {
+                Some(k) => k,
+                None => continue,
+            };
+            let typ = match known.remove(&key) {
+                None => EventType::Added,
+                Some(ref old)
+                    if old["metadata"]["resourceVersion"]
+                        == event.object["metadata"]["resourceVersion"] =>
+                {
+                    self.known.insert(key, event.object);
+                    continue;
+                }
+                Some(_) => EventType::Modified,
+            };
+            self.known.insert(key, event.object.clone());
+            changed.push(WatchEvent { typ, ..event });
+        }
+        let mut gone: Vec<_> = known.into_iter().map(|(_, object)| object).collect();
+        gone.sort_by_key(|o| {
+            let meta = &o["metadata"];
+            (
+                meta["namespace"].as_str().unwrap_or("").to_string(),
+                meta["name"].as_str().unwrap_or("").to_string(),
+            )
+        });
+        self.listed
+            .extend(gone.into_iter().map(|object| WatchEvent {
+                typ: EventType::Deleted,
+                object,
+            }));
+        self.listed.extend(changed);
+    }
+
+    fn remember(&mut self, event: &WatchEvent) {
+        if let Some(key) = object_key(event) {
+            if event.typ == EventType::Deleted {
+                self.known.remove(&key);
+            } else {
+                self.known.insert(key, event.object.clone());
+            }
+        }
+    }
+
+    /// Waits before trying again, or gives up with `err`.
+    fn retry(&mut self, err: Error, relist: bool) -> Result<State, Error> {
+        if self.failures >= self.policy.max_retries {
+            return Err(err);
+        }
+        let delay = self.policy.backoff(self.failures);
+        debug!("Watch failed ({}), retrying in {:?}", err, delay);
+        self.failures += 1;
+        Ok(State::Waiting(Delay::new(Instant::now() + delay), relist))
+    }
+}
+
+impl<C: HttpService> Stream for ResilientWatch<C> {
+    type Item = WatchEvent;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            if let Some(event) = self.listed.pop_front() {
+                return Ok(Async::Ready(Some(event)));
+            }
+            self.state = match mem::replace(&mut self.state, State::Done) {
+                State::Done => return Ok(Async::Ready(None)),
+
+                State::Idle => {
+                    if self.transport.shutdown.is_closed() {
+                        return Ok(Async::Ready(None));
+                    }
+                    self.watch()
+                }
+
+                State::Waiting(mut delay, relist) => match delay.poll()? {
+                    Async::NotReady => {
+                        self.state = State::Waiting(delay, relist);
+                        return Ok(Async::NotReady);
+                    }
+                    Async::Ready(()) if relist => self.list(),
+                    Async::Ready(()) => State::Idle,
+                },
+
+                State::Listing(mut f) => match f.poll() {
+                    Ok(Async::NotReady) => {
+                        self.state = State::Listing(f);
+                        return Ok(Async::NotReady);
+                    }
+                    Ok(Async::Ready(mut list)) => {
+                        if let Some(rv) = list["metadata"]["resourceVersion"].as_str() {
+                            self.opts.resource_version = rv.into();
+                        }
+                        let items = match list["items"].take() {
+                            Value::Array(items) => items,
+                            _ => vec![],
+                        };
+                        self.resync(items);
+                        self.failures = 0;
+                        State::Idle
+                    }
+                    Err(e) => {
+                        if !is_recoverable(&e) {
+                            return Err(e);
+                        }
+                        self.retry(e, true)?
+                    }
+                },
+
+                State::Watching(mut events) => match events.poll() {
+                    Ok(Async::NotReady) => {
+                        self.state = State::Watching(events);
+                        return Ok(Async::NotReady);
+                    }
+                    Ok(Async::Ready(Some(event))) => {
+                        if event.typ == EventType::Error {
+                            let status = serde_json::from_value::<Status>(event.object.clone());
+                            match status {
+                                Ok(ref s) if is_expired(s) => {
+                                    debug!("Watch expired, relisting");
+                                    self.list()
+                                }
+                                // Let the caller see the error Status, then stop
+                                _ => return Ok(Async::Ready(Some(event))),
+                            }
+                        } else {
+                            if let Some(rv) = event.object["metadata"]["resourceVersion"].as_str() {
+                                self.opts.resource_version = rv.into();
+                            }
+                            self.remember(&event);
+                            self.failures = 0;
+                            self.empty_closes = 0;
+                            self.saw_event = true;
+                            self.state = State::Watching(events);
+                            return Ok(Async::Ready(Some(event)));
+                        }
+                    }
+                    Ok(Async::Ready(None)) if self.saw_event => State::Idle,
+                    // The server ends idle watches after a timeout too,
+                    // so this isn't a failure, but don't renew in a
+                    // tight loop if it keeps doing so straight away
+                    Ok(Async::Ready(None)) => {
+                        let delay = self.policy.backoff(self.empty_closes);
+                        debug!("Watch closed without any events, renewing in {:?}", delay);
+                        self.empty_closes = self.empty_closes.saturating_add(1);
+                        State::Waiting(Delay::new(Instant::now() + delay), false)
+                    }
+                    Err(ref e) if e.downcast_ref().map(is_expired).unwrap_or(false) => {
+                        debug!("Watch expired, relisting");
+                        self.list()
+                    }
+                    Err(e) => {
+                        if !is_recoverable(&e) {
+                            return Err(e);
+                        }
+                        self.retry(e, false)?
+                    }
+                },
+            };
+        }
+    }
+}
+
+/// Coalesces rapid changes to the same object in a watch stream.
+///
+/// Each event is held for up to `window` after the first pending
+/// event for its object (by UID, else namespace and name) arrived.  Later events
+/// for the same object replace it, so only the latest state is
+/// emitted.  `Deleted` events are never dropped: a deletion replaces
+/// any pending `Added`/`Modified`, and an event following a pending
+/// deletion releases the deletion immediately.  An `Added` followed by
+/// `Modified` is emitted as `Added` with the latest object.  `ERROR`
+/// events and objects without a name are passed through immediately.
+///
+/// Requires a tokio timer (ie: runs within a tokio runtime).
+pub fn coalesce_latest<S>(events: S, window: Duration) -> CoalesceLatest<S>
+where
+    S: Stream<Item = WatchEvent, Error = Error>,
+{
+    CoalesceLatest {
+        inner: events,
+        inner_done: false,
+        window,
+        ready: VecDeque::new(),
+        order: VecDeque::new(),
+        pending: HashMap::new(),
+        delay: None,
+    }
+}
+
+/// Stream returned by `coalesce_latest`.
+#[must_use = "streams do nothing unless polled"]
+pub struct CoalesceLatest<S> {
+    inner: S,
+    inner_done: bool,
+    window: Duration,
+    // Events to emit without further delay.
+    ready: VecDeque<WatchEvent>,
+    // Pending keys, in deadline order.
+    order: VecDeque<(ObjectKey, Instant)>,
+    pending: HashMap<ObjectKey, WatchEvent>,
+    delay: Option<Delay>,
+}
+
+/// Identifies an object across events: by UID where it has one, so
+/// that an object deleted and recreated with the same name is a
+/// different object, else by namespace and name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ObjectKey {
+    Uid(String),
+    Name(String, String),
+}
+
+fn object_key(event: &WatchEvent) -> Option<ObjectKey> {
+    let meta = &event.object["metadata"];
+    if let Some(uid) = meta["uid"].as_str() {
+        return Some(ObjectKey::Uid(uid.to_string()));
+    }
+    meta["name"].as_str().map(|name| {
+        ObjectKey::Name(
+            meta["namespace"].as_str().unwrap_or("").to_string(),
+            name.to_string(),
+        )
+    })
+}
+
+impl<S> CoalesceLatest<S> {
+    fn push(&mut self, event: WatchEvent) {
+        let key = match object_key(&event) {
+            Some(ref k) if event.typ != EventType::Error => k.clone(),
+            _ => {
+                self.ready.push_back(event);
+                return;
+            }
+        };
+
+        let release = match self.pending.get_mut(&key) {
+            None => false,
+            Some(ref p) if p.typ == EventType::Deleted => true,
+            Some(p) => {
+                if !(p.typ == EventType::Added && event.typ == EventType::Modified) {
+                    p.typ = event.typ;
+                }
+                p.object = event.object;
+                return;
+            }
+        };
+        if release {
+            // Don't merge across a deletion
+            self.order.retain(|(k, _)| *k != key);
+            let deleted = self.pending.remove(&key).unwrap();
+            self.ready.push_back(deleted);
+        }
+
+        self.order
+            .push_back((key.clone(), Instant::now() + self.window));
+        self.pending.insert(key, event);
+    }
+
+    fn pop_pending(&mut self) -> Option<WatchEvent> {
+        self.order
+            .pop_front()
+            .map(|(key, _)| self.pending.remove(&key).unwrap())
+    }
+}
+
+impl<S> Stream for CoalesceLatest<S>
+where
+    S: Stream<Item = WatchEvent, Error = Error>,
+{
+    type Item = WatchEvent;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        while !self.inner_done {
+            match self.inner.poll()? {
+                Async::Ready(Some(event)) => self.push(event),
+                Async::Ready(None) => self.inner_done = true,
+                Async::NotReady => break,
+            }
+        }
+
+        if let Some(event) = self.ready.pop_front() {
+            return Ok(Async::Ready(Some(event)));
+        }
+
+        if self.inner_done {
+            // Nothing more to wait for
+            return Ok(Async::Ready(self.pop_pending()));
+        }
+
+        loop {
+            let deadline = match self.order.front() {
+                Some(&(_, deadline)) => deadline,
+                None => return Ok(Async::NotReady),
+            };
+            if deadline <= Instant::now() {
+                return Ok(Async::Ready(self.pop_pending()));
+            }
+            match self.delay {
+                Some(ref mut d) if d.deadline() == deadline => (),
+                _ => self.delay = Some(Delay::new(deadline)),
+            }
+            match self.delay.as_mut().unwrap().poll()? {
+                Async::Ready(()) => continue,
+                Async::NotReady => return Ok(Async::NotReady),
+            }
+        }
+    }
+}
+
+/// Drops `Modified` events that change nothing but bookkeeping
+/// metadata (`resourceVersion`, `managedFields` and `generation`),
+/// eg: other controllers taking ownership of fields.  Changes to
+/// `status`, `spec` or the rest of `metadata` pass through.  This cuts
+/// reconciles for controllers that react to status.
+///
+/// The previous state is remembered per object (by UID, else
+/// namespace and name), so the first event seen for each object always
+/// passes, even if it reuses an earlier object's name.
+/// `Added`, `Deleted` and `ERROR` events are always passed through.
+pub fn only_status_changed<S>(events: S) -> OnlyStatusChanged<S>
+where
+    S: Stream<Item = WatchEvent, Error = Error>,
+{
+    OnlyStatusChanged {
+        inner: events,
+        last: HashMap::new(),
+    }
+}
+
+/// Stream returned by `only_status_changed`.
+#[must_use = "streams do nothing unless polled"]
+pub struct OnlyStatusChanged<S> {
+    inner: S,
+    // Last state seen for each object, without bookkeeping metadata
+    last: HashMap<ObjectKey, Value>,
+}
+
+impl<S> OnlyStatusChanged<S> {
+    fn wanted(&mut self, event: &WatchEvent) -> bool {
+        let key = match object_key(event) {
+            Some(k) => k,
+            None => return true,
+        };
+        let mut state = event.object.clone();
+        if let Some(meta) = state["metadata"].as_object_mut() {
+            meta.remove("resourceVersion");
+            meta.remove("managedFields");
+            meta.remove("generation");
+        }
+        match event.typ {
+            EventType::Error | EventType::Bookmark => true,
+            EventType::Deleted => {
+                self.last.remove(&key);
+                true
+            }
+            EventType::Added => {
+                self.last.insert(key, state);
+                true
+            }
+            EventType::Modified => self.last.insert(key, state.clone()) != Some(state),
+        }
+    }
+}
+
+impl<S> Stream for OnlyStatusChanged<S>
+where
+    S: Stream<Item = WatchEvent, Error = Error>,
+{
+    type Item = WatchEvent;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            match self.inner.poll()? {
+                Async::Ready(Some(event)) => {
+                    if self.wanted(&event) {
+                        return Ok(Async::Ready(Some(event)));
+                    }
+                }
+                Async::Ready(None) => return Ok(Async::Ready(None)),
+                Async::NotReady => return Ok(Async::NotReady),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{json_response, mock_client};
+    use super::*;
+    use api::core::v1::GROUP_VERSION;
+    use futures::stream;
+    use hyper;
+    use std::sync::{Arc, Mutex};
+    use tokio::runtime::current_thread::Runtime;
+
+    fn watch_line(typ: &str, rv: usize) -> String {
+        format!(
+            "{{\"type\":\"{}\",\"object\":{{\"metadata\":{{\"resourceVersion\":\"{}\"}}}}}}\n",
+            typ, rv
+        )
+    }
+
+    #[test]
+    fn renew_after_timeout() {
+        let queries = Arc::new(Mutex::new(vec![]));
+        let queries2 = Arc::clone(&queries);
+        let client = mock_client(move |req| {
+            let mut queries = queries2.lock().unwrap();
+            queries.push(req.uri().query().unwrap_or("").to_string());
+            // Each watch delivers one event and then ends cleanly, as
+            // if timeoutSeconds had passed.
+            let line = watch_line("MODIFIED", 10 + queries.len());
+            hyper::Response::new(Body::from(line))
+        });
+
+        let opts = ListOptions {
+            resource_version: "10".into(),
+            timeout_seconds: 30,
+            ..Default::default()
+        };
+        let events = client
+            .watch_list_renewing(&GROUP_VERSION.with_resource("pods"), None, opts)
+            .take(3)
+            .collect()
+            .wait()
+            .unwrap();
+        let rvs: Vec<_> = events
+            .iter()
+            .map(|e| e.object["metadata"]["resourceVersion"].as_str().unwrap())
+            .collect();
+        assert_eq!(rvs, vec!["11", "12", "13"]);
+
+        assert_eq!(
+            *queries.lock().unwrap(),
+            vec![
+                "resourceVersion=10&timeoutSeconds=30&watch=true",
+                "resourceVersion=11&timeoutSeconds=30&watch=true",
+                "resourceVersion=12&timeoutSeconds=30&watch=true",
+            ]
+        );
+    }
+
+    fn status(code: u16, reason: &str) -> Value {
+        json!({
+            "kind": "Status",
+            "apiVersion": "v1",
+            "metadata": {},
+            "status": "Failure",
+            "reason": reason,
+            "code": code,
+        })
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn resilient_reconnect() {
+        let queries = Arc::new(Mutex::new(vec![]));
+        let queries2 = Arc::clone(&queries);
+        let client = mock_client(move |req| {
+            let mut queries = queries2.lock().unwrap();
+            queries.push(req.uri().query().unwrap_or("").to_string());
+            match queries.len() {
+                // Closed cleanly before any events: renewed after a backoff
+                1 => hyper::Response::new(Body::empty()),
+                2 => json_response(503, &status(503, "ServiceUnavailable")),
+                3 => hyper::Response::new(Body::from(
+                    watch_line("ADDED", 11) + &watch_line("MODIFIED", 12),
+                )),
+                _ => hyper::Response::new(Body::from(watch_line("MODIFIED", 13))),
+            }
+        });
+
+        let opts = ListOptions {
+            resource_version: "10".into(),
+            ..Default::default()
+        };
+        let events = client
+            .watch_list_resilient(&GROUP_VERSION.with_resource("pods"), None, opts, policy())
+            .take(3)
+            .collect();
+        let events = Runtime::new().unwrap().block_on(events).unwrap();
+        let rvs: Vec<_> = events
+            .iter()
+            .map(|e| e.object["metadata"]["resourceVersion"].as_str().unwrap())
+            .collect();
+        assert_eq!(rvs, vec!["11", "12", "13"]);
+        assert_eq!(
+            *queries.lock().unwrap(),
+            vec![
+                "resourceVersion=10&watch=true",
+                "resourceVersion=10&watch=true",
+                "resourceVersion=10&watch=true",
+                "resourceVersion=12&watch=true",
+            ]
+        );
+    }
+
+    #[test]
+    fn renew_after_empty_close() {
+        let queries = Arc::new(Mutex::new(0));
+        let queries2 = Arc::clone(&queries);
+        let client = mock_client(move |_| {
+            let mut queries = queries2.lock().unwrap();
+            *queries += 1;
+            match *queries {
+                1..=2 => hyper::Response::new(Body::empty()),
+                _ => hyper::Response::new(Body::from(watch_line("MODIFIED", 11))),
+            }
+        });
+
+        let start = Instant::now();
+        let events = client
+            .watch_list_renewing(
+                &GROUP_VERSION.with_resource("pods"),
+                None,
+                Default::default(),
+            )
+            .take(1)
+            .collect();
+        let events = Runtime::new().unwrap().block_on(events).unwrap();
+        assert_eq!(events[0].object["metadata"]["resourceVersion"], "11");
+        assert_eq!(*queries.lock().unwrap(), 3);
+        // Backed off 100ms, then 200ms
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[test]
+    fn resilient_empty_closes() {
+        // More clean closes in a row than policy() allows failures:
+        // backed off, but not given up on
+        let queries = Arc::new(Mutex::new(0));
+        let queries2 = Arc::clone(&queries);
+        let client = mock_client(move |_| {
+            let mut queries = queries2.lock().unwrap();
+            *queries += 1;
+            match *queries {
+                1..=5 => hyper::Response::new(Body::empty()),
+                _ => hyper::Response::new(Body::from(watch_line("MODIFIED", 11))),
+            }
+        });
+
+        let opts = ListOptions {
+            resource_version: "10".into(),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let events = client
+            .watch_list_resilient(&GROUP_VERSION.with_resource("pods"), None, opts, policy())
+            .take(1)
+            .collect();
+        let events = Runtime::new().unwrap().block_on(events).unwrap();
+        assert_eq!(events[0].object["metadata"]["resourceVersion"], "11");
+        assert_eq!(*queries.lock().unwrap(), 6);
+        // Backed off 1, 2, 4, 8 and 10ms
+        assert!(start.elapsed() >= Duration::from_millis(25));
+    }
+
+    #[test]
+    fn resilient_relist() {
+        for &as_event in &[true, false] {
+            let queries = Arc::new(Mutex::new(vec![]));
+            let queries2 = Arc::clone(&queries);
+            let client = mock_client(move |req| {
+                let mut queries = queries2.lock().unwrap();
+                queries.push(req.uri().query().unwrap_or("").to_string());
+                match queries.len() {
+                    1 if as_event => hyper::Response::new(Body::from(format!(
+                        "{}\n",
+                        json!({"type": "ERROR", "object": status(410, "Expired")})
+                    ))),
+                    1 => json_response(410, &status(410, "Gone")),
+                    2 => json_response(
+                        200,
+                        &json!({
+                            "kind": "PodList",
+                            "apiVersion": "v1",
+                            "metadata": {"resourceVersion": "20"},
+                            "items": [{"metadata": {"name": "a", "resourceVersion": "15"}}],
+                        }),
+                    ),
+                    _ => hyper::Response::new(Body::from(watch_line("MODIFIED", 21))),
+                }
+            });
+
+            let opts = ListOptions {
+                resource_version: "10".into(),
+                label_selector: "app=web".into(),
+                ..Default::default()
+            };
+            let events = client
+                .watch_list_resilient(&GROUP_VERSION.with_resource("pods"), None, opts, policy())
+                .take(2)
+                .collect();
+            let events = Runtime::new().unwrap().block_on(events).unwrap();
+            assert_eq!(events[0].typ, EventType::Added);
+            assert_eq!(events[0].object["metadata"]["name"], "a");
+            assert_eq!(events[1].typ, EventType::Modified);
+            assert_eq!(
+                *queries.lock().unwrap(),
+                vec![
+                    "resourceVersion=10&watch=true&labelSelector=app%3Dweb",
+                    "labelSelector=app%3Dweb",
+                    "resourceVersion=20&watch=true&labelSelector=app%3Dweb",
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn resilient_gives_up() {
+        let calls = Arc::new(Mutex::new(0));
+        let calls2 = Arc::clone(&calls);
+        let client = mock_client(move |_| {
+            *calls2.lock().unwrap() += 1;
+            json_response(503, &status(503, "ServiceUnavailable"))
+        });
+        let events = client
+            .watch_list_resilient(
+                &GROUP_VERSION.with_resource("pods"),
+                None,
+                Default::default(),
+                policy(),
+            )
+            .collect();
+        assert!(Runtime::new().unwrap().block_on(events).is_err());
+        assert_eq!(*calls.lock().unwrap(), 4);
+
+        // Not worth retrying
+        let calls = Arc::new(Mutex::new(0));
+        let calls2 = Arc::clone(&calls);
+        let client = mock_client(move |_| {
+            *calls2.lock().unwrap() += 1;
+            json_response(403, &status(403, "Forbidden"))
+        });
+        let events = client
+            .watch_list_resilient(
+                &GROUP_VERSION.with_resource("pods"),
+                None,
+                Default::default(),
+                policy(),
+            )
+            .collect();
+        assert!(Runtime::new().unwrap().block_on(events).is_err());
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn no_renew_after_error_event() {
+        let calls = Arc::new(Mutex::new(0));
+        let calls2 = Arc::clone(&calls);
+        let client = mock_client(move |_| {
+            *calls2.lock().unwrap() += 1;
+            hyper::Response::new(Body::from(watch_line("ERROR", 0)))
+        });
+
+        let events = client
+            .watch_list_renewing(
+                &GROUP_VERSION.with_resource("pods"),
+                None,
+                Default::default(),
+            )
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].typ, EventType::Error);
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
+
+    fn pod_event(typ: EventType, name: &str, rv: &str) -> WatchEvent {
+        WatchEvent {
+            typ,
+            object: json!({
+                "metadata": {"namespace": "default", "name": name, "resourceVersion": rv},
+            }),
+        }
+    }
+
+    fn summary(events: &[WatchEvent]) -> Vec<(EventType, &str, &str)> {
+        events
+            .iter()
+            .map(|e| {
+                let meta = &e.object["metadata"];
+                (
+                    e.typ.clone(),
+                    meta["name"].as_str().unwrap(),
+                    meta["resourceVersion"].as_str().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn resilient_relist_diff() {
+        let lines = |events: &[WatchEvent]| -> String {
+            events
+                .iter()
+                .map(|e| serde_json::to_string(e).unwrap() + "\n")
+                .collect()
+        };
+        let first = lines(&[
+            pod_event(EventType::Added, "a", "1"),
+            pod_event(EventType::Added, "b", "2"),
+            pod_event(EventType::Added, "c", "3"),
+            pod_event(EventType::Added, "e", "4"),
+            pod_event(EventType::Deleted, "e", "5"),
+        ]);
+        let client = mock_client(move |req| {
+            let query = req.uri().query().unwrap_or("");
+            match query {
+                "watch=true" => hyper::Response::new(Body::from(first.clone())),
+                "resourceVersion=5&watch=true" => json_response(410, &status(410, "Expired")),
+                "" => json_response(
+                    200,
+                    &json!({
+                        "kind": "PodList",
+                        "apiVersion": "v1",
+                        "metadata": {"resourceVersion": "9"},
+                        "items": [
+                            pod_event(EventType::Added, "a", "1").object,
+                            pod_event(EventType::Added, "c", "7").object,
+                            pod_event(EventType::Added, "d", "8").object,
+                        ],
+                    }),
+                ),
+                _ => hyper::Response::new(Body::from(lines(&[pod_event(
+                    EventType::Modified,
+                    "a",
+                    "10",
+                )]))),
+            }
+        });
+
+        let events = client
+            .watch_list_resilient(
+                &GROUP_VERSION.with_resource("pods"),
+                None,
+                Default::default(),
+                policy(),
+            )
+            .take(9)
+            .collect();
+        let events = Runtime::new().unwrap().block_on(events).unwrap();
+        assert_eq!(
+            summary(&events[5..]),
+            vec![
+                (EventType::Deleted, "b", "2"),
+                (EventType::Modified, "c", "7"),
+                (EventType::Added, "d", "8"),
+                (EventType::Modified, "a", "10"),
+            ]
+        );
+    }
+
+    #[test]
+    fn resilient_relist_recreated() {
+        let with_uid = |typ, name, rv, uid| {
+            let mut event = pod_event(typ, name, rv);
+            event.object["metadata"]["uid"] = json!(uid);
+            event
+        };
+        let first = [
+            with_uid(EventType::Added, "a", "1", "uid-a1"),
+            with_uid(EventType::Added, "b", "2", "uid-b"),
+        ]
+        .iter()
+        .map(|e| serde_json::to_string(e).unwrap() + "\n")
+        .collect::<String>();
+        // While the watch was down, "a" was deleted and recreated
+        let items = vec![
+            with_uid(EventType::Added, "a", "7", "uid-a2").object,
+            with_uid(EventType::Added, "b", "2", "uid-b").object,
+        ];
+        let client = mock_client(move |req| match req.uri().query().unwrap_or("") {
+            "watch=true" => hyper::Response::new(Body::from(first.clone())),
+            "resourceVersion=2&watch=true" => json_response(410, &status(410, "Expired")),
+            "" => json_response(
+                200,
+                &json!({
+                    "kind": "PodList",
+                    "apiVersion": "v1",
+                    "metadata": {"resourceVersion": "9"},
+                    "items": items,
+                }),
+            ),
+            _ => hyper::Response::new(Body::empty()),
+        });
+
+        let events = client
+            .watch_list_resilient(
+                &GROUP_VERSION.with_resource("pods"),
+                None,
+                Default::default(),
+                policy(),
+            )
+            .take(4)
+            .collect();
+        let events = Runtime::new().unwrap().block_on(events).unwrap();
+        assert_eq!(
+            summary(&events[2..]),
+            vec![(EventType::Deleted, "a", "1"), (EventType::Added, "a", "7")]
+        );
+        assert_eq!(events[2].object["metadata"]["uid"], "uid-a1");
+        assert_eq!(events[3].object["metadata"]["uid"], "uid-a2");
+    }
+
+    #[test]
+    fn coalesce() {
+        use self::EventType::*;
+        let window = Duration::from_millis(50);
+        let burst = vec![
+            pod_event(Modified, "a", "1"),
+            pod_event(Modified, "a", "2"),
+            pod_event(Modified, "b", "3"),
+            pod_event(Modified, "a", "4"),
+            pod_event(Deleted, "b", "5"),
+        ];
+        // Arrives after the window has passed
+        let later = Delay::new(Instant::now() + window * 3)
+            .map(|_| pod_event(Modified, "a", "6"))
+            .from_err()
+            .into_stream();
+        let events = stream::iter_ok(burst).chain(later);
+
+        let mut rt = Runtime::new().unwrap();
+        let result = rt
+            .block_on(coalesce_latest(events, window).collect())
+            .unwrap();
+        assert_eq!(
+            summary(&result),
+            vec![
+                (Modified, "a", "4"),
+                (Deleted, "b", "5"),
+                (Modified, "a", "6")
+            ]
+        );
+    }
+
+    #[test]
+    fn coalesce_recreated() {
+        use self::EventType::*;
+        let with_uid = |typ, rv, uid| {
+            let mut event = pod_event(typ, "a", rv);
+            event.object["metadata"]["uid"] = json!(uid);
+            event
+        };
+        // The deletion of the first "a" was missed (eg: coalesced
+        // upstream), so only its replacement's events say it was
+        // recreated.
+        let events = stream::iter_ok(vec![
+            with_uid(Modified, "1", "uid-1"),
+            with_uid(Added, "2", "uid-2"),
+            with_uid(Modified, "3", "uid-1"),
+            with_uid(Modified, "4", "uid-2"),
+        ]);
+
+        let mut rt = Runtime::new().unwrap();
+        let result = rt
+            .block_on(coalesce_latest(events, Duration::from_secs(60)).collect())
+            .unwrap();
+        assert_eq!(
+            summary(&result),
+            vec![(Modified, "a", "3"), (Added, "a", "4")]
+        );
+    }
+
+    #[test]
+    fn coalesce_across_delete() {
+        use self::EventType::*;
+        let events = stream::iter_ok(vec![
+            pod_event(Added, "a", "1"),
+            pod_event(Modified, "a", "2"),
+            pod_event(Deleted, "a", "3"),
+            pod_event(Added, "a", "4"),
+            pod_event(Modified, "a", "5"),
+        ]);
+
+        let mut rt = Runtime::new().unwrap();
+        let result = rt
+            .block_on(coalesce_latest(events, Duration::from_secs(60)).collect())
+            .unwrap();
+        assert_eq!(
+            summary(&result),
+            vec![(Deleted, "a", "3"), (Added, "a", "5")]
+        );
+    }
+
+    fn status_event(typ: EventType, managers: &[&str], phase: &str) -> WatchEvent {
+        let fields: Vec<_> = managers.iter().map(|m| json!({"manager": m})).collect();
+        WatchEvent {
+            typ,
+            object: json!({
+                "metadata": {"namespace": "default", "name": "web", "managedFields": fields},
+                "status": {"phase": phase},
+            }),
+        }
+    }
+
+    #[test]
+    fn status_changes() {
+        use self::EventType::*;
+        let events = stream::iter_ok(vec![
+            status_event(Added, &["kubectl"], "Pending"),
+            // Another controller took ownership of some fields
+            status_event(Modified, &["kubectl", "istio"], "Pending"),
+            status_event(Modified, &["kubectl", "istio"], "Running"),
+            status_event(Modified, &["kubectl"], "Running"),
+            status_event(Deleted, &["kubectl"], "Running"),
+            status_event(Added, &["kubectl"], "Running"),
+        ]);
+        let result = only_status_changed(events).collect().wait().unwrap();
+        let summary: Vec<_> = result
+            .iter()
+            .map(|e| (e.typ.clone(), e.object["status"]["phase"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Added, "Pending"),
+                (Modified, "Running"),
+                (Deleted, "Running"),
+                (Added, "Running"),
+            ]
+        );
+    }
+
+    #[test]
+    fn status_changes_spec() {
+        use self::EventType::*;
+        let with_spec = |typ, image, rv| {
+            let mut event = status_event(typ, &["kubectl"], "Running");
+            event.object["metadata"]["resourceVersion"] = json!(rv);
+            event.object["spec"] = json!({"containers": [{"name": "web", "image": image}]});
+            event
+        };
+        let events = stream::iter_ok(vec![
+            with_spec(Added, "nginx:1.19", "1"),
+            with_spec(Modified, "nginx:1.19", "2"),
+            // Spec changed, status not (yet)
+            with_spec(Modified, "nginx:1.20", "3"),
+        ]);
+        let result = only_status_changed(events).collect().wait().unwrap();
+        let rvs: Vec<_> = result
+            .iter()
+            .map(|e| e.object["metadata"]["resourceVersion"].as_str().unwrap())
+            .collect();
+        assert_eq!(rvs, vec!["1", "3"]);
+    }
+
+    #[test]
+    fn status_changes_recreated() {
+        use self::EventType::*;
+        let with_uid = |typ, phase, uid| {
+            let mut event = status_event(typ, &["kubectl"], phase);
+            event.object["metadata"]["uid"] = json!(uid);
+            event
+        };
+        // Recreated with the same name, with the deletion missed: the
+        // new object's first status isn't compared with the old one's.
+        let events = stream::iter_ok(vec![
+            with_uid(Added, "Running", "uid-1"),
+            with_uid(Modified, "Running", "uid-2"),
+            with_uid(Modified, "Running", "uid-2"),
+            with_uid(Modified, "Running", "uid-1"),
+        ]);
+        let result = only_status_changed(events).collect().wait().unwrap();
+        let uids: Vec<_> = result
+            .iter()
+            .map(|e| e.object["metadata"]["uid"].as_str().unwrap())
+            .collect();
+        assert_eq!(uids, vec!["uid-1", "uid-2"]);
+    }
+}
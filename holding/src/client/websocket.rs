@@ -0,0 +1,476 @@
+//! Just enough of a websocket client (RFC 6455) for the streaming
+//! subresources (`exec`, `attach` and `portforward`), which the API
+//! server only offers over websockets.
+//!
+//! Messages are binary, sent unfragmented, and there are no
+//! extensions.  Pings are not answered: the API server doesn't send
+//! any.
+
+use super::{hyper_uri, stream_response, HttpService, Transport, UpgradedIo};
+use base64;
+use failure::Error;
+use futures::future::{self, Either};
+use futures::task::{self, Task};
+use futures::{Async, AsyncSink, Future, Poll, Sink, StartSend, Stream};
+use hyper::header::{
+    HeaderValue, CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_PROTOCOL,
+    SEC_WEBSOCKET_VERSION, UPGRADE,
+};
+use hyper::{self, Body, Request, StatusCode};
+use openssl::rand::rand_bytes;
+use openssl::sha::sha1;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+pub(super) const CONTINUATION: u8 = 0x0;
+pub(super) const TEXT: u8 = 0x1;
+pub(super) const BINARY: u8 = 0x2;
+pub(super) const CLOSE: u8 = 0x8;
+
+/// Larger frames are refused, rather than buffered.
+const MAX_FRAME: u64 = 16 * 1024 * 1024;
+/// `start_send` waits for the connection beyond this much unwritten
+/// data.
+const WRITE_BUFFER: usize = 64 * 1024;
+
+/// Adds the headers asking to upgrade `req` to a websocket speaking
+/// `protocol`.  Returns the key the response must answer.
+pub(super) fn upgrade_request(
+    req: &mut Request<Body>,
+    protocol: &'static str,
+) -> Result<String, Error> {
+    let mut nonce = [0; 16];
+    rand_bytes(&mut nonce)?;
+    let key = base64::encode(&nonce);
+    let headers = req.headers_mut();
+    headers.insert(CONNECTION, HeaderValue::from_static("Upgrade"));
+    headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(SEC_WEBSOCKET_VERSION, HeaderValue::from_static("13"));
+    headers.insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static(protocol));
+    headers.insert(SEC_WEBSOCKET_KEY, HeaderValue::from_str(&key)?);
+    Ok(key)
+}
+
+/// The `Sec-WebSocket-Accept` a server answers `key` with.
+pub(super) fn accept_key(key: &str) -> String {
+    base64::encode(&sha1(format!("{}{}", key, GUID).as_bytes()))
+}
+
+/// Checks the server agreed to the upgrade asked for with `key`.
+fn check_response(res: &hyper::Response<Body>, key: &str, protocol: &str) -> Result<(), Error> {
+    let header = |name| res.headers().get(name).and_then(|v| v.to_str().ok());
+    if header(SEC_WEBSOCKET_ACCEPT) != Some(accept_key(key).as_str()) {
+        bail!("Server did not accept the websocket upgrade");
+    }
+    if header(SEC_WEBSOCKET_PROTOCOL) != Some(protocol) {
+        bail!("Server did not agree to the {} protocol", protocol);
+    }
+    Ok(())
+}
+
+/// Upgrades a GET of `url` to a websocket speaking `protocol`.  An
+/// error status from the server is returned as its `Status`.
+pub(super) fn connect<C: HttpService>(
+    transport: &Transport<C>,
+    url: Result<Url, Error>,
+    protocol: &'static str,
+) -> impl Future<Item = WebSocket, Error = Error> + Send {
+    let service = Arc::clone(&transport.service);
+    let transport = transport.clone();
+    let req = url.and_then(|url| {
+        let mut req = Request::get(hyper_uri(url)).body(Body::empty())?;
+        let key = upgrade_request(&mut req, protocol)?;
+        Ok((req, key))
+    });
+    future::result(req)
+        .and_then(move |(req, key)| transport.send(req).map(|res| (res, key)))
+        .and_then(move |(res, key)| {
+            if res.status() != StatusCode::SWITCHING_PROTOCOLS {
+                let status = res.status();
+                return Either::A(stream_response(res).and_then(move |_| {
+                    Err(format_err!("Expected a websocket upgrade, got {}", status))
+                }));
+            }
+            Either::B(
+                future::result(check_response(&res, &key, protocol))
+                    .and_then(move |()| service.upgrade(res))
+                    .map(WebSocket::new),
+            )
+        })
+}
+
+/// One websocket frame.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Frame {
+    pub fin: bool,
+    pub opcode: u8,
+    pub payload: Vec<u8>,
+}
+
+/// Appends a complete frame to `out`.  Clients must `mask` what they
+/// send, and servers must not.
+pub(super) fn encode_frame(opcode: u8, payload: &[u8], mask: Option<[u8; 4]>, out: &mut Vec<u8>) {
+    out.push(0x80 | opcode);
+    let masked = if mask.is_some() { 0x80 } else { 0 };
+    let len = payload.len();
+    if len < 126 {
+        out.push(masked | len as u8);
+    } else if len <= 0xffff {
+        out.push(masked | 126);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        out.push(masked | 127);
+        out.extend_from_slice(&(len as u64).to_be_bytes());
+    }
+    match mask {
+        Some(key) => {
+            out.extend_from_slice(&key);
+            out.extend(payload.iter().enumerate().map(|(i, b)| b ^ key[i % 4]));
+        }
+        None => out.extend_from_slice(payload),
+    }
+}
+
+/// Removes the first frame from `buf`, once it has all arrived.
+pub(super) fn decode_frame(buf: &mut Vec<u8>) -> Result<Option<Frame>, Error> {
+    if buf.len() < 2 {
+        return Ok(None);
+    }
+    let fin = buf[0] & 0x80 != 0;
+    let opcode = buf[0] & 0x0f;
+    let masked = buf[1] & 0x80 != 0;
+    let (len, mut start) = match buf[1] & 0x7f {
+        126 if buf.len() >= 4 => (u64::from(buf[2]) << 8 | u64::from(buf[3]), 4),
+        127 if buf.len() >= 10 => {
+            let mut n = [0; 8];
+            n.copy_from_slice(&buf[2..10]);
+            (u64::from_be_bytes(n), 10)
+        }
+        126 | 127 => return Ok(None),
+        n => (u64::from(n), 2),
+    };
+    if len > MAX_FRAME {
+        bail!("Websocket frame of {} bytes is too large", len);
+    }
+    let mut mask = None;
+    if masked {
+        if buf.len() < start + 4 {
+            return Ok(None);
+        }
+        let mut key = [0; 4];
+        key.copy_from_slice(&buf[start..start + 4]);
+        mask = Some(key);
+        start += 4;
+    }
+    let end = start + len as usize;
+    if buf.len() < end {
+        return Ok(None);
+    }
+    let mut payload: Vec<u8> = buf.drain(..end).skip(start).collect();
+    if let Some(key) = mask {
+        for (i, b) in payload.iter_mut().enumerate() {
+            *b ^= key[i % 4];
+        }
+    }
+    Ok(Some(Frame {
+        fin,
+        opcode,
+        payload,
+    }))
+}
+
+/// A websocket connection: a stream of the messages received, and a
+/// sink of those to send.  The stream ends when the server closes the
+/// connection.
+pub(super) struct WebSocket {
+    io: Box<dyn UpgradedIo>,
+    /// Received, but not yet a whole frame
+    rbuf: Vec<u8>,
+    /// The fragments so far of a message
+    message: Vec<u8>,
+    /// Frames not yet written
+    wbuf: Vec<u8>,
+    closed: bool,
+}
+
+impl WebSocket {
+    pub fn new(io: Box<dyn UpgradedIo>) -> Self {
+        WebSocket {
+            io,
+            rbuf: Vec::new(),
+            message: Vec::new(),
+            wbuf: Vec::new(),
+            closed: false,
+        }
+    }
+
+    fn push_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), Error> {
+        let mut mask = [0; 4];
+        rand_bytes(&mut mask)?;
+        encode_frame(opcode, payload, Some(mask), &mut self.wbuf);
+        Ok(())
+    }
+}
+
+impl Stream for WebSocket {
+    type Item = Vec<u8>;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Vec<u8>>, Error> {
+        loop {
+            if self.closed {
+                return Ok(Async::Ready(None));
+            }
+            while let Some(frame) = decode_frame(&mut self.rbuf)? {
+                match frame.opcode {
+                    CLOSE => {
+                        self.closed = true;
+                        return Ok(Async::Ready(None));
+                    }
+                    CONTINUATION | TEXT | BINARY => {
+                        self.message.extend(frame.payload);
+                        if frame.fin {
+                            return Ok(Async::Ready(Some(mem::replace(
+                                &mut self.message,
+                                Vec::new(),
+                            ))));
+                        }
+                    }
+                    // Ping and pong
+                    _ => (),
+                }
+            }
+            let mut chunk = [0; 8192];
+            match self.io.poll_read(&mut chunk)? {
+                Async::Ready(0) if self.rbuf.is_empty() && self.message.is_empty() => {
+                    self.closed = true;
+                }
+                Async::Ready(0) => bail!("Websocket connection closed mid-message"),
+                Async::Ready(n) => self.rbuf.extend_from_slice(&chunk[..n]),
+                Async::NotReady => return Ok(Async::NotReady),
+            }
+        }
+    }
+}
+
+impl Sink for WebSocket {
+    type SinkItem = Vec<u8>;
+    type SinkError = Error;
+
+    fn start_send(&mut self, msg: Vec<u8>) -> StartSend<Vec<u8>, Error> {
+        if self.wbuf.len() >= WRITE_BUFFER {
+            self.poll_complete()?;
+            if self.wbuf.len() >= WRITE_BUFFER {
+                return Ok(AsyncSink::NotReady(msg));
+            }
+        }
+        self.push_frame(BINARY, &msg)?;
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Error> {
+        while !self.wbuf.is_empty() {
+            let n = match self.io.poll_write(&self.wbuf)? {
+                Async::Ready(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "websocket connection closed",
+                    )
+                    .into())
+                }
+                Async::Ready(n) => n,
+                Async::NotReady => return Ok(Async::NotReady),
+            };
+            self.wbuf.drain(..n);
+        }
+        Ok(self.io.poll_flush()?)
+    }
+
+    fn close(&mut self) -> Poll<(), Error> {
+        if !self.closed {
+            self.closed = true;
+            self.push_frame(CLOSE, &[])?;
+        }
+        match self.poll_complete()? {
+            Async::Ready(()) => Ok(self.io.shutdown()?),
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+/// For errors from `Channels`, in `Read` and `Write` implementations.
+pub(super) fn io_error(e: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.compat())
+}
+
+/// A websocket shared between the streams of its channels, as the
+/// `*.k8s.io` subprotocols use it: the first byte of each message
+/// says which stream it belongs to.
+///
+/// Whichever stream is polled reads from the connection, and queues
+/// what arrives for the others.  Messages for a channel that was never
+/// opened, or has been closed, are dropped.
+#[derive(Clone)]
+pub(super) struct Channels {
+    inner: Arc<Mutex<Shared>>,
+}
+
+struct Shared {
+    ws: WebSocket,
+    /// Received but unread, by channel
+    queues: HashMap<u8, VecDeque<Vec<u8>>>,
+    /// Tasks waiting on the connection
+    waiting: Vec<Task>,
+    eof: bool,
+    /// Errors aren't `Clone`, so each stream gets the message
+    error: Option<String>,
+}
+
+impl Shared {
+    fn wake(&mut self) {
+        for t in self.waiting.drain(..) {
+            t.notify();
+        }
+    }
+}
+
+impl fmt::Debug for Channels {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Channels")
+    }
+}
+
+impl Channels {
+    pub fn new(ws: WebSocket, channels: &[u8]) -> Self {
+        let queues = channels.iter().map(|c| (*c, VecDeque::new())).collect();
+        Channels {
+            inner: Arc::new(Mutex::new(Shared {
+                ws,
+                queues,
+                waiting: Vec::new(),
+                eof: false,
+                error: None,
+            })),
+        }
+    }
+
+    /// The next message on `channel` (without the channel byte), or
+    /// `None` once the connection has closed.
+    pub fn poll_recv(&self, channel: u8) -> Poll<Option<Vec<u8>>, Error> {
+        let mut shared = self.inner.lock().unwrap();
+        let mut received = false;
+        let res = loop {
+            if let Some(msg) = shared.queues.get_mut(&channel).and_then(|q| q.pop_front()) {
+                break Ok(Async::Ready(Some(msg)));
+            }
+            if let Some(ref e) = shared.error {
+                break Err(format_err!("{}", e));
+            }
+            if shared.eof {
+                break Ok(Async::Ready(None));
+            }
+            match shared.ws.poll() {
+                Ok(Async::Ready(Some(msg))) => {
+                    received = true;
+                    let (c, data) = match msg.split_first() {
+                        Some((c, data)) => (*c, data.to_vec()),
+                        None => continue,
+                    };
+                    match shared.queues.get_mut(&c) {
+                        Some(q) => q.push_back(data),
+                        None => debug!("Dropping message for channel {}", c),
+                    }
+                }
+                Ok(Async::Ready(None)) => shared.eof = true,
+                Err(e) => shared.error = Some(e.to_string()),
+                Ok(Async::NotReady) => {
+                    shared.waiting.push(task::current());
+                    break Ok(Async::NotReady);
+                }
+            }
+        };
+        // The connection only wakes the last task to poll it, so others
+        // (that may now have messages queued) must look again.
+        if received || shared.eof || shared.error.is_some() {
+            shared.wake();
+        }
+        res
+    }
+
+    /// Stops queueing messages for `channel`.
+    pub fn close(&self, channel: u8) {
+        self.inner.lock().unwrap().queues.remove(&channel);
+    }
+
+    pub fn start_send(&self, msg: Vec<u8>) -> StartSend<Vec<u8>, Error> {
+        self.inner.lock().unwrap().ws.start_send(msg)
+    }
+
+    pub fn poll_complete(&self) -> Poll<(), Error> {
+        let mut shared = self.inner.lock().unwrap();
+        let res = shared.ws.poll_complete();
+        match res {
+            Ok(Async::NotReady) => shared.waiting.push(task::current()),
+            _ => shared.wake(),
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames() {
+        for len in &[0, 125, 126, 0xffff, 0x10000] {
+            let payload: Vec<u8> = (0..*len).map(|i| i as u8).collect();
+            for mask in &[None, Some([1, 2, 3, 4])] {
+                let mut buf = Vec::new();
+                encode_frame(BINARY, &payload, *mask, &mut buf);
+                encode_frame(CLOSE, b"", *mask, &mut buf);
+                let whole = buf.len();
+
+                // Nothing until a frame has all arrived
+                let mut partial = buf[..whole / 2].to_vec();
+                if *len > 0 {
+                    assert_eq!(decode_frame(&mut partial).unwrap(), None);
+                }
+
+                assert_eq!(
+                    decode_frame(&mut buf).unwrap(),
+                    Some(Frame {
+                        fin: true,
+                        opcode: BINARY,
+                        payload: payload.clone(),
+                    })
+                );
+                assert_eq!(decode_frame(&mut buf).unwrap().unwrap().opcode, CLOSE);
+                assert!(buf.is_empty());
+            }
+        }
+
+        // Masked on the wire
+        let mut buf = Vec::new();
+        encode_frame(BINARY, b"\0\0", Some([1, 2, 3, 4]), &mut buf);
+        assert_eq!(buf, b"\x82\x82\x01\x02\x03\x04\x01\x02");
+
+        let mut huge = vec![0x82, 127, 0, 0, 0, 0, 0x10, 0, 0, 0];
+        assert!(decode_frame(&mut huge).is_err());
+    }
+
+    #[test]
+    fn accept() {
+        // The example from RFC 6455
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+}
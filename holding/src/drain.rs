@@ -0,0 +1,229 @@
+//! Rules for deciding which pods can be evicted from a node, as
+//! `kubectl drain` does.
+
+use api::core::v1::{Pod, VolumeSource};
+use api::well_known;
+use std::fmt;
+use std::sync::Arc;
+
+/// True if the pod is managed by a DaemonSet.  Evicting these is
+/// pointless, since the DaemonSet controller ignores unschedulable
+/// nodes and will immediately replace it.
+pub fn is_daemonset_pod(pod: &Pod) -> bool {
+    pod.metadata
+        .owner_references
+        .iter()
+        .any(|o| o.controller && o.kind == "DaemonSet")
+}
+
+/// True if the pod is the API server's mirror of a static pod.  These
+/// are owned by the kubelet and can't be deleted via the API.
+pub fn is_mirror_pod(pod: &Pod) -> bool {
+    pod.metadata
+        .annotations
+        .contains_key(well_known::CONFIG_MIRROR)
+}
+
+/// True if the pod has `emptyDir` volumes, whose data will be lost
+/// when the pod is evicted.
+pub fn has_local_storage(pod: &Pod) -> bool {
+    pod.spec.volumes.iter().any(|v| match v.source {
+        VolumeSource::EmptyDir(_) => true,
+        _ => false,
+    })
+}
+
+/// True if nothing will recreate the pod once it is evicted.
+pub fn is_unreplicated(pod: &Pod) -> bool {
+    !pod.metadata.owner_references.iter().any(|o| o.controller)
+}
+
+/// A composable predicate over pods.
+#[derive(Clone)]
+pub struct PodFilter(Arc<dyn Fn(&Pod) -> bool + Send + Sync>);
+
+impl fmt::Debug for PodFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("PodFilter")
+    }
+}
+
+impl PodFilter {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&Pod) -> bool + Send + Sync + 'static,
+    {
+        PodFilter(Arc::new(f))
+    }
+
+    pub fn matches(&self, pod: &Pod) -> bool {
+        (self.0)(pod)
+    }
+
+    pub fn and(self, other: PodFilter) -> Self {
+        PodFilter::new(move |pod| self.matches(pod) && other.matches(pod))
+    }
+
+    pub fn or(self, other: PodFilter) -> Self {
+        PodFilter::new(move |pod| self.matches(pod) || other.matches(pod))
+    }
+
+    pub fn negate(self) -> Self {
+        PodFilter::new(move |pod| !self.matches(pod))
+    }
+
+    /// Pods that are safe to evict when draining a node.  DaemonSet
+    /// and mirror pods are always skipped.  Pods using local storage
+    /// are only included if `delete_local_data`, and pods that won't
+    /// be recreated only if `force`.
+    pub fn drain(delete_local_data: bool, force: bool) -> Self {
+        let mut filter = PodFilter::new(is_daemonset_pod)
+            .or(PodFilter::new(is_mirror_pod))
+            .negate();
+        if !delete_local_data {
+            filter = filter.and(PodFilter::new(has_local_storage).negate());
+        }
+        if !force {
+            filter = filter.and(PodFilter::new(is_unreplicated).negate());
+        }
+        filter
+    }
+
+    /// Returns the pods in `pods` that match this filter.
+    pub fn filter<'a, I>(&self, pods: I) -> Vec<&'a Pod>
+    where
+        I: IntoIterator<Item = &'a Pod>,
+    {
+        pods.into_iter().filter(|p| self.matches(p)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_yaml;
+
+    fn pod(yaml: &str) -> Pod {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn mirror_pod() -> Pod {
+        pod(r#"
+          apiVersion: v1
+          kind: Pod
+          metadata:
+            annotations:
+              kubernetes.io/config.hash: 9d80efa9dfda66b126ec4f9b5f7a004f
+              kubernetes.io/config.mirror: 9d80efa9dfda66b126ec4f9b5f7a004f
+              kubernetes.io/config.source: file
+            name: etcd-minikube
+            namespace: kube-system
+          spec:
+            containers:
+            - image: gcr.io/google_containers/etcd-amd64:3.1.10
+              name: etcd
+            hostNetwork: true
+            volumes:
+            - hostPath:
+                path: /data
+              name: data
+        "#)
+    }
+
+    fn daemonset_pod() -> Pod {
+        pod(r#"
+          metadata:
+            name: kube-proxy-x7f2q
+            namespace: kube-system
+            ownerReferences:
+            - apiVersion: apps/v1
+              kind: DaemonSet
+              name: kube-proxy
+              uid: 2c2b6e45-1439-11e8-bdc8-525400cf4e41
+              controller: true
+              blockOwnerDeletion: true
+        "#)
+    }
+
+    fn replicaset_pod(volumes: &str) -> Pod {
+        pod(&format!(
+            r#"
+          metadata:
+            name: web-5d4f7c9b8-abcde
+            namespace: default
+            ownerReferences:
+            - apiVersion: apps/v1
+              kind: ReplicaSet
+              name: web-5d4f7c9b8
+              uid: 6a8f1a1e-1439-11e8-bdc8-525400cf4e41
+              controller: true
+          spec:
+            volumes: {}
+        "#,
+            volumes
+        ))
+    }
+
+    fn bare_pod() -> Pod {
+        pod(r#"
+          metadata:
+            name: debug
+            namespace: default
+        "#)
+    }
+
+    #[test]
+    fn predicates() {
+        let mirror = mirror_pod();
+        assert!(is_mirror_pod(&mirror));
+        assert!(!is_daemonset_pod(&mirror));
+        assert!(!has_local_storage(&mirror)); // hostPath isn't emptyDir
+        assert!(is_unreplicated(&mirror));
+
+        let ds = daemonset_pod();
+        assert!(is_daemonset_pod(&ds));
+        assert!(!is_mirror_pod(&ds));
+        assert!(!is_unreplicated(&ds));
+
+        let rs = replicaset_pod("[]");
+        assert!(!is_daemonset_pod(&rs));
+        assert!(!is_mirror_pod(&rs));
+        assert!(!has_local_storage(&rs));
+        assert!(!is_unreplicated(&rs));
+
+        let scratch = replicaset_pod("[{name: scratch, emptyDir: {}}]");
+        assert!(has_local_storage(&scratch));
+
+        assert!(is_unreplicated(&bare_pod()));
+    }
+
+    #[test]
+    fn drain() {
+        let mirror = mirror_pod();
+        let ds = daemonset_pod();
+        let rs = replicaset_pod("[]");
+        let scratch = replicaset_pod("[{name: scratch, emptyDir: {}}]");
+        let bare = bare_pod();
+        let pods = vec![&mirror, &ds, &rs, &scratch, &bare];
+        let names = |filter: PodFilter| -> Vec<String> {
+            filter
+                .filter(pods.iter().cloned())
+                .iter()
+                .map(|p| p.metadata.name.clone().unwrap())
+                .collect()
+        };
+
+        assert_eq!(
+            names(PodFilter::drain(false, false)),
+            vec!["web-5d4f7c9b8-abcde"]
+        );
+        assert_eq!(
+            names(PodFilter::drain(true, false)),
+            vec!["web-5d4f7c9b8-abcde", "web-5d4f7c9b8-abcde"]
+        );
+        assert_eq!(
+            names(PodFilter::drain(false, true)),
+            vec!["web-5d4f7c9b8-abcde", "debug"]
+        );
+    }
+}
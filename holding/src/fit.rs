@@ -0,0 +1,180 @@
+//! Checks whether a pod fits in the resources of a node, as the
+//! scheduler's `NodeResourcesFit` filter does.
+
+use api::core::v1::{Container, Node, Pod, PodPhase};
+use api::Quantity;
+use std::collections::BTreeMap;
+
+/// The resources a pod requests: the sum of its containers' requests,
+/// or for each resource the largest init container request if that is
+/// larger (init containers run one at a time, before the others).
+pub fn pod_requests(pod: &Pod) -> BTreeMap<String, Quantity> {
+    let mut total = BTreeMap::new();
+    for (name, q) in pod.spec.containers.iter().flat_map(container_requests) {
+        let sum = total.remove(name).unwrap_or_default() + q;
+        total.insert(name.clone(), sum);
+    }
+    for (name, q) in pod.spec.init_containers.iter().flat_map(container_requests) {
+        if total.get(name).map(|t| q > t).unwrap_or(true) {
+            total.insert(name.clone(), q.clone());
+        }
+    }
+    total
+}
+
+fn container_requests(c: &Container) -> Vec<(&String, &Quantity)> {
+    c.resources
+        .as_ref()
+        .map(|r| r.requests.iter().collect())
+        .unwrap_or_default()
+}
+
+fn is_terminated(pod: &Pod) -> bool {
+    match pod.status.phase {
+        Some(PodPhase::Succeeded) | Some(PodPhase::Failed) => true,
+        _ => false,
+    }
+}
+
+/// The resources `node` is short of to run `pod` alongside the pods
+/// already `scheduled` on it, or an empty list if the pod fits.  The
+/// number of pods counts as the `pods` resource.
+///
+/// Terminated pods in `scheduled` are ignored, since they no longer
+/// use their requests.
+pub fn insufficient<'a, I>(node: &Node, pod: &Pod, scheduled: I) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a Pod>,
+{
+    let mut used: BTreeMap<String, Quantity> = BTreeMap::new();
+    let mut count = 0;
+    for p in scheduled.into_iter().filter(|p| !is_terminated(p)) {
+        count += 1;
+        for (name, q) in pod_requests(p) {
+            let sum = used.remove(&name).unwrap_or_default() + q;
+            used.insert(name, sum);
+        }
+    }
+
+    let allocatable = &node.status.allocatable;
+    let zero = Quantity::default();
+    let mut ret = vec![];
+    if let Some(max) = allocatable.get("pods") {
+        if count + 1 > max.as_milli() / 1000 {
+            ret.push("pods".to_string());
+        }
+    }
+    for (name, q) in pod_requests(pod) {
+        if q <= zero {
+            continue;
+        }
+        let free = allocatable.get(&name).cloned().unwrap_or_default()
+            - used.get(&name).cloned().unwrap_or_default();
+        if q > free {
+            ret.push(name);
+        }
+    }
+    ret
+}
+
+/// True if `pod` fits on `node` alongside the pods already
+/// `scheduled` on it.  See `insufficient`.
+pub fn fits<'a, I>(node: &Node, pod: &Pod, scheduled: I) -> bool
+where
+    I: IntoIterator<Item = &'a Pod>,
+{
+    insufficient(node, pod, scheduled).is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_yaml;
+
+    fn node() -> Node {
+        serde_yaml::from_str(
+            r#"
+          apiVersion: v1
+          kind: Node
+          metadata:
+            name: worker-1
+          status:
+            capacity:
+              cpu: "2"
+              memory: 4039048Ki
+              pods: "110"
+            allocatable:
+              cpu: 1900m
+              memory: 3936648Ki
+              pods: "3"
+        "#,
+        )
+        .unwrap()
+    }
+
+    fn pod(cpu: &str, memory: &str) -> Pod {
+        serde_yaml::from_str(&format!(
+            r#"
+          metadata:
+            name: web
+          spec:
+            initContainers:
+            - name: init
+              image: busybox
+              resources:
+                requests:
+                  cpu: 500m
+            containers:
+            - name: app
+              image: nginx
+              resources:
+                requests:
+                  cpu: {}
+                  memory: {}
+            - name: sidecar
+              image: envoy
+              resources:
+                requests:
+                  cpu: 100m
+                  memory: 64Mi
+        "#,
+            cpu, memory
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn sum_requests() {
+        let r = pod_requests(&pod("250m", "1Gi"));
+        assert_eq!(r["cpu"].as_milli(), 500); // init container is larger
+        assert_eq!(r["memory"], "1088Mi".parse::<Quantity>().unwrap());
+
+        let r = pod_requests(&pod("1.5", "1Gi"));
+        assert_eq!(r["cpu"].as_milli(), 1600);
+    }
+
+    #[test]
+    fn fit() {
+        let node = node();
+        let small = pod("250m", "1Gi");
+        assert!(fits(&node, &small, vec![]));
+        assert!(fits(&node, &small, vec![&small]));
+
+        // 1088Mi + 3136Mi > 3936648Ki (~3844Mi)
+        let big = pod("250m", "3Gi");
+        assert!(fits(&node, &big, vec![]));
+        assert_eq!(insufficient(&node, &big, vec![&small]), vec!["memory"]);
+
+        let huge = pod("250m", "4Gi");
+        assert_eq!(insufficient(&node, &huge, vec![]), vec!["memory"]);
+
+        // Only 3 pods are allowed, and 4 * 500m > 1900m
+        let mut done = small.clone();
+        done.status.phase = Some(PodPhase::Succeeded);
+        assert!(fits(&node, &small, vec![&done, &done, &done]));
+        assert_eq!(
+            insufficient(&node, &small, vec![&small, &small, &small, &done]),
+            vec!["pods", "cpu", "memory"]
+        );
+    }
+}
@@ -1,7 +1,7 @@
 //! # Kubernetes client
 
 extern crate serde;
-#[cfg_attr(test, macro_use)]
+#[macro_use]
 extern crate serde_json;
 extern crate url;
 #[macro_use]
@@ -11,15 +11,20 @@ extern crate serde_yaml;
 #[macro_use]
 extern crate failure;
 extern crate base64;
+extern crate chrono;
+#[cfg(feature = "gzip")]
+extern crate flate2;
 extern crate futures;
 extern crate hyper;
 extern crate hyper_tls;
 extern crate native_tls;
 extern crate openssl;
+extern crate tokio;
 extern crate tokio_core;
 #[macro_use]
 extern crate log;
 extern crate kubernetes_api as api;
 
 pub mod client;
-mod serde_base64;
+pub mod drain;
+pub mod fit;